@@ -0,0 +1,67 @@
+// Frame diffing on a 300 x 100 grid, the size of a maximized console on a 4K screen:
+//
+//     cargo bench --features render --bench frame_diff
+//
+// `Presenter::diff` encodes every cell the way the console stores it, then compares the packed
+// cells 16 at a time; the per-cell loop over `FrameCell` is the comparison it replaces.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use win_term::frame::{Frame, Presenter};
+use win_term::style::Rgb;
+
+const WIDTH: usize = 300;
+const HEIGHT: usize = 100;
+
+/// Runs `f` for about a second and returns the mean time of one run.
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    let mut runs = 0u32;
+    while start.elapsed() < Duration::from_secs(1) {
+        f();
+        runs += 1;
+    }
+    start.elapsed() / runs
+}
+
+/// A frame of text, with one cell in `every` altered by `seed`.
+fn frame(every: usize, seed: u8) -> Frame {
+    let mut frame = Frame::new(WIDTH, HEIGHT);
+    for (i, cell) in frame.cells.iter_mut().enumerate() {
+        cell.ch = (b'a' + (i % 26) as u8) as char;
+        if every > 0 && i % every == 0 {
+            cell.fg = Rgb::new(seed, 128, 255 - seed);
+        }
+    }
+    frame
+}
+
+fn per_cell(old: &Frame, new: &Frame) -> usize {
+    old.cells
+        .iter()
+        .zip(&new.cells)
+        .filter(|(a, b)| a != b)
+        .count()
+}
+
+fn main() {
+    for (name, every) in [("unchanged", 0), ("1% changed", 100), ("all changed", 1)] {
+        let frames = [frame(every, 0), frame(every, 255)];
+        let mut presenter = Presenter::new();
+        let mut turn = 0;
+        // Every run diffs against the previous frame, presented by the run before.
+        let diff = time(|| {
+            turn ^= 1;
+            black_box(presenter.diff(&frames[turn], 0, 0).len());
+            presenter.commit();
+        });
+        let naive = time(|| {
+            black_box(per_cell(&frames[0], black_box(&frames[1])));
+        });
+        println!(
+            "{:<12} Presenter::diff {:>9.1?}   per-cell FrameCell compare {:>9.1?}",
+            name, diff, naive
+        );
+    }
+}
@@ -0,0 +1,143 @@
+// Allocations of a high-volume logger writing to the console through `TerminalWriter`:
+//
+//     cargo bench --features pty --bench terminal_writer
+//
+// Run it in a console window: the lines are written to it, and the results printed after. The
+// baseline converts every line into a new UTF-16 buffer before `WriteConsoleW`, as a writer
+// without a reusable buffer does; `TerminalWriter` converts into the buffer of its thread.
+
+#[cfg(windows)]
+use std::alloc::{GlobalAlloc, Layout, System};
+#[cfg(windows)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts the allocations of the whole process.
+#[cfg(windows)]
+struct Counting;
+
+#[cfg(windows)]
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(windows)]
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[cfg(windows)]
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[cfg(windows)]
+fn main() {
+    use std::fmt::Write as _;
+    use std::io::Write as _;
+    use std::time::{Duration, Instant};
+
+    use win_term::writer::{TeeOptions, TerminalWriter};
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, WriteConsoleW, STD_OUTPUT_HANDLE,
+    };
+
+    const LINES: usize = 20_000;
+
+    /// Writes `LINES` log lines with `write`, returning the allocations and time per line.
+    fn run(mut write: impl FnMut(usize)) -> (f64, Duration) {
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let start = Instant::now();
+        for line in 0..LINES {
+            write(line);
+        }
+        let elapsed = start.elapsed();
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+        (allocations as f64 / LINES as f64, elapsed / LINES as u32)
+    }
+
+    let handle = unsafe { GetStdHandle(STD_OUTPUT_HANDLE) };
+    let mut mode = 0;
+    if unsafe { GetConsoleMode(handle, &mut mode) } == 0 {
+        eprintln!("terminal_writer: the standard output must be a console");
+        return;
+    }
+
+    // The same line is formatted into a reused string, so only the conversion allocates.
+    let mut text = String::with_capacity(128);
+    let baseline = run(|line| {
+        text.clear();
+        let _ = writeln!(
+            text,
+            "INFO  request {:>6} served in {} µs ─ ok",
+            line,
+            line % 997
+        );
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let mut written = 0;
+        unsafe {
+            WriteConsoleW(
+                handle,
+                units.as_ptr().cast(),
+                units.len() as u32,
+                &mut written,
+                std::ptr::null(),
+            )
+        };
+    });
+
+    // The first line sizes the buffers, as the first line of any logger does.
+    let mut writer = TerminalWriter::stdout();
+    let _ = writeln!(writer, "warming up");
+    let plain = run(|line| {
+        let _ = writeln!(
+            writer,
+            "INFO  request {:>6} served in {} µs ─ ok",
+            line,
+            line % 997
+        );
+    });
+
+    let path = std::env::temp_dir().join(format!("win-term-bench-{}.log", std::process::id()));
+    let options = TeeOptions {
+        timestamps: true,
+        max_bytes: 0,
+        ..TeeOptions::default()
+    };
+    let mut writer = TerminalWriter::stdout().tee_with(&path, options).unwrap();
+    let _ = writeln!(writer, "warming up");
+    let teed = run(|line| {
+        let _ = writeln!(
+            writer,
+            "INFO  request {:>6} served in {} µs ─ ok",
+            line,
+            line % 997
+        );
+    });
+    drop(writer);
+    let _ = std::fs::remove_file(&path);
+
+    for (name, (allocations, time)) in [
+        ("new UTF-16 buffer per line", baseline),
+        ("TerminalWriter", plain),
+        ("TerminalWriter, timestamped tee", teed),
+    ] {
+        println!(
+            "{:<32} {:>6.2} allocations/line {:>9.1?}/line",
+            name, allocations, time
+        );
+    }
+}
+
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("terminal_writer: needs a Windows console");
+}
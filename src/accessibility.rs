@@ -0,0 +1,98 @@
+use std::io::{self, Write};
+
+use windows_sys::Win32::System::Console::{WriteConsoleOutputCharacterW, COORD};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION,
+};
+
+use crate::console::{console_output, screen_buffer_info};
+use crate::measure::cells_exact;
+
+/// Enum to represent how urgently an announcement should reach the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Priority {
+    #[default]
+    Polite, // Only shown in the log region
+    Assertive, // Must not be missed; also written to the standard error as its own line
+}
+
+/// This function announces a state change to screen readers, e.g. "build finished, 3 errors".
+///
+/// The text is written into the log region, the bottom row of the visible window, replacing
+/// the previous announcement. The console exposes its text through UI Automation, so screen
+/// readers pick the change up like any other output, without moving the cursor or
+/// disturbing a full-screen layout.
+///
+/// ## Returns:
+/// - `Ok(())` once the text is written.
+/// - `Err(io::Error)` if the console can't be written to.
+///
+/// ## Note:
+/// - Without a console, the announcement is written to the standard error instead.
+/// - The text is cut to the width of the window and its line breaks become spaces.
+/// - Raising UI Automation notification events needs a provider on the console window, which
+///   belongs to the console host rather than the application, so none is raised.
+pub fn announce(text: &str, priority: Priority) -> io::Result<()> {
+    let text: String = text
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    let Some(handle) = console_output() else {
+        return writeln!(io::stderr(), "{}", text);
+    };
+    if priority == Priority::Assertive {
+        writeln!(io::stderr(), "{}", text)?;
+    }
+    let info = screen_buffer_info(handle).map_err(|_| io::Error::last_os_error())?;
+    let window = info.srWindow;
+    let columns = (window.Right - window.Left + 1).max(0) as usize;
+    let mut line = String::new();
+    let mut used = 0;
+    for span in cells_exact(&text) {
+        if span.column + span.width > columns {
+            break;
+        }
+        line.push_str(&text[span.start..span.end]);
+        used = span.column + span.width;
+    }
+    line.extend(std::iter::repeat_n(' ', columns - used));
+    let units: Vec<u16> = line.encode_utf16().collect();
+    let origin = COORD {
+        X: window.Left,
+        Y: window.Bottom,
+    };
+    let mut written = 0;
+    if unsafe {
+        WriteConsoleOutputCharacterW(
+            handle,
+            units.as_ptr(),
+            units.len() as u32,
+            origin,
+            &mut written,
+        )
+    } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// This function tells whether the user turned off "Show animations in Windows" (Settings,
+/// Accessibility, Visual effects), in which case animations should give way to static
+/// indicators.
+///
+/// ## Note:
+/// - The setting is read on every call, so a change applies to the next animation.
+/// - Returns `false` when the setting can't be read.
+pub fn prefers_reduced_motion() -> bool {
+    let mut animate = 1;
+    let ok = unsafe {
+        SystemParametersInfoW(
+            SPI_GETCLIENTAREAANIMATION,
+            0,
+            (&mut animate as *mut i32).cast(),
+            0,
+        )
+    };
+    ok != 0 && animate == 0
+}
@@ -0,0 +1,69 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use windows_sys::Win32::Media::Audio::{
+    PlaySoundW, SND_ALIAS, SND_ASYNC, SND_FILENAME, SND_NODEFAULT,
+};
+
+/// Enum to represent a sound played by [`play`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cue {
+    Default,       // The default beep, what the bell plays in conhost
+    Asterisk,      // Information
+    Exclamation,   // Warning
+    Critical,      // Error ("Critical Stop" in the sound settings)
+    Question,      // Confirmation asked
+    File(PathBuf), // Any `.wav` file
+}
+
+impl Cue {
+    /// The sound alias of a system cue, as named in the registry.
+    fn alias(&self) -> Option<&'static str> {
+        match self {
+            Cue::Default => Some(".Default"),
+            Cue::Asterisk => Some("SystemAsterisk"),
+            Cue::Exclamation => Some("SystemExclamation"),
+            Cue::Critical => Some("SystemHand"),
+            Cue::Question => Some("SystemQuestion"),
+            Cue::File(_) => None,
+        }
+    }
+}
+
+/// This function plays a sound in the background, returning without waiting for it to end.
+///
+/// ## Returns:
+/// - `Err(io::Error)` if the sound can't be played, e.g. the file doesn't exist or isn't a
+///   wave file.
+///
+/// ## Note:
+/// - Unlike the bell, the cues are distinct sounds the user set in the Control Panel, so a tool
+///   can tell success, warning and failure apart by ear, and the user keeps control over
+///   them (or mutes them) from there.
+/// - Only one sound plays at a time in a process: a new one stops the previous one.
+pub fn play(cue: &Cue) -> io::Result<()> {
+    let (name, kind) = match cue {
+        Cue::File(path) => (wide_path(path), SND_FILENAME),
+        _ => (
+            cue.alias()
+                .unwrap_or_default()
+                .encode_utf16()
+                .chain([0])
+                .collect(),
+            SND_ALIAS,
+        ),
+    };
+    let flags = kind | SND_ASYNC | SND_NODEFAULT;
+    if unsafe { PlaySoundW(name.as_ptr(), std::ptr::null_mut(), flags) } == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "the sound couldn't be played",
+        ));
+    }
+    Ok(())
+}
+
+/// A path as a null-terminated wide string.
+fn wide_path(path: &Path) -> Vec<u16> {
+    path.to_string_lossy().encode_utf16().chain([0]).collect()
+}
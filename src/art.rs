@@ -0,0 +1,349 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::encoding::{decode_oem, oem_to_unicode};
+
+/// Width of ANSI art files without a SAUCE record saying otherwise.
+const DEFAULT_WIDTH: usize = 80;
+
+/// Size of a SAUCE record, stored at the very end of the file.
+const SAUCE_LEN: usize = 128;
+
+/// Size of each comment line of the optional `COMNT` block preceding the SAUCE record.
+const COMMENT_LEN: usize = 64;
+
+/// Struct to hold a cell of an ANSI art picture, with colors of the 16-color legacy palette in
+/// SGR order (0 black, 1 red, ... 7 white, 8-15 their bright variants).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ArtCell {
+    pub ch: char,    // Glyph, already translated from CP437
+    pub fg: u8,      // Foreground color index
+    pub bg: u8,      // Background color index
+    pub blink: bool, // Blinking text, when the file doesn't use iCE colors
+}
+
+impl Default for ArtCell {
+    fn default() -> Self {
+        ArtCell {
+            ch: ' ',
+            fg: 7,
+            bg: 0,
+            blink: false,
+        }
+    }
+}
+
+/// Struct to hold the SAUCE metadata record of an ANSI art file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Sauce {
+    pub title: String,
+    pub author: String,
+    pub group: String,
+    pub date: String,          // `CCYYMMDD`
+    pub data_type: u8,         // 1 for character-based files
+    pub file_type: u8,         // 1 for ANSi within character files
+    pub width: u16,            // `TInfo1`: columns, for character files
+    pub height: u16,           // `TInfo2`: lines, for character files
+    pub ice_colors: bool,      // Blink selects bright backgrounds instead of blinking
+    pub font: String,          // `TInfoS`, e.g. "IBM VGA"
+    pub comments: Vec<String>, // Lines of the `COMNT` block
+}
+
+/// Struct to hold a decoded ANSI art picture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Art {
+    pub width: usize,         // Columns
+    pub height: usize,        // Rows
+    pub cells: Vec<ArtCell>,  // Row-major, `width * height` cells
+    pub sauce: Option<Sauce>, // Metadata, if the file has a SAUCE record
+}
+
+/// This function reads and decodes a CP437 ANSI art file (`.ans`, `.nfo`, `.diz`, ...).
+///
+/// See [`parse_ans`] for details.
+pub fn load_ans(path: impl AsRef<Path>) -> io::Result<Art> {
+    Ok(parse_ans(&fs::read(path)?))
+}
+
+/// This function decodes CP437 ANSI art, the format of DOS-era `ANSI.SYS` screens.
+///
+/// ## Returns:
+/// - The picture, `width` columns wide (80, or the width recorded in the SAUCE record) and as
+///   tall as the lowest row drawn.
+///
+/// ## Note:
+/// - Text stops at the DOS end-of-file marker (`0x1A`) or the SAUCE record, whichever
+///   comes first.
+/// - Supported sequences are SGR colors (bold meaning bright foreground), cursor moves and
+///   positioning, save/restore, and erasing the screen or line; others are skipped.
+/// - Control bytes other than CR, LF, TAB and ESC are drawn as their CP437 glyphs.
+pub fn parse_ans(bytes: &[u8]) -> Art {
+    let (data, sauce) = split_sauce(bytes);
+    let data = match data.iter().position(|&b| b == 0x1a) {
+        Some(end) => &data[..end],
+        None => data,
+    };
+    let width = match &sauce {
+        Some(sauce) if sauce.data_type == 1 && sauce.width > 0 => sauce.width as usize,
+        _ => DEFAULT_WIDTH,
+    };
+    let ice = sauce.as_ref().is_some_and(|sauce| sauce.ice_colors);
+    let mut canvas = Canvas::new(width, ice);
+    let mut i = 0;
+    while i < data.len() {
+        match data[i] {
+            0x1b if data.get(i + 1) == Some(&b'[') => {
+                let start = i + 2;
+                let mut end = start;
+                while end < data.len() && !(0x40..=0x7e).contains(&data[end]) {
+                    end += 1;
+                }
+                if end == data.len() {
+                    break;
+                }
+                canvas.csi(&data[start..end], data[end]);
+                i = end;
+            }
+            b'\r' => canvas.x = 0,
+            b'\n' => {
+                canvas.x = 0;
+                canvas.y += 1;
+            }
+            b'\t' => canvas.x = ((canvas.x / 8 + 1) * 8).min(width),
+            0x1b => {}
+            byte => canvas.put(oem_to_unicode(byte)),
+        }
+        i += 1;
+    }
+    canvas.into_art(sauce)
+}
+
+/// Splits the SAUCE record and its comment block off the end of the file.
+fn split_sauce(bytes: &[u8]) -> (&[u8], Option<Sauce>) {
+    let Some(start) = bytes.len().checked_sub(SAUCE_LEN) else {
+        return (bytes, None);
+    };
+    let record = &bytes[start..];
+    if &record[..7] != b"SAUCE00" {
+        return (bytes, None);
+    }
+    let text = |range: std::ops::Range<usize>| {
+        decode_oem(&record[range])
+            .trim_end_matches([' ', '\0'])
+            .to_string()
+    };
+    let word = |at: usize| u16::from_le_bytes([record[at], record[at + 1]]);
+    let comment_count = record[104] as usize;
+    let mut sauce = Sauce {
+        title: text(7..42),
+        author: text(42..62),
+        group: text(62..82),
+        date: text(82..90),
+        data_type: record[94],
+        file_type: record[95],
+        width: word(96),
+        height: word(98),
+        ice_colors: record[105] & 1 != 0,
+        font: text(106..128),
+        comments: Vec::new(),
+    };
+    let mut data_end = start;
+    let comments_len = 5 + comment_count * COMMENT_LEN;
+    if comment_count > 0 && start >= comments_len {
+        let block = &bytes[start - comments_len..start];
+        if &block[..5] == b"COMNT" {
+            sauce.comments = block[5..]
+                .chunks(COMMENT_LEN)
+                .map(|line| decode_oem(line).trim_end_matches([' ', '\0']).to_string())
+                .collect();
+            data_end = start - comments_len;
+        }
+    }
+    (&bytes[..data_end], Some(sauce))
+}
+
+/// Struct to hold the state of the `ANSI.SYS` emulation while decoding.
+struct Canvas {
+    width: usize,
+    ice: bool,
+    rows: Vec<Vec<ArtCell>>,
+    x: usize,
+    y: usize,
+    saved: (usize, usize),
+    fg: u8,
+    bg: u8,
+    bold: bool,
+    blink: bool,
+    reverse: bool,
+}
+
+impl Canvas {
+    fn new(width: usize, ice: bool) -> Self {
+        Canvas {
+            width,
+            ice,
+            rows: Vec::new(),
+            x: 0,
+            y: 0,
+            saved: (0, 0),
+            fg: 7,
+            bg: 0,
+            bold: false,
+            blink: false,
+            reverse: false,
+        }
+    }
+
+    fn row(&mut self, y: usize) -> &mut Vec<ArtCell> {
+        let width = self.width;
+        if self.rows.len() <= y {
+            self.rows
+                .resize_with(y + 1, || vec![ArtCell::default(); width]);
+        }
+        &mut self.rows[y]
+    }
+
+    fn put(&mut self, ch: char) {
+        // Wrapping is deferred to the next glyph, so a full line followed by CR LF doesn't
+        // leave an empty row behind.
+        if self.x >= self.width {
+            self.x = 0;
+            self.y += 1;
+        }
+        let mut fg = self.fg + if self.bold { 8 } else { 0 };
+        let mut bg = self.bg + if self.blink && self.ice { 8 } else { 0 };
+        if self.reverse {
+            std::mem::swap(&mut fg, &mut bg);
+        }
+        let cell = ArtCell {
+            ch: if ch == '\0' { ' ' } else { ch },
+            fg,
+            bg,
+            blink: self.blink && !self.ice,
+        };
+        let x = self.x;
+        let y = self.y;
+        self.row(y)[x] = cell;
+        self.x += 1;
+    }
+
+    fn csi(&mut self, params: &[u8], command: u8) {
+        let params = String::from_utf8_lossy(params);
+        // Private sequences like `?7h` don't draw anything.
+        if params.starts_with(['?', '=', '>']) {
+            return;
+        }
+        let values: Vec<usize> = params
+            .split(';')
+            .map(|value| value.parse().unwrap_or(0))
+            .collect();
+        let count = values.first().copied().unwrap_or(0).max(1);
+        match command {
+            b'm' => values.iter().for_each(|&value| self.sgr(value)),
+            b'H' | b'f' => {
+                self.y = values.first().copied().unwrap_or(1).max(1) - 1;
+                self.x = (values.get(1).copied().unwrap_or(1).max(1) - 1).min(self.width - 1);
+            }
+            b'A' => self.y = self.y.saturating_sub(count),
+            b'B' => self.y += count,
+            b'C' => self.x = (self.x + count).min(self.width - 1),
+            b'D' => self.x = self.x.min(self.width).saturating_sub(count),
+            b'J' if values[0] == 2 => {
+                self.rows.clear();
+                (self.x, self.y) = (0, 0);
+            }
+            b'K' => {
+                let (x, y) = (self.x.min(self.width), self.y);
+                self.row(y)[x..].fill(ArtCell::default());
+            }
+            b's' => self.saved = (self.x, self.y),
+            b'u' => (self.x, self.y) = self.saved,
+            _ => {}
+        }
+    }
+
+    fn sgr(&mut self, value: usize) {
+        match value {
+            0 => {
+                (self.fg, self.bg) = (7, 0);
+                (self.bold, self.blink, self.reverse) = (false, false, false);
+            }
+            1 => self.bold = true,
+            5 | 6 => self.blink = true,
+            7 => self.reverse = true,
+            22 => self.bold = false,
+            25 => self.blink = false,
+            27 => self.reverse = false,
+            30..=37 => self.fg = (value - 30) as u8,
+            39 => self.fg = 7,
+            40..=47 => self.bg = (value - 40) as u8,
+            49 => self.bg = 0,
+            90..=97 => {
+                self.fg = (value - 90) as u8;
+                self.bold = true;
+            }
+            100..=107 => {
+                self.bg = (value - 100) as u8;
+                self.blink = self.ice;
+            }
+            _ => {}
+        }
+    }
+
+    fn into_art(self, sauce: Option<Sauce>) -> Art {
+        let height = self.rows.len();
+        Art {
+            width: self.width,
+            height,
+            cells: self.rows.into_iter().flatten().collect(),
+            sauce,
+        }
+    }
+}
+
+impl Art {
+    /// The cell at a column and row, `None` outside the picture.
+    pub fn cell(&self, x: usize, y: usize) -> Option<&ArtCell> {
+        if x >= self.width {
+            return None;
+        }
+        self.cells.get(y * self.width + x)
+    }
+
+    /// This function renders the picture with 16-color SGR sequences, one line per row.
+    ///
+    /// ## Note:
+    /// - Colors are the legacy console palette, so the picture looks as intended with the
+    ///   default "Campbell" scheme, and follows the user's scheme otherwise.
+    /// - Each row ends with a reset, so a terminal narrower than the picture wraps the rows
+    ///   without bleeding colors.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::with_capacity(self.cells.len() * 2);
+        for row in self.cells.chunks(self.width.max(1)) {
+            let mut current = None;
+            for cell in row {
+                let key = (cell.fg, cell.bg, cell.blink);
+                if current != Some(key) {
+                    let fg = if cell.fg < 8 {
+                        30 + cell.fg
+                    } else {
+                        82 + cell.fg
+                    };
+                    let bg = if cell.bg < 8 {
+                        40 + cell.bg
+                    } else {
+                        92 + cell.bg
+                    };
+                    let blink = if cell.blink { ";5" } else { "" };
+                    let _ = write!(out, "\x1b[0;{};{}{}m", fg, bg, blink);
+                    current = Some(key);
+                }
+                out.push(cell.ch);
+            }
+            out.push_str("\x1b[0m\r\n");
+        }
+        out
+    }
+}
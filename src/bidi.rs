@@ -0,0 +1,174 @@
+use std::ops::Range;
+
+use windows_sys::Win32::Globalization::{
+    ScriptItemize, ScriptLayout, SCRIPT_CONTROL, SCRIPT_ITEM, SCRIPT_STATE,
+};
+
+use crate::measure::{cells, cells_exact};
+use crate::wrap;
+
+/// Enum to represent the base direction of a paragraph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Ltr, // Left to right, e.g. Latin, Cyrillic, CJK
+    Rtl, // Right to left, e.g. Hebrew, Arabic
+}
+
+/// Whether a character belongs to a right-to-left script (Hebrew, Arabic, Syriac, Thaana,
+/// N'Ko and their presentation forms).
+fn is_rtl(c: char) -> bool {
+    matches!(c as u32,
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF | 0x10800..=0x10FFF | 0x1E800..=0x1EFFF)
+}
+
+/// This function finds the base direction of a paragraph from its first strong character, as
+/// rules P2 and P3 of the Unicode Bidirectional Algorithm do. Text without any letter is
+/// left to right.
+pub fn base_direction(text: &str) -> Direction {
+    text.chars()
+        .find(|&c| c.is_alphabetic())
+        .map_or(Direction::Ltr, |c| {
+            if is_rtl(c) {
+                Direction::Rtl
+            } else {
+                Direction::Ltr
+            }
+        })
+}
+
+/// Splits a line into runs of one embedding level, with Uniscribe's implementation of the
+/// Unicode Bidirectional Algorithm. Returns byte ranges and levels in logical order.
+fn runs(text: &str, base: Direction) -> Option<Vec<(Range<usize>, u8)>> {
+    let units: Vec<u16> = text.encode_utf16().collect();
+    if units.is_empty() {
+        return Some(Vec::new());
+    }
+    let mut items: Vec<SCRIPT_ITEM> = vec![unsafe { std::mem::zeroed() }; units.len() + 1];
+    let control: SCRIPT_CONTROL = unsafe { std::mem::zeroed() };
+    // The low 5 bits of the state are the paragraph embedding level.
+    let state = SCRIPT_STATE {
+        _bitfield: (base == Direction::Rtl) as u16,
+    };
+    let mut count = 0;
+    let result = unsafe {
+        ScriptItemize(
+            units.as_ptr(),
+            units.len() as i32,
+            items.len() as i32,
+            &control,
+            &state,
+            items.as_mut_ptr(),
+            &mut count,
+        )
+    };
+    if result < 0 {
+        return None;
+    }
+    // Byte offset of every UTF-16 offset, plus the end of the text.
+    let mut byte_of = Vec::with_capacity(units.len() + 1);
+    for (start, c) in text.char_indices() {
+        byte_of.extend(std::iter::repeat_n(start, c.len_utf16()));
+    }
+    byte_of.push(text.len());
+    // Item `count` is a sentinel starting at the end of the text.
+    let runs = items[..count as usize + 1]
+        .windows(2)
+        .map(|pair| {
+            let start = byte_of[pair[0].iCharPos as usize];
+            let end = byte_of[pair[1].iCharPos as usize];
+            (start..end, (pair[0].a.s._bitfield & 0x1f) as u8)
+        })
+        .collect();
+    Some(runs)
+}
+
+/// The mirrored form of a paired punctuation mark, as drawn inside right-to-left runs.
+fn mirror(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        '‹' => '›',
+        '›' => '‹',
+        c => c,
+    }
+}
+
+/// This function reorders one line from logical (typed) order to visual (displayed) order,
+/// for hosts that draw cells left to right without reordering, like conhost.
+///
+/// ## Note:
+/// - The base direction is found with [`base_direction`]; right-to-left runs are reversed by
+///   cell, keeping combining marks on their base character, and their brackets mirrored.
+/// - Reordering never changes the width: `measure::cells` of the result is that of the
+///   input.
+/// - The line is returned unchanged if Uniscribe can't analyse it.
+pub fn visual(line: &str) -> String {
+    visual_with(line, base_direction(line))
+}
+
+fn visual_with(line: &str, base: Direction) -> String {
+    let Some(runs) = runs(line, base).filter(|runs| !runs.is_empty()) else {
+        return line.to_string();
+    };
+    let levels: Vec<u8> = runs.iter().map(|(_, level)| *level).collect();
+    let mut order = vec![0i32; runs.len()];
+    let result = unsafe {
+        ScriptLayout(
+            runs.len() as i32,
+            levels.as_ptr(),
+            order.as_mut_ptr(),
+            std::ptr::null_mut(),
+        )
+    };
+    if result < 0 {
+        return line.to_string();
+    }
+    let mut out = String::with_capacity(line.len());
+    for index in order {
+        let (range, level) = &runs[index as usize];
+        let run = &line[range.clone()];
+        if level % 2 == 0 {
+            out.push_str(run);
+            continue;
+        }
+        for span in cells_exact(run).iter().rev() {
+            let mut cluster = run[span.start..span.end].chars();
+            out.extend(cluster.next().map(mirror));
+            out.extend(cluster);
+        }
+    }
+    out
+}
+
+/// This function wraps text to `width` cells and returns the lines in visual order, right
+/// aligned for right-to-left paragraphs.
+///
+/// ## Note:
+/// - Lines are broken in logical order first, then each line is reordered, as the Unicode
+///   Bidirectional Algorithm requires, so a line never starts with the end of a sentence.
+/// - Lines break at spaces; a word wider than `width` is split between cells.
+/// - Every `\n` starts a paragraph with its own base direction.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let base = base_direction(paragraph);
+        for line in wrap::wrap(paragraph, width) {
+            let line = visual_with(&line, base);
+            let pad = width.saturating_sub(cells(&line));
+            lines.push(match base {
+                Direction::Rtl => format!("{}{}", " ".repeat(pad), line),
+                Direction::Ltr => line,
+            });
+        }
+    }
+    lines
+}
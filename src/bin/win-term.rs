@@ -0,0 +1,46 @@
+use std::process::{self, Command, ExitCode};
+
+const USAGE: &str = "usage: win-term <command>
+
+commands:
+    reset    restore sane console modes, attributes, cursor and code page
+    reset -- <program> [args...]
+             run a program and restore the console if it exits abnormally,
+             exiting with its exit code
+    debug    print what win-term detects about this console";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["reset", "--", program, ..] => {
+            let mut command = Command::new(program);
+            command.args(&args[3..]);
+            match win_term::run_with_reset(&mut command) {
+                Ok(status) => process::exit(status.code().unwrap_or(1)),
+                Err(e) => {
+                    eprintln!("win-term reset: {}: {}", program, e);
+                    ExitCode::from(127)
+                }
+            }
+        }
+        ["reset"] => match win_term::reset_terminal() {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("win-term reset: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        ["debug"] => match win_term::debug_banner() {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(_) => ExitCode::FAILURE,
+        },
+        ["-h" | "--help" | "help"] => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use std::io::Write;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::json::Json;
+use crate::metrics::{Metrics, POLL_INTERVAL};
+use crate::pipe::{pipe_path, poke, Pipe};
+
+/// How long `Drop` waits for the accept thread to listen again, between two pokes.
+const POKE_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Both events of `metrics`, one per line.
+fn both_events(metrics: &Metrics) -> String {
+    format!("{}\n{}\n", resize_event(metrics), metrics_event(metrics))
+}
+
+fn resize_event(metrics: &Metrics) -> Json {
+    Json::object([
+        ("event", "resize".into()),
+        ("columns", (metrics.columns as i64).into()),
+        ("rows", (metrics.rows as i64).into()),
+    ])
+}
+
+fn metrics_event(metrics: &Metrics) -> Json {
+    let measured = metrics.cell_width > 0 && metrics.cell_height > 0;
+    Json::object([
+        ("event", "metrics".into()),
+        (
+            "cell_width",
+            measured.then_some(metrics.cell_width as i64).into(),
+        ),
+        (
+            "cell_height",
+            measured.then_some(metrics.cell_height as i64).into(),
+        ),
+        ("dpi", (metrics.dpi as i64).into()),
+    ])
+}
+
+/// The events telling a client that saw `seen` (nothing yet with `None`) about `now`.
+fn events(seen: Option<Metrics>, now: &Metrics) -> String {
+    let Some(last) = seen else {
+        return both_events(now);
+    };
+    let mut events = String::new();
+    if (last.columns, last.rows) != (now.columns, now.rows) {
+        events.push_str(&format!("{}\n", resize_event(now)));
+    }
+    if (last.cell_width, last.cell_height, last.dpi) != (now.cell_width, now.cell_height, now.dpi) {
+        events.push_str(&format!("{}\n", metrics_event(now)));
+    }
+    events
+}
+
+/// Struct to hold a connected client and the metrics it was last told about.
+struct Client {
+    pipe: Pipe,
+    seen: Option<Metrics>, // `None` until it is greeted
+}
+
+/// Struct to hold what the accept and poll threads share.
+struct State {
+    clients: Vec<Client>,
+    stop: bool,
+}
+
+/// Struct to hold a running broadcast service, stopped when dropped.
+///
+/// Every client of the pipe receives one JSON object per line:
+/// - `{"event": "resize", "columns", "rows"}` when the visible window changes size in cells.
+/// - `{"event": "metrics", "cell_width", "cell_height", "dpi"}` when the font cell or the DPI
+///   changes, the cell members being `null` when the font can't be measured.
+///
+/// A client gets both events with the current values as soon as it connects, so it never
+/// has to query the geometry itself. Clients that close their end are dropped.
+pub struct Broadcast {
+    shared: Arc<(Mutex<State>, Condvar)>,
+    path: Vec<u16>,
+    accept: Option<JoinHandle<()>>,
+    poll: Option<JoinHandle<()>>,
+}
+
+/// This function starts publishing the console geometry on the named pipe
+/// `\\.\pipe\<pipe_name>`, for any number of clients.
+///
+/// ## Returns:
+/// - `Ok(Broadcast)` once the pipe is created; dropping it disconnects the clients.
+/// - `Err(io::Error)` if the pipe can't be created, e.g. with `PermissionDenied` when
+///   another server, of this process or another one, already owns the name.
+///
+/// ## Note:
+/// - The geometry is sampled every 250 ms from `CONOUT$`, so redirected standard handles
+///   don't matter.
+/// - Writes never wait for a client: one that stops reading and lets its pipe buffer fill
+///   up is disconnected, so it can't hold up the others.
+/// - Only the current user can connect, and only from this machine.
+pub fn serve(pipe_name: &str) -> std::io::Result<Broadcast> {
+    let path = pipe_path(pipe_name);
+    // The first instance is created here so a taken name is reported to the caller.
+    let first = Pipe::create(&path, true)?;
+    let shared = Arc::new((
+        Mutex::new(State {
+            clients: Vec::new(),
+            stop: false,
+        }),
+        Condvar::new(),
+    ));
+    let accept = {
+        let shared = Arc::clone(&shared);
+        let path = path.clone();
+        thread::Builder::new()
+            .name("win-term broadcast accept".to_string())
+            .spawn(move || accept(&shared, &path, first))
+            .ok()
+    };
+    let poll = {
+        let shared = Arc::clone(&shared);
+        thread::Builder::new()
+            .name("win-term broadcast poll".to_string())
+            .spawn(move || poll(&shared))
+            .ok()
+    };
+    Ok(Broadcast {
+        shared,
+        path,
+        accept,
+        poll,
+    })
+}
+
+impl Broadcast {
+    /// The number of clients currently connected.
+    pub fn clients(&self) -> usize {
+        self.shared
+            .0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clients
+            .len()
+    }
+}
+
+/// Hands every client that connects over to the poll thread, which greets it.
+fn accept(shared: &(Mutex<State>, Condvar), path: &[u16], first: Pipe) {
+    let (lock, wake) = shared;
+    let mut pipe = first;
+    loop {
+        if pipe.connect().is_err() {
+            return;
+        }
+        let mut state = lock.lock().unwrap_or_else(|e| e.into_inner());
+        if state.stop {
+            return;
+        }
+        if pipe.set_nonblocking().is_ok() {
+            state.clients.push(Client { pipe, seen: None });
+            wake.notify_all();
+        }
+        drop(state);
+        pipe = match Pipe::create(path, false) {
+            Ok(pipe) => pipe,
+            Err(_) => return,
+        };
+    }
+}
+
+/// Samples the geometry and tells every client what changed since it was last told, without
+/// holding the lock while writing.
+fn poll(shared: &(Mutex<State>, Condvar)) {
+    let (lock, wake) = shared;
+    let mut state = lock.lock().unwrap_or_else(|e| e.into_inner());
+    loop {
+        // A client that just connected is greeted right away.
+        if !state.stop && state.clients.iter().all(|client| client.seen.is_some()) {
+            state = wake
+                .wait_timeout(state, POLL_INTERVAL)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+        if state.stop {
+            return;
+        }
+        let mut clients = std::mem::take(&mut state.clients);
+        drop(state);
+        if let Some(metrics) = Metrics::current() {
+            let mut kept = Vec::with_capacity(clients.len());
+            for mut client in clients {
+                let events = events(client.seen, &metrics);
+                if events.is_empty() || (&client.pipe).write_all(events.as_bytes()).is_ok() {
+                    client.seen = Some(metrics);
+                    kept.push(client);
+                } else {
+                    client.pipe.discard();
+                }
+            }
+            clients = kept;
+        }
+        state = lock.lock().unwrap_or_else(|e| e.into_inner());
+        // Clients accepted meanwhile come after the ones already there.
+        clients.append(&mut state.clients);
+        state.clients = clients;
+    }
+}
+
+impl Drop for Broadcast {
+    fn drop(&mut self) {
+        let (lock, wake) = &*self.shared;
+        lock.lock().unwrap_or_else(|e| e.into_inner()).stop = true;
+        wake.notify_all();
+        // The accept thread is blocked waiting for a client: be that client, again if it was
+        // between two instances (or took a client's connection) and didn't see `stop` yet.
+        if let Some(accept) = self.accept.take() {
+            while !accept.is_finished() {
+                poke(&self.path, POKE_TIMEOUT);
+            }
+            let _ = accept.join();
+        }
+        if let Some(poll) = self.poll.take() {
+            let _ = poll.join();
+        }
+        let clients = std::mem::take(&mut lock.lock().unwrap_or_else(|e| e.into_inner()).clients);
+        for client in clients {
+            client.pipe.discard();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(columns: i32, cell_width: i32) -> Metrics {
+        Metrics {
+            columns,
+            rows: 30,
+            cell_width,
+            cell_height: 16,
+            dpi: 96,
+        }
+    }
+
+    fn kinds(events: &str) -> Vec<Option<String>> {
+        events
+            .lines()
+            .map(|line| Json::parse(line)?.get("event")?.as_str().map(String::from))
+            .collect()
+    }
+
+    #[test]
+    fn tells_each_client_what_it_missed() {
+        let now = metrics(120, 8);
+        let both = [Some("resize".into()), Some("metrics".into())];
+        assert_eq!(kinds(&events(None, &now)), both);
+        assert_eq!(events(Some(now), &now), "");
+        assert_eq!(
+            kinds(&events(Some(metrics(100, 8)), &now)),
+            [Some("resize".into())]
+        );
+        assert_eq!(
+            kinds(&events(Some(metrics(120, 9)), &now)),
+            [Some("metrics".into())]
+        );
+        assert_eq!(kinds(&events(Some(metrics(100, 9)), &now)), both);
+    }
+}
@@ -0,0 +1,150 @@
+use std::io;
+
+use windows_sys::Win32::System::Console::{
+    SetConsoleScreenBufferSize, SetConsoleWindowInfo, CONSOLE_SCREEN_BUFFER_INFO, COORD,
+    SMALL_RECT, STD_OUTPUT_HANDLE,
+};
+
+use crate::console::{screen_buffer_info, std_handle};
+use crate::{TerminalCells, TerminalError};
+
+/// Struct to hold a rectangle of cells of the screen buffer, `right` and `bottom` included as
+/// the console reports it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CellRect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl CellRect {
+    /// Number of columns.
+    pub fn columns(&self) -> i32 {
+        self.right - self.left + 1
+    }
+
+    /// Number of rows.
+    pub fn rows(&self) -> i32 {
+        self.bottom - self.top + 1
+    }
+}
+
+/// Struct to hold everything `GetConsoleScreenBufferInfo` reports about a screen buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenBufferInfo {
+    pub size: TerminalCells,       // Whole buffer, scrollback included (`dwSize`)
+    pub cursor: (i32, i32),        // Cursor column and row in the buffer
+    pub attributes: u16,           // Legacy attributes new text is written with
+    pub window: CellRect,          // Visible part of the buffer (`srWindow`)
+    pub max_window: TerminalCells, // Largest window for this buffer, font and screen
+}
+
+impl ScreenBufferInfo {
+    /// This function reads the screen buffer of the standard output.
+    ///
+    /// ## Returns:
+    /// - `Ok(ScreenBufferInfo)` from a single `GetConsoleScreenBufferInfo`.
+    /// - `Err(TerminalError)` if there's no standard handle or it isn't a console.
+    ///
+    /// ## Note:
+    /// - `max_window` is bounded by the buffer size as well as by the screen: after
+    ///   [`set_size`] it can grow, up to what fits on the screen with the current font.
+    /// - `attributes` holds the colors as indices of the console color table, with SGR red and
+    ///   blue swapped; see [`crate::style::Attributes::legacy`] for the other bits.
+    pub fn current() -> Result<ScreenBufferInfo, TerminalError> {
+        Ok(screen_buffer_info(std_handle(STD_OUTPUT_HANDLE)?)?.into())
+    }
+}
+
+impl From<CONSOLE_SCREEN_BUFFER_INFO> for ScreenBufferInfo {
+    /// Decodes the raw info, e.g. from [`crate::sys::screen_buffer_info`] on another handle.
+    fn from(info: CONSOLE_SCREEN_BUFFER_INFO) -> Self {
+        let window = info.srWindow;
+        ScreenBufferInfo {
+            size: TerminalCells {
+                columns: info.dwSize.X as i32,
+                rows: info.dwSize.Y as i32,
+            },
+            cursor: (
+                info.dwCursorPosition.X as i32,
+                info.dwCursorPosition.Y as i32,
+            ),
+            attributes: info.wAttributes,
+            window: CellRect {
+                left: window.Left as i32,
+                top: window.Top as i32,
+                right: window.Right as i32,
+                bottom: window.Bottom as i32,
+            },
+            max_window: TerminalCells {
+                columns: info.dwMaximumWindowSize.X as i32,
+                rows: info.dwMaximumWindowSize.Y as i32,
+            },
+        }
+    }
+}
+
+/// This function reads the size of the screen buffer of the standard output.
+///
+/// ## Returns:
+/// - `Ok(TerminalCells)` with the columns and rows of the buffer, scrollback included: the
+///   rows are how many lines the user can scroll back through, plus the visible ones.
+/// - `Err(TerminalError)` if there's no standard handle or it isn't a console.
+pub fn size() -> Result<TerminalCells, TerminalError> {
+    Ok(ScreenBufferInfo::current()?.size)
+}
+
+/// This function resizes the screen buffer of the standard output, e.g. to 10 000 rows for a
+/// long scrollback.
+///
+/// ## Returns:
+/// - `Err(io::Error)` with `InvalidInput` for a size below 1 x 1.
+/// - `Err(io::Error)` without a console, or if the console refuses the size (a buffer
+///   narrower than the smallest window, or too large for the memory of the host).
+///
+/// ## Note:
+/// - The window must always fit in the buffer: when the new buffer is smaller, the window
+///   shrinks and moves inside it first.
+/// - Rows cut off at the bottom, and columns at the right, are lost. Lines in the scrollback
+///   stay where they are; a longer buffer only adds room below them.
+/// - Pseudo console hosts (Windows Terminal) keep their own scrollback, and give the console
+///   a buffer as high as the window; they may ignore a larger one.
+pub fn set_size(columns: i16, rows: i16) -> io::Result<()> {
+    if columns < 1 || rows < 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "a screen buffer is at least 1x1 cells",
+        ));
+    }
+    let handle = std_handle(STD_OUTPUT_HANDLE)?;
+    let window = screen_buffer_info(handle)?.srWindow;
+    let width = (window.Right - window.Left + 1).min(columns);
+    let height = (window.Bottom - window.Top + 1).min(rows);
+    let left = window.Left.min(columns - width);
+    let top = window.Top.min(rows - height);
+    let fitted = SMALL_RECT {
+        Left: left,
+        Top: top,
+        Right: left + width - 1,
+        Bottom: top + height - 1,
+    };
+    let moved = (fitted.Left, fitted.Top, fitted.Right, fitted.Bottom)
+        != (window.Left, window.Top, window.Right, window.Bottom);
+    unsafe {
+        if moved && SetConsoleWindowInfo(handle, 1, &fitted) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if SetConsoleScreenBufferSize(
+            handle,
+            COORD {
+                X: columns,
+                Y: rows,
+            },
+        ) == 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,244 @@
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::cell_size_from;
+use crate::console::{console_dpi, console_output, screen_buffer_info};
+#[cfg(feature = "input")]
+use crate::events::{DpiWatcher, ResizeWatcher};
+
+/// Struct to hold the geometry of the console: the visible window, the cell size and the DPI.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Metrics {
+    pub columns: i32,     // Visible window width in cells
+    pub rows: i32,        // Visible window height in cells
+    pub cell_width: i32,  // Cell width in pixels, 0 if the font can't be measured
+    pub cell_height: i32, // Cell height in pixels, 0 if the font can't be measured
+    pub dpi: u32,         // DPI of the console window
+}
+
+impl Metrics {
+    /// This function samples the geometry of the attached console, through `CONOUT$` so that
+    /// redirected standard handles don't matter. `None` without a console.
+    pub fn current() -> Option<Metrics> {
+        let handle = console_output()?;
+        let window = screen_buffer_info(handle).ok()?.srWindow;
+        let cell = cell_size_from(handle, true).ok();
+        Some(Metrics {
+            columns: (window.Right - window.Left + 1) as i32,
+            rows: (window.Bottom - window.Top + 1) as i32,
+            cell_width: cell.as_ref().map_or(0, |size| size.width),
+            cell_height: cell.as_ref().map_or(0, |size| size.height),
+            dpi: console_dpi(),
+        })
+    }
+}
+
+/// Bumped when the crate changes the console metrics itself, see [`invalidate_all`].
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Forgets the metrics of every [`CachedMetrics`], after a change made through this crate
+/// (`font::set`) that no watcher reports.
+pub(crate) fn invalidate_all() {
+    GENERATION.fetch_add(1, Ordering::AcqRel);
+}
+
+/// Struct to hold one sample of the metrics.
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    metrics: Metrics,
+    at: Instant,            // When it was sampled
+    generation: (u64, u64), // Generations it was sampled in, see `CacheState::generation`
+}
+
+/// Struct to hold what a [`CachedMetrics`] shares with its refresh thread and its watchers.
+#[derive(Debug)]
+struct CacheState {
+    sample: Option<Sample>,
+    invalidations: u64, // Calls to `CachedMetrics::invalidate`
+    refresh: bool,      // Whether the refresh thread was asked for a sample
+    stopped: bool,
+}
+
+impl CacheState {
+    /// The generation of the cache: its own invalidations, and the ones of every cache.
+    fn generation(&self) -> (u64, u64) {
+        (self.invalidations, GENERATION.load(Ordering::Acquire))
+    }
+
+    /// The last sample, unless it was invalidated since.
+    fn valid(&self) -> Option<Sample> {
+        self.sample
+            .filter(|sample| sample.generation == self.generation())
+    }
+}
+
+/// Measures the metrics and stores them, unless the cache was invalidated while they were
+/// measured: the sample is swapped in only if the generation is still the one read before.
+fn sample(shared: &(Mutex<CacheState>, Condvar)) -> Option<Metrics> {
+    let lock = &shared.0;
+    let generation = lock.lock().unwrap_or_else(|e| e.into_inner()).generation();
+    let metrics = Metrics::current();
+    let mut state = lock.lock().unwrap_or_else(|e| e.into_inner());
+    if state.generation() == generation {
+        state.sample = metrics.map(|metrics| Sample {
+            metrics,
+            at: Instant::now(),
+            generation,
+        });
+    }
+    metrics
+}
+
+/// Struct to hold the console [`Metrics`] cached for a while, for callers asking in hot paths
+/// (formatting every log line to the width of the window), stopped when dropped.
+#[derive(Debug)]
+pub struct CachedMetrics {
+    ttl: Duration,
+    shared: Arc<(Mutex<CacheState>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+    #[cfg(feature = "input")]
+    _resize: Option<ResizeWatcher>,
+    #[cfg(feature = "input")]
+    _dpi: Option<DpiWatcher>,
+}
+
+impl CachedMetrics {
+    /// This function starts a cache of the console metrics, sampled once now.
+    ///
+    /// ## Returns:
+    /// - `Ok(CachedMetrics)` once its refresh thread runs.
+    /// - `Err(io::Error)` if the thread can't be started.
+    ///
+    /// ## Note:
+    /// - Older than `ttl`, the metrics are still served while a background thread samples them
+    ///   again, so [`CachedMetrics::get`] never waits on the console after the first sample.
+    /// - With the `input` feature, resizes and DPI changes replace the metrics right away,
+    ///   through a `ResizeWatcher` and a `DpiWatcher`; without it, or without a console input
+    ///   or window, the cache relies on `ttl` alone. The resize watcher sets
+    ///   `ENABLE_WINDOW_INPUT` and reads the resize records at the front of the input queue.
+    /// - [`font::set`](crate::font::set) invalidates every cache by itself.
+    pub fn new(ttl: Duration) -> io::Result<CachedMetrics> {
+        let shared = Arc::new((
+            Mutex::new(CacheState {
+                sample: None,
+                invalidations: 0,
+                refresh: false,
+                stopped: false,
+            }),
+            Condvar::new(),
+        ));
+        sample(&shared);
+        let thread = {
+            let shared = Arc::clone(&shared);
+            thread::Builder::new()
+                .name("win-term metrics cache".to_string())
+                .spawn(move || {
+                    let (lock, wake) = &*shared;
+                    loop {
+                        let mut state = lock.lock().unwrap_or_else(|e| e.into_inner());
+                        while !state.refresh && !state.stopped {
+                            state = wake.wait(state).unwrap_or_else(|e| e.into_inner());
+                        }
+                        if state.stopped {
+                            return;
+                        }
+                        drop(state);
+                        sample(&shared);
+                        lock.lock().unwrap_or_else(|e| e.into_inner()).refresh = false;
+                    }
+                })?
+        };
+        #[cfg(feature = "input")]
+        let (resized, dpi_changed) = (Arc::clone(&shared), Arc::clone(&shared));
+        Ok(CachedMetrics {
+            ttl,
+            shared,
+            thread: Some(thread),
+            #[cfg(feature = "input")]
+            _resize: ResizeWatcher::start(move |_| {
+                sample(&resized);
+            })
+            .ok(),
+            #[cfg(feature = "input")]
+            _dpi: DpiWatcher::start(move |_| {
+                sample(&dpi_changed);
+            })
+            .ok(),
+        })
+    }
+
+    /// This function returns the cached metrics, asking for a new sample in the background
+    /// once they are older than the TTL.
+    ///
+    /// ## Returns:
+    /// - `Some(Metrics)` from the cache, measured right away only when there is none (the first
+    ///   sample failed, or after [`CachedMetrics::invalidate`]).
+    /// - `None` without a console.
+    pub fn get(&self) -> Option<Metrics> {
+        let (lock, wake) = &*self.shared;
+        let mut state = lock.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(sample) = state.valid() {
+            if sample.at.elapsed() >= self.ttl && !state.refresh {
+                state.refresh = true;
+                wake.notify_all();
+            }
+            return Some(sample.metrics);
+        }
+        drop(state);
+        sample(&self.shared)
+    }
+
+    /// This function forgets the cached metrics, so the next [`CachedMetrics::get`] measures
+    /// them again, e.g. after the program changed the window itself.
+    ///
+    /// ## Note:
+    /// - A sample being measured meanwhile, by the refresh thread or a watcher, is dropped
+    ///   rather than stored over the invalidation.
+    pub fn invalidate(&self) {
+        let mut state = self.shared.0.lock().unwrap_or_else(|e| e.into_inner());
+        state.invalidations += 1;
+        state.sample = None;
+    }
+}
+
+impl Drop for CachedMetrics {
+    fn drop(&mut self) {
+        let (lock, wake) = &*self.shared;
+        lock.lock().unwrap_or_else(|e| e.into_inner()).stopped = true;
+        wake.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalidations_outdate_samples() {
+        let mut state = CacheState {
+            sample: None,
+            invalidations: 0,
+            refresh: false,
+            stopped: false,
+        };
+        let measured = |state: &CacheState| Sample {
+            metrics: Metrics::default(),
+            at: Instant::now(),
+            generation: state.generation(),
+        };
+        state.sample = Some(measured(&state));
+        assert!(state.valid().is_some());
+        state.invalidations += 1;
+        assert!(state.valid().is_none());
+        state.sample = Some(measured(&state));
+        invalidate_all();
+        assert!(state.valid().is_none());
+    }
+}
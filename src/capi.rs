@@ -0,0 +1,263 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+
+use crate::{
+    get_size_of_the_font, get_size_of_the_terminal, get_terminal_cells, viewport_size_px, FontSize,
+    TerminalCells, TerminalError, TerminalSize,
+};
+
+/// Version of the layout of the structs and of the calling convention below; bumped on any
+/// incompatible change, so bindings can refuse a DLL they weren't written for.
+pub const WT_ABI_VERSION: u32 = 1;
+
+/// Status returned by every function, `WT_OK` or one per [`TerminalError`] variant.
+pub const WT_OK: i32 = 0;
+pub const WT_NO_STD_HANDLE: i32 = 1;
+pub const WT_NO_SCREEN_BUFFER_INFO: i32 = 2;
+pub const WT_UNSUPPORTED_DPI: i32 = 3;
+pub const WT_NO_FONT_INFO: i32 = 4;
+pub const WT_NOT_A_CONSOLE: i32 = 5;
+pub const WT_INVALID_FONT: i32 = 6;
+pub const WT_FONT_NOT_SET: i32 = 7;
+pub const WT_FULLSCREEN_UNSUPPORTED: i32 = 8;
+/// A required pointer argument was null.
+pub const WT_NULL_ARGUMENT: i32 = -1;
+
+/// Struct to hold a size in pixels, `{ int32_t width; int32_t height; }` in C.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WtSize {
+    pub width: i32,  // Width in pixels
+    pub height: i32, // Height in pixels
+}
+
+/// Struct to hold a size in cells, `{ int32_t columns; int32_t rows; }` in C.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WtCells {
+    pub columns: i32, // Number of columns
+    pub rows: i32,    // Number of rows
+}
+
+impl From<FontSize> for WtSize {
+    fn from(size: FontSize) -> Self {
+        WtSize {
+            width: size.width,
+            height: size.height,
+        }
+    }
+}
+
+impl From<TerminalSize> for WtSize {
+    fn from(size: TerminalSize) -> Self {
+        WtSize {
+            width: size.width,
+            height: size.height,
+        }
+    }
+}
+
+impl From<TerminalCells> for WtCells {
+    fn from(cells: TerminalCells) -> Self {
+        WtCells {
+            columns: cells.columns,
+            rows: cells.rows,
+        }
+    }
+}
+
+thread_local! {
+    // Message of the last failure on this thread, for `wt_last_error`.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn status(error: &TerminalError) -> i32 {
+    match error {
+        TerminalError::NoStdHandle(_) => WT_NO_STD_HANDLE,
+        TerminalError::NoScreenBufferInfo(_) => WT_NO_SCREEN_BUFFER_INFO,
+        TerminalError::UnsupportedDpi => WT_UNSUPPORTED_DPI,
+        TerminalError::NoFontInfo(_) => WT_NO_FONT_INFO,
+        TerminalError::NotAConsole => WT_NOT_A_CONSOLE,
+        TerminalError::InvalidFont => WT_INVALID_FONT,
+        TerminalError::FontNotSet(_) => WT_FONT_NOT_SET,
+        TerminalError::FullscreenUnsupported => WT_FULLSCREEN_UNSUPPORTED,
+    }
+}
+
+fn set_last_error(message: Option<String>) {
+    // Display strings never contain NUL, but an error path shouldn't panic across the ABI.
+    let message = message.map(|message| CString::new(message.replace('\0', " ")).unwrap());
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+/// Writes the result to `out` and returns its status, recording the error message.
+fn complete<T, U: From<T>>(out: *mut U, result: Result<T, TerminalError>) -> i32 {
+    if out.is_null() {
+        set_last_error(Some("null output pointer".to_string()));
+        return WT_NULL_ARGUMENT;
+    }
+    match result {
+        Ok(value) => {
+            unsafe { out.write(U::from(value)) };
+            set_last_error(None);
+            WT_OK
+        }
+        Err(e) => {
+            set_last_error(Some(e.to_string()));
+            status(&e)
+        }
+    }
+}
+
+/// This function returns [`WT_ABI_VERSION`].
+#[no_mangle]
+pub extern "system" fn wt_abi_version() -> u32 {
+    WT_ABI_VERSION
+}
+
+/// This function writes the cell size of the console font in pixels to `out`, see
+/// [`get_size_of_the_font`].
+///
+/// ## Returns:
+/// - `WT_OK`, or the status of the failure, `out` left untouched then.
+///
+/// # Safety
+/// - `out` must be null or point to a writable `WtSize`.
+#[no_mangle]
+pub unsafe extern "system" fn wt_font_size(out: *mut WtSize) -> i32 {
+    complete(out, get_size_of_the_font())
+}
+
+/// This function writes the size of the screen buffer in pixels to `out`, see
+/// [`get_size_of_the_terminal`] and [`wt_font_size`].
+///
+/// # Safety
+/// - `out` must be null or point to a writable `WtSize`.
+#[no_mangle]
+pub unsafe extern "system" fn wt_terminal_size(out: *mut WtSize) -> i32 {
+    complete(out, get_size_of_the_terminal())
+}
+
+/// This function writes the size of the visible window in pixels to `out`, see
+/// [`viewport_size_px`] and [`wt_font_size`].
+///
+/// # Safety
+/// - `out` must be null or point to a writable `WtSize`.
+#[no_mangle]
+pub unsafe extern "system" fn wt_viewport_size(out: *mut WtSize) -> i32 {
+    complete(out, viewport_size_px())
+}
+
+/// This function writes the size of the visible window in cells to `out`, see
+/// [`get_terminal_cells`] and [`wt_font_size`].
+///
+/// # Safety
+/// - `out` must be null or point to a writable `WtCells`.
+#[no_mangle]
+pub unsafe extern "system" fn wt_terminal_cells(out: *mut WtCells) -> i32 {
+    complete(out, get_terminal_cells())
+}
+
+/// This function returns the message of the last failed call on this thread, as UTF-8
+/// (`Marshal.PtrToStringUTF8` in .NET).
+///
+/// ## Returns:
+/// - The message, valid until the next call of this library on the same thread; it is owned
+///   by the library and must not be freed.
+/// - Null if the last call succeeded.
+#[no_mangle]
+pub extern "system" fn wt_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// This function returns a static description of a status, as UTF-8, never null.
+#[no_mangle]
+pub extern "system" fn wt_status_message(status: i32) -> *const c_char {
+    let message: &'static [u8] = match status {
+        WT_OK => b"ok\0",
+        WT_NO_STD_HANDLE => b"no console standard handle\0",
+        WT_NO_SCREEN_BUFFER_INFO => b"can't read the console screen buffer\0",
+        WT_UNSUPPORTED_DPI => b"can't read the DPI of the console window\0",
+        WT_NO_FONT_INFO => b"can't read the console font\0",
+        WT_NOT_A_CONSOLE => b"not a console (redirected to a file or a pipe)\0",
+        WT_INVALID_FONT => b"invalid console font face or size\0",
+        WT_FONT_NOT_SET => b"can't change the console font\0",
+        WT_FULLSCREEN_UNSUPPORTED => b"the console host has no fullscreen mode\0",
+        WT_NULL_ARGUMENT => b"null argument\0",
+        _ => b"unknown status\0",
+    };
+    message.as_ptr().cast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn layout_is_stable() {
+        assert_eq!(std::mem::size_of::<WtSize>(), 8);
+        assert_eq!(std::mem::align_of::<WtSize>(), 4);
+        assert_eq!(std::mem::size_of::<WtCells>(), 8);
+        assert_eq!(wt_abi_version(), 1);
+    }
+
+    #[test]
+    fn statuses_match_errors() {
+        let errors = [
+            TerminalError::NoStdHandle(6),
+            TerminalError::NoScreenBufferInfo(0),
+            TerminalError::UnsupportedDpi,
+            TerminalError::NoFontInfo(0),
+            TerminalError::NotAConsole,
+            TerminalError::InvalidFont,
+            TerminalError::FontNotSet(0),
+            TerminalError::FullscreenUnsupported,
+        ];
+        for (i, error) in errors.iter().enumerate() {
+            assert_eq!(status(error), i as i32 + 1);
+            let message = unsafe { CStr::from_ptr(wt_status_message(status(error))) };
+            // The static messages are the Display ones, without the OS error.
+            assert!(error.to_string().starts_with(message.to_str().unwrap()));
+        }
+        let unknown = unsafe { CStr::from_ptr(wt_status_message(42)) };
+        assert_eq!(unknown.to_str(), Ok("unknown status"));
+    }
+
+    #[test]
+    fn last_error_follows_calls() {
+        let mut size = WtSize::default();
+        assert_eq!(
+            complete(&mut size, Err::<FontSize, _>(TerminalError::NotAConsole)),
+            WT_NOT_A_CONSOLE
+        );
+        let message = unsafe { CStr::from_ptr(wt_last_error()) };
+        assert_eq!(
+            message.to_str(),
+            Ok("not a console (redirected to a file or a pipe)")
+        );
+        assert_eq!(size, WtSize::default());
+        let cell = FontSize {
+            width: 8,
+            height: 16,
+        };
+        assert_eq!(complete(&mut size, Ok(cell)), WT_OK);
+        assert_eq!(
+            size,
+            WtSize {
+                width: 8,
+                height: 16
+            }
+        );
+        assert!(wt_last_error().is_null());
+        assert_eq!(
+            complete(std::ptr::null_mut::<WtSize>(), Ok(cell)),
+            WT_NULL_ARGUMENT
+        );
+        assert!(!wt_last_error().is_null());
+    }
+}
@@ -0,0 +1,141 @@
+use windows_sys::Win32::Foundation::RECT;
+use windows_sys::Win32::Graphics::Gdi::{
+    BitBlt, CreateCompatibleDC, CreateDIBSection, DeleteDC, DeleteObject, GdiFlush, GetDC,
+    ReleaseDC, SelectObject, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, SRCCOPY,
+};
+use windows_sys::Win32::System::Console::GetConsoleWindow;
+use windows_sys::Win32::UI::WindowsAndMessaging::GetClientRect;
+
+use crate::console::console_output;
+use crate::export::encode_png;
+use crate::image::{Rect, Rgba};
+use crate::{cell_size_from, last_error_code, TerminalError};
+
+/// Struct to hold pixels captured from the console window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screenshot {
+    pub width: u32,      // Width in pixels
+    pub height: u32,     // Height in pixels
+    pub pixels: Vec<u8>, // RGBA8 pixels, rows top to bottom, alpha always 255
+}
+
+impl Screenshot {
+    /// The pixels as an image, e.g. to render them back with [`crate::image::render_blocks`].
+    pub fn as_rgba(&self) -> Rgba<'_> {
+        Rgba {
+            pixels: &self.pixels,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Encodes the screenshot as an 8-bit RGB PNG.
+    pub fn to_png(&self) -> Vec<u8> {
+        let rgb: Vec<u8> = self
+            .pixels
+            .chunks_exact(4)
+            .flat_map(|rgba| [rgba[0], rgba[1], rgba[2]])
+            .collect();
+        encode_png(self.width, self.height, &rgb)
+    }
+}
+
+/// This function screenshots the pixels covered by a rectangle of cells of the visible
+/// window, e.g. so a visual regression test compares only the widget under test.
+///
+/// ## Returns:
+/// - `Ok(Screenshot)` of `rect.columns * cell width` by `rect.rows * cell height` pixels,
+///   clipped to the client area of the console window.
+/// - `Err(TerminalError::NoStdHandle(_))` without a console window.
+/// - `Err(TerminalError::NoScreenBufferInfo(_))` if the pixels can't be copied.
+/// - The errors of [`crate::get_size_of_the_font`] if the cell size is unknown.
+///
+/// ## Note:
+/// - Cell `(0, 0)` is the top left cell of the visible window, at the top left of the client
+///   area.
+/// - The pixels are copied from the screen, so the console window must be visible and not
+///   covered by other windows. Under Windows Terminal the console window is a hidden
+///   pseudo-window and there are no pixels to copy.
+pub fn cells_image(rect: Rect) -> Result<Screenshot, TerminalError> {
+    let cell = cell_size_from(
+        console_output().ok_or_else(|| TerminalError::NoStdHandle(last_error_code()))?,
+        true,
+    )?;
+    unsafe {
+        let window = GetConsoleWindow();
+        if window.is_null() {
+            return Err(TerminalError::NoStdHandle(last_error_code()));
+        }
+        let mut client = RECT {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        };
+        if GetClientRect(window, &mut client) == 0 {
+            return Err(TerminalError::NoScreenBufferInfo(last_error_code()));
+        }
+        let left = (rect.left as i32 * cell.width).min(client.right);
+        let top = (rect.top as i32 * cell.height).min(client.bottom);
+        let width = (rect.columns as i32 * cell.width).min(client.right - left);
+        let height = (rect.rows as i32 * cell.height).min(client.bottom - top);
+        if width <= 0 || height <= 0 {
+            return Ok(Screenshot {
+                width: 0,
+                height: 0,
+                pixels: Vec::new(),
+            });
+        }
+
+        let source = GetDC(window);
+        if source.is_null() {
+            return Err(TerminalError::NoScreenBufferInfo(last_error_code()));
+        }
+        let dc = CreateCompatibleDC(source);
+        let mut info: BITMAPINFO = std::mem::zeroed();
+        info.bmiHeader = BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width,
+            biHeight: -height, // Top-down rows
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB,
+            ..std::mem::zeroed()
+        };
+        let mut bits = std::ptr::null_mut();
+        let bitmap = CreateDIBSection(
+            dc,
+            &info,
+            DIB_RGB_COLORS,
+            &mut bits,
+            std::ptr::null_mut(),
+            0,
+        );
+        let mut result = Err(TerminalError::NoScreenBufferInfo(last_error_code()));
+        if !dc.is_null() && !bitmap.is_null() && !bits.is_null() {
+            let previous = SelectObject(dc, bitmap);
+            if BitBlt(dc, 0, 0, width, height, source, left, top, SRCCOPY) != 0 {
+                GdiFlush();
+                let bgrx =
+                    std::slice::from_raw_parts(bits as *const u8, (width * height * 4) as usize);
+                result = Ok(Screenshot {
+                    width: width as u32,
+                    height: height as u32,
+                    pixels: bgrx
+                        .chunks_exact(4)
+                        .flat_map(|bgrx| [bgrx[2], bgrx[1], bgrx[0], 255])
+                        .collect(),
+                });
+            }
+            SelectObject(dc, previous);
+        }
+        if !bitmap.is_null() {
+            DeleteObject(bitmap);
+        }
+        if !dc.is_null() {
+            DeleteDC(dc);
+        }
+        ReleaseDC(window, source);
+        result
+    }
+}
@@ -0,0 +1,229 @@
+use std::sync::OnceLock;
+
+use windows_sys::Win32::{
+    Foundation::{GENERIC_READ, GENERIC_WRITE, HANDLE, HWND, INVALID_HANDLE_VALUE},
+    Storage::FileSystem::{
+        CreateFileW, GetFileType, FILE_SHARE_READ, FILE_SHARE_WRITE, FILE_TYPE_CHAR, OPEN_EXISTING,
+    },
+    System::Console::{
+        GetConsoleMode, GetConsoleScreenBufferInfo, GetConsoleWindow, GetStdHandle, SetConsoleMode,
+        CONSOLE_MODE, CONSOLE_SCREEN_BUFFER_INFO, ENABLE_VIRTUAL_TERMINAL_PROCESSING, STD_HANDLE,
+        STD_OUTPUT_HANDLE,
+    },
+    UI::{
+        HiDpi::{GetDpiForSystem, GetDpiForWindow},
+        WindowsAndMessaging::{GetWindow, GW_OWNER},
+    },
+};
+
+use crate::environment::{self, TerminalHost};
+use crate::{last_error_code, TerminalError};
+
+/// Returns the standard handle, or `NoStdHandle` if there is none.
+pub(crate) fn std_handle(which: STD_HANDLE) -> Result<HANDLE, TerminalError> {
+    let handle = unsafe { GetStdHandle(which) };
+    if handle.is_null() {
+        return Err(TerminalError::NoStdHandle(last_error_code()));
+    }
+    Ok(handle)
+}
+
+/// Opens `CONIN$` or `CONOUT$` once per process, `None` without a console.
+fn open_console(name: &str, cache: &'static OnceLock<usize>) -> Option<HANDLE> {
+    let handle = *cache.get_or_init(|| {
+        let wide: Vec<u16> = name.encode_utf16().chain([0]).collect();
+        let handle = unsafe {
+            CreateFileW(
+                wide.as_ptr(),
+                GENERIC_READ | GENERIC_WRITE,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                std::ptr::null(),
+                OPEN_EXISTING,
+                0,
+                std::ptr::null_mut(),
+            )
+        };
+        handle as usize
+    }) as HANDLE;
+    (!handle.is_null() && handle != INVALID_HANDLE_VALUE).then_some(handle)
+}
+
+/// The active screen buffer of the attached console, even when the standard handles are
+/// redirected.
+pub(crate) fn console_output() -> Option<HANDLE> {
+    static CONOUT: OnceLock<usize> = OnceLock::new();
+    open_console("CONOUT$", &CONOUT)
+}
+
+/// The input buffer of the attached console, even when the standard input is redirected.
+pub(crate) fn console_input() -> Option<HANDLE> {
+    static CONIN: OnceLock<usize> = OnceLock::new();
+    open_console("CONIN$", &CONIN)
+}
+
+/// Whether `handle` is a console, rather than a file, a pipe or the `NUL` device.
+pub(crate) fn is_console_handle(handle: HANDLE) -> bool {
+    let mut mode = 0;
+    unsafe { GetFileType(handle) == FILE_TYPE_CHAR && GetConsoleMode(handle, &mut mode) != 0 }
+}
+
+/// Reads the screen buffer info of `handle`, `NotAConsole` if it is redirected.
+pub(crate) fn screen_buffer_info(
+    handle: HANDLE,
+) -> Result<CONSOLE_SCREEN_BUFFER_INFO, TerminalError> {
+    unsafe {
+        let mut info: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
+        if GetConsoleScreenBufferInfo(handle, &mut info) == 0 {
+            let code = last_error_code();
+            if !is_console_handle(handle) {
+                return Err(TerminalError::NotAConsole);
+            }
+            return Err(TerminalError::NoScreenBufferInfo(code));
+        }
+        Ok(info)
+    }
+}
+
+/// Visible window of the standard output in cells, as `(columns, rows)`.
+pub(crate) fn visible_cells() -> Result<(i32, i32), TerminalError> {
+    let info = screen_buffer_info(std_handle(STD_OUTPUT_HANDLE)?)?;
+    let window = info.srWindow;
+    Ok((
+        (window.Right - window.Left + 1) as i32,
+        (window.Bottom - window.Top + 1) as i32,
+    ))
+}
+
+/// Resizes the window of a screen buffer to `columns` x `rows` cells, without horizontal
+/// scrolling and keeping at least the current scrollback.
+///
+/// The window must always fit in the buffer, so the window shrinks first, then the buffer
+/// takes its new size, and the window grows last.
+#[cfg(any(feature = "pty", feature = "window"))]
+pub(crate) fn resize_window(handle: HANDLE, columns: i16, rows: i16) -> std::io::Result<()> {
+    use std::io;
+    use windows_sys::Win32::System::Console::{
+        GetLargestConsoleWindowSize, SetConsoleScreenBufferSize, SetConsoleWindowInfo, COORD,
+        SMALL_RECT,
+    };
+
+    unsafe {
+        let info = screen_buffer_info(handle).map_err(|_| io::Error::last_os_error())?;
+        let largest = GetLargestConsoleWindowSize(handle);
+        if columns < 1 || rows < 1 || columns > largest.X || rows > largest.Y {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{}x{} cells doesn't fit on the screen (largest is {}x{})",
+                    columns, rows, largest.X, largest.Y
+                ),
+            ));
+        }
+        let window = info.srWindow;
+        let shrunk = SMALL_RECT {
+            Left: 0,
+            Top: window.Top,
+            Right: (window.Right - window.Left).min(columns - 1),
+            Bottom: window.Top + (window.Bottom - window.Top).min(rows - 1),
+        };
+        if SetConsoleWindowInfo(handle, 1, &shrunk) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let buffer = COORD {
+            X: columns,
+            Y: info.dwSize.Y.max(rows),
+        };
+        if SetConsoleScreenBufferSize(handle, buffer) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let top = window.Top.min(buffer.Y - rows);
+        let grown = SMALL_RECT {
+            Left: 0,
+            Top: top,
+            Right: columns - 1,
+            Bottom: top + rows - 1,
+        };
+        if SetConsoleWindowInfo(handle, 1, &grown) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+/// The window the console is shown in, null if there's none.
+///
+/// That is the console window under conhost. Under a pseudo console the console window is a
+/// hidden stand-in placed on the primary monitor: the window owning it is used instead, which
+/// Windows Terminal sets to its own window, and null without an owner.
+pub(crate) fn host_window() -> HWND {
+    unsafe {
+        let window = GetConsoleWindow();
+        if window.is_null() || !environment::host().is_some_and(TerminalHost::is_pseudo_console) {
+            return window;
+        }
+        GetWindow(window, GW_OWNER)
+    }
+}
+
+/// DPI of the window the console is shown in (see [`host_window`]), or the system DPI under a
+/// pseudo console without an owner window.
+pub(crate) fn console_dpi() -> u32 {
+    window_dpi(host_window())
+}
+
+/// DPI of a window returned by [`host_window`], with the same fallback as [`console_dpi`].
+pub(crate) fn window_dpi(window: HWND) -> u32 {
+    unsafe {
+        if window.is_null() && !GetConsoleWindow().is_null() {
+            return GetDpiForSystem();
+        }
+        GetDpiForWindow(window)
+    }
+}
+
+/// Struct to hold a console mode changed for a while, put back when dropped.
+#[derive(Debug)]
+pub struct ModeGuard {
+    handle: HANDLE,
+    previous: CONSOLE_MODE,
+}
+
+// The handle is a process-wide console handle, not tied to the creating thread.
+unsafe impl Send for ModeGuard {}
+
+impl ModeGuard {
+    /// This function applies `set` and clears `clear` on the mode of `handle`, keeping the
+    /// other flags.
+    ///
+    /// ## Returns:
+    /// - `Some(ModeGuard)` restoring the previous mode when dropped.
+    /// - `None` if the handle isn't a console or the host refuses the new mode.
+    #[allow(clippy::not_unsafe_ptr_arg_deref)] // Checked by the console, like in `sys`.
+    pub fn change(handle: HANDLE, set: CONSOLE_MODE, clear: CONSOLE_MODE) -> Option<Self> {
+        unsafe {
+            let mut previous = 0;
+            if GetConsoleMode(handle, &mut previous) == 0 {
+                return None;
+            }
+            if SetConsoleMode(handle, (previous | set) & !clear) == 0 {
+                return None;
+            }
+            Some(ModeGuard { handle, previous })
+        }
+    }
+
+    /// This function enables VT sequence processing on the standard output, see
+    /// [`ModeGuard::change`].
+    pub fn virtual_terminal() -> Option<Self> {
+        let handle = std_handle(STD_OUTPUT_HANDLE).ok()?;
+        Self::change(handle, ENABLE_VIRTUAL_TERMINAL_PROCESSING, 0)
+    }
+}
+
+impl Drop for ModeGuard {
+    fn drop(&mut self) {
+        unsafe {
+            SetConsoleMode(self.handle, self.previous);
+        }
+    }
+}
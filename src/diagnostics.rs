@@ -0,0 +1,109 @@
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use windows_sys::Win32::System::Console::GetConsoleOutputCP;
+
+use crate::console::{console_dpi, visible_cells};
+use crate::environment::{self, Multiplexer, TerminalHost};
+use crate::{dpi, font, get_size_of_the_font, get_size_of_the_terminal};
+
+/// This function describes what the crate detected about the terminal, one `name: value` row
+/// per line, for logs and bug reports.
+///
+/// ## Note:
+/// - Every row is filled independently, so a failing probe shows its error instead of hiding
+///   the rest of the report.
+pub fn debug_report() -> String {
+    let mut rows: Vec<(&str, String)> = Vec::new();
+    let strategies = environment::size_strategies();
+    rows.push(("strategies", format!("{:?}", strategies)));
+    let host = match (environment::multiplexer(), environment::is_ssh()) {
+        (Some(Multiplexer::Tmux), ssh) => format!("tmux{}", if ssh { " over ssh" } else { "" }),
+        (Some(Multiplexer::Screen), ssh) => format!("screen{}", if ssh { " over ssh" } else { "" }),
+        (None, true) => "ssh".to_string(),
+        (None, false) => match environment::host() {
+            Some(TerminalHost::Conhost) => "conhost".to_string(),
+            Some(TerminalHost::WindowsTerminal) => "Windows Terminal".to_string(),
+            Some(TerminalHost::ConPty) => "pseudo console".to_string(),
+            None => "no console window".to_string(),
+        },
+    };
+    rows.push(("host", host));
+    rows.push((
+        "font",
+        match font::FontInfo::current() {
+            Ok(info) => format!(
+                "{} {}x{} weight {}{}",
+                info.face,
+                info.size.width,
+                info.size.height,
+                info.weight,
+                if info.raster { " (raster)" } else { "" },
+            ),
+            Err(e) => e.to_string(),
+        },
+    ));
+    rows.push((
+        "ligatures",
+        match font::current_has_ligatures() {
+            Ok(ligatures) => ligatures.to_string(),
+            Err(e) => e.to_string(),
+        },
+    ));
+    rows.push((
+        "emoji",
+        match font::emoji_support() {
+            Ok(support) => format!(
+                "{} cell{}{}",
+                support.width,
+                if support.width == 1 { "" } else { "s" },
+                if support.glyphs { "" } else { ", no glyphs" },
+            ),
+            Err(e) => e.to_string(),
+        },
+    ));
+    rows.push((
+        "dpi",
+        format!("{} ({:?})", console_dpi(), dpi::current_awareness()),
+    ));
+    rows.push(("code page", unsafe { GetConsoleOutputCP() }.to_string()));
+    rows.push((
+        "cell",
+        match get_size_of_the_font() {
+            Ok(size) => format!("{}x{} px", size.width, size.height),
+            Err(e) => e.to_string(),
+        },
+    ));
+    rows.push((
+        "window",
+        match visible_cells() {
+            Ok((columns, rows)) => format!("{}x{} cells", columns, rows),
+            Err(e) => e.to_string(),
+        },
+    ));
+    rows.push((
+        "buffer",
+        match get_size_of_the_terminal() {
+            Ok(size) => format!("{}x{} px", size.width, size.height),
+            Err(e) => e.to_string(),
+        },
+    ));
+
+    let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let mut report = String::new();
+    for (name, value) in rows {
+        let _ = writeln!(report, "{:>width$}: {}", name, value, width = width);
+    }
+    report
+}
+
+/// This function prints [`debug_report`] on the standard error under a `win-term` header.
+///
+/// Apps embedding the crate can call it behind their own flag (e.g. `--debug-terminal`) when
+/// users report a wrong layout.
+pub fn debug_banner() -> io::Result<()> {
+    let mut err = io::stderr().lock();
+    writeln!(err, "win-term {}", env!("CARGO_PKG_VERSION"))?;
+    err.write_all(debug_report().as_bytes())?;
+    err.flush()
+}
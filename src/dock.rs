@@ -0,0 +1,145 @@
+use std::io;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use windows_sys::Win32::Foundation::{HWND, RECT};
+use windows_sys::Win32::System::Console::GetConsoleWindow;
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    GetWindowRect, IsIconic, IsWindow, SetWindowPos, SWP_ASYNCWINDOWPOS, SWP_NOACTIVATE,
+    SWP_NOOWNERZORDER, SWP_NOZORDER,
+};
+
+/// How often the console window position is checked; about two frames at 60 Hz.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(30);
+
+/// Enum to represent the side of the console window a companion window is docked to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,   // Left of the console, as tall as it
+    Right,  // Right of the console, as tall as it
+    Top,    // Above the console, as wide as it
+    Bottom, // Below the console, as wide as it
+}
+
+/// Struct to hold a companion window kept glued to the console window, released when
+/// dropped.
+#[derive(Debug)]
+pub struct Dock {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// This function keeps `hwnd` docked to one side of the console window as the console moves
+/// and resizes, `size_px` wide (or tall, for `Top` and `Bottom`), e.g. a preview pane or a
+/// GPU-rendered companion of a TUI.
+///
+/// ## Returns:
+/// - `Ok(Dock)` with the window already in place; dropping it leaves the window where it is.
+/// - `Err(io::Error)` without a console window, if `hwnd` isn't a window, or `size_px` isn't
+///   positive.
+///
+/// ## Note:
+/// - The companion is moved without being activated or changing its z-order, asynchronously,
+///   so a busy companion thread never stalls the follower.
+/// - The follower stops by itself once either window is destroyed, and doesn't move the
+///   companion while the console is minimized.
+/// - Under Windows Terminal the console window is a hidden pseudo-window, so there is no
+///   visible window to follow.
+pub fn attach(hwnd: HWND, side: Side, size_px: i32) -> io::Result<Dock> {
+    // Window handles are opaque values, never dereferenced; they cross into the follower
+    // thread as integers.
+    let (console, hwnd) = (unsafe { GetConsoleWindow() } as usize, hwnd as usize);
+    if console == 0 {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no console window"));
+    }
+    if unsafe { IsWindow(hwnd as HWND) } == 0 || size_px <= 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "expected a window and a positive size",
+        ));
+    }
+    let mut last = follow(console as HWND, hwnd as HWND, side, size_px, None);
+    let stop = Arc::new((Mutex::new(false), Condvar::new()));
+    let thread = {
+        let stop = Arc::clone(&stop);
+        thread::Builder::new()
+            .name("win-term dock".to_string())
+            .spawn(move || {
+                let (console, hwnd) = (console as HWND, hwnd as HWND);
+                let (lock, wake) = &*stop;
+                let mut stopped = lock.lock().unwrap_or_else(|e| e.into_inner());
+                loop {
+                    stopped = wake
+                        .wait_timeout(stopped, FOLLOW_INTERVAL)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0;
+                    if *stopped || unsafe { IsWindow(console) == 0 || IsWindow(hwnd) == 0 } {
+                        return;
+                    }
+                    last = follow(console, hwnd, side, size_px, last);
+                }
+            })?
+    };
+    Ok(Dock {
+        stop,
+        thread: Some(thread),
+    })
+}
+
+/// Moves `hwnd` next to `console` if the console moved since `last`; returns the console
+/// rectangle the companion is now aligned to.
+fn follow(
+    console: HWND,
+    hwnd: HWND,
+    side: Side,
+    size: i32,
+    last: Option<(i32, i32, i32, i32)>,
+) -> Option<(i32, i32, i32, i32)> {
+    unsafe {
+        if IsIconic(console) != 0 {
+            return last;
+        }
+        let mut rect = RECT {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        };
+        if GetWindowRect(console, &mut rect) == 0 {
+            return last;
+        }
+        let current = (rect.left, rect.top, rect.right, rect.bottom);
+        if last == Some(current) {
+            return last;
+        }
+        let (width, height) = (rect.right - rect.left, rect.bottom - rect.top);
+        let (x, y, cx, cy) = match side {
+            Side::Left => (rect.left - size, rect.top, size, height),
+            Side::Right => (rect.right, rect.top, size, height),
+            Side::Top => (rect.left, rect.top - size, width, size),
+            Side::Bottom => (rect.left, rect.bottom, width, size),
+        };
+        SetWindowPos(
+            hwnd,
+            std::ptr::null_mut(),
+            x,
+            y,
+            cx,
+            cy,
+            SWP_NOACTIVATE | SWP_NOZORDER | SWP_NOOWNERZORDER | SWP_ASYNCWINDOWPOS,
+        );
+        Some(current)
+    }
+}
+
+impl Drop for Dock {
+    fn drop(&mut self) {
+        let (lock, wake) = &*self.stop;
+        *lock.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        wake.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use windows_sys::Win32::UI::HiDpi::{
+    AreDpiAwarenessContextsEqual, GetAwarenessFromDpiAwarenessContext,
+    GetThreadDpiAwarenessContext, SetProcessDpiAwarenessContext, SetThreadDpiAwarenessContext,
+    DPI_AWARENESS_CONTEXT, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE,
+    DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, DPI_AWARENESS_PER_MONITOR_AWARE,
+    DPI_AWARENESS_SYSTEM_AWARE,
+};
+
+/// Enum to represent how Windows scales the DPI values the calling thread sees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DpiAwareness {
+    Unaware,      // Every window reads as 96 DPI and is bitmap-stretched by the system
+    System,       // Every window reads as the DPI of the primary monitor at logon
+    PerMonitor,   // Real DPI of the monitor of each window
+    PerMonitorV2, // Same, with the non-client area and dialogs scaled too (Windows 10 1703+)
+}
+
+impl DpiAwareness {
+    /// Whether `GetDpiForWindow` reports the real DPI of every window.
+    pub fn is_per_monitor(self) -> bool {
+        matches!(self, DpiAwareness::PerMonitor | DpiAwareness::PerMonitorV2)
+    }
+}
+
+/// This function reads the DPI awareness of the calling thread.
+///
+/// ## Returns:
+/// - The awareness the thread runs with: that of the process (from its manifest or an earlier
+///   call), unless the thread changed its own.
+/// - `DpiAwareness::Unaware` for the GDI-scaled mode too, which reports 96 DPI the same way.
+pub fn current_awareness() -> DpiAwareness {
+    unsafe {
+        let context = GetThreadDpiAwarenessContext();
+        if AreDpiAwarenessContextsEqual(context, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2) != 0 {
+            return DpiAwareness::PerMonitorV2;
+        }
+        match GetAwarenessFromDpiAwarenessContext(context) {
+            DPI_AWARENESS_PER_MONITOR_AWARE => DpiAwareness::PerMonitor,
+            DPI_AWARENESS_SYSTEM_AWARE => DpiAwareness::System,
+            _ => DpiAwareness::Unaware,
+        }
+    }
+}
+
+/// This function makes the process per-monitor DPI aware if it isn't already, so the DPI the
+/// crate reads, and every pixel size derived from it, is the real one.
+///
+/// ## Returns:
+/// - The awareness in effect afterwards, [`DpiAwareness::is_per_monitor`] on success.
+///
+/// ## Note:
+/// - Tried in order: the per-monitor V2 context for the process (Windows 10 1703+), the V1
+///   context (1607), then the same two for the calling thread only, when the process
+///   awareness is already fixed. Older versions have neither, nor `GetDpiForWindow`, which the
+///   crate already needs.
+/// - The process awareness can be set only once, and not at all when the manifest declares
+///   one: call this at startup, before any window is created. Windows created before keep
+///   their awareness.
+pub fn ensure_per_monitor_awareness() -> DpiAwareness {
+    let current = current_awareness();
+    if current.is_per_monitor() {
+        return current;
+    }
+    let contexts: [DPI_AWARENESS_CONTEXT; 2] = [
+        DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2,
+        DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE,
+    ];
+    unsafe {
+        let process = contexts
+            .iter()
+            .any(|&context| SetProcessDpiAwarenessContext(context) != 0);
+        if !process {
+            // The process awareness is fixed (manifest, earlier call): this thread can still
+            // opt in.
+            contexts
+                .iter()
+                .any(|&context| !SetThreadDpiAwarenessContext(context).is_null());
+        }
+    }
+    current_awareness()
+}
+
+/// Runs `f` with the calling thread per-monitor aware, so the window coordinates it reads and
+/// writes are physical pixels whatever the awareness of the process.
+#[cfg(feature = "window")]
+pub(crate) fn in_physical_pixels<T>(f: impl FnOnce() -> T) -> T {
+    unsafe {
+        let previous = SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
+        let result = f();
+        if !previous.is_null() {
+            SetThreadDpiAwarenessContext(previous);
+        }
+        result
+    }
+}
@@ -0,0 +1,86 @@
+use std::io::{self, Write};
+
+/// Unicode glyphs of code page 437, the original IBM PC character set, indexed by byte.
+///
+/// The control range maps to the glyphs the PC displayed for it (☺, ♥, ►, ...), as DOS-era
+/// art and UIs draw with them.
+const CP437: [char; 256] = [
+    '\0', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼', '►', '◄', '↕',
+    '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼', ' ', '!', '"', '#', '$', '%',
+    '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/', '0', '1', '2', '3', '4', '5', '6', '7', '8',
+    '9', ':', ';', '<', '=', '>', '?', '@', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K',
+    'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '[', '\\', ']', '^',
+    '_', '`', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q',
+    'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '{', '|', '}', '~', '⌂', 'Ç', 'ü', 'é', 'â', 'ä',
+    'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', 'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù',
+    'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', 'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬',
+    '½', '¼', '¡', '«', '»', '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜',
+    '╛', '┐', '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', '╨',
+    '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀', 'α', 'ß', 'Γ', 'π',
+    'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', '≡', '±', '≥', '≤', '⌠', '⌡', '÷',
+    '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+/// This function maps a CP437 byte to the character the IBM PC displayed for it, whatever the
+/// active console code page.
+///
+/// ## Note:
+/// - Control bytes map to their glyphs (`0x01` is `☺`, `0x10` is `►`, `0x7F` is `⌂`), except
+///   `0x00`, which stays NUL.
+pub fn oem_to_unicode(byte: u8) -> char {
+    CP437[byte as usize]
+}
+
+/// This function maps a character back to its CP437 byte, `None` if CP437 can't represent it.
+pub fn unicode_to_oem(c: char) -> Option<u8> {
+    if c.is_ascii() {
+        return Some(c as u8);
+    }
+    CP437.iter().position(|&glyph| glyph == c).map(|i| i as u8)
+}
+
+/// This function decodes a whole CP437 string, control bytes included (see [`oem_to_unicode`]).
+pub fn decode_oem(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| oem_to_unicode(b)).collect()
+}
+
+/// Struct to hold a writer that takes CP437 bytes, as DOS-era programs and data produce them,
+/// and forwards them as UTF-8.
+///
+/// Box drawing and shade characters (`0xB0`-`0xDF`) come out right even when the console
+/// code page is 1252 or 65001, as the Rust standard output converts UTF-8 for the console.
+/// Control bytes (below `0x20`, and `0x7F`) are passed through untouched, so line breaks and
+/// escape sequences keep working.
+#[derive(Debug)]
+pub struct OemWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> OemWriter<W> {
+    pub fn new(inner: W) -> Self {
+        OemWriter { inner }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for OemWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut out = String::with_capacity(buf.len());
+        for &byte in buf {
+            match byte {
+                0x00..=0x1f | 0x7f => out.push(byte as char),
+                _ => out.push(oem_to_unicode(byte)),
+            }
+        }
+        // Every byte maps to whole characters, so the input is consumed all or nothing.
+        self.inner.write_all(out.as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,171 @@
+use std::env;
+use std::io;
+use std::time::Duration;
+
+use windows_sys::Win32::System::Console::GetConsoleWindow;
+use windows_sys::Win32::System::SystemInformation::GetTickCount;
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+use windows_sys::Win32::UI::WindowsAndMessaging::GetClassNameW;
+
+/// Enum to represent a terminal multiplexer sitting between the application and the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplexer {
+    Tmux,   // `$TMUX` is set
+    Screen, // `$STY` is set
+}
+
+/// This function detects whether the process runs inside tmux or GNU screen, e.g. a Windows
+/// binary launched from a tmux pane in WSL or over SSH.
+///
+/// ## Returns:
+/// - `Some(Multiplexer)` when `$TMUX` or `$STY` is set and non-empty, tmux winning when both are.
+/// - `None` otherwise.
+pub fn multiplexer() -> Option<Multiplexer> {
+    let set = |name: &str| env::var_os(name).is_some_and(|value| !value.is_empty());
+    if set("TMUX") {
+        Some(Multiplexer::Tmux)
+    } else if set("STY") {
+        Some(Multiplexer::Screen)
+    } else {
+        None
+    }
+}
+
+/// Largest DCS string screen forwards in one piece.
+const SCREEN_CHUNK: usize = 768;
+
+/// This function wraps a VT query in the DCS passthrough of the detected multiplexer, so it
+/// reaches the outer terminal instead of being answered (or swallowed) by the multiplexer.
+///
+/// ## Returns:
+/// - The sequence unchanged when no multiplexer is detected.
+/// - `ESC Ptmux; ... ESC \` with every inner ESC doubled under tmux.
+/// - One `ESC P ... ESC \` per 768-byte chunk under screen.
+///
+/// ## Note:
+/// - tmux 3.3 and later only forward passthrough when `allow-passthrough` is on.
+/// - Under screen, an inner `ESC \` would end the DCS early, so sequences are best terminated
+///   with BEL there.
+pub fn passthrough(sequence: &str) -> String {
+    match multiplexer() {
+        None => sequence.to_string(),
+        Some(Multiplexer::Tmux) => {
+            format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+        }
+        Some(Multiplexer::Screen) => {
+            let mut out = String::with_capacity(sequence.len() + 8);
+            let mut rest = sequence;
+            while !rest.is_empty() {
+                let mut end = rest.len().min(SCREEN_CHUNK);
+                while !rest.is_char_boundary(end) {
+                    end -= 1;
+                }
+                out.push_str("\x1bP");
+                out.push_str(&rest[..end]);
+                out.push_str("\x1b\\");
+                rest = &rest[end..];
+            }
+            out
+        }
+    }
+}
+
+/// This function detects whether the process runs in an SSH session, e.g. one hosted by the
+/// Windows OpenSSH server.
+///
+/// ## Returns:
+/// - `true` when `$SSH_CONNECTION`, `$SSH_CLIENT` or `$SSH_TTY` is set and non-empty.
+pub fn is_ssh() -> bool {
+    ["SSH_CONNECTION", "SSH_CLIENT", "SSH_TTY"]
+        .iter()
+        .any(|name| env::var_os(name).is_some_and(|value| !value.is_empty()))
+}
+
+/// Enum to represent the program drawing the console the process is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalHost {
+    Conhost,         // The classic console window, which owns its window, font and DPI
+    WindowsTerminal, // A pseudo console drawn by Windows Terminal (`$WT_SESSION` is set)
+    ConPty,          // A pseudo console drawn by anything else: VS Code, an SSH server, ...
+}
+
+impl TerminalHost {
+    /// Whether the console window is a hidden stand-in rather than what the user sees.
+    pub fn is_pseudo_console(self) -> bool {
+        self != TerminalHost::Conhost
+    }
+}
+
+/// This function detects the host of the console, from the class of the console window.
+///
+/// ## Returns:
+/// - `Some(TerminalHost::Conhost)` when the window is a `ConsoleWindowClass`.
+/// - `Some(TerminalHost::WindowsTerminal)` or `Some(TerminalHost::ConPty)` when it is the
+///   hidden `PseudoConsoleWindow` of ConPTY, depending on `$WT_SESSION`.
+/// - `None` without a console window.
+///
+/// ## Note:
+/// - `$WT_SESSION` alone is not enough: it is inherited by programs started from Windows
+///   Terminal that open a console of their own, and by SSH sessions started from one.
+/// - Under a pseudo console the DPI and rectangle of the console window don't match what the
+///   user sees; the crate then takes the DPI from the window owning it, if the terminal set
+///   one, and prefers [`SizeStrategy::VtQuery`].
+pub fn host() -> Option<TerminalHost> {
+    let window = unsafe { GetConsoleWindow() };
+    if window.is_null() {
+        return None;
+    }
+    let mut class = [0u16; 64];
+    let len = unsafe { GetClassNameW(window, class.as_mut_ptr(), class.len() as i32) };
+    let pseudo = String::from_utf16_lossy(&class[..len.max(0) as usize]) == "PseudoConsoleWindow";
+    let wt = env::var_os("WT_SESSION").is_some_and(|value| !value.is_empty());
+    Some(match (pseudo, wt) {
+        (false, _) => TerminalHost::Conhost,
+        (true, true) => TerminalHost::WindowsTerminal,
+        (true, false) => TerminalHost::ConPty,
+    })
+}
+
+/// Enum to represent a way of measuring the terminal in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeStrategy {
+    VtQuery, // Ask the terminal itself with `CSI 14 t` / `CSI 16 t`
+    Gdi,     // Derive the size from the local console font and window DPI
+}
+
+/// This function returns the order in which the measuring strategies should be tried.
+///
+/// ## Returns:
+/// - `[VtQuery, Gdi]` over SSH, inside a multiplexer or under a pseudo console, where the
+///   console window and font are the ones of a hidden stand-in, not of the terminal the user
+///   looks at.
+/// - `[Gdi, VtQuery]` otherwise, as the local console answers without a round trip.
+pub fn size_strategies() -> [SizeStrategy; 2] {
+    if is_ssh() || multiplexer().is_some() || host().is_some_and(TerminalHost::is_pseudo_console) {
+        [SizeStrategy::VtQuery, SizeStrategy::Gdi]
+    } else {
+        [SizeStrategy::Gdi, SizeStrategy::VtQuery]
+    }
+}
+
+/// This function measures how long the user hasn't touched the keyboard or the mouse.
+///
+/// ## Returns:
+/// - `Ok(Duration)` since the last input, in milliseconds.
+/// - `Err(io::Error)` if it can't be read, e.g. from a service without a desktop.
+///
+/// ## Note:
+/// - The last input is the one of the whole session, not only of the console: a user typing
+///   in another window isn't idle. Over Remote Desktop it is the input of the client.
+pub fn idle_time() -> io::Result<Duration> {
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    if unsafe { GetLastInputInfo(&mut info) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // Both are 32-bit tick counts, which wrap after 49.7 days.
+    let ticks = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+    Ok(Duration::from_millis(ticks as u64))
+}
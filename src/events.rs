@@ -0,0 +1,481 @@
+use std::cell::RefCell;
+use std::io;
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use windows_sys::Win32::Foundation::{HANDLE, HWND, LPARAM, LRESULT, WPARAM};
+use windows_sys::Win32::System::Console::{
+    GetConsoleWindow, PeekConsoleInputW, ReadConsoleInputW, ENABLE_WINDOW_INPUT, INPUT_RECORD,
+    WINDOW_BUFFER_SIZE_EVENT,
+};
+use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows_sys::Win32::System::RemoteDesktop::{
+    WTSRegisterSessionNotification, WTSUnRegisterSessionNotification, NOTIFY_FOR_THIS_SESSION,
+};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetMessageW, PostMessageW,
+    RegisterClassW, HWND_MESSAGE, MSG, WM_QUIT, WM_WTSSESSION_CHANGE, WNDCLASSW, WTS_SESSION_LOCK,
+    WTS_SESSION_UNLOCK,
+};
+
+use crate::console::{console_dpi, console_input, console_output, screen_buffer_info, ModeGuard};
+use crate::environment::idle_time;
+use crate::{cell_size_from, TerminalCells, TerminalSize};
+
+/// How often the input queue is looked at; short enough for a redraw to follow the mouse.
+const WATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How often the DPI of the console window is read; a monitor change isn't a drag, so this
+/// can be slower than the resize watch.
+const DPI_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often the idle time is read.
+const IDLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Records looked at in one peek.
+const PEEK_RECORDS: usize = 64;
+
+/// Struct to hold the size of the console after a resize.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResizeEvent {
+    pub cells: TerminalCells,         // Visible window in cells
+    pub pixels: Option<TerminalSize>, // Visible window in pixels, `None` if the cell size is unknown
+}
+
+/// Struct to hold a background watcher of console resizes, stopped when dropped.
+#[derive(Debug)]
+pub struct ResizeWatcher {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ResizeWatcher {
+    /// This function starts watching the console for resizes, calling `callback` from a
+    /// background thread with the new size every time the visible window changes.
+    ///
+    /// ## Returns:
+    /// - `Ok(ResizeWatcher)` once the thread runs; the callback isn't called for the size the
+    ///   console has at that point.
+    /// - `Err(io::Error)` without a console input or output.
+    ///
+    /// ## Note:
+    /// - `ENABLE_WINDOW_INPUT` is set on the console input for as long as the watcher lives, so
+    ///   the host queues `WINDOW_BUFFER_SIZE_EVENT` records; the previous mode is restored
+    ///   when it is dropped.
+    /// - Only resize records at the front of the input queue are read; keys and mouse records
+    ///   are left for the program. A resize queued behind input nobody reads is still caught,
+    ///   as the size is also compared on every look at the queue.
+    /// - The size reported is the visible window, measured when the record is read, rather than
+    ///   the buffer size the record carries; several quick resizes give a single call.
+    pub fn start<F>(mut callback: F) -> io::Result<ResizeWatcher>
+    where
+        F: FnMut(ResizeEvent) + Send + 'static,
+    {
+        let input = console_input().ok_or_else(io::Error::last_os_error)?;
+        let output = console_output().ok_or_else(io::Error::last_os_error)?;
+        let mut last = measure(output).ok_or_else(io::Error::last_os_error)?;
+        let mode = ModeGuard::change(input, ENABLE_WINDOW_INPUT, 0)
+            .ok_or_else(io::Error::last_os_error)?;
+        // Console handles are process-wide, they cross into the thread as integers.
+        let (input, output) = (input as usize, output as usize);
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread = {
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name("win-term resize watcher".to_string())
+                .spawn(move || {
+                    let _mode = mode;
+                    let (input, output) = (input as HANDLE, output as HANDLE);
+                    let (lock, wake) = &*stop;
+                    let mut stopped = lock.lock().unwrap_or_else(|e| e.into_inner());
+                    loop {
+                        stopped = wake
+                            .wait_timeout(stopped, WATCH_INTERVAL)
+                            .unwrap_or_else(|e| e.into_inner())
+                            .0;
+                        if *stopped {
+                            return;
+                        }
+                        take_resize_records(input);
+                        if let Some(current) = measure(output).filter(|size| *size != last) {
+                            last = current;
+                            callback(current);
+                        }
+                    }
+                })?
+        };
+        Ok(ResizeWatcher {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for ResizeWatcher {
+    fn drop(&mut self) {
+        let (lock, wake) = &*self.stop;
+        *lock.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        wake.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Reads the resize records at the front of the input queue, leaving anything behind them.
+fn take_resize_records(input: HANDLE) {
+    let mut records: [INPUT_RECORD; PEEK_RECORDS] = unsafe { std::mem::zeroed() };
+    let mut count = 0;
+    if unsafe { PeekConsoleInputW(input, records.as_mut_ptr(), PEEK_RECORDS as u32, &mut count) }
+        == 0
+    {
+        return;
+    }
+    let leading = records[..count as usize]
+        .iter()
+        .take_while(|record| record.EventType == WINDOW_BUFFER_SIZE_EVENT as u16)
+        .count();
+    if leading > 0 {
+        unsafe { ReadConsoleInputW(input, records.as_mut_ptr(), leading as u32, &mut count) };
+    }
+}
+
+/// The visible window of `output`, in cells and in pixels.
+pub(crate) fn measure(output: HANDLE) -> Option<ResizeEvent> {
+    let window = screen_buffer_info(output).ok()?.srWindow;
+    let cells = TerminalCells {
+        columns: (window.Right - window.Left + 1) as i32,
+        rows: (window.Bottom - window.Top + 1) as i32,
+    };
+    let pixels = cell_size_from(output, true).ok().map(|cell| TerminalSize {
+        width: cells.columns * cell.width,
+        height: cells.rows * cell.height,
+    });
+    Some(ResizeEvent { cells, pixels })
+}
+
+/// Struct to hold a change of DPI of the console window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DpiChanged {
+    pub old: u32, // DPI before, 96 being 100% scaling
+    pub new: u32, // DPI now
+}
+
+/// Struct to hold a background watcher of the console window DPI, stopped when dropped.
+#[derive(Debug)]
+pub struct DpiWatcher {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DpiWatcher {
+    /// This function starts watching the DPI of the console window, calling `callback` from a
+    /// background thread every time it changes, e.g. when the window is dragged to a monitor
+    /// with another scaling.
+    ///
+    /// ## Returns:
+    /// - `Ok(DpiWatcher)` once the thread runs.
+    /// - `Err(io::Error)` without a console window, or if its DPI can't be read.
+    ///
+    /// ## Note:
+    /// - The DPI is polled with `GetDpiForWindow`: `WM_DPICHANGED` is only sent to the thread
+    ///   owning the window, which is the console host and not this process. Under a pseudo
+    ///   console it is the DPI of the terminal window, when the terminal makes it the owner of
+    ///   the console window as Windows Terminal does.
+    /// - Pixel sizes measured before the change are wrong afterwards; call
+    ///   [`crate::Terminal::refresh`] (or measure again) from the callback.
+    /// - The watcher stops by itself once the console window is destroyed.
+    pub fn start<F>(mut callback: F) -> io::Result<DpiWatcher>
+    where
+        F: FnMut(DpiChanged) + Send + 'static,
+    {
+        if unsafe { GetConsoleWindow() }.is_null() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no console window"));
+        }
+        let mut last = console_dpi();
+        if last == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread = {
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name("win-term dpi watcher".to_string())
+                .spawn(move || {
+                    let (lock, wake) = &*stop;
+                    let mut stopped = lock.lock().unwrap_or_else(|e| e.into_inner());
+                    loop {
+                        stopped = wake
+                            .wait_timeout(stopped, DPI_INTERVAL)
+                            .unwrap_or_else(|e| e.into_inner())
+                            .0;
+                        if *stopped {
+                            return;
+                        }
+                        let dpi = console_dpi();
+                        if dpi == 0 {
+                            // The window is gone.
+                            return;
+                        }
+                        if dpi != last {
+                            callback(DpiChanged {
+                                old: last,
+                                new: dpi,
+                            });
+                            last = dpi;
+                        }
+                    }
+                })?
+        };
+        Ok(DpiWatcher {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for DpiWatcher {
+    fn drop(&mut self) {
+        let (lock, wake) = &*self.stop;
+        *lock.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        wake.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Struct to hold a change of idle state reported by an [`IdleWatcher`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdleStateChanged {
+    pub level: usize,   // Number of thresholds passed, 0 once the user is back
+    pub idle: Duration, // Time since the last input when the change was seen
+}
+
+/// Struct to hold a background watcher of user inactivity, stopped when dropped.
+#[derive(Debug)]
+pub struct IdleWatcher {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl IdleWatcher {
+    /// This function starts watching how long the user has been idle, calling `callback` from
+    /// a background thread every time the idle time crosses one of `thresholds`, and when the
+    /// user comes back.
+    ///
+    /// ## Returns:
+    /// - `Ok(IdleWatcher)` once the thread runs.
+    /// - `Err(io::Error)` if the idle time can't be read.
+    ///
+    /// ## Note:
+    /// - With thresholds of 1 and 10 minutes, a dashboard gets level 1 (e.g. to dim) after a
+    ///   minute, level 2 (e.g. to stop polling) after ten, and level 0 on the next input.
+    ///   Thresholds are sorted; the order they are given in doesn't matter.
+    /// - The idle time is read once a second, see [`idle_time`] for what counts as input.
+    pub fn start<F>(thresholds: &[Duration], mut callback: F) -> io::Result<IdleWatcher>
+    where
+        F: FnMut(IdleStateChanged) + Send + 'static,
+    {
+        let mut thresholds = thresholds.to_vec();
+        thresholds.sort();
+        let level_of = move |idle: Duration| thresholds.iter().filter(|&&t| idle >= t).count();
+        let mut last = level_of(idle_time()?);
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread = {
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name("win-term idle watcher".to_string())
+                .spawn(move || {
+                    let (lock, wake) = &*stop;
+                    let mut stopped = lock.lock().unwrap_or_else(|e| e.into_inner());
+                    loop {
+                        stopped = wake
+                            .wait_timeout(stopped, IDLE_INTERVAL)
+                            .unwrap_or_else(|e| e.into_inner())
+                            .0;
+                        if *stopped {
+                            return;
+                        }
+                        let Ok(idle) = idle_time() else {
+                            continue;
+                        };
+                        let level = level_of(idle);
+                        if level != last {
+                            last = level;
+                            callback(IdleStateChanged { level, idle });
+                        }
+                    }
+                })?
+        };
+        Ok(IdleWatcher {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for IdleWatcher {
+    fn drop(&mut self) {
+        let (lock, wake) = &*self.stop;
+        *lock.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        wake.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Enum to represent a change of state of the Windows session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    Locked,   // The workstation was locked (Win+L, screen saver, Remote Desktop disconnect)
+    Unlocked, // The user is back
+}
+
+/// Callback of a [`SessionWatcher`].
+type SessionCallback = Box<dyn FnMut(SessionEvent)>;
+
+thread_local! {
+    /// Callback of the [`SessionWatcher`] running on this thread, called from its window
+    /// procedure.
+    static SESSION_CALLBACK: RefCell<Option<SessionCallback>> = const { RefCell::new(None) };
+}
+
+/// Struct to hold a background watcher of session locks, stopped when dropped.
+#[derive(Debug)]
+pub struct SessionWatcher {
+    window: usize, // Message-only window receiving the notifications, as an integer
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SessionWatcher {
+    /// This function starts watching the session, calling `callback` from a background thread
+    /// when the workstation is locked and unlocked, e.g. to hide sensitive output or stop
+    /// polling meanwhile.
+    ///
+    /// ## Returns:
+    /// - `Ok(SessionWatcher)` once the notifications are registered.
+    /// - `Err(io::Error)` if the window can't be created or the registration fails, e.g. in a
+    ///   service without a desktop.
+    ///
+    /// ## Note:
+    /// - The notifications come from `WTSRegisterSessionNotification`, delivered to a
+    ///   message-only window pumped by the thread; nothing is shown.
+    /// - Only the session of the process is watched.
+    pub fn start<F>(callback: F) -> io::Result<SessionWatcher>
+    where
+        F: FnMut(SessionEvent) + Send + 'static,
+    {
+        let (ready, started) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("win-term session watcher".to_string())
+            .spawn(move || {
+                let window = match session_window() {
+                    Ok(window) => window,
+                    Err(e) => {
+                        let _ = ready.send(Err(e));
+                        return;
+                    }
+                };
+                SESSION_CALLBACK.with(|slot| *slot.borrow_mut() = Some(Box::new(callback)));
+                let _ = ready.send(Ok(window as usize));
+                unsafe {
+                    let mut msg: MSG = std::mem::zeroed();
+                    while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) > 0 {
+                        DispatchMessageW(&msg);
+                    }
+                    WTSUnRegisterSessionNotification(window);
+                    DestroyWindow(window);
+                }
+            })?;
+        match started.recv() {
+            Ok(Ok(window)) => Ok(SessionWatcher {
+                window,
+                thread: Some(thread),
+            }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => Err(io::Error::other("the session watcher thread stopped")),
+        }
+    }
+}
+
+impl Drop for SessionWatcher {
+    fn drop(&mut self) {
+        unsafe { PostMessageW(self.window as HWND, WM_QUIT, 0, 0) };
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Creates the message-only window of a [`SessionWatcher`] and registers it.
+fn session_window() -> io::Result<HWND> {
+    unsafe {
+        let instance = GetModuleHandleW(std::ptr::null());
+        let class: Vec<u16> = "win-term session watcher"
+            .encode_utf16()
+            .chain([0])
+            .collect();
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(session_procedure),
+            hInstance: instance,
+            lpszClassName: class.as_ptr(),
+            ..std::mem::zeroed()
+        };
+        // Registering twice fails harmlessly; creating the window reports real errors.
+        RegisterClassW(&wc);
+        let window = CreateWindowExW(
+            0,
+            class.as_ptr(),
+            std::ptr::null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            std::ptr::null_mut(),
+            instance,
+            std::ptr::null(),
+        );
+        if window.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        if WTSRegisterSessionNotification(window, NOTIFY_FOR_THIS_SESSION) == 0 {
+            let error = io::Error::last_os_error();
+            DestroyWindow(window);
+            return Err(error);
+        }
+        Ok(window)
+    }
+}
+
+unsafe extern "system" fn session_procedure(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if message == WM_WTSSESSION_CHANGE {
+        let event = match wparam as u32 {
+            WTS_SESSION_LOCK => Some(SessionEvent::Locked),
+            WTS_SESSION_UNLOCK => Some(SessionEvent::Unlocked),
+            _ => None,
+        };
+        if let Some(event) = event {
+            SESSION_CALLBACK.with(|slot| {
+                if let Some(callback) = slot.borrow_mut().as_mut() {
+                    callback(event);
+                }
+            });
+        }
+        return 0;
+    }
+    DefWindowProcW(window, message, wparam, lparam)
+}
@@ -0,0 +1,779 @@
+use std::fmt::Write as _;
+
+#[cfg(all(windows, feature = "d2d"))]
+use windows::{
+    core::PCWSTR,
+    Win32::Foundation::RECT as D2dRect,
+    Win32::Graphics::Direct2D::Common::{
+        D2D1_ALPHA_MODE_IGNORE, D2D1_COLOR_F, D2D1_PIXEL_FORMAT, D2D_RECT_F,
+    },
+    Win32::Graphics::Direct2D::{
+        D2D1CreateFactory, ID2D1Factory, D2D1_DRAW_TEXT_OPTIONS_CLIP,
+        D2D1_FACTORY_TYPE_SINGLE_THREADED, D2D1_FEATURE_LEVEL_DEFAULT,
+        D2D1_RENDER_TARGET_PROPERTIES, D2D1_RENDER_TARGET_TYPE_DEFAULT,
+        D2D1_RENDER_TARGET_USAGE_NONE,
+    },
+    Win32::Graphics::DirectWrite::{
+        DWriteCreateFactory, IDWriteFactory, IDWriteFontCollection, IDWriteTextFormat,
+        DWRITE_FACTORY_TYPE_SHARED, DWRITE_FONT_STRETCH_NORMAL, DWRITE_FONT_STYLE_ITALIC,
+        DWRITE_FONT_STYLE_NORMAL, DWRITE_FONT_WEIGHT_BOLD, DWRITE_FONT_WEIGHT_NORMAL,
+        DWRITE_MEASURING_MODE_GDI_CLASSIC, DWRITE_WORD_WRAPPING_NO_WRAP,
+    },
+    Win32::Graphics::Dxgi::Common::DXGI_FORMAT_B8G8R8A8_UNORM,
+    Win32::Graphics::Gdi::HDC as D2dHdc,
+};
+use windows_sys::Win32::Foundation::RECT;
+use windows_sys::Win32::Graphics::Gdi::{
+    CreateCompatibleDC, CreateDIBSection, CreateSolidBrush, DeleteDC, DeleteObject, ExtTextOutW,
+    FillRect, GdiFlush, SelectObject, SetBkColor, SetTextColor, BITMAPINFO, BITMAPINFOHEADER,
+    BI_RGB, DIB_RGB_COLORS, ETO_CLIPPED, ETO_OPAQUE, HDC, HFONT,
+};
+#[cfg(all(windows, feature = "d2d"))]
+use windows_sys::Win32::Graphics::Gdi::{GetTextMetricsW, TEXTMETRICW};
+use windows_sys::Win32::System::Console::STD_OUTPUT_HANDLE;
+
+use crate::console::std_handle;
+use crate::font::{self, FontStyle};
+use crate::frame::{Frame, FrameCell};
+use crate::style::{Attributes, Palette, Rgb, Underline};
+use crate::{get_size_of_the_font, last_error_code, FontSize, TerminalError};
+
+/// Cell size of Consolas 12pt at 96 DPI, used when the console font can't be measured.
+const FALLBACK_CELL: FontSize = FontSize {
+    width: 9,
+    height: 20,
+};
+
+/// Struct to hold a run of cells of a row sharing the same style.
+struct Run {
+    column: usize,
+    columns: usize,
+    text: String,
+    fg: Rgb,
+    bg: Rgb,
+    attributes: Attributes,
+}
+
+/// Splits a row into runs, folding the tail of double-width glyphs into their glyph.
+fn runs(row: &[FrameCell]) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for (column, cell) in row.iter().enumerate() {
+        let (mut fg, mut bg) = (cell.fg, cell.bg);
+        if cell.attributes.reverse {
+            std::mem::swap(&mut fg, &mut bg);
+        }
+        if cell.attributes.hidden {
+            fg = bg;
+        }
+        match runs.last_mut() {
+            Some(run) if cell.wide_tail => run.columns += 1,
+            Some(run) if (run.fg, run.bg, run.attributes) == (fg, bg, cell.attributes) => {
+                run.columns += 1;
+                run.text.push(cell.ch);
+            }
+            _ => runs.push(Run {
+                column,
+                columns: 1,
+                text: cell.ch.to_string(),
+                fg,
+                bg,
+                attributes: cell.attributes,
+            }),
+        }
+    }
+    runs
+}
+
+fn hex(rgb: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb.r, rgb.g, rgb.b)
+}
+
+fn escape(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// The `font-family` list for `face`, CSS-quoted then escaped for a double-quoted attribute,
+/// so a face name can't end the string, the declaration or the attribute.
+fn font_family(face: &str) -> String {
+    let mut quoted = String::from("'");
+    for c in face.chars() {
+        match c {
+            '\\' | '\'' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\{:x} ", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push_str("',Consolas,monospace");
+    let mut out = String::new();
+    escape(&quoted, &mut out);
+    out
+}
+
+/// CSS declarations for the text attributes, shared by both formats.
+fn text_style(attributes: &Attributes) -> String {
+    let mut style = String::new();
+    if attributes.bold {
+        style.push_str("font-weight:bold;");
+    }
+    if attributes.italic {
+        style.push_str("font-style:italic;");
+    }
+    if attributes.dim {
+        style.push_str("opacity:0.6;");
+    }
+    let mut lines = Vec::new();
+    if attributes.underline != Underline::None {
+        lines.push("underline");
+    }
+    if attributes.strikethrough {
+        lines.push("line-through");
+    }
+    if !lines.is_empty() {
+        let _ = write!(style, "text-decoration:{};", lines.join(" "));
+        let line_style = match attributes.underline {
+            Underline::Double => "double",
+            Underline::Curly => "wavy",
+            Underline::Dotted => "dotted",
+            Underline::Dashed => "dashed",
+            Underline::None | Underline::Single => "",
+        };
+        if !line_style.is_empty() {
+            let _ = write!(style, "text-decoration-style:{};", line_style);
+        }
+    }
+    style
+}
+
+/// This function renders a frame as a standalone HTML `<pre>` block with inline styles, for
+/// embedding terminal screenshots in documentation.
+///
+/// ## Note:
+/// - The block uses the console font face when it can be read, falling back to Consolas and
+///   then any monospace font.
+/// - Colors are baked in, so the result looks the same on any page.
+pub fn frame_to_html(frame: &Frame) -> String {
+    let face = current_face();
+    let background = hex(Palette::Ansi16.rgb(0));
+    let foreground = hex(Palette::Ansi16.rgb(7));
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "<pre class=\"win-term\" style=\"font-family:{};background:{};color:{};padding:0.5em;line-height:1.2\">",
+        font_family(&face),
+        background,
+        foreground
+    );
+    for (y, row) in frame.rows().enumerate() {
+        if y > 0 {
+            out.push('\n');
+        }
+        for run in runs(row) {
+            let _ = write!(
+                out,
+                "<span style=\"color:{};background:{};{}\">",
+                hex(run.fg),
+                hex(run.bg),
+                text_style(&run.attributes)
+            );
+            escape(&run.text, &mut out);
+            out.push_str("</span>");
+        }
+    }
+    out.push_str("</pre>");
+    out
+}
+
+/// This function renders a frame as SVG with the measured cell size of the console font.
+///
+/// See [`frame_to_svg_with`]; the cell size falls back to 9x20 pixels (Consolas 12pt at
+/// 96 DPI) when it can't be measured.
+pub fn frame_to_svg(frame: &Frame) -> String {
+    let cell = get_size_of_the_font().unwrap_or(FALLBACK_CELL);
+    frame_to_svg_with(frame, &cell, &current_face())
+}
+
+/// This function renders a frame as SVG laid out on an exact grid of `cell`-sized cells.
+///
+/// ## Note:
+/// - Every run of text is stretched to its exact cell span with `textLength`, so the grid holds
+///   even when the viewer substitutes another font for `face`.
+/// - Backgrounds are drawn as rectangles under the text, one per run differing from the
+///   default background.
+pub fn frame_to_svg_with(frame: &Frame, cell: &FontSize, face: &str) -> String {
+    let (w, h) = (cell.width.max(1) as usize, cell.height.max(1) as usize);
+    let default_bg = Palette::Ansi16.rgb(0);
+    let mut out = String::new();
+    let (width, height) = (frame.width * w, frame.height * h);
+    let _ = write!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" font-family=\"{2}\" font-size=\"{3}\" xml:space=\"preserve\">",
+        width,
+        height,
+        font_family(face),
+        h * 4 / 5
+    );
+    let _ = write!(
+        out,
+        "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+        width,
+        height,
+        hex(default_bg)
+    );
+    let mut text = String::new();
+    for (y, row) in frame.rows().enumerate() {
+        for run in runs(row) {
+            let x = run.column * w;
+            if run.bg != default_bg {
+                let _ = write!(
+                    out,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                    x,
+                    y * h,
+                    run.columns * w,
+                    h,
+                    hex(run.bg)
+                );
+            }
+            if run.text.trim().is_empty() {
+                continue;
+            }
+            let _ = write!(
+                text,
+                "<text x=\"{}\" y=\"{}\" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\" fill=\"{}\" style=\"{}\">",
+                x,
+                y * h + h * 4 / 5,
+                run.columns * w,
+                hex(run.fg),
+                text_style(&run.attributes)
+            );
+            escape(&run.text, &mut text);
+            text.push_str("</text>");
+        }
+    }
+    out.push_str(&text);
+    out.push_str("</svg>");
+    out
+}
+
+/// Face name of the console font, Consolas when it can't be read.
+pub(crate) fn current_face() -> String {
+    std_handle(STD_OUTPUT_HANDLE)
+        .and_then(font::current_font)
+        .map(|info| font::face_name(&info.FaceName))
+        .ok()
+        .filter(|face| !face.is_empty())
+        .unwrap_or_else(|| "Consolas".to_string())
+}
+
+/// This function rasterizes a frame into a PNG image with the console font and measured cell
+/// size, for golden-image tests.
+///
+/// See [`frame_to_png_with`]; the cell size falls back to 9x20 pixels when it can't be measured.
+pub fn frame_to_png(frame: &Frame) -> Result<Vec<u8>, TerminalError> {
+    let cell = get_size_of_the_font().unwrap_or(FALLBACK_CELL);
+    frame_to_png_with(frame, &cell, &current_face())
+}
+
+/// This function rasterizes a frame into a PNG image of `cell`-sized cells drawn with `face`.
+///
+/// ## Returns:
+/// - `Ok(bytes)` of an 8-bit RGB PNG, `frame.width * cell.width` pixels wide.
+/// - `Err(TerminalError::NoFontInfo(_))` if GDI can't create the font or the off-screen bitmap.
+///
+/// ## Note:
+/// - Glyphs are drawn each in its own cell so the grid matches the console exactly: with
+///   DirectWrite, like Windows Terminal draws them, with the `d2d` feature, and otherwise with
+///   GDI, like conhost draws them, whose anti-aliasing differs by a few pixel shades. GDI is
+///   also the fallback when DirectWrite can't be initialized.
+/// - Bold and italic use the font's bold and italic faces; underline and strikethrough are
+///   drawn as one-pixel lines.
+pub fn frame_to_png_with(
+    frame: &Frame,
+    cell: &FontSize,
+    face: &str,
+) -> Result<Vec<u8>, TerminalError> {
+    let rgb = rasterize(frame, cell, face)?;
+    let (w, h) = (cell.width.max(1), cell.height.max(1));
+    Ok(encode_png(
+        (frame.width as i32 * w) as u32,
+        (frame.height as i32 * h) as u32,
+        &rgb,
+    ))
+}
+
+/// Struct to hold one glyph to draw, with its colors and decorations resolved.
+struct Glyph {
+    ch: char,
+    style: FontStyle,
+    fg: Rgb,
+    bg: Rgb,
+    rect: RECT,      // Its cell, or both cells of a double-width glyph
+    lines: Vec<i32>, // Tops of the one-pixel underline and strikethrough lines
+}
+
+/// The glyphs of a frame of `w` x `h` pixel cells, the tails of double-width glyphs folded
+/// into their glyph.
+fn glyphs(frame: &Frame, w: i32, h: i32) -> impl Iterator<Item = Glyph> + '_ {
+    frame.rows().enumerate().flat_map(move |(y, row)| {
+        row.iter()
+            .enumerate()
+            .filter(|(_, cell)| !cell.wide_tail)
+            .map(move |(x, cell)| {
+                let (mut fg, mut bg) = (cell.fg, cell.bg);
+                if cell.attributes.reverse {
+                    std::mem::swap(&mut fg, &mut bg);
+                }
+                if cell.attributes.hidden {
+                    fg = bg;
+                }
+                let span = if row.get(x + 1).is_some_and(|next| next.wide_tail) {
+                    2
+                } else {
+                    1
+                };
+                let rect = RECT {
+                    left: x as i32 * w,
+                    top: y as i32 * h,
+                    right: (x as i32 + span) * w,
+                    bottom: (y as i32 + 1) * h,
+                };
+                let mut lines = Vec::new();
+                if cell.attributes.underline != Underline::None {
+                    lines.push(rect.bottom - 2);
+                }
+                if cell.attributes.strikethrough {
+                    lines.push(rect.top + h / 2);
+                }
+                Glyph {
+                    ch: cell.ch,
+                    style: FontStyle::from(&cell.attributes),
+                    fg,
+                    bg,
+                    rect,
+                    lines,
+                }
+            })
+    })
+}
+
+/// Draws a frame as 8-bit RGB pixels, rows top to bottom, `frame.width * cell.width` wide.
+pub(crate) fn rasterize(
+    frame: &Frame,
+    cell: &FontSize,
+    face: &str,
+) -> Result<Vec<u8>, TerminalError> {
+    let (w, h) = (cell.width.max(1), cell.height.max(1));
+    let (width, height) = (frame.width as i32 * w, frame.height as i32 * h);
+    if width == 0 || height == 0 {
+        return Ok(Vec::new());
+    }
+    unsafe {
+        let dc = CreateCompatibleDC(std::ptr::null_mut());
+        if dc.is_null() {
+            return Err(TerminalError::NoFontInfo(last_error_code()));
+        }
+        let mut info: BITMAPINFO = std::mem::zeroed();
+        info.bmiHeader = BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width,
+            biHeight: -height, // Top-down rows
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB,
+            ..std::mem::zeroed()
+        };
+        let mut bits = std::ptr::null_mut();
+        let bitmap = CreateDIBSection(
+            dc,
+            &info,
+            DIB_RGB_COLORS,
+            &mut bits,
+            std::ptr::null_mut(),
+            0,
+        );
+        if bitmap.is_null() || bits.is_null() {
+            DeleteDC(dc);
+            return Err(TerminalError::NoFontInfo(last_error_code()));
+        }
+        let previous_bitmap = SelectObject(dc, bitmap);
+        #[cfg(all(windows, feature = "d2d"))]
+        let result =
+            draw_dwrite(dc, frame, w, h, face).or_else(|_| draw_gdi(dc, frame, w, h, face));
+        #[cfg(not(all(windows, feature = "d2d")))]
+        let result = draw_gdi(dc, frame, w, h, face);
+        GdiFlush();
+        let pixels = std::slice::from_raw_parts(bits as *const u8, (width * height * 4) as usize);
+        // BGRX to RGB.
+        let rgb: Vec<u8> = pixels
+            .chunks_exact(4)
+            .flat_map(|bgrx| [bgrx[2], bgrx[1], bgrx[0]])
+            .collect();
+        SelectObject(dc, previous_bitmap);
+        DeleteObject(bitmap);
+        DeleteDC(dc);
+        result.map(|()| rgb)
+    }
+}
+
+/// Draws the glyphs into the bitmap selected in `dc` with GDI, as conhost does.
+unsafe fn draw_gdi(
+    dc: HDC,
+    frame: &Frame,
+    w: i32,
+    h: i32,
+    face: &str,
+) -> Result<(), TerminalError> {
+    let mut fonts: [HFONT; 4] = [std::ptr::null_mut(); 4];
+    let mut result = Ok(());
+    let previous_font = SelectObject(dc, std::ptr::null_mut());
+    for glyph in glyphs(frame, w, h) {
+        let font = &mut fonts[glyph.style as usize];
+        if font.is_null() {
+            *font = font::create_font(face, h, glyph.style);
+            if font.is_null() {
+                result = Err(TerminalError::NoFontInfo(last_error_code()));
+                break;
+            }
+        }
+        SelectObject(dc, *font);
+        SetTextColor(dc, colorref(glyph.fg));
+        SetBkColor(dc, colorref(glyph.bg));
+        let rect = glyph.rect;
+        let mut units = [0u16; 2];
+        let units = glyph.ch.encode_utf16(&mut units);
+        let advances = [rect.right - rect.left, 0];
+        ExtTextOutW(
+            dc,
+            rect.left,
+            rect.top,
+            ETO_OPAQUE | ETO_CLIPPED,
+            &rect,
+            units.as_ptr(),
+            units.len() as u32,
+            advances.as_ptr(),
+        );
+        if !glyph.lines.is_empty() {
+            let brush = CreateSolidBrush(colorref(glyph.fg));
+            for &top in &glyph.lines {
+                let line = RECT {
+                    top,
+                    bottom: top + 1,
+                    ..rect
+                };
+                FillRect(dc, &line, brush);
+            }
+            DeleteObject(brush);
+        }
+    }
+    SelectObject(dc, previous_font);
+    for font in fonts.into_iter().filter(|font| !font.is_null()) {
+        DeleteObject(font);
+    }
+    result
+}
+
+/// Em size in pixels of `face` with a cell height of `h`, as GDI sizes it.
+#[cfg(all(windows, feature = "d2d"))]
+unsafe fn em_size(dc: HDC, face: &str, h: i32) -> Result<f32, TerminalError> {
+    let font = font::create_font(face, h, FontStyle::Regular);
+    if font.is_null() {
+        return Err(TerminalError::NoFontInfo(last_error_code()));
+    }
+    let previous = SelectObject(dc, font);
+    let mut metrics: TEXTMETRICW = std::mem::zeroed();
+    let ok = GetTextMetricsW(dc, &mut metrics);
+    SelectObject(dc, previous);
+    DeleteObject(font);
+    if ok == 0 {
+        return Err(TerminalError::NoFontInfo(last_error_code()));
+    }
+    Ok((metrics.tmHeight - metrics.tmInternalLeading) as f32)
+}
+
+/// Draws the glyphs into the bitmap selected in `dc` with DirectWrite, through a Direct2D
+/// target bound to it, as Windows Terminal does.
+///
+/// The font is sized to the em size GDI gives `face` at a cell height of `h`, so both
+/// renderers agree on the glyph size.
+#[cfg(all(windows, feature = "d2d"))]
+unsafe fn draw_dwrite(
+    dc: HDC,
+    frame: &Frame,
+    w: i32,
+    h: i32,
+    face: &str,
+) -> Result<(), TerminalError> {
+    let error = |e: windows::core::Error| TerminalError::NoFontInfo(e.code().0 as u32);
+    let em = em_size(dc, face, h)?;
+    let factory: ID2D1Factory =
+        D2D1CreateFactory(D2D1_FACTORY_TYPE_SINGLE_THREADED, None).map_err(error)?;
+    let properties = D2D1_RENDER_TARGET_PROPERTIES {
+        r#type: D2D1_RENDER_TARGET_TYPE_DEFAULT,
+        pixelFormat: D2D1_PIXEL_FORMAT {
+            format: DXGI_FORMAT_B8G8R8A8_UNORM,
+            alphaMode: D2D1_ALPHA_MODE_IGNORE,
+        },
+        // 96 DPI makes device-independent pixels actual pixels.
+        dpiX: 96.0,
+        dpiY: 96.0,
+        usage: D2D1_RENDER_TARGET_USAGE_NONE,
+        minLevel: D2D1_FEATURE_LEVEL_DEFAULT,
+    };
+    let target = factory.CreateDCRenderTarget(&properties).map_err(error)?;
+    let bounds = D2dRect {
+        left: 0,
+        top: 0,
+        right: frame.width as i32 * w,
+        bottom: frame.height as i32 * h,
+    };
+    target.BindDC(D2dHdc(dc), &bounds).map_err(error)?;
+    let writer: IDWriteFactory = DWriteCreateFactory(DWRITE_FACTORY_TYPE_SHARED).map_err(error)?;
+    let family: Vec<u16> = face.encode_utf16().chain([0]).collect();
+    let locale: Vec<u16> = "en-us".encode_utf16().chain([0]).collect();
+    let format = |weight, style| -> Result<IDWriteTextFormat, TerminalError> {
+        let format = writer
+            .CreateTextFormat(
+                PCWSTR(family.as_ptr()),
+                None::<&IDWriteFontCollection>,
+                weight,
+                style,
+                DWRITE_FONT_STRETCH_NORMAL,
+                em,
+                PCWSTR(locale.as_ptr()),
+            )
+            .map_err(error)?;
+        format
+            .SetWordWrapping(DWRITE_WORD_WRAPPING_NO_WRAP)
+            .map_err(error)?;
+        Ok(format)
+    };
+    // In `FontStyle` order.
+    let formats = [
+        format(DWRITE_FONT_WEIGHT_NORMAL, DWRITE_FONT_STYLE_NORMAL)?,
+        format(DWRITE_FONT_WEIGHT_BOLD, DWRITE_FONT_STYLE_NORMAL)?,
+        format(DWRITE_FONT_WEIGHT_NORMAL, DWRITE_FONT_STYLE_ITALIC)?,
+        format(DWRITE_FONT_WEIGHT_BOLD, DWRITE_FONT_STYLE_ITALIC)?,
+    ];
+    let color = |rgb: Rgb| D2D1_COLOR_F {
+        r: rgb.r as f32 / 255.0,
+        g: rgb.g as f32 / 255.0,
+        b: rgb.b as f32 / 255.0,
+        a: 1.0,
+    };
+    let brush = target
+        .CreateSolidColorBrush(&color(Rgb { r: 0, g: 0, b: 0 }), None)
+        .map_err(error)?;
+    let rect = |left: i32, top: i32, right: i32, bottom: i32| D2D_RECT_F {
+        left: left as f32,
+        top: top as f32,
+        right: right as f32,
+        bottom: bottom as f32,
+    };
+    target.BeginDraw();
+    for glyph in glyphs(frame, w, h) {
+        let cell = rect(
+            glyph.rect.left,
+            glyph.rect.top,
+            glyph.rect.right,
+            glyph.rect.bottom,
+        );
+        brush.SetColor(&color(glyph.bg));
+        target.FillRectangle(&cell, &brush);
+        brush.SetColor(&color(glyph.fg));
+        let mut units = [0u16; 2];
+        target.DrawText(
+            glyph.ch.encode_utf16(&mut units),
+            &formats[glyph.style as usize],
+            &cell,
+            &brush,
+            D2D1_DRAW_TEXT_OPTIONS_CLIP,
+            DWRITE_MEASURING_MODE_GDI_CLASSIC,
+        );
+        for &top in &glyph.lines {
+            let line = rect(glyph.rect.left, top, glyph.rect.right, top + 1);
+            target.FillRectangle(&line, &brush);
+        }
+    }
+    target.EndDraw(None, None).map_err(error)
+}
+
+fn colorref(rgb: Rgb) -> u32 {
+    rgb.r as u32 | (rgb.g as u32) << 8 | (rgb.b as u32) << 16
+}
+
+/// Encodes 8-bit RGB pixels as a PNG, with uncompressed deflate blocks to stay dependency-free.
+pub(crate) fn encode_png(width: u32, height: u32, rgb: &[u8]) -> Vec<u8> {
+    fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = out.len();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(data);
+        let crc = crc32(&out[start..]);
+        out.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]); // 8-bit RGB, no interlace
+
+    // Every scanline starts with filter type 0.
+    let stride = width as usize * 3;
+    let mut raw = Vec::with_capacity((stride + 1) * height as usize);
+    for row in rgb.chunks(stride.max(1)).take(height as usize) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        zlib.push(blocks.peek().is_none() as u8);
+        let len = block.len() as u16;
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut out, b"IHDR", &ihdr);
+    chunk(&mut out, b"IDAT", &zlib);
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                crc >> 1 ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(adler32(b""), 1);
+        // Past the 5552-byte blocks the sums are reduced in.
+        let long = vec![0xff; 100_000];
+        let (mut a, mut b) = (1u64, 0u64);
+        for &byte in &long {
+            a = (a + byte as u64) % 65521;
+            b = (b + a) % 65521;
+        }
+        assert_eq!(adler32(&long), (b << 16 | a) as u32);
+    }
+
+    /// Decodes what `encode_png` writes: checks every chunk CRC and the zlib checksum, and
+    /// returns the size and the RGB pixels.
+    fn decode_png(png: &[u8]) -> (u32, u32, Vec<u8>) {
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let mut rest = &png[8..];
+        let mut chunks = Vec::new();
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let (kind, data) = (&rest[4..8], &rest[8..8 + len]);
+            let crc = u32::from_be_bytes(rest[8 + len..12 + len].try_into().unwrap());
+            assert_eq!(crc32(&rest[4..8 + len]), crc);
+            chunks.push((kind.to_vec(), data.to_vec()));
+            rest = &rest[12 + len..];
+        }
+        let kinds: Vec<&[u8]> = chunks.iter().map(|(kind, _)| &kind[..]).collect();
+        assert_eq!(kinds, [&b"IHDR"[..], b"IDAT", b"IEND"]);
+        let ihdr = &chunks[0].1;
+        let width = u32::from_be_bytes(ihdr[..4].try_into().unwrap());
+        let height = u32::from_be_bytes(ihdr[4..8].try_into().unwrap());
+        assert_eq!(&ihdr[8..], [8, 2, 0, 0, 0]);
+        let zlib = &chunks[1].1;
+        assert_eq!(&zlib[..2], [0x78, 0x01]);
+        let mut raw = Vec::new();
+        let mut at = 2;
+        loop {
+            let last = zlib[at] == 1;
+            let len = u16::from_le_bytes([zlib[at + 1], zlib[at + 2]]);
+            let nlen = u16::from_le_bytes([zlib[at + 3], zlib[at + 4]]);
+            assert_eq!(!len, nlen);
+            raw.extend_from_slice(&zlib[at + 5..at + 5 + len as usize]);
+            at += 5 + len as usize;
+            if last {
+                break;
+            }
+        }
+        assert_eq!(zlib[at..], adler32(&raw).to_be_bytes());
+        let stride = width as usize * 3;
+        let mut rgb = Vec::new();
+        for line in raw.chunks(stride + 1) {
+            assert_eq!(line[0], 0);
+            rgb.extend_from_slice(&line[1..]);
+        }
+        assert_eq!(rgb.len(), stride * height as usize);
+        (width, height, rgb)
+    }
+
+    #[test]
+    fn png_round_trips() {
+        let rgb: Vec<u8> = (0..3 * 7 * 5).map(|i| (i * 37 % 256) as u8).collect();
+        assert_eq!(decode_png(&encode_png(7, 5, &rgb)), (7, 5, rgb));
+        // Several stored blocks.
+        let rgb: Vec<u8> = (0..3 * 300 * 200).map(|i| (i % 251) as u8).collect();
+        assert_eq!(decode_png(&encode_png(300, 200, &rgb)), (300, 200, rgb));
+        assert_eq!(decode_png(&encode_png(0, 0, &[])), (0, 0, Vec::new()));
+    }
+
+    #[test]
+    fn font_family_quotes_and_escapes() {
+        assert_eq!(
+            font_family("Cascadia Mono"),
+            "'Cascadia Mono',Consolas,monospace"
+        );
+        assert_eq!(font_family("a'b\\c"), "'a\\'b\\\\c',Consolas,monospace");
+        assert_eq!(
+            font_family("x\"><script>"),
+            "'x&quot;&gt;&lt;script&gt;',Consolas,monospace"
+        );
+        assert_eq!(font_family("a\nb"), "'a\\a b',Consolas,monospace");
+    }
+}
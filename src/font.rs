@@ -0,0 +1,860 @@
+use windows_sys::Win32::{
+    Foundation::{
+        CloseHandle, GENERIC_READ, GENERIC_WRITE, HANDLE, INVALID_HANDLE_VALUE, LPARAM, SIZE,
+    },
+    Graphics::Gdi::{
+        CreateCompatibleDC, CreateFontW, DeleteDC, DeleteObject, EnumFontFamiliesExW, GetDC,
+        GetFontData, GetGlyphIndicesW, GetTextExtentPoint32W, GetTextMetricsW, ReleaseDC,
+        SelectObject, DEFAULT_CHARSET, FF_DONTCARE, FIXED_PITCH, FW_BOLD, FW_NORMAL,
+        GGI_MARK_NONEXISTING_GLYPHS, HDC, HFONT, LF_FACESIZE, LOGFONTW, OEM_CHARSET,
+        RASTER_FONTTYPE, TEXTMETRICW, TMPF_TRUETYPE,
+    },
+    Storage::FileSystem::{FILE_SHARE_READ, FILE_SHARE_WRITE},
+    System::Console::{
+        CreateConsoleScreenBuffer, GetConsoleScreenBufferInfo, GetConsoleWindow,
+        GetCurrentConsoleFontEx, SetCurrentConsoleFontEx, WriteConsoleW, CONSOLE_FONT_INFOEX,
+        CONSOLE_SCREEN_BUFFER_INFO, CONSOLE_TEXTMODE_BUFFER, STD_OUTPUT_HANDLE,
+    },
+};
+
+use crate::cache::invalidate_all;
+use crate::console::{console_dpi, std_handle};
+use crate::style::Attributes;
+use crate::{last_error_code, FontSize, TerminalError};
+
+/// Face name the console gives its bitmap font.
+const RASTER_FACE: &str = "Terminal";
+
+/// Reads the current font of a console output handle.
+pub(crate) fn current_font(handle: HANDLE) -> Result<CONSOLE_FONT_INFOEX, TerminalError> {
+    unsafe {
+        let mut info: CONSOLE_FONT_INFOEX = std::mem::zeroed();
+        info.cbSize = std::mem::size_of::<CONSOLE_FONT_INFOEX>() as u32;
+        if GetCurrentConsoleFontEx(handle, 0, &mut info) == 0 {
+            return Err(TerminalError::NoFontInfo(last_error_code()));
+        }
+        Ok(info)
+    }
+}
+
+/// Converts a null-terminated UTF-16 face name.
+pub(crate) fn face_name(face: &[u16]) -> String {
+    let len = face.iter().position(|&c| c == 0).unwrap_or(face.len());
+    String::from_utf16_lossy(&face[..len])
+}
+
+/// Whether a console font is the raster "Terminal" font rather than a TrueType one.
+///
+/// The console reports raster fonts without `TMPF_TRUETYPE` in `FontFamily`, and always
+/// under the face name "Terminal".
+pub(crate) fn is_raster(info: &CONSOLE_FONT_INFOEX) -> bool {
+    info.FontFamily & TMPF_TRUETYPE as u32 == 0 || face_name(&info.FaceName) == RASTER_FACE
+}
+
+/// This function tells whether the console currently uses the raster "Terminal" font.
+///
+/// ## Returns:
+/// - `Ok(true)` for the raster font, `Ok(false)` for TrueType fonts.
+/// - `Err(TerminalError)` if there's no standard handle or the font can't be read.
+pub fn is_raster_font() -> Result<bool, TerminalError> {
+    let handle = std_handle(STD_OUTPUT_HANDLE)?;
+    Ok(is_raster(&current_font(handle)?))
+}
+
+/// Struct to hold the console font as the console itself reports it.
+#[derive(Debug)]
+pub struct FontInfo {
+    pub face: String,   // Face name, e.g. "Consolas" or "Terminal"
+    pub size: FontSize, // Cell size in pixels (`dwFontSize`)
+    pub weight: u32,    // Font weight, 400 for regular and 700 for bold
+    pub raster: bool,   // Whether it is the raster "Terminal" font
+}
+
+impl FontInfo {
+    /// This function reads the current console font with `GetCurrentConsoleFontEx`.
+    ///
+    /// ## Returns:
+    /// - `Ok(FontInfo)` with the face, cell size and weight the user configured.
+    /// - `Err(TerminalError)` if there's no standard handle or the font can't be read.
+    ///
+    /// ## Note:
+    /// - Older console hosts report a width of 0 for some TrueType fonts; only the height is
+    ///   reliable then.
+    pub fn current() -> Result<FontInfo, TerminalError> {
+        let info = current_font(std_handle(STD_OUTPUT_HANDLE)?)?;
+        Ok(FontInfo {
+            face: face_name(&info.FaceName),
+            size: FontSize {
+                width: info.dwFontSize.X as i32,
+                height: info.dwFontSize.Y as i32,
+            },
+            weight: info.FontWeight,
+            raster: is_raster(&info),
+        })
+    }
+}
+
+/// Enum to represent the weight of a console font, see [`set`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontWeight {
+    Light,    // 300
+    Regular,  // 400, what the console uses by default
+    SemiBold, // 600
+    Bold,     // 700
+}
+
+impl FontWeight {
+    /// The weight as `FontWeight` of `CONSOLE_FONT_INFOEX` and GDI take it.
+    pub fn value(self) -> u32 {
+        match self {
+            FontWeight::Light => 300,
+            FontWeight::Regular => 400,
+            FontWeight::SemiBold => 600,
+            FontWeight::Bold => 700,
+        }
+    }
+}
+
+/// This function changes the console font with `SetCurrentConsoleFontEx`.
+///
+/// ## Returns:
+/// - `Ok(())` once the console took the font.
+/// - `Err(TerminalError::InvalidFont)` if `face` is empty, contains a null character or is
+///   longer than the 31 UTF-16 units the console stores, or if `points` is 0.
+/// - `Err(TerminalError)` if there's no standard handle or the console refuses the font.
+///
+/// ## Note:
+/// - `points` is converted to a cell height in pixels at the DPI of the console window, as the
+///   Properties dialog does (12 points are 16 pixels at 96 DPI).
+/// - The free functions of the crate measure again on every call and see the new size right
+///   away; a [`Terminal`](crate::terminal::Terminal) should use
+///   [`Terminal::set_font`](crate::terminal::Terminal::set_font), which refreshes it.
+/// - A face the system doesn't have is silently replaced by the console; read
+///   [`FontInfo::current`] to see what it picked.
+pub fn set(face: &str, points: u16, weight: FontWeight) -> Result<(), TerminalError> {
+    set_font(std_handle(STD_OUTPUT_HANDLE)?, face, points, weight)
+}
+
+/// The UTF-16 units of a face name, if it and the point size are valid for the console and
+/// GDI (31 units and a null in a `LF_FACESIZE` array).
+fn face_units(face: &str, points: u16) -> Result<Vec<u16>, TerminalError> {
+    let wide: Vec<u16> = face.encode_utf16().collect();
+    if points == 0 || wide.is_empty() || wide.len() >= LF_FACESIZE as usize || wide.contains(&0) {
+        return Err(TerminalError::InvalidFont);
+    }
+    Ok(wide)
+}
+
+/// Sets the font of a console output handle, see [`set`].
+pub(crate) fn set_font(
+    handle: HANDLE,
+    face: &str,
+    points: u16,
+    weight: FontWeight,
+) -> Result<(), TerminalError> {
+    let wide = face_units(face, points)?;
+    let mut info: CONSOLE_FONT_INFOEX = unsafe { std::mem::zeroed() };
+    let dpi = match console_dpi() {
+        0 => 96,
+        dpi => dpi,
+    };
+    info.cbSize = std::mem::size_of::<CONSOLE_FONT_INFOEX>() as u32;
+    // A width of 0 lets the console derive it from the height and the face.
+    info.dwFontSize.Y = ((points as u32 * dpi + 36) / 72).min(i16::MAX as u32) as i16;
+    info.FontFamily = FF_DONTCARE as u32;
+    info.FontWeight = weight.value();
+    info.FaceName[..wide.len()].copy_from_slice(&wide);
+    if unsafe { SetCurrentConsoleFontEx(handle, 0, &info) } == 0 {
+        return Err(TerminalError::FontNotSet(last_error_code()));
+    }
+    invalidate_all();
+    Ok(())
+}
+
+/// This function enumerates the fixed cell sizes the raster "Terminal" font comes in.
+///
+/// ## Returns:
+/// - The available sizes in pixels, sorted by height then width. Empty if GDI doesn't list the
+///   font (it ships with every Windows version, so this normally means no display is attached).
+///
+/// ## Note:
+/// - Raster fonts can't be scaled: the console picks one of these sizes, and
+///   `dwFontSize` of the current font is exactly the cell size in pixels.
+pub fn raster_sizes() -> Vec<FontSize> {
+    face_sizes(RASTER_FACE, OEM_CHARSET)
+}
+
+/// Fixed sizes GDI lists for a raster face, sorted by height then width.
+fn face_sizes(face: &str, charset: u8) -> Vec<FontSize> {
+    unsafe extern "system" fn collect(
+        _font: *const LOGFONTW,
+        metric: *const TEXTMETRICW,
+        font_type: u32,
+        sizes: LPARAM,
+    ) -> i32 {
+        let sizes = &mut *(sizes as *mut Vec<FontSize>);
+        if font_type & RASTER_FONTTYPE != 0 && !metric.is_null() {
+            let metric = &*metric;
+            let size = FontSize {
+                width: metric.tmAveCharWidth,
+                height: metric.tmHeight,
+            };
+            if !sizes
+                .iter()
+                .any(|s| s.width == size.width && s.height == size.height)
+            {
+                sizes.push(size);
+            }
+        }
+        1
+    }
+
+    let mut sizes: Vec<FontSize> = Vec::new();
+    unsafe {
+        let window = GetConsoleWindow();
+        let dc = GetDC(window);
+        if dc.is_null() {
+            return sizes;
+        }
+        let mut query: LOGFONTW = std::mem::zeroed();
+        query.lfCharSet = charset;
+        for (dst, src) in query
+            .lfFaceName
+            .iter_mut()
+            .take(31)
+            .zip(face.encode_utf16())
+        {
+            *dst = src;
+        }
+        EnumFontFamiliesExW(
+            dc,
+            &query,
+            Some(collect),
+            &mut sizes as *mut Vec<FontSize> as LPARAM,
+            0,
+        );
+        ReleaseDC(window, dc);
+    }
+    sizes.sort_by_key(|s| (s.height, s.width));
+    sizes
+}
+
+/// Struct to hold a monospace face installed on the system, as listed by [`enumerate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontFace {
+    pub face: String,         // Face name, to pass to `SetCurrentConsoleFontEx`
+    pub raster: bool,         // Whether it is a bitmap font, only available in fixed sizes
+    pub sizes: Vec<FontSize>, // Fixed sizes of a raster face; empty for scalable faces
+}
+
+/// This function lists the monospace faces installed on the system, for a font picker.
+///
+/// ## Returns:
+/// - One entry per face, sorted by name. Raster faces come with the cell sizes they exist in;
+///   TrueType and OpenType faces scale to any size, so their `sizes` are empty.
+/// - An empty list if GDI can't be reached (no display attached).
+///
+/// ## Note:
+/// - Faces are enumerated with `EnumFontFamiliesExW` on the DC of the console window, every
+///   charset included, keeping fixed-pitch faces and skipping vertical (`@`) ones. The console
+///   API to list its own fonts (`GetNumberOfConsoleFonts`) is undocumented and only covers
+///   the font in use.
+/// - conhost only offers a subset in its properties dialog (on older versions, what is listed
+///   under `HKLM\...\Console\TrueTypeFont`), and may substitute a face it doesn't support when
+///   it is set; read [`FontInfo::current`] back to know what was picked.
+pub fn enumerate() -> Vec<FontFace> {
+    unsafe extern "system" fn collect(
+        font: *const LOGFONTW,
+        _metric: *const TEXTMETRICW,
+        font_type: u32,
+        faces: LPARAM,
+    ) -> i32 {
+        let faces = &mut *(faces as *mut Vec<(String, bool, u8)>);
+        if font.is_null() {
+            return 1;
+        }
+        let font = &*font;
+        let face = face_name(&font.lfFaceName);
+        if font.lfPitchAndFamily & 3 == FIXED_PITCH
+            && !face.starts_with('@')
+            && !faces.iter().any(|(name, _, _)| *name == face)
+        {
+            faces.push((face, font_type & RASTER_FONTTYPE != 0, font.lfCharSet));
+        }
+        1
+    }
+
+    let mut faces: Vec<(String, bool, u8)> = Vec::new();
+    unsafe {
+        let window = GetConsoleWindow();
+        let dc = GetDC(window);
+        if dc.is_null() {
+            return Vec::new();
+        }
+        let mut query: LOGFONTW = std::mem::zeroed();
+        query.lfCharSet = DEFAULT_CHARSET;
+        EnumFontFamiliesExW(
+            dc,
+            &query,
+            Some(collect),
+            &mut faces as *mut Vec<(String, bool, u8)> as LPARAM,
+            0,
+        );
+        ReleaseDC(window, dc);
+    }
+    faces.sort_by_key(|(face, _, _)| face.to_lowercase());
+    faces
+        .into_iter()
+        .map(|(face, raster, charset)| FontFace {
+            sizes: match raster {
+                true => face_sizes(&face, charset),
+                false => Vec::new(),
+            },
+            face,
+            raster,
+        })
+        .collect()
+}
+
+/// Enum to represent the font variants a cell can be drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FontStyle {
+    #[default]
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+}
+
+impl From<&Attributes> for FontStyle {
+    fn from(attributes: &Attributes) -> Self {
+        match (attributes.bold, attributes.italic) {
+            (false, false) => FontStyle::Regular,
+            (true, false) => FontStyle::Bold,
+            (false, true) => FontStyle::Italic,
+            (true, true) => FontStyle::BoldItalic,
+        }
+    }
+}
+
+/// Struct to hold the measured metrics of the console font and its styled variants.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontMetrics {
+    pub face: String, // Face name of the console font
+    pub height: i32,  // Cell height in pixels
+    advances: [i32; 4],
+}
+
+impl FontMetrics {
+    /// This function measures the current console font in its regular, bold, italic and
+    /// bold-italic variants with GDI.
+    ///
+    /// ## Returns:
+    /// - `Ok(FontMetrics)` with the advance width of each variant.
+    /// - `Err(TerminalError)` if there's no standard handle, the font can't be read, or GDI
+    ///   can't create the font.
+    ///
+    /// ## Note:
+    /// - The console always lays text out on a fixed grid, so a wider bold glyph is clipped or
+    ///   overlaps its neighbour rather than moving the next cell. These widths matter for
+    ///   pixel-precise overlays drawn on top of styled text, not for cell positions.
+    pub fn current() -> Result<FontMetrics, TerminalError> {
+        let handle = std_handle(STD_OUTPUT_HANDLE)?;
+        let info = current_font(handle)?;
+        let face = face_name(&info.FaceName);
+        let height = info.dwFontSize.Y as i32;
+        let styles = [
+            FontStyle::Regular,
+            FontStyle::Bold,
+            FontStyle::Italic,
+            FontStyle::BoldItalic,
+        ];
+        let mut advances = [0; 4];
+        unsafe {
+            let dc = CreateCompatibleDC(std::ptr::null_mut());
+            if dc.is_null() {
+                return Err(TerminalError::NoFontInfo(last_error_code()));
+            }
+            for (advance, style) in advances.iter_mut().zip(styles) {
+                let measured = measure_cell(dc, &face, height, style);
+                match measured {
+                    Some(size) => *advance = size.width,
+                    None => {
+                        DeleteDC(dc);
+                        return Err(TerminalError::NoFontInfo(last_error_code()));
+                    }
+                }
+            }
+            DeleteDC(dc);
+        }
+        Ok(FontMetrics {
+            face,
+            height,
+            advances,
+        })
+    }
+
+    /// Advance width in pixels of a glyph drawn with `style`.
+    pub fn advance(&self, style: impl Into<FontStyle>) -> i32 {
+        self.advances[style.into() as usize]
+    }
+
+    /// Whether every variant has the same advance, i.e. styled text keeps the pixel grid.
+    pub fn is_uniform(&self) -> bool {
+        self.advances.iter().all(|&a| a == self.advances[0])
+    }
+}
+
+/// This function measures the cell of any font at any size and DPI with GDI, without
+/// changing the console font.
+///
+/// ## Returns:
+/// - `Ok(FontSize)` with the advance of "M" as width and the full line height (`tmHeight`,
+///   internal leading included) as height, in pixels at `dpi`.
+/// - `Err(TerminalError::InvalidFont)` for an empty or too long face name, or 0 points.
+/// - `Err(TerminalError::UnsupportedDpi)` if `dpi` is 0.
+/// - `Err(TerminalError)` if GDI can't create or measure the font.
+///
+/// ## Note:
+/// - `points` is the em height, as in font pickers: Consolas 12pt at 96 DPI is a 16 pixel em
+///   in a 19 pixel line. The console adds no spacing of its own to TrueType fonts, so this is
+///   the cell it would use for the same face and size.
+/// - A face the system doesn't have is replaced by GDI with its closest match, and measured.
+pub fn measure(face: &str, points: u16, dpi: u32) -> Result<FontSize, TerminalError> {
+    face_units(face, points)?;
+    if dpi == 0 {
+        return Err(TerminalError::UnsupportedDpi);
+    }
+    // A negative height asks GDI for the em height rather than the cell height.
+    let em = ((points as u32 * dpi + 36) / 72) as i32;
+    measure_height(face, -em)
+}
+
+/// Measures `face` at a GDI height (`CreateFontW` semantics) in the DC of the console window,
+/// or of the screen without one.
+pub(crate) fn measure_height(face: &str, height: i32) -> Result<FontSize, TerminalError> {
+    unsafe {
+        let window = GetConsoleWindow();
+        let dc = GetDC(window);
+        if dc.is_null() {
+            return Err(TerminalError::NoFontInfo(last_error_code()));
+        }
+        let measured = measure_cell(dc, face, height, FontStyle::Regular);
+        ReleaseDC(window, dc);
+        measured.ok_or_else(|| TerminalError::NoFontInfo(last_error_code()))
+    }
+}
+
+/// Creates a GDI font for `face` (truncated to the 31 characters LOGFONT allows) with a cell
+/// height of `height` pixels. Returns null on failure; the caller owns the font.
+pub(crate) unsafe fn create_font(face: &str, height: i32, style: FontStyle) -> HFONT {
+    let mut wide: Vec<u16> = face.encode_utf16().take(31).collect();
+    wide.push(0);
+    let (bold, italic) = match style {
+        FontStyle::Regular => (false, false),
+        FontStyle::Bold => (true, false),
+        FontStyle::Italic => (false, true),
+        FontStyle::BoldItalic => (true, true),
+    };
+    CreateFontW(
+        height,
+        0,
+        0,
+        0,
+        if bold { FW_BOLD } else { FW_NORMAL } as i32,
+        italic as u32,
+        0,
+        0,
+        DEFAULT_CHARSET as u32,
+        0,
+        0,
+        0,
+        0,
+        wide.as_ptr(),
+    )
+}
+
+/// Creates `face` at `height` pixels with `style` in `dc` and measures one cell with it.
+///
+/// The width is the extent of "M" (the widest Latin glyph, and the same as any other in a
+/// monospace font), the height the font's `tmHeight`.
+pub(crate) unsafe fn measure_cell(
+    dc: HDC,
+    face: &str,
+    height: i32,
+    style: FontStyle,
+) -> Option<FontSize> {
+    let font = create_font(face, height, style);
+    if font.is_null() {
+        return None;
+    }
+    let previous = SelectObject(dc, font);
+    let mut metric: TEXTMETRICW = std::mem::zeroed();
+    let mut extent = SIZE { cx: 0, cy: 0 };
+    let sample = [b'M' as u16];
+    let ok = GetTextMetricsW(dc, &mut metric) != 0
+        && GetTextExtentPoint32W(dc, sample.as_ptr(), 1, &mut extent) != 0;
+    SelectObject(dc, previous);
+    DeleteObject(font);
+    ok.then_some(FontSize {
+        width: extent.cx,
+        height: metric.tmHeight,
+    })
+}
+
+/// OpenType table tag as `GetFontData` expects it (the four bytes in file order).
+const fn table_tag(tag: &[u8; 4]) -> u32 {
+    u32::from_le_bytes(*tag)
+}
+
+/// Reads an OpenType table of the font selected into `dc`, empty if it has none.
+unsafe fn font_table(dc: HDC, tag: &[u8; 4]) -> Vec<u8> {
+    let tag = table_tag(tag);
+    let len = GetFontData(dc, tag, 0, std::ptr::null_mut(), 0);
+    let mut table = Vec::new();
+    if len != u32::MAX && len > 0 {
+        table.resize(len as usize, 0u8);
+        if GetFontData(dc, tag, 0, table.as_mut_ptr().cast(), len) != len {
+            table.clear();
+        }
+    }
+    table
+}
+
+/// GSUB features that substitute sequences of characters with ligature glyphs.
+const LIGATURE_FEATURES: [&[u8; 4]; 3] = [b"liga", b"dlig", b"calt"];
+
+/// This function tells whether a font can render programming ligatures, by looking for
+/// ligature features in its OpenType `GSUB` table.
+///
+/// ## Returns:
+/// - `Ok(true)` for fonts like Cascadia Code or Fira Code, `Ok(false)` for fonts without
+///   ligature substitutions (and for raster fonts, which have no OpenType tables).
+/// - `Err(TerminalError)` if GDI can't create the font.
+///
+/// ## Note:
+/// - Classic conhost never applies ligatures even with such a font, Windows Terminal does.
+///   Either way a ligature keeps the cells of its characters, see `measure::cells_exact`.
+pub fn has_ligatures(face: &str) -> Result<bool, TerminalError> {
+    unsafe {
+        let dc = CreateCompatibleDC(std::ptr::null_mut());
+        if dc.is_null() {
+            return Err(TerminalError::NoFontInfo(last_error_code()));
+        }
+        let font = create_font(face, 16, FontStyle::Regular);
+        if font.is_null() {
+            DeleteDC(dc);
+            return Err(TerminalError::NoFontInfo(last_error_code()));
+        }
+        let previous = SelectObject(dc, font);
+        let table = font_table(dc, b"GSUB");
+        SelectObject(dc, previous);
+        DeleteObject(font);
+        DeleteDC(dc);
+        Ok(gsub_has_features(&table, &LIGATURE_FEATURES))
+    }
+}
+
+/// This function tells whether the current console font can render ligatures.
+pub fn current_has_ligatures() -> Result<bool, TerminalError> {
+    let info = current_font(std_handle(STD_OUTPUT_HANDLE)?)?;
+    if is_raster(&info) {
+        return Ok(false);
+    }
+    has_ligatures(&face_name(&info.FaceName))
+}
+
+/// Looks for any of `features` in the FeatureList of a big-endian GSUB table.
+fn gsub_has_features(table: &[u8], features: &[&[u8; 4]]) -> bool {
+    let u16_at = |at: usize| {
+        table
+            .get(at..at + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+    };
+    // Header: majorVersion, minorVersion, scriptListOffset, featureListOffset, ...
+    let Some(list) = u16_at(6).map(usize::from) else {
+        return false;
+    };
+    let count = u16_at(list).unwrap_or(0) as usize;
+    (0..count).any(|i| {
+        // FeatureRecord: 4-byte tag + 2-byte offset.
+        let at = list + 2 + i * 6;
+        table
+            .get(at..at + 4)
+            .is_some_and(|tag| features.iter().any(|f| f[..] == *tag))
+    })
+}
+
+/// Struct to hold how the console host presents emoji.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmojiSupport {
+    pub width: usize, // Cells the host advances for an emoji (1 on legacy conhost, 2 elsewhere)
+    pub glyphs: bool, // Whether emoji get a real glyph rather than a replacement box ("tofu")
+}
+
+/// Emoji written to the probe buffer: one with default emoji presentation.
+const EMOJI_SAMPLE: &str = "\u{1F600}";
+
+/// This function detects how the console host lays out and draws emoji.
+///
+/// ## Returns:
+/// - `Ok(EmojiSupport)` with the width measured by writing a sample emoji into an inactive
+///   screen buffer, which never shows on screen, and whether it can be drawn.
+/// - `Err(TerminalError)` if there's no console or its font can't be read.
+///
+/// ## Note:
+/// - Windows Terminal (`$WT_SESSION`) falls back to Segoe UI Emoji for missing glyphs; classic
+///   conhost only draws what the console font itself contains, which for Consolas and the
+///   raster font is nothing.
+/// - The result is cached by `measure::cells`, which uses the measured width for emoji.
+pub fn emoji_support() -> Result<EmojiSupport, TerminalError> {
+    let width = probe_width(EMOJI_SAMPLE)
+        .ok_or_else(|| TerminalError::NoScreenBufferInfo(last_error_code()))?;
+    let fallback = std::env::var_os("WT_SESSION").is_some_and(|value| !value.is_empty());
+    let glyphs = fallback || {
+        let info = current_font(std_handle(STD_OUTPUT_HANDLE)?)?;
+        !is_raster(&info) && has_glyph(&face_name(&info.FaceName), EMOJI_SAMPLE)
+    };
+    Ok(EmojiSupport { width, glyphs })
+}
+
+/// Cells the console cursor advances when `text` is written, measured in a scratch screen
+/// buffer so the visible one is left alone.
+fn probe_width(text: &str) -> Option<usize> {
+    unsafe {
+        let buffer = CreateConsoleScreenBuffer(
+            GENERIC_READ | GENERIC_WRITE,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            std::ptr::null(),
+            CONSOLE_TEXTMODE_BUFFER,
+            std::ptr::null(),
+        );
+        if buffer.is_null() || buffer == INVALID_HANDLE_VALUE {
+            return None;
+        }
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let mut written = 0;
+        let mut info: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
+        let ok = WriteConsoleW(
+            buffer,
+            units.as_ptr().cast(),
+            units.len() as u32,
+            &mut written,
+            std::ptr::null(),
+        ) != 0
+            && GetConsoleScreenBufferInfo(buffer, &mut info) != 0;
+        CloseHandle(buffer);
+        ok.then_some(info.dwCursorPosition.X as usize)
+    }
+}
+
+/// Whether a font has a glyph for every character of `text`.
+///
+/// `GetGlyphIndicesW` maps UTF-16 units one by one, so it never finds characters beyond the
+/// BMP (most emoji); those are looked up in the font's `cmap` table instead. Fonts without one
+/// (raster fonts) fall back to `GetGlyphIndicesW`.
+fn has_glyph(face: &str, text: &str) -> bool {
+    unsafe {
+        let dc = CreateCompatibleDC(std::ptr::null_mut());
+        if dc.is_null() {
+            return false;
+        }
+        let font = create_font(face, 16, FontStyle::Regular);
+        if font.is_null() {
+            DeleteDC(dc);
+            return false;
+        }
+        let previous = SelectObject(dc, font);
+        let table = font_table(dc, b"cmap");
+        let found = match Cmap::parse(&table) {
+            Some(cmap) => text.chars().all(|c| cmap.maps(c)),
+            None => {
+                let units: Vec<u16> = text.encode_utf16().collect();
+                let mut indices = vec![0u16; units.len()];
+                GetGlyphIndicesW(
+                    dc,
+                    units.as_ptr(),
+                    units.len() as i32,
+                    indices.as_mut_ptr(),
+                    GGI_MARK_NONEXISTING_GLYPHS,
+                ) != u32::MAX
+                    && indices.iter().all(|&index| index != 0xFFFF)
+            }
+        };
+        SelectObject(dc, previous);
+        DeleteObject(font);
+        DeleteDC(dc);
+        found
+    }
+}
+
+/// Enum to represent the Unicode subtable of a big-endian OpenType `cmap` table.
+#[derive(Debug, Clone, Copy)]
+enum Cmap<'a> {
+    Full(&'a [u8]), // Format 12, every code point
+    Bmp(&'a [u8]),  // Format 4, BMP only
+}
+
+impl<'a> Cmap<'a> {
+    /// Picks the format 12 subtable if there's one, else the format 4 one.
+    fn parse(table: &'a [u8]) -> Option<Cmap<'a>> {
+        let count = be_u16(table, 2)? as usize;
+        let mut bmp = None;
+        for i in 0..count {
+            // EncodingRecord: platformID, encodingID, 4-byte subtable offset.
+            let record = 4 + i * 8;
+            let platform = be_u16(table, record)?;
+            let encoding = be_u16(table, record + 2)?;
+            let Some(subtable) = table.get(be_u32(table, record + 4)? as usize..) else {
+                continue;
+            };
+            match (platform, encoding, be_u16(subtable, 0)) {
+                (0, 4 | 6, Some(12)) | (3, 10, Some(12)) => return Some(Cmap::Full(subtable)),
+                (0, 3, Some(4)) | (3, 1, Some(4)) => bmp = bmp.or(Some(Cmap::Bmp(subtable))),
+                _ => {}
+            }
+        }
+        bmp
+    }
+
+    /// Whether `c` maps to a glyph other than `.notdef`.
+    fn maps(self, c: char) -> bool {
+        self.glyph(c as u32).is_some_and(|glyph| glyph != 0)
+    }
+
+    fn glyph(self, c: u32) -> Option<u32> {
+        match self {
+            Cmap::Full(table) => {
+                // format, reserved, length, language, numGroups, then 12-byte groups.
+                let groups = be_u32(table, 12)? as usize;
+                (0..groups).find_map(|i| {
+                    let group = 16 + i * 12;
+                    let start = be_u32(table, group)?;
+                    let end = be_u32(table, group + 4)?;
+                    (start..=end)
+                        .contains(&c)
+                        .then(|| be_u32(table, group + 8))?
+                        .map(|glyph| glyph + (c - start))
+                })
+            }
+            Cmap::Bmp(table) => {
+                let c = u16::try_from(c).ok()?;
+                // format, length, language, segCountX2, searchRange, entrySelector, rangeShift,
+                // then the endCode, startCode, idDelta and idRangeOffset arrays.
+                let segments = be_u16(table, 6)? as usize / 2;
+                let ends = 14;
+                let starts = ends + segments * 2 + 2;
+                let deltas = starts + segments * 2;
+                let offsets = deltas + segments * 2;
+                let segment = (0..segments).find(|&i| be_u16(table, ends + i * 2) >= Some(c))?;
+                let start = be_u16(table, starts + segment * 2)?;
+                if c < start {
+                    return Some(0);
+                }
+                let delta = be_u16(table, deltas + segment * 2)?;
+                let offset = be_u16(table, offsets + segment * 2)? as usize;
+                if offset == 0 {
+                    return Some(c.wrapping_add(delta) as u32);
+                }
+                let at = offsets + segment * 2 + offset + (c - start) as usize * 2;
+                match be_u16(table, at)? {
+                    0 => Some(0),
+                    glyph => Some(glyph.wrapping_add(delta) as u32),
+                }
+            }
+        }
+    }
+}
+
+fn be_u16(table: &[u8], at: usize) -> Option<u16> {
+    table
+        .get(at..at + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn be_u32(table: &[u8], at: usize) -> Option<u32> {
+    table
+        .get(at..at + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A cmap with one subtable per `(platform, encoding, subtable)`.
+    fn cmap(subtables: &[(u16, u16, Vec<u8>)]) -> Vec<u8> {
+        let mut table = [0u16.to_be_bytes(), (subtables.len() as u16).to_be_bytes()].concat();
+        let mut offset = 4 + subtables.len() * 8;
+        for (platform, encoding, subtable) in subtables {
+            table.extend(platform.to_be_bytes());
+            table.extend(encoding.to_be_bytes());
+            table.extend((offset as u32).to_be_bytes());
+            offset += subtable.len();
+        }
+        for (_, _, subtable) in subtables {
+            table.extend(subtable);
+        }
+        table
+    }
+
+    /// Format 4: 'A'..='C' by delta, 'a'..='b' through the glyph array, then the 0xFFFF end.
+    fn format4() -> Vec<u8> {
+        let header = [4, 44, 0, 6, 4, 1, 2]; // 3 segments
+        let ends = [0x43, 0x62, 0xFFFF];
+        let starts = [0x41, 0x61, 0xFFFF];
+        let deltas = [10u16.wrapping_sub(0x41), 0, 1];
+        let offsets = [0, 4, 0]; // The second segment points at the glyph array
+        let glyphs = [20, 0]; // 'a' has glyph 20, 'b' none
+        [
+            &header[..],
+            &ends,
+            &[0],
+            &starts,
+            &deltas,
+            &offsets,
+            &glyphs,
+        ]
+        .concat()
+        .iter()
+        .flat_map(|word: &u16| word.to_be_bytes())
+        .collect()
+    }
+
+    /// Format 12: U+1F600..=U+1F602 from glyph 100.
+    fn format12() -> Vec<u8> {
+        let words: [u32; 7] = [12 << 16, 28, 0, 1, 0x1F600, 0x1F602, 100];
+        words.iter().flat_map(|word| word.to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn bmp_subtable() {
+        let table = cmap(&[(3, 1, format4())]);
+        let cmap = Cmap::parse(&table).unwrap();
+        assert_eq!(cmap.glyph('A' as u32), Some(10));
+        assert_eq!(cmap.glyph('C' as u32), Some(12));
+        assert_eq!(cmap.glyph('a' as u32), Some(20));
+        assert!(!cmap.maps('b'));
+        assert!(!cmap.maps('D'));
+        assert!(!cmap.maps('\u{1F600}'));
+    }
+
+    #[test]
+    fn full_subtable_maps_astral_code_points() {
+        let table = cmap(&[(3, 1, format4()), (3, 10, format12())]);
+        let cmap = Cmap::parse(&table).unwrap();
+        assert_eq!(cmap.glyph(0x1F601), Some(101));
+        assert!(cmap.maps('\u{1F600}'));
+        assert!(!cmap.maps('\u{1F603}'));
+        assert!(!cmap.maps('A'));
+    }
+
+    #[test]
+    fn rejects_truncated_tables() {
+        assert!(Cmap::parse(&[]).is_none());
+        assert!(Cmap::parse(&cmap(&[(1, 0, format4())])).is_none());
+        let mut table = cmap(&[(3, 10, format12())]);
+        table.truncate(table.len() - 4);
+        assert!(!Cmap::parse(&table).unwrap().maps('\u{1F600}'));
+    }
+}
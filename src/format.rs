@@ -0,0 +1,225 @@
+use std::ptr;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+use windows_sys::Win32::Globalization::{
+    GetLocaleInfoEx, LOCALE_SDECIMAL, LOCALE_SGROUPING, LOCALE_SSHORTDATE, LOCALE_STHOUSAND,
+};
+
+/// Struct to hold the number formatting conventions of a locale.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    pub thousands: String, // Digit group separator ("," in en-US, "." in de-DE, " " in fr-FR)
+    pub decimal: String,   // Decimal separator
+    pub grouping: Vec<u8>, // Group sizes from the right, the last one repeating ([3] or [3, 2])
+    pub date: String,      // Short date pattern ("M/d/yyyy" in en-US, "dd.MM.yyyy" in de-DE)
+}
+
+static OVERRIDE: RwLock<Option<Locale>> = RwLock::new(None);
+
+/// This function overrides the locale every formatting function (and the widgets using them)
+/// follows, e.g. to pin the output of tests. `None` goes back to the user's settings.
+pub fn set_locale(locale: Option<Locale>) {
+    *OVERRIDE.write().unwrap_or_else(|e| e.into_inner()) = locale;
+}
+
+impl Locale {
+    /// The conventions every fallback uses: `1,234,567.89` and ISO 8601 dates.
+    pub fn invariant() -> Self {
+        Locale {
+            thousands: ",".to_string(),
+            decimal: ".".to_string(),
+            grouping: vec![3],
+            date: "yyyy-MM-dd".to_string(),
+        }
+    }
+
+    /// The locale the formatting functions follow: the one given to [`set_locale`], or the
+    /// user's.
+    pub fn current() -> Locale {
+        OVERRIDE
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .unwrap_or_else(|| Locale::user().clone())
+    }
+
+    /// This function reads the user's regional settings through `GetLocaleInfoEx`.
+    ///
+    /// ## Note:
+    /// - The settings are read once and cached for the lifetime of the process.
+    /// - Any value that can't be read falls back to the invariant one.
+    pub fn user() -> &'static Locale {
+        static USER: OnceLock<Locale> = OnceLock::new();
+        USER.get_or_init(|| {
+            let fallback = Locale::invariant();
+            Locale {
+                thousands: locale_info(LOCALE_STHOUSAND).unwrap_or(fallback.thousands),
+                decimal: locale_info(LOCALE_SDECIMAL)
+                    .filter(|s| !s.is_empty())
+                    .unwrap_or(fallback.decimal),
+                grouping: locale_info(LOCALE_SGROUPING)
+                    .map(|s| parse_grouping(&s))
+                    .unwrap_or(fallback.grouping),
+                date: locale_info(LOCALE_SSHORTDATE)
+                    .filter(|s| !s.is_empty())
+                    .unwrap_or(fallback.date),
+            }
+        })
+    }
+
+    /// Formats an integer with digit grouping, e.g. `1234567` -> `1,234,567` (or `12,34,567`
+    /// with Indian grouping).
+    pub fn count(&self, n: u64) -> String {
+        let digits = n.to_string();
+        let mut groups = Vec::new();
+        let mut end = digits.len();
+        let mut sizes = self.grouping.iter().copied();
+        let mut size = sizes.next().unwrap_or(0);
+        while end > 0 {
+            if size == 0 {
+                groups.push(&digits[..end]);
+                break;
+            }
+            let start = end.saturating_sub(size as usize);
+            groups.push(&digits[start..end]);
+            end = start;
+            size = sizes.next().unwrap_or(size);
+        }
+        groups.reverse();
+        groups.join(&self.thousands)
+    }
+
+    /// Formats a float with `decimals` digits after the decimal separator and a grouped
+    /// integer part.
+    pub fn decimal(&self, value: f64, decimals: usize) -> String {
+        let formatted = format!("{:.*}", decimals, value.abs());
+        let (int, frac) = formatted.split_once('.').unwrap_or((&formatted, ""));
+        let mut out = String::new();
+        if value.is_sign_negative() && formatted.chars().any(|c| c != '0' && c != '.') {
+            out.push('-');
+        }
+        out.push_str(&self.count(int.parse().unwrap_or(0)));
+        if !frac.is_empty() {
+            out.push_str(&self.decimal);
+            out.push_str(frac);
+        }
+        out
+    }
+
+    /// Formats a byte count with binary units, e.g. `1536` -> `1.5 KiB`.
+    pub fn bytes(&self, n: u64) -> String {
+        const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+        if n < 1024 {
+            return format!("{} B", n);
+        }
+        let mut value = n as f64 / 1024.0;
+        let mut unit = 0;
+        while value >= 1024.0 && unit + 1 < UNITS.len() {
+            value /= 1024.0;
+            unit += 1;
+        }
+        let decimals = if value < 10.0 { 1 } else { 0 };
+        format!("{} {}", self.decimal(value, decimals), UNITS[unit])
+    }
+
+    /// Formats a calendar date with the short date pattern, e.g. `3/7/2024` in en-US or
+    /// `07.03.2024` in de-DE.
+    ///
+    /// ## Note:
+    /// - The pattern follows the `LOCALE_SSHORTDATE` syntax: `d`/`dd` for the day, `M`/`MM` for
+    ///   the month, `yy`/`yyyy` for the year, and `'...'` for literal text. Month and day names
+    ///   (`MMM`, `ddd`) are written as numbers, and era specifiers (`g`) are dropped.
+    pub fn date(&self, year: i32, month: u32, day: u32) -> String {
+        let mut out = String::new();
+        let mut chars = self.date.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\'' {
+                out.extend(chars.by_ref().take_while(|&c| c != '\''));
+                continue;
+            }
+            let mut run = 1;
+            while chars.next_if_eq(&c).is_some() {
+                run += 1;
+            }
+            match (c, run) {
+                ('d', 1) => out.push_str(&day.to_string()),
+                ('d', _) => out.push_str(&format!("{:02}", day)),
+                ('M', 1) => out.push_str(&month.to_string()),
+                ('M', _) => out.push_str(&format!("{:02}", month)),
+                ('y', 1) => out.push_str(&(year.rem_euclid(100)).to_string()),
+                ('y', 2) => out.push_str(&format!("{:02}", year.rem_euclid(100))),
+                ('y', _) => out.push_str(&year.to_string()),
+                ('g', _) => {}
+                (c, run) => out.extend(std::iter::repeat_n(c, run)),
+            }
+        }
+        out.trim().to_string()
+    }
+}
+
+/// Parses a `LOCALE_SGROUPING` string: "3;0" repeats groups of 3, "3;2;0" is 3 then
+/// repeated 2s, and a missing trailing ";0" means the digits left are not grouped.
+fn parse_grouping(grouping: &str) -> Vec<u8> {
+    let mut sizes: Vec<u8> = grouping
+        .split(';')
+        .filter_map(|size| size.trim().parse().ok())
+        .collect();
+    match sizes.last() {
+        Some(0) => {
+            sizes.pop();
+        }
+        Some(_) => sizes.push(0),
+        None => {}
+    }
+    if sizes.is_empty() {
+        vec![3]
+    } else {
+        sizes
+    }
+}
+
+fn locale_info(kind: u32) -> Option<String> {
+    unsafe {
+        let mut buffer = [0u16; 32];
+        let len = GetLocaleInfoEx(ptr::null(), kind, buffer.as_mut_ptr(), buffer.len() as i32);
+        if len <= 0 {
+            return None;
+        }
+        // The returned length includes the terminating null.
+        Some(String::from_utf16_lossy(&buffer[..len as usize - 1]))
+    }
+}
+
+/// This function formats an integer with the user's digit grouping, e.g. `1,234,567`.
+pub fn count(n: u64) -> String {
+    Locale::current().count(n)
+}
+
+/// This function formats a byte count with binary units and the user's decimal separator,
+/// e.g. `1.5 MiB` (or `1,5 MiB` in de-DE).
+pub fn bytes(n: u64) -> String {
+    Locale::current().bytes(n)
+}
+
+/// This function formats a calendar date with the user's short date pattern, e.g. `3/7/2024`
+/// (or `07.03.2024` in de-DE).
+pub fn date(year: i32, month: u32, day: u32) -> String {
+    Locale::current().date(year, month, day)
+}
+
+/// This function formats a duration compactly for progress and ETA displays.
+///
+/// ## Returns:
+/// - `850ms` below a second, `42s` below a minute, `4m 05s` below an hour, `1h 02m` above,
+///   and `3d 04h` past a day.
+pub fn duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0 => format!("{}ms", duration.subsec_millis()),
+        1..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {:02}s", secs / 60, secs % 60),
+        3600..=86399 => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d {:02}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
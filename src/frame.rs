@@ -0,0 +1,911 @@
+use windows_sys::Win32::Foundation::HANDLE;
+use windows_sys::Win32::System::Console::{
+    GetConsoleScreenBufferInfoEx, ReadConsoleOutputW, WriteConsoleOutputW, CHAR_INFO,
+    COMMON_LVB_LEADING_BYTE, COMMON_LVB_REVERSE_VIDEO, COMMON_LVB_TRAILING_BYTE,
+    COMMON_LVB_UNDERSCORE, CONSOLE_SCREEN_BUFFER_INFOEX, COORD, SMALL_RECT, STD_OUTPUT_HANDLE,
+};
+
+use crate::art::Art;
+use crate::console::std_handle;
+use crate::image::Rect;
+use crate::measure::cells_exact;
+use crate::style::{quantize, Attributes, Palette, Rgb, Underline};
+use crate::{last_error_code, TerminalError};
+
+/// Struct to hold a cell of a [`Frame`], with resolved colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameCell {
+    pub ch: char,
+    pub fg: Rgb,
+    pub bg: Rgb,
+    pub attributes: Attributes,
+    pub wide_tail: bool, // Right half of a double-width glyph drawn by the cell on its left
+}
+
+impl Default for FrameCell {
+    fn default() -> Self {
+        FrameCell {
+            ch: ' ',
+            fg: Palette::Ansi16.rgb(7),
+            bg: Palette::Ansi16.rgb(0),
+            attributes: Attributes::default(),
+            wide_tail: false,
+        }
+    }
+}
+
+/// Struct to hold a rectangle of terminal cells, e.g. a capture of the console window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<FrameCell>, // Row-major, `width * height` cells
+}
+
+impl Frame {
+    /// A frame of blank cells in the default colors.
+    pub fn new(width: usize, height: usize) -> Self {
+        Frame {
+            width,
+            height,
+            cells: vec![FrameCell::default(); width * height],
+        }
+    }
+
+    /// The cell at a column and row, `None` outside the frame.
+    pub fn cell(&self, x: usize, y: usize) -> Option<&FrameCell> {
+        if x >= self.width {
+            return None;
+        }
+        self.cells.get(y * self.width + x)
+    }
+
+    pub fn cell_mut(&mut self, x: usize, y: usize) -> Option<&mut FrameCell> {
+        if x >= self.width {
+            return None;
+        }
+        self.cells.get_mut(y * self.width + x)
+    }
+
+    /// The rows of the frame.
+    pub fn rows(&self) -> impl Iterator<Item = &[FrameCell]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    /// This function draws the frame into the screen buffer of the standard output, its top
+    /// left cell at `column` and `row` of the buffer.
+    ///
+    /// ## Returns:
+    /// - `Err(TerminalError)` if there's no console or the buffer can't be written.
+    ///
+    /// ## Note:
+    /// - See [`FixedFrame::draw`]; this allocates one row of console cells per call.
+    pub fn draw(&self, column: i16, row: i16) -> Result<(), TerminalError> {
+        let handle = std_handle(STD_OUTPUT_HANDLE)?;
+        let mut buffer = vec![unsafe { std::mem::zeroed::<CHAR_INFO>() }; self.width];
+        for (y, cells) in self.rows().enumerate() {
+            let Some(row) = offset(row, y) else {
+                break;
+            };
+            write_row(handle, cells, &mut buffer, column, row)?;
+        }
+        Ok(())
+    }
+
+    /// This function captures the visible window of the standard output console.
+    ///
+    /// ## Returns:
+    /// - `Ok(Frame)` with the characters, the colors resolved through the console's own color
+    ///   table, and reverse video and underline from the legacy attributes.
+    /// - `Err(TerminalError)` if there's no console or the buffer can't be read.
+    ///
+    /// ## Note:
+    /// - Only what the console API exposes is captured: VT-only styles such as italic, or
+    ///   24-bit colors, come back as their nearest legacy attributes.
+    pub fn capture() -> Result<Frame, TerminalError> {
+        Self::capture_from(std_handle(STD_OUTPUT_HANDLE)?)
+    }
+
+    /// Captures the visible window of the screen buffer `handle`.
+    pub(crate) fn capture_from(handle: HANDLE) -> Result<Frame, TerminalError> {
+        unsafe {
+            let mut info: CONSOLE_SCREEN_BUFFER_INFOEX = std::mem::zeroed();
+            info.cbSize = std::mem::size_of::<CONSOLE_SCREEN_BUFFER_INFOEX>() as u32;
+            if GetConsoleScreenBufferInfoEx(handle, &mut info) == 0 {
+                return Err(TerminalError::NoScreenBufferInfo(last_error_code()));
+            }
+            let window = info.srWindow;
+            let width = (window.Right - window.Left + 1).max(0) as usize;
+            let height = (window.Bottom - window.Top + 1).max(0) as usize;
+            let table = info
+                .ColorTable
+                .map(|color| Rgb::new(color as u8, (color >> 8) as u8, (color >> 16) as u8));
+            let mut frame = Frame::new(width, height);
+            // One row per call, as the console caps a single read at about 64 KiB.
+            let mut row = vec![std::mem::zeroed::<CHAR_INFO>(); width];
+            for y in 0..height {
+                let mut region = SMALL_RECT {
+                    Left: window.Left,
+                    Top: window.Top + y as i16,
+                    Right: window.Right,
+                    Bottom: window.Top + y as i16,
+                };
+                let size = COORD {
+                    X: width as i16,
+                    Y: 1,
+                };
+                if ReadConsoleOutputW(
+                    handle,
+                    row.as_mut_ptr(),
+                    size,
+                    COORD { X: 0, Y: 0 },
+                    &mut region,
+                ) == 0
+                {
+                    return Err(TerminalError::NoScreenBufferInfo(last_error_code()));
+                }
+                let mut x = 0;
+                while x < width {
+                    let unit = row[x].Char.UnicodeChar;
+                    let attributes = row[x].Attributes;
+                    let mut cell = FrameCell {
+                        fg: table[(attributes & 0x0f) as usize],
+                        bg: table[(attributes >> 4 & 0x0f) as usize],
+                        ..FrameCell::default()
+                    };
+                    if attributes & COMMON_LVB_REVERSE_VIDEO != 0 {
+                        cell.attributes.reverse = true;
+                    }
+                    if attributes & COMMON_LVB_UNDERSCORE != 0 {
+                        cell.attributes.underline = Underline::Single;
+                    }
+                    let next = row.get(x + 1).map(|c| c.Char.UnicodeChar);
+                    let (ch, tail) = match (unit, next) {
+                        (0xD800..=0xDBFF, Some(low @ 0xDC00..=0xDFFF)) => (
+                            char::decode_utf16([unit, low]).next().and_then(Result::ok),
+                            true,
+                        ),
+                        _ => (char::from_u32(unit as u32), false),
+                    };
+                    cell.ch = ch.filter(|&c| c != '\0').unwrap_or(' ');
+                    let dbcs_lead = attributes & COMMON_LVB_LEADING_BYTE != 0;
+                    *frame.cell_mut(x, y).expect("in bounds") = cell;
+                    if (tail || dbcs_lead) && x + 1 < width {
+                        let trail = frame.cell_mut(x + 1, y).expect("in bounds");
+                        *trail = FrameCell {
+                            ch: ' ',
+                            wide_tail: true,
+                            ..cell
+                        };
+                        x += 1;
+                    } else if attributes & COMMON_LVB_TRAILING_BYTE != 0 {
+                        frame.cell_mut(x, y).expect("in bounds").wide_tail = true;
+                    }
+                    x += 1;
+                }
+            }
+            Ok(frame)
+        }
+    }
+}
+
+/// Struct to hold a [`Frame`] being drawn through a clip rectangle, see [`Frame::with_clip`].
+///
+/// Coordinates are those of the frame; whatever falls outside the rectangle is dropped.
+#[derive(Debug)]
+pub struct Clip<'a> {
+    frame: &'a mut Frame,
+    rect: Rect, // Always inside the frame
+}
+
+impl Frame {
+    /// This function runs `draw` with a view of the frame that only writes inside `rect`, so a
+    /// pane or a popup can't paint over its neighbours whatever it draws.
+    ///
+    /// ## Returns:
+    /// - What `draw` returns.
+    ///
+    /// ## Note:
+    /// - `rect` is cut to the frame; a clip outside of it draws nothing.
+    /// - A wide glyph that would straddle an edge of the clip is dropped as a whole.
+    pub fn with_clip<T>(&mut self, rect: Rect, draw: impl FnOnce(&mut Clip<'_>) -> T) -> T {
+        let whole = Rect {
+            left: 0,
+            top: 0,
+            columns: self.width.min(u16::MAX as usize) as u16,
+            rows: self.height.min(u16::MAX as usize) as u16,
+        };
+        draw(&mut Clip {
+            rect: intersect(whole, rect),
+            frame: self,
+        })
+    }
+}
+
+impl Clip<'_> {
+    /// The part of the frame this clip writes to.
+    pub fn area(&self) -> Rect {
+        self.rect
+    }
+
+    /// Whether a cell of the frame is inside the clip.
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        let rect = self.rect;
+        (rect.left as usize..rect.left as usize + rect.columns as usize).contains(&x)
+            && (rect.top as usize..rect.top as usize + rect.rows as usize).contains(&y)
+    }
+
+    /// The cell at a column and row of the frame, `None` outside the clip.
+    pub fn cell_mut(&mut self, x: usize, y: usize) -> Option<&mut FrameCell> {
+        match self.contains(x, y) {
+            true => self.frame.cell_mut(x, y),
+            false => None,
+        }
+    }
+
+    /// Sets a cell if it is inside the clip. The glyph a cell was half of loses its other
+    /// half too, so no half of a wide glyph remains on screen.
+    pub fn put(&mut self, x: usize, y: usize, cell: FrameCell) {
+        if !self.contains(x, y) {
+            return;
+        }
+        let width = self.frame.width;
+        let row = &mut self.frame.cells[y * width..(y + 1) * width];
+        // A tail put on a tail keeps the lead on its left, which is the new glyph's.
+        if row[x].wide_tail && x > 0 && !cell.wide_tail {
+            row[x - 1].ch = ' ';
+        }
+        if row.get(x + 1).is_some_and(|next| next.wide_tail) {
+            row[x + 1] = FrameCell {
+                ch: ' ',
+                wide_tail: false,
+                ..row[x + 1]
+            };
+        }
+        row[x] = cell;
+    }
+
+    /// This function writes a line of text from `x`, `y` in the colors and attributes of
+    /// `style`, stopping at the right edge of the clip.
+    ///
+    /// ## Returns:
+    /// - The column just past the last cell written, or past the edge when the text is cut.
+    pub fn text(&mut self, x: usize, y: usize, text: &str, style: FrameCell) -> usize {
+        let mut column = x;
+        for span in cells_exact(text) {
+            let Some(ch) = text[span.start..span.end].chars().next() else {
+                continue;
+            };
+            let start = x + span.column;
+            column = start + span.width;
+            // Drop glyphs that don't fit whole; the cells stay as they were.
+            if !(start..column).all(|cx| self.contains(cx, y)) {
+                continue;
+            }
+            self.put(start, y, FrameCell { ch, ..style });
+            if span.width == 2 {
+                let tail = FrameCell {
+                    ch: ' ',
+                    wide_tail: true,
+                    ..style
+                };
+                self.put(start + 1, y, tail);
+            }
+        }
+        column
+    }
+
+    /// Fills a rectangle of the frame with `cell`, as far as it is inside the clip.
+    pub fn fill(&mut self, rect: Rect, cell: FrameCell) {
+        let rect = intersect(self.rect, rect);
+        for y in rect.top as usize..rect.top as usize + rect.rows as usize {
+            for x in rect.left as usize..rect.left as usize + rect.columns as usize {
+                self.put(x, y, cell);
+            }
+        }
+    }
+
+    /// Copies another frame, e.g. an image or a widget drawn on its own, with its top left
+    /// cell at `x`, `y`.
+    pub fn blit(&mut self, x: usize, y: usize, frame: &Frame) {
+        for (dy, row) in frame.rows().enumerate() {
+            for (dx, cell) in row.iter().enumerate() {
+                let (cx, cy) = (x + dx, y + dy);
+                // A glyph goes with its tail, or not at all.
+                let lead = cell.wide_tail && dx > 0;
+                let tail = row.get(dx + 1).is_some_and(|next| next.wide_tail);
+                if (lead && !self.contains(cx - 1, cy)) || (tail && !self.contains(cx + 1, cy)) {
+                    continue;
+                }
+                self.put(cx, cy, *cell);
+            }
+        }
+    }
+
+    /// This function narrows the clip further for nested panes, see [`Frame::with_clip`].
+    pub fn with_clip<T>(&mut self, rect: Rect, draw: impl FnOnce(&mut Clip<'_>) -> T) -> T {
+        draw(&mut Clip {
+            rect: intersect(self.rect, rect),
+            frame: self.frame,
+        })
+    }
+}
+
+/// Cells both rectangles cover, empty at the corner of `a` when they don't meet.
+fn intersect(a: Rect, b: Rect) -> Rect {
+    let end = |start: u16, len: u16| start as u32 + len as u32;
+    let left = a.left.max(b.left);
+    let top = a.top.max(b.top);
+    let right = end(a.left, a.columns).min(end(b.left, b.columns));
+    let bottom = end(a.top, a.rows).min(end(b.top, b.rows));
+    Rect {
+        left,
+        top,
+        columns: right.saturating_sub(left as u32) as u16,
+        rows: bottom.saturating_sub(top as u32) as u16,
+    }
+}
+
+/// Struct to hold a frame of `W` x `H` cells stored inline, for small overlays (a HUD, a
+/// status box) rebuilt and drawn every frame without touching the heap.
+///
+/// [`Frame`] is the same for sizes known at run time, e.g. captures of the whole window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedFrame<const W: usize, const H: usize> {
+    pub cells: [[FrameCell; W]; H], // Rows of `W` cells
+}
+
+impl<const W: usize, const H: usize> Default for FixedFrame<W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const W: usize, const H: usize> FixedFrame<W, H> {
+    /// A frame of blank cells in the default colors.
+    pub fn new() -> Self {
+        FixedFrame {
+            cells: [[FrameCell::default(); W]; H],
+        }
+    }
+
+    /// The cell at a column and row, `None` outside the frame.
+    pub fn cell(&self, x: usize, y: usize) -> Option<&FrameCell> {
+        self.cells.get(y)?.get(x)
+    }
+
+    pub fn cell_mut(&mut self, x: usize, y: usize) -> Option<&mut FrameCell> {
+        self.cells.get_mut(y)?.get_mut(x)
+    }
+
+    /// The rows of the frame.
+    pub fn rows(&self) -> impl Iterator<Item = &[FrameCell]> {
+        self.cells.iter().map(|row| row.as_slice())
+    }
+
+    /// This function draws the frame into the screen buffer of the standard output, its top
+    /// left cell at `column` and `row` of the buffer, without allocating.
+    ///
+    /// ## Returns:
+    /// - `Err(TerminalError)` if there's no console or the buffer can't be written.
+    ///
+    /// ## Note:
+    /// - Cells go straight into the buffer with `WriteConsoleOutputW`, one row at a time from a
+    ///   buffer on the stack: no VT sequence is parsed, and the cursor doesn't move.
+    /// - Colors are written as the nearest of the 16 console colors and attributes as their
+    ///   legacy bits, see [`Attributes::legacy`]. Cells outside the buffer are clipped.
+    pub fn draw(&self, column: i16, row: i16) -> Result<(), TerminalError> {
+        let handle = std_handle(STD_OUTPUT_HANDLE)?;
+        let mut buffer = [unsafe { std::mem::zeroed::<CHAR_INFO>() }; W];
+        for (y, cells) in self.rows().enumerate() {
+            let Some(row) = offset(row, y) else {
+                break;
+            };
+            write_row(handle, cells, &mut buffer, column, row)?;
+        }
+        Ok(())
+    }
+}
+
+impl<const W: usize, const H: usize> From<&FixedFrame<W, H>> for Frame {
+    /// Copies the cells, e.g. to export the overlay with the `export` functions.
+    fn from(frame: &FixedFrame<W, H>) -> Self {
+        Frame {
+            width: W,
+            height: H,
+            cells: frame.cells.iter().flatten().copied().collect(),
+        }
+    }
+}
+
+/// Struct to hold a run of cells of a row that changed since the last [`Presenter::present`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change {
+    pub row: usize,   // Row of the frame
+    pub start: usize, // First changed cell
+    pub end: usize,   // One past the last changed cell
+}
+
+/// Unchanged cells between two changes of a row below which both are written at once, as one
+/// call costs about as much as writing a few more cells.
+const MERGE_GAP: usize = 4;
+
+/// Cells compared at once: on a fixed-size array the comparison is vectorized.
+const LANES: usize = 16;
+
+/// Struct to hold what a [`Presenter`] last drew, encoded the way the console stores it.
+#[derive(Debug, Clone, Default)]
+struct Shown {
+    width: usize,
+    height: usize,
+    at: (i16, i16),  // Buffer column and row of the top left cell
+    cells: Vec<u32>, // Every cell as `unit | attributes << 16`, the layout of a `CHAR_INFO`
+}
+
+/// Struct to hold the frame last drawn to the console, to redraw only the cells that changed.
+///
+/// Every [`Presenter::present`] encodes the frame into packed console cells (the character
+/// and the attributes `WriteConsoleOutputW` takes, 32 bits each), compares them with the ones
+/// drawn before, 16 at a time, and writes the runs that differ. A frame that changes a little
+/// between two presents costs a little to draw, even at 300 x 100 cells.
+#[derive(Debug, Default)]
+pub struct Presenter {
+    shown: Option<Shown>,
+    next: Shown, // What the last diff compared, drawn once committed
+    changes: Vec<Change>,
+}
+
+const _: () = assert!(std::mem::size_of::<CHAR_INFO>() == std::mem::size_of::<u32>());
+
+impl Presenter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// This function draws what changed in `frame` since the last present into the screen
+    /// buffer of the standard output, its top left cell at `column` and `row`.
+    ///
+    /// ## Returns:
+    /// - `Ok(cells)` with the number of cells written, 0 when nothing changed.
+    /// - `Err(TerminalError)` if there's no console or the buffer can't be written; the next
+    ///   present draws the whole frame then.
+    ///
+    /// ## Note:
+    /// - The first present, and any present after a change of size or position, draws the
+    ///   whole frame. So does the next one after [`Presenter::invalidate`].
+    /// - Only the console itself is compared with: call [`Presenter::invalidate`] when
+    ///   something else wrote over the frame (a clear, a scroll, a resize).
+    pub fn present(
+        &mut self,
+        frame: &Frame,
+        column: i16,
+        row: i16,
+    ) -> Result<usize, TerminalError> {
+        let handle = std_handle(STD_OUTPUT_HANDLE)?;
+        self.diff(frame, column, row);
+        let mut written = 0;
+        for change in &self.changes {
+            let Some(top) = offset(row, change.row) else {
+                break;
+            };
+            let packed = &self.next.cells[change.row * frame.width..][..frame.width];
+            // A packed cell has the layout of a `CHAR_INFO` on little-endian Windows.
+            let cells = unsafe {
+                std::slice::from_raw_parts(packed.as_ptr().cast::<CHAR_INFO>(), packed.len())
+            };
+            if let Err(e) = write_span(handle, cells, change.start..change.end, column, top) {
+                self.shown = None;
+                return Err(e);
+            }
+            written += change.end - change.start;
+        }
+        self.commit();
+        Ok(written)
+    }
+
+    /// This function compares `frame` with the last one presented (or committed), without
+    /// drawing it.
+    ///
+    /// ## Returns:
+    /// - The runs of cells [`Presenter::present`] would write, row by row, left to right. A
+    ///   double-width character is never split between a change and an unchanged cell.
+    pub fn diff(&mut self, frame: &Frame, column: i16, row: i16) -> &[Change] {
+        let width = frame.width;
+        self.next.width = width;
+        self.next.height = frame.height;
+        self.next.at = (column, row);
+        self.next.cells.clear();
+        let mut colors = Colors::default();
+        for cells in frame.rows() {
+            self.next.cells.extend((0..cells.len()).map(|x| {
+                let (unit, attributes) = encode(cells, x, &mut colors);
+                unit as u32 | (attributes as u32) << 16
+            }));
+        }
+        self.changes.clear();
+        let same_place = self.shown.as_ref().filter(|shown| {
+            (shown.width, shown.height, shown.at) == (width, frame.height, (column, row))
+        });
+        let Some(shown) = same_place else {
+            self.changes
+                .extend((0..frame.height).filter(|_| width > 0).map(|row| Change {
+                    row,
+                    start: 0,
+                    end: width,
+                }));
+            return &self.changes;
+        };
+        for y in 0..frame.height {
+            let range = y * width..(y + 1) * width;
+            let (old, new) = (&shown.cells[range.clone()], &self.next.cells[range]);
+            if old == new {
+                continue;
+            }
+            for (start, end) in changed_runs(old, new) {
+                match self.changes.last_mut() {
+                    Some(last) if last.row == y && start.saturating_sub(last.end) < MERGE_GAP => {
+                        last.end = end
+                    }
+                    _ => self.changes.push(Change { row: y, start, end }),
+                }
+            }
+        }
+        &self.changes
+    }
+
+    /// This function records the frame of the last [`Presenter::diff`] as drawn, for callers
+    /// writing the changes themselves (as VT sequences, or into another screen buffer).
+    pub fn commit(&mut self) {
+        let previous = self.shown.take().unwrap_or_default();
+        self.shown = Some(std::mem::replace(&mut self.next, previous));
+    }
+
+    /// This function forgets what was drawn, so the next [`Presenter::present`] draws the
+    /// whole frame.
+    pub fn invalidate(&mut self) {
+        self.shown = None;
+    }
+}
+
+/// Whether a packed console cell is the left or the right half of a double-width character.
+fn is_lead(cell: u32) -> bool {
+    (cell >> 16) as u16 & COMMON_LVB_LEADING_BYTE != 0
+}
+
+fn is_tail(cell: u32) -> bool {
+    (cell >> 16) as u16 & COMMON_LVB_TRAILING_BYTE != 0
+}
+
+/// Runs of cells that differ between two packed rows of the same width, widened so that both
+/// halves of a double-width character are in the same run.
+fn changed_runs<'a>(old: &'a [u32], new: &'a [u32]) -> impl Iterator<Item = (usize, usize)> + 'a {
+    let mut x = 0;
+    std::iter::from_fn(move || {
+        // Skip equal cells a block of lanes at a time, then one at a time.
+        while x + LANES <= new.len() && old[x..x + LANES] == new[x..x + LANES] {
+            x += LANES;
+        }
+        while x < new.len() && old[x] == new[x] {
+            x += 1;
+        }
+        if x == new.len() {
+            return None;
+        }
+        let mut start = x;
+        while x < new.len() && old[x] != new[x] {
+            x += 1;
+        }
+        if start > 0 && (is_tail(new[start]) || is_tail(old[start])) {
+            start -= 1;
+        }
+        if x < new.len() && (is_lead(new[x - 1]) || is_lead(old[x - 1])) {
+            x += 1;
+        }
+        Some((start, x))
+    })
+}
+
+/// Struct to hold the console colors of the last truecolor values encoded: most cells of a
+/// frame share a few colors, and finding the nearest console color searches the palette.
+#[derive(Debug, Default)]
+struct Colors {
+    recent: [Option<(Rgb, u16)>; 4],
+    next: usize, // Entry replaced by the next new color
+}
+
+impl Colors {
+    fn get(&mut self, rgb: Rgb) -> u16 {
+        if let Some((_, color)) = self.recent.iter().flatten().find(|(seen, _)| *seen == rgb) {
+            return *color;
+        }
+        let color = console_color(rgb);
+        self.recent[self.next] = Some((rgb, color));
+        self.next = (self.next + 1) % self.recent.len();
+        color
+    }
+}
+
+/// Console color of a truecolor value: the nearest legacy color, whose table swaps red and
+/// blue compared to the SGR order.
+fn console_color(rgb: Rgb) -> u16 {
+    let index = quantize(rgb, Palette::Ansi16) as u16;
+    index & 0b1010 | (index & 1) << 2 | (index >> 2) & 1
+}
+
+/// Buffer row `y` rows below `row`, `None` past the last row a buffer can have.
+fn offset(row: i16, y: usize) -> Option<i16> {
+    i16::try_from(y).ok().and_then(|y| row.checked_add(y))
+}
+
+/// Width of a row of `len` cells written at `column` and `row`, and the region it covers,
+/// clipped to the coordinates a buffer can have.
+fn destination(column: i16, row: i16, len: usize) -> (i16, SMALL_RECT) {
+    let width = len.min(i16::MAX as usize) as i16;
+    let region = SMALL_RECT {
+        Left: column,
+        Top: row,
+        Right: column.saturating_add(width - 1),
+        Bottom: row,
+    };
+    (width, region)
+}
+
+/// Writes a row of cells at a position of the screen buffer, through `buffer` (as long as the
+/// row).
+fn write_row(
+    handle: HANDLE,
+    cells: &[FrameCell],
+    buffer: &mut [CHAR_INFO],
+    column: i16,
+    row: i16,
+) -> Result<(), TerminalError> {
+    if cells.is_empty() {
+        return Ok(());
+    }
+    let mut colors = Colors::default();
+    for (x, info) in buffer[..cells.len()].iter_mut().enumerate() {
+        let (unit, attributes) = encode(cells, x, &mut colors);
+        info.Char.UnicodeChar = unit;
+        info.Attributes = attributes;
+    }
+    write_span(handle, buffer, 0..cells.len(), column, row)
+}
+
+/// The character unit and the attributes the console stores for cell `x` of a row.
+fn encode(cells: &[FrameCell], x: usize, colors: &mut Colors) -> (u16, u16) {
+    let cell = &cells[x];
+    let mut units = [0u16; 2];
+    let attributes = colors.get(cell.fg) | colors.get(cell.bg) << 4 | cell.attributes.legacy();
+    let wide = cells.get(x + 1).is_some_and(|next| next.wide_tail);
+    let (unit, flags) = match (cell.wide_tail, wide) {
+        // The tail repeats the character, or carries the low surrogate.
+        (true, _) if x > 0 => {
+            let lead = cells[x - 1].ch.encode_utf16(&mut units);
+            (lead[lead.len() - 1], COMMON_LVB_TRAILING_BYTE)
+        }
+        (_, true) => (cell.ch.encode_utf16(&mut units)[0], COMMON_LVB_LEADING_BYTE),
+        _ => {
+            let encoded = cell.ch.encode_utf16(&mut units);
+            // A wide glyph without its tail cell can't be shown in one cell.
+            let unit = if encoded.len() == 1 {
+                encoded[0]
+            } else {
+                b' ' as u16
+            };
+            (unit, 0)
+        }
+    };
+    (unit, attributes | flags)
+}
+
+/// Writes the cells `span` of a row encoded in `buffer` (as long as the row), the first cell
+/// of the row at `column` and `row` of the screen buffer.
+fn write_span(
+    handle: HANDLE,
+    buffer: &[CHAR_INFO],
+    span: std::ops::Range<usize>,
+    column: i16,
+    row: i16,
+) -> Result<(), TerminalError> {
+    let Some(left) = offset(column, span.start) else {
+        return Ok(());
+    };
+    let (width, _) = destination(column, row, buffer.len());
+    let (_, mut region) = destination(left, row, span.len());
+    let ok = unsafe {
+        WriteConsoleOutputW(
+            handle,
+            buffer.as_ptr(),
+            COORD { X: width, Y: 1 },
+            COORD {
+                X: span.start.min(i16::MAX as usize) as i16,
+                Y: 0,
+            },
+            &mut region,
+        )
+    };
+    if ok == 0 {
+        return Err(TerminalError::NoScreenBufferInfo(last_error_code()));
+    }
+    Ok(())
+}
+
+impl From<&Art> for Frame {
+    /// Resolves the legacy color indices of an ANSI art picture with the Campbell palette.
+    fn from(art: &Art) -> Self {
+        let cells = art
+            .cells
+            .iter()
+            .map(|cell| FrameCell {
+                ch: cell.ch,
+                fg: Palette::Ansi16.rgb(cell.fg),
+                bg: Palette::Ansi16.rgb(cell.bg),
+                attributes: Attributes {
+                    blink: cell.blink,
+                    ..Attributes::default()
+                },
+                wide_tail: false,
+            })
+            .collect();
+        Frame {
+            width: art.width,
+            height: art.height,
+            cells,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(presenter: &mut Presenter, frame: &Frame) -> Vec<(usize, usize, usize)> {
+        let changes = presenter.diff(frame, 0, 0).to_vec();
+        presenter.commit();
+        changes
+            .iter()
+            .map(|change| (change.row, change.start, change.end))
+            .collect()
+    }
+
+    #[test]
+    fn diffs_changed_runs() {
+        let mut presenter = Presenter::new();
+        let mut frame = Frame::new(40, 3);
+        assert_eq!(
+            changes(&mut presenter, &frame),
+            [(0, 0, 40), (1, 0, 40), (2, 0, 40)]
+        );
+        assert!(changes(&mut presenter, &frame).is_empty());
+        for x in [5, 7, 30] {
+            frame.cell_mut(x, 1).unwrap().ch = 'x';
+        }
+        frame.cell_mut(39, 2).unwrap().bg = Rgb::new(255, 255, 255);
+        assert_eq!(
+            changes(&mut presenter, &frame),
+            [(1, 5, 8), (1, 30, 31), (2, 39, 40)]
+        );
+        assert_eq!(presenter.diff(&frame, 1, 0).len(), 3);
+    }
+
+    #[test]
+    fn keeps_wide_characters_whole() {
+        let mut presenter = Presenter::new();
+        let mut frame = Frame::new(20, 1);
+        frame.cell_mut(10, 0).unwrap().ch = '\u{4E2D}';
+        frame.cell_mut(11, 0).unwrap().wide_tail = true;
+        changes(&mut presenter, &frame);
+        frame.cell_mut(11, 0).unwrap().fg = Rgb::new(255, 0, 0);
+        assert_eq!(changes(&mut presenter, &frame), [(0, 10, 12)]);
+        frame.cell_mut(10, 0).unwrap().ch = '\u{6587}';
+        assert_eq!(changes(&mut presenter, &frame), [(0, 10, 12)]);
+    }
+
+    #[test]
+    fn clips_positions_to_the_buffer() {
+        assert_eq!(offset(10, 5), Some(15));
+        assert_eq!(offset(i16::MAX - 1, 1), Some(i16::MAX));
+        assert_eq!(offset(i16::MAX, 1), None);
+        assert_eq!(offset(0, usize::MAX), None);
+        let (width, region) = destination(4, 2, 10);
+        assert_eq!(
+            (width, region.Left, region.Right, region.Top),
+            (10, 4, 13, 2)
+        );
+        let (width, region) = destination(i16::MAX - 2, 0, 10);
+        assert_eq!((width, region.Right), (10, i16::MAX));
+        let (width, region) = destination(0, 0, 100_000);
+        assert_eq!((width, region.Right), (i16::MAX, i16::MAX - 1));
+    }
+
+    fn text(frame: &Frame, y: usize) -> String {
+        frame
+            .rows()
+            .nth(y)
+            .unwrap()
+            .iter()
+            .map(|cell| cell.ch)
+            .collect()
+    }
+
+    fn rect(left: u16, top: u16, columns: u16, rows: u16) -> Rect {
+        Rect {
+            left,
+            top,
+            columns,
+            rows,
+        }
+    }
+
+    #[test]
+    fn clips_text_and_fills() {
+        let mut frame = Frame::new(10, 3);
+        let style = FrameCell::default();
+        frame.with_clip(rect(2, 1, 4, 1), |clip| {
+            clip.fill(rect(0, 0, 10, 3), FrameCell { ch: '.', ..style });
+            assert_eq!(clip.text(1, 1, "abcdefgh", style), 9);
+            clip.text(0, 0, "never", style);
+        });
+        assert_eq!(text(&frame, 0), " ".repeat(10));
+        assert_eq!(text(&frame, 1), "  bcde    ");
+        assert_eq!(text(&frame, 2), " ".repeat(10));
+    }
+
+    #[test]
+    fn drops_wide_glyphs_across_edges() {
+        let mut frame = Frame::new(6, 1);
+        let style = FrameCell::default();
+        frame.with_clip(rect(0, 0, 5, 1), |clip| clip.text(0, 0, "日本語", style));
+        assert_eq!(text(&frame, 0), "日 本   ");
+        assert!(frame.cells[1].wide_tail && frame.cells[3].wide_tail);
+        assert!(!frame.cells[4].wide_tail);
+    }
+
+    #[test]
+    fn overwriting_half_a_glyph_clears_the_other_half() {
+        let mut frame = Frame::new(4, 1);
+        let style = FrameCell::default();
+        frame.with_clip(rect(0, 0, 4, 1), |clip| clip.text(0, 0, "日本", style));
+        frame.with_clip(rect(1, 0, 2, 1), |clip| clip.text(1, 0, "ab", style));
+        assert_eq!(text(&frame, 0), " ab ");
+        assert!(frame.cells.iter().all(|cell| !cell.wide_tail));
+
+        frame.with_clip(rect(0, 0, 4, 1), |clip| clip.text(0, 0, "日本", style));
+        frame.with_clip(rect(0, 0, 4, 1), |clip| clip.text(1, 0, "語", style));
+        assert_eq!(text(&frame, 0), " 語  ");
+        let tails: Vec<bool> = frame.cells.iter().map(|cell| cell.wide_tail).collect();
+        assert_eq!(tails, [false, false, true, false]);
+    }
+
+    #[test]
+    fn nested_clips_intersect() {
+        let mut frame = Frame::new(8, 4);
+        let mark = FrameCell {
+            ch: '#',
+            ..FrameCell::default()
+        };
+        frame.with_clip(rect(1, 1, 5, 2), |clip| {
+            clip.with_clip(rect(4, 0, 10, 10), |inner| {
+                assert_eq!(inner.area(), rect(4, 1, 2, 2));
+                inner.fill(rect(0, 0, 8, 4), mark);
+            });
+        });
+        assert_eq!(text(&frame, 1), "    ##  ");
+        assert_eq!(text(&frame, 3), "        ");
+        frame.with_clip(rect(u16::MAX, u16::MAX, u16::MAX, u16::MAX), |clip| {
+            assert_eq!(clip.area().columns, 0);
+        });
+    }
+
+    #[test]
+    fn blits_inside_the_clip() {
+        let mut image = Frame::new(3, 2);
+        for cell in &mut image.cells {
+            cell.ch = '@';
+        }
+        let mut frame = Frame::new(4, 3);
+        frame.with_clip(rect(0, 0, 4, 2), |clip| clip.blit(2, 1, &image));
+        assert_eq!(text(&frame, 1), "  @@");
+        assert_eq!(text(&frame, 2), "    ");
+    }
+}
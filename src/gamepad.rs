@@ -0,0 +1,212 @@
+use windows_sys::Win32::Foundation::ERROR_SUCCESS;
+use windows_sys::Win32::UI::Input::XboxController::{
+    XInputGetState, XINPUT_GAMEPAD_A, XINPUT_GAMEPAD_B, XINPUT_GAMEPAD_BACK,
+    XINPUT_GAMEPAD_DPAD_DOWN, XINPUT_GAMEPAD_DPAD_LEFT, XINPUT_GAMEPAD_DPAD_RIGHT,
+    XINPUT_GAMEPAD_DPAD_UP, XINPUT_GAMEPAD_LEFT_SHOULDER, XINPUT_GAMEPAD_LEFT_THUMB,
+    XINPUT_GAMEPAD_LEFT_THUMB_DEADZONE, XINPUT_GAMEPAD_RIGHT_SHOULDER, XINPUT_GAMEPAD_RIGHT_THUMB,
+    XINPUT_GAMEPAD_RIGHT_THUMB_DEADZONE, XINPUT_GAMEPAD_START, XINPUT_GAMEPAD_TRIGGER_THRESHOLD,
+    XINPUT_GAMEPAD_X, XINPUT_GAMEPAD_Y, XINPUT_STATE,
+};
+
+/// Number of controllers XInput handles.
+pub const MAX_GAMEPADS: u32 = 4;
+
+/// Enum to represent a button of an XInput controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    A,
+    B,
+    X,
+    Y,
+    Up,    // D-pad
+    Down,  // D-pad
+    Left,  // D-pad
+    Right, // D-pad
+    Start,
+    Back,
+    LeftShoulder,
+    RightShoulder,
+    LeftThumb,  // Left stick pressed
+    RightThumb, // Right stick pressed
+}
+
+impl Button {
+    fn mask(self) -> u16 {
+        match self {
+            Button::A => XINPUT_GAMEPAD_A,
+            Button::B => XINPUT_GAMEPAD_B,
+            Button::X => XINPUT_GAMEPAD_X,
+            Button::Y => XINPUT_GAMEPAD_Y,
+            Button::Up => XINPUT_GAMEPAD_DPAD_UP,
+            Button::Down => XINPUT_GAMEPAD_DPAD_DOWN,
+            Button::Left => XINPUT_GAMEPAD_DPAD_LEFT,
+            Button::Right => XINPUT_GAMEPAD_DPAD_RIGHT,
+            Button::Start => XINPUT_GAMEPAD_START,
+            Button::Back => XINPUT_GAMEPAD_BACK,
+            Button::LeftShoulder => XINPUT_GAMEPAD_LEFT_SHOULDER,
+            Button::RightShoulder => XINPUT_GAMEPAD_RIGHT_SHOULDER,
+            Button::LeftThumb => XINPUT_GAMEPAD_LEFT_THUMB,
+            Button::RightThumb => XINPUT_GAMEPAD_RIGHT_THUMB,
+        }
+    }
+}
+
+/// Struct to hold the state of a controller, with the dead zones XInput recommends applied.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GamepadState {
+    pub buttons: u16,            // Raw `XINPUT_GAMEPAD_*` button bits, see `pressed`
+    pub left_trigger: f32,       // 0.0 released to 1.0 fully pulled
+    pub right_trigger: f32,      // 0.0 released to 1.0 fully pulled
+    pub left_stick: (f32, f32),  // -1.0 to 1.0 on each axis, up being positive
+    pub right_stick: (f32, f32), // -1.0 to 1.0 on each axis, up being positive
+}
+
+// No field is ever NaN: all are computed from integers, and a stick within its dead zone
+// reads 0 rather than dividing by a zero magnitude.
+impl Eq for GamepadState {}
+
+impl GamepadState {
+    /// Whether `button` is held.
+    pub fn pressed(&self, button: Button) -> bool {
+        self.buttons & button.mask() != 0
+    }
+}
+
+/// This function reads the state of a controller.
+///
+/// ## Returns:
+/// - `Some(GamepadState)` for a connected controller, `index` going from 0 to
+///   [`MAX_GAMEPADS`] - 1.
+/// - `None` if no controller is connected at `index`.
+pub fn state(index: u32) -> Option<GamepadState> {
+    read(index).map(|(_, state)| state)
+}
+
+/// Reads a controller with the packet number XInput bumps on every change.
+fn read(index: u32) -> Option<(u32, GamepadState)> {
+    let mut raw: XINPUT_STATE = unsafe { std::mem::zeroed() };
+    if unsafe { XInputGetState(index, &mut raw) } != ERROR_SUCCESS {
+        return None;
+    }
+    let pad = raw.Gamepad;
+    let trigger = |value: u8| {
+        let threshold = XINPUT_GAMEPAD_TRIGGER_THRESHOLD as f32;
+        ((value as f32 - threshold) / (255.0 - threshold)).max(0.0)
+    };
+    Some((
+        raw.dwPacketNumber,
+        GamepadState {
+            buttons: pad.wButtons,
+            left_trigger: trigger(pad.bLeftTrigger),
+            right_trigger: trigger(pad.bRightTrigger),
+            left_stick: stick(
+                pad.sThumbLX,
+                pad.sThumbLY,
+                XINPUT_GAMEPAD_LEFT_THUMB_DEADZONE,
+            ),
+            right_stick: stick(
+                pad.sThumbRX,
+                pad.sThumbRY,
+                XINPUT_GAMEPAD_RIGHT_THUMB_DEADZONE,
+            ),
+        },
+    ))
+}
+
+/// Scales a stick to the unit circle around a radial dead zone, so small drift reads as 0 and
+/// the full range is still reached.
+fn stick(x: i16, y: i16, dead_zone: u16) -> (f32, f32) {
+    let (x, y) = (x as f32, y as f32);
+    let magnitude = (x * x + y * y).sqrt();
+    let dead_zone = dead_zone as f32;
+    if magnitude <= dead_zone {
+        return (0.0, 0.0);
+    }
+    let scaled = ((magnitude - dead_zone) / (32767.0 - dead_zone)).min(1.0);
+    (x / magnitude * scaled, y / magnitude * scaled)
+}
+
+/// Struct to hold the last state seen of every controller, for polling from a game loop next
+/// to the console input.
+#[derive(Debug, Clone, Default)]
+pub struct Gamepads {
+    seen: [Option<(u32, GamepadState)>; MAX_GAMEPADS as usize],
+}
+
+impl Gamepads {
+    /// Creates a poller that has seen no controller yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// This function reads every controller and reports those that changed since the last
+    /// poll.
+    ///
+    /// ## Returns:
+    /// - The index and new state of each controller that changed, connected, or
+    ///   disconnected (`None`), in index order.
+    ///
+    /// ## Note:
+    /// - XInput has no events to wait on; call this once per frame. Reading a disconnected
+    ///   slot is slow on some systems, so a game polling at a high rate may want to look for
+    ///   new controllers less often than it reads the known ones.
+    pub fn poll(&mut self) -> Vec<(u32, Option<GamepadState>)> {
+        self.update(read)
+    }
+
+    /// Compares what `read` returns for every controller with the last states seen.
+    fn update(
+        &mut self,
+        mut read: impl FnMut(u32) -> Option<(u32, GamepadState)>,
+    ) -> Vec<(u32, Option<GamepadState>)> {
+        let mut changes = Vec::new();
+        for index in 0..MAX_GAMEPADS {
+            let current = read(index);
+            let seen = &mut self.seen[index as usize];
+            if current.map(|(packet, _)| packet) != seen.map(|(packet, _)| packet) {
+                changes.push((index, current.map(|(_, state)| state)));
+            }
+            *seen = current;
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_changed_packets_only() {
+        let pressed = GamepadState {
+            buttons: XINPUT_GAMEPAD_A,
+            ..GamepadState::default()
+        };
+        let mut gamepads = Gamepads::new();
+        let mut packets = [None, Some((1, GamepadState::default())), None, None];
+        assert_eq!(
+            gamepads.update(|i| packets[i as usize]),
+            [(1, Some(GamepadState::default()))]
+        );
+        assert_eq!(gamepads.update(|i| packets[i as usize]), []);
+        packets[1] = Some((2, pressed));
+        packets[3] = Some((1, GamepadState::default()));
+        let changes = gamepads.update(|i| packets[i as usize]);
+        assert_eq!(
+            changes,
+            [(1, Some(pressed)), (3, Some(GamepadState::default()))]
+        );
+        assert!(changes[0].1.unwrap().pressed(Button::A));
+        packets[1] = None;
+        assert_eq!(gamepads.update(|i| packets[i as usize]), [(1, None)]);
+    }
+
+    #[test]
+    fn sticks_have_a_radial_dead_zone() {
+        assert_eq!(stick(1000, -1000, 7849), (0.0, 0.0));
+        let (x, y) = stick(32767, 0, 7849);
+        assert_eq!((x, y), (1.0, 0.0));
+        let (x, y) = stick(-20000, -20000, 7849);
+        assert!(x < 0.0 && (x - y).abs() < 1e-6 && x * x + y * y <= 1.0);
+    }
+}
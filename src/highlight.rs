@@ -0,0 +1,156 @@
+use std::ops::Range;
+
+use crate::style::{self, Attributes, ColorDepth, Rgb};
+
+/// Struct to hold the style of a piece of a highlighted line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub range: Range<usize>,    // Byte range in the line
+    pub fg: Option<Rgb>,        // Foreground color, `None` for the terminal default
+    pub attributes: Attributes, // Bold, italic, ...
+}
+
+/// Trait to implement for syntax highlighters feeding [`highlight`].
+///
+/// Highlighters are called once per line, in order, so they can carry state across lines
+/// (e.g. being inside a block comment). Spans may leave gaps, drawn unstyled, but must not
+/// overlap and must fall on character boundaries.
+pub trait Highlighter {
+    fn highlight_line(&mut self, line: &str) -> Vec<Span>;
+}
+
+/// This function highlights code for the terminal.
+///
+/// ## Returns:
+/// - One string per line of `code`, styled with SGR sequences at the detected
+///   [`style::color_depth`]: truecolor spans are downgraded to what the terminal shows, and
+///   plain text is returned when colors are disabled.
+pub fn highlight(code: &str, highlighter: &mut dyn Highlighter) -> Vec<String> {
+    highlight_with(code, highlighter, style::color_depth())
+}
+
+/// This function highlights code as [`highlight`] does, at an explicit color depth.
+pub fn highlight_with(
+    code: &str,
+    highlighter: &mut dyn Highlighter,
+    depth: ColorDepth,
+) -> Vec<String> {
+    code.lines()
+        .map(|line| {
+            let spans = highlighter.highlight_line(line);
+            if depth == ColorDepth::None {
+                return line.to_string();
+            }
+            paint(line, &spans, depth)
+        })
+        .collect()
+}
+
+fn paint(line: &str, spans: &[Span], depth: ColorDepth) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut at = 0;
+    for span in spans {
+        let Some(text) = line
+            .get(span.range.clone())
+            .filter(|_| span.range.start >= at)
+        else {
+            continue;
+        };
+        out.push_str(&line[at..span.range.start]);
+        out.push_str(&span.attributes.sgr(false));
+        out.push_str(&span.fg.map(|fg| depth.foreground(fg)).unwrap_or_default());
+        out.push_str(text);
+        out.push_str("\x1b[0m");
+        at = span.range.end;
+    }
+    out.push_str(&line[at..]);
+    out
+}
+
+/// Colors of [`Keywords`], from the default console scheme.
+const KEYWORD: Rgb = Rgb::new(180, 0, 158);
+const STRING: Rgb = Rgb::new(22, 198, 12);
+const NUMBER: Rgb = Rgb::new(193, 156, 0);
+const COMMENT: Rgb = Rgb::new(118, 118, 118);
+
+/// Struct to hold a small highlighter for C-like languages: keywords, strings, numbers and
+/// line comments.
+///
+/// Good enough for short previews; tools needing grammars for many languages can implement
+/// [`Highlighter`] over a full highlighting engine.
+#[derive(Debug, Clone)]
+pub struct Keywords<'a> {
+    keywords: &'a [&'a str],
+    comment: &'a str,
+}
+
+impl<'a> Keywords<'a> {
+    /// A highlighter for `keywords`, with line comments starting at `comment` (e.g. `//`).
+    pub fn new(keywords: &'a [&'a str], comment: &'a str) -> Self {
+        Keywords { keywords, comment }
+    }
+
+    /// Keywords of Rust, with `//` comments.
+    pub fn rust() -> Keywords<'static> {
+        const RUST: &[&str] = &[
+            "as", "break", "const", "continue", "crate", "else", "enum", "false", "fn", "for",
+            "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+            "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+            "unsafe", "use", "where", "while",
+        ];
+        Keywords::new(RUST, "//")
+    }
+}
+
+impl Highlighter for Keywords<'_> {
+    fn highlight_line(&mut self, line: &str) -> Vec<Span> {
+        let span = |range: Range<usize>, fg: Rgb, bold: bool| Span {
+            range,
+            fg: Some(fg),
+            attributes: Attributes {
+                bold,
+                ..Attributes::default()
+            },
+        };
+        let mut spans = Vec::new();
+        let mut chars = line.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            if !self.comment.is_empty() && line[start..].starts_with(self.comment) {
+                spans.push(span(start..line.len(), COMMENT, false));
+                break;
+            }
+            if c == '"' {
+                let mut end = line.len();
+                while let Some((at, next)) = chars.next() {
+                    match next {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => {
+                            end = at + 1;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                spans.push(span(start..end, STRING, false));
+            } else if c.is_alphanumeric() || c == '_' {
+                let mut end = start + c.len_utf8();
+                while let Some(&(at, next)) = chars.peek() {
+                    if !(next.is_alphanumeric() || next == '_') {
+                        break;
+                    }
+                    end = at + next.len_utf8();
+                    chars.next();
+                }
+                let word = &line[start..end];
+                if c.is_ascii_digit() {
+                    spans.push(span(start..end, NUMBER, false));
+                } else if self.keywords.contains(&word) {
+                    spans.push(span(start..end, KEYWORD, true));
+                }
+            }
+        }
+        spans
+    }
+}
@@ -0,0 +1,626 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::io;
+use std::time::Duration;
+
+use crate::accessibility::prefers_reduced_motion;
+use crate::get_size_of_the_font;
+use crate::sim::{Clock, SystemClock};
+use crate::style::{console_palette, nearest_in, quantize, Palette, Rgb};
+
+/// Struct to hold a borrowed RGBA8 image (4 bytes per pixel, rows top to bottom).
+#[derive(Debug, Clone, Copy)]
+pub struct Rgba<'a> {
+    pub pixels: &'a [u8], // Pixel data, `width * height * 4` bytes
+    pub width: u32,       // Width of the image in pixels
+    pub height: u32,      // Height of the image in pixels
+}
+
+/// Struct to hold a rectangle of terminal cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub left: u16,    // Leftmost column (0-based)
+    pub top: u16,     // Topmost row (0-based)
+    pub columns: u16, // Width of the rectangle in cells
+    pub rows: u16,    // Height of the rectangle in cells
+}
+
+/// Enum to represent the dithering applied when reducing an image to the palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dither {
+    None,    // Plain nearest-color mapping
+    Ordered, // 4x4 Bayer matrix, stable across frames
+    #[default]
+    FloydSteinberg, // Error diffusion, best quality for still images
+}
+
+/// Struct to hold the options used by [`render_blocks_with`].
+#[derive(Debug, Clone, Copy)]
+pub struct BlockOptions {
+    pub palette: Palette,        // Palette the image is reduced to
+    pub dither: Dither,          // Dithering algorithm
+    pub transparent: Option<u8>, // Palette index used for fully transparent pixels, `None` to blend on black
+}
+
+impl Default for BlockOptions {
+    fn default() -> Self {
+        BlockOptions {
+            palette: Palette::Xterm256,
+            dither: Dither::default(),
+            transparent: None,
+        }
+    }
+}
+
+/// Struct to hold one half-block cell: the upper half is drawn with the foreground color of `▀`,
+/// the lower half with its background color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockCell {
+    pub upper: u8, // Palette index of the upper pixel
+    pub lower: u8, // Palette index of the lower pixel
+}
+
+/// Struct to hold a rendered image, ready to be written at `rect`.
+#[derive(Debug, Clone)]
+pub struct Blocks {
+    pub rect: Rect, // Cells actually covered by the image (centered inside the requested rect)
+    pub palette: Palette, // Palette the cells index into
+    pub cells: Vec<BlockCell>, // `rect.columns * rect.rows` cells, row-major
+}
+
+impl Blocks {
+    /// Returns the cell at `column`/`row`, relative to `rect`.
+    pub fn cell(&self, column: u16, row: u16) -> Option<BlockCell> {
+        if column >= self.rect.columns || row >= self.rect.rows {
+            return None;
+        }
+        self.cells
+            .get(row as usize * self.rect.columns as usize + column as usize)
+            .copied()
+    }
+
+    /// This function turns the cells into VT output that draws the image at its rect.
+    ///
+    /// ## Note:
+    /// - Every row is positioned absolutely (`CSI row;col H`), and attributes are reset at the end.
+    /// - `Ansi16` images use `SGR 30-37/90-97`, `Xterm256` images use `SGR 38;5;n`.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        for row in 0..self.rect.rows {
+            // Widened: a rect at the bottom of the `u16` range mustn't overflow.
+            let _ = write!(
+                out,
+                "\x1b[{};{}H",
+                self.rect.top as u32 + row as u32 + 1,
+                self.rect.left as u32 + 1
+            );
+            let mut last = None;
+            for column in 0..self.rect.columns {
+                let cell = self.cells[row as usize * self.rect.columns as usize + column as usize];
+                if last != Some(cell) {
+                    match self.palette {
+                        Palette::Ansi16 => {
+                            let fg = ansi16_code(cell.upper, 30);
+                            let bg = ansi16_code(cell.lower, 40);
+                            let _ = write!(out, "\x1b[{};{}m", fg, bg);
+                        }
+                        Palette::Xterm256 => {
+                            let _ = write!(out, "\x1b[38;5;{};48;5;{}m", cell.upper, cell.lower);
+                        }
+                    }
+                    last = Some(cell);
+                }
+                out.push('▀');
+            }
+        }
+        out.push_str("\x1b[0m");
+        out
+    }
+}
+
+fn ansi16_code(index: u8, base: u8) -> u8 {
+    if index < 8 {
+        base + index
+    } else {
+        base + 60 + (index - 8)
+    }
+}
+
+/// This function renders an image onto half-block cells with the default options
+/// (256 colors, Floyd–Steinberg dithering).
+///
+/// See [`render_blocks_with`] for details.
+pub fn render_blocks(image: Rgba, rect: Rect) -> Blocks {
+    render_blocks_with(image, rect, &BlockOptions::default())
+}
+
+/// This function renders an image onto half-block cells, for hosts without sixel support.
+///
+/// ## Returns:
+/// - The rendered `Blocks`, whose rect is the largest area inside `rect` that keeps the
+///   image's aspect ratio, centered in it.
+///
+/// ## Note:
+/// - Each cell carries two vertical pixels, and the pixel aspect ratio is taken from the
+///   measured font size so images are not squashed. If the font size can't be detected
+///   (e.g. unsupported DPI or no console), cells are assumed to be twice as tall as wide.
+/// - Pixels are box-filtered down to the cell grid before dithering.
+/// - Colors are matched against the console's own color table (see
+///   [`console_palette`](crate::style::console_palette)) for the 16 legacy colors, so a
+///   customized scheme is dithered with the colors it really shows; the rest of the 256-color
+///   palette is the same everywhere.
+pub fn render_blocks_with(image: Rgba, rect: Rect, options: &BlockOptions) -> Blocks {
+    let (cell_w, cell_h) = match get_size_of_the_font() {
+        Ok(font) if font.width > 0 && font.height > 0 => (font.width as f64, font.height as f64),
+        _ => (1.0, 2.0),
+    };
+    let empty = Blocks {
+        rect: Rect {
+            columns: 0,
+            rows: 0,
+            ..rect
+        },
+        palette: options.palette,
+        cells: Vec::new(),
+    };
+    if image.width == 0
+        || image.height == 0
+        || rect.columns == 0
+        || rect.rows == 0
+        || image.pixels.len() < image.width as usize * image.height as usize * 4
+    {
+        return empty;
+    }
+
+    // Fit the image into the rect in physical pixels, then convert to cells.
+    let scale = (rect.columns as f64 * cell_w / image.width as f64)
+        .min(rect.rows as f64 * cell_h / image.height as f64);
+    let columns = ((image.width as f64 * scale / cell_w).round() as u16).clamp(1, rect.columns);
+    let rows = ((image.height as f64 * scale / cell_h).round() as u16).clamp(1, rect.rows);
+    let (w, h) = (columns as usize, rows as usize * 2);
+
+    let mut colors = Colors::new(options.palette);
+    let mut pixels = resample(image, w, h, options.transparent.is_some());
+    let mut indices = vec![0u8; w * h];
+    for y in 0..h {
+        for x in 0..w {
+            let [r, g, b, a] = pixels[y * w + x];
+            if a < 0.5 {
+                if let Some(index) = options.transparent {
+                    indices[y * w + x] = index;
+                    continue;
+                }
+            }
+            let (r, g, b) = match options.dither {
+                Dither::Ordered => {
+                    let offset =
+                        (BAYER[y % 4][x % 4] as f32 / 16.0 - 0.5) * spread(options.palette);
+                    (r + offset, g + offset, b + offset)
+                }
+                _ => (r, g, b),
+            };
+            let wanted = Rgb::new(to_u8(r), to_u8(g), to_u8(b));
+            let index = colors.index(wanted);
+            indices[y * w + x] = index;
+
+            if options.dither == Dither::FloydSteinberg {
+                let got = colors.rgb(index);
+                let error = [r - got.r as f32, g - got.g as f32, b - got.b as f32];
+                let mut diffuse = |dx: isize, dy: usize, weight: f32| {
+                    let nx = x as isize + dx;
+                    if nx < 0 || nx as usize >= w || y + dy >= h {
+                        return;
+                    }
+                    let p = &mut pixels[(y + dy) * w + nx as usize];
+                    for (channel, error) in p.iter_mut().zip(error) {
+                        *channel += error * weight;
+                    }
+                };
+                diffuse(1, 0, 7.0 / 16.0);
+                diffuse(-1, 1, 3.0 / 16.0);
+                diffuse(0, 1, 5.0 / 16.0);
+                diffuse(1, 1, 1.0 / 16.0);
+            }
+        }
+    }
+
+    let cells = (0..rows as usize)
+        .flat_map(|row| {
+            let indices = &indices;
+            (0..w).map(move |x| BlockCell {
+                upper: indices[row * 2 * w + x],
+                lower: indices[(row * 2 + 1) * w + x],
+            })
+        })
+        .collect();
+    Blocks {
+        rect: Rect {
+            left: rect.left.saturating_add((rect.columns - columns) / 2),
+            top: rect.top.saturating_add((rect.rows - rows) / 2),
+            columns,
+            rows,
+        },
+        palette: options.palette,
+        cells,
+    }
+}
+
+/// Struct to hold the colors an image is reduced to, and the matches found so far.
+struct Colors {
+    palette: Palette,
+    table: Option<Vec<Rgb>>, // The palette with the console's legacy colors, `None` without one
+    found: HashMap<Rgb, u8>,
+}
+
+impl Colors {
+    fn new(palette: Palette) -> Self {
+        let table = console_palette().map(|legacy| {
+            let mut table: Vec<Rgb> = (0..palette.len()).map(|i| palette.rgb(i as u8)).collect();
+            table[..16].copy_from_slice(&legacy);
+            table
+        });
+        Colors {
+            palette,
+            table,
+            found: HashMap::new(),
+        }
+    }
+
+    /// Index of the entry closest to `rgb`.
+    fn index(&mut self, rgb: Rgb) -> u8 {
+        match &self.table {
+            Some(table) => *self
+                .found
+                .entry(rgb)
+                .or_insert_with(|| nearest_in(rgb, table)),
+            None => quantize(rgb, self.palette),
+        }
+    }
+
+    /// Color shown for an index.
+    fn rgb(&self, index: u8) -> Rgb {
+        match &self.table {
+            Some(table) => table[index as usize],
+            None => self.palette.rgb(index),
+        }
+    }
+}
+
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Approximate distance between neighbouring palette levels, used as the ordered dither amplitude.
+fn spread(palette: Palette) -> f32 {
+    match palette {
+        Palette::Ansi16 => 96.0,
+        Palette::Xterm256 => 40.0,
+    }
+}
+
+fn to_u8(value: f32) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+/// Box-filters the image down (or nearest-samples it up) to `w` x `h` premultiplied pixels.
+fn resample(image: Rgba, w: usize, h: usize, keep_alpha: bool) -> Vec<[f32; 4]> {
+    let (src_w, src_h) = (image.width as usize, image.height as usize);
+    let mut out = Vec::with_capacity(w * h);
+    for y in 0..h {
+        let y0 = y * src_h / h;
+        let y1 = ((y + 1) * src_h / h).max(y0 + 1);
+        for x in 0..w {
+            let x0 = x * src_w / w;
+            let x1 = ((x + 1) * src_w / w).max(x0 + 1);
+            let mut sum = [0f32; 4];
+            for sy in y0..y1 {
+                for sx in x0..x1 {
+                    let i = (sy * src_w + sx) * 4;
+                    let alpha = image.pixels[i + 3] as f32 / 255.0;
+                    for (total, value) in sum.iter_mut().zip(&image.pixels[i..i + 3]) {
+                        *total += *value as f32 * alpha;
+                    }
+                    sum[3] += alpha;
+                }
+            }
+            let count = ((y1 - y0) * (x1 - x0)) as f32;
+            let alpha = sum[3] / count;
+            let pixel = if keep_alpha && alpha > 0.0 {
+                // Un-premultiply so partially covered cells keep their real color.
+                [sum[0] / sum[3], sum[1] / sum[3], sum[2] / sum[3], alpha]
+            } else {
+                [sum[0] / count, sum[1] / count, sum[2] / count, alpha]
+            };
+            out.push(pixel);
+        }
+    }
+    out
+}
+
+/// Struct to hold one fully composited frame of an [`Animation`].
+#[derive(Debug, Clone)]
+pub struct AnimationFrame {
+    pub pixels: Vec<u8>, // RGBA8 pixels, `width * height * 4` bytes
+    pub delay: Duration, // How long the frame stays on screen
+}
+
+/// Struct to hold the outcome of [`Animation::play`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlaybackStats {
+    pub shown: usize,   // Frames written to the output
+    pub dropped: usize, // Frames skipped because the output fell behind schedule
+}
+
+/// Struct to hold a decoded animated image, played back on half-block cells.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    width: u32,
+    height: u32,
+    frames: Vec<AnimationFrame>,
+}
+
+impl Animation {
+    /// Creates an empty animation of the given pixel size.
+    pub fn new(width: u32, height: u32) -> Self {
+        Animation {
+            width,
+            height,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Appends a frame. Frames whose pixel buffer doesn't match the animation size are ignored.
+    pub fn push_frame(&mut self, pixels: Vec<u8>, delay: Duration) {
+        if pixels.len() == self.width as usize * self.height as usize * 4 {
+            self.frames.push(AnimationFrame { pixels, delay });
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn frames(&self) -> &[AnimationFrame] {
+        &self.frames
+    }
+
+    /// This function decodes an animated (or still) GIF, compositing every frame onto the
+    /// logical screen so each [`AnimationFrame`] is a complete picture.
+    ///
+    /// ## Note:
+    /// - Only available with the `gif` feature.
+    /// - Delays below 20ms are raised to 100ms, like browsers do, since many GIFs rely on it.
+    #[cfg(feature = "gif")]
+    pub fn decode_gif(bytes: &[u8]) -> Result<Self, gif::DecodingError> {
+        use gif::DisposalMethod;
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(bytes)?;
+        let (width, height) = (decoder.width() as usize, decoder.height() as usize);
+        let mut animation = Animation::new(width as u32, height as u32);
+        let mut canvas = vec![0u8; width * height * 4];
+
+        while let Some(frame) = decoder.read_next_frame()? {
+            let previous = (frame.dispose == DisposalMethod::Previous).then(|| canvas.clone());
+            let (left, top) = (frame.left as usize, frame.top as usize);
+            let (frame_w, frame_h) = (frame.width as usize, frame.height as usize);
+            let visible = |x: usize, y: usize| left + x < width && top + y < height;
+
+            for y in 0..frame_h {
+                for x in 0..frame_w {
+                    let src = (y * frame_w + x) * 4;
+                    if !visible(x, y) || frame.buffer[src + 3] == 0 {
+                        continue;
+                    }
+                    let dst = ((top + y) * width + left + x) * 4;
+                    canvas[dst..dst + 4].copy_from_slice(&frame.buffer[src..src + 4]);
+                }
+            }
+
+            let delay = match frame.delay {
+                0 | 1 => Duration::from_millis(100),
+                centiseconds => Duration::from_millis(centiseconds as u64 * 10),
+            };
+            animation.push_frame(canvas.clone(), delay);
+
+            match frame.dispose {
+                DisposalMethod::Background => {
+                    for y in 0..frame_h {
+                        for x in 0..frame_w {
+                            if visible(x, y) {
+                                let dst = ((top + y) * width + left + x) * 4;
+                                canvas[dst..dst + 4].fill(0);
+                            }
+                        }
+                    }
+                }
+                DisposalMethod::Previous => {
+                    if let Some(previous) = previous {
+                        canvas = previous;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(animation)
+    }
+
+    /// This function converts every frame to half-block cells, see [`render_blocks_with`].
+    pub fn render(&self, rect: Rect, options: &BlockOptions) -> Vec<Blocks> {
+        self.frames
+            .iter()
+            .map(|frame| {
+                let image = Rgba {
+                    pixels: &frame.pixels,
+                    width: self.width,
+                    height: self.height,
+                };
+                render_blocks_with(image, rect, options)
+            })
+            .collect()
+    }
+
+    /// This function plays the animation into `out`, `loops` times (`None` loops forever).
+    ///
+    /// ## Returns:
+    /// - `Ok(PlaybackStats)` once playback is complete.
+    /// - `Err(io::Error)` if writing to `out` fails.
+    ///
+    /// ## Note:
+    /// - Frames are converted once up-front, then scheduled against the wall clock. When writing
+    ///   a frame takes longer than its delay (slow hosts, RDP), the frames that are already late
+    ///   are dropped instead of slowing the whole animation down.
+    /// - When the user prefers reduced motion (see
+    ///   [`accessibility::prefers_reduced_motion`](crate::accessibility::prefers_reduced_motion)),
+    ///   only the first frame is shown, once.
+    pub fn play<W: io::Write>(
+        &self,
+        out: &mut W,
+        rect: Rect,
+        options: &BlockOptions,
+        loops: Option<u32>,
+    ) -> io::Result<PlaybackStats> {
+        self.play_with(out, rect, options, loops, SystemClock)
+    }
+
+    /// This function plays the animation like [`Animation::play`], scheduling the frames
+    /// against `clock` rather than the wall clock, e.g. a [`SimClock`](crate::sim::SimClock)
+    /// to test playback without waiting.
+    pub fn play_with<W: io::Write>(
+        &self,
+        out: &mut W,
+        rect: Rect,
+        options: &BlockOptions,
+        loops: Option<u32>,
+        clock: impl Clock,
+    ) -> io::Result<PlaybackStats> {
+        let rendered = self.render(rect, options);
+        let mut stats = PlaybackStats::default();
+        if rendered.is_empty() {
+            return Ok(stats);
+        }
+
+        if prefers_reduced_motion() {
+            out.write_all(rendered[0].to_ansi().as_bytes())?;
+            out.flush()?;
+            stats.shown = 1;
+            return Ok(stats);
+        }
+        self.schedule(&rendered, out, loops, clock)
+    }
+
+    /// Writes the rendered frames in turn, waiting for the delay of each on `clock` and
+    /// dropping the ones already late.
+    fn schedule<W: io::Write>(
+        &self,
+        rendered: &[Blocks],
+        out: &mut W,
+        loops: Option<u32>,
+        clock: impl Clock,
+    ) -> io::Result<PlaybackStats> {
+        let mut stats = PlaybackStats::default();
+        let mut iteration = 0;
+        while loops.is_none_or(|loops| iteration < loops) {
+            let mut deadline = clock.now();
+            for (i, blocks) in rendered.iter().enumerate() {
+                deadline += self.frames[i].delay;
+                let is_last = i + 1 == rendered.len();
+                if clock.now() >= deadline && !is_last {
+                    stats.dropped += 1;
+                    continue;
+                }
+                out.write_all(blocks.to_ansi().as_bytes())?;
+                out.flush()?;
+                stats.shown += 1;
+                let now = clock.now();
+                if deadline > now {
+                    clock.sleep(deadline - now);
+                }
+            }
+            iteration += 1;
+        }
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::SimClock;
+
+    /// Output taking `cost` of virtual time for every write, as a slow host does.
+    struct Slow {
+        clock: SimClock,
+        cost: Duration,
+    }
+
+    impl io::Write for Slow {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.clock.advance(self.cost);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn play(cost: Duration, loops: u32) -> (PlaybackStats, Duration) {
+        let mut animation = Animation::new(1, 2);
+        for _ in 0..3 {
+            animation.push_frame(vec![255; 8], Duration::from_millis(100));
+        }
+        let clock = SimClock::new();
+        let mut out = Slow {
+            clock: clock.clone(),
+            cost,
+        };
+        let blocks = Blocks {
+            rect: Rect {
+                left: 0,
+                top: 0,
+                columns: 1,
+                rows: 1,
+            },
+            palette: Palette::Xterm256,
+            cells: vec![BlockCell {
+                upper: 15,
+                lower: 15,
+            }],
+        };
+        let rendered = vec![blocks; 3];
+        let stats = animation
+            .schedule(&rendered, &mut out, Some(loops), &clock)
+            .unwrap();
+        (stats, clock.elapsed())
+    }
+
+    #[test]
+    fn plays_frames_on_schedule() {
+        let (stats, elapsed) = play(Duration::ZERO, 2);
+        assert_eq!(
+            stats,
+            PlaybackStats {
+                shown: 6,
+                dropped: 0
+            }
+        );
+        assert_eq!(elapsed, Duration::from_millis(600));
+    }
+
+    #[test]
+    fn drops_late_frames_but_the_last() {
+        let (stats, elapsed) = play(Duration::from_millis(250), 1);
+        assert_eq!(
+            stats,
+            PlaybackStats {
+                shown: 2,
+                dropped: 1
+            }
+        );
+        assert_eq!(elapsed, Duration::from_millis(500));
+    }
+}
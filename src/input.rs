@@ -0,0 +1,240 @@
+use std::io;
+use std::time::Duration;
+
+use windows_sys::Win32::System::Console::{
+    WriteConsoleInputW, COORD, FROM_LEFT_1ST_BUTTON_PRESSED, INPUT_RECORD, KEY_EVENT,
+    KEY_EVENT_RECORD, LEFT_ALT_PRESSED, LEFT_CTRL_PRESSED, MOUSE_EVENT, SHIFT_PRESSED,
+};
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+    MapVirtualKeyW, SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP,
+    MAPVK_VK_TO_CHAR, MAPVK_VK_TO_VSC, VK_CONTROL, VK_MENU, VK_RETURN, VK_SHIFT,
+};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    SystemParametersInfoW, SPI_GETKEYBOARDDELAY, SPI_GETKEYBOARDSPEED,
+};
+
+use crate::console::console_input;
+
+/// Struct to hold the modifier keys held during an injected key press.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+/// Enum to represent an input event to inject with [`inject`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputEvent {
+    Text(String),              // Typed as key presses, one per UTF-16 unit; `\n` is Enter
+    Key(u16, Modifiers),       // A virtual key (e.g. `VK_F5`) pressed and released
+    Click(i16, i16),           // Left click on a buffer cell, column then row
+    WindowKey(u16, Modifiers), // A key sent to the foreground window with `SendInput`
+}
+
+/// This function injects input events, in order, as if the user made them.
+///
+/// ## Returns:
+/// - `Ok(count)` with the number of records written to the console input queue, plus the
+///   number of window-level inputs sent.
+/// - `Err(io::Error)` without a console input (when console records are needed), or if
+///   writing the queue fails.
+///
+/// ## Note:
+/// - `Text`, `Key` and `Click` go to the console input queue with `WriteConsoleInput`, so every
+///   program reading the same console sees them, whether or not its window has the focus.
+///   That is what drives a TUI from an integration test or a helper process.
+/// - `WindowKey` goes through `SendInput` to whatever window has the keyboard focus, and is
+///   handled by the host before any program: shortcuts like Alt+Enter or Ctrl+Shift+T. The
+///   console window needs to be in the foreground, and `SendInput` is blocked by UIPI when
+///   the foreground window runs at a higher integrity level.
+pub fn inject(events: &[InputEvent]) -> io::Result<usize> {
+    let mut records = Vec::new();
+    let mut count = 0;
+    for event in events {
+        match event {
+            InputEvent::Text(text) => records.extend(key_presses(text)),
+            InputEvent::Key(vk, modifiers) => records.extend(key_press(*vk, *modifiers)),
+            InputEvent::Click(column, row) => records.extend(click(*column, *row)),
+            InputEvent::WindowKey(vk, modifiers) => {
+                // Keep the order of the events: what is queued so far goes first.
+                count += write_records(&std::mem::take(&mut records))?;
+                count += send_key(*vk, *modifiers)?;
+            }
+        }
+    }
+    count += write_records(&records)?;
+    Ok(count)
+}
+
+/// Writes records to the console input queue, returning how many were written.
+fn write_records(records: &[INPUT_RECORD]) -> io::Result<usize> {
+    if records.is_empty() {
+        return Ok(0);
+    }
+    let input = console_input().ok_or_else(io::Error::last_os_error)?;
+    let mut written = 0;
+    if unsafe { WriteConsoleInputW(input, records.as_ptr(), records.len() as u32, &mut written) }
+        == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(written as usize)
+}
+
+/// A key down or up record.
+fn key_record(vk: u16, unit: u16, down: bool, state: u32) -> INPUT_RECORD {
+    let mut record: INPUT_RECORD = unsafe { std::mem::zeroed() };
+    record.EventType = KEY_EVENT as u16;
+    let mut key: KEY_EVENT_RECORD = unsafe { std::mem::zeroed() };
+    key.bKeyDown = down as i32;
+    key.wRepeatCount = 1;
+    key.wVirtualKeyCode = vk;
+    key.wVirtualScanCode = unsafe { MapVirtualKeyW(vk as u32, MAPVK_VK_TO_VSC) } as u16;
+    key.uChar.UnicodeChar = unit;
+    key.dwControlKeyState = state;
+    record.Event.KeyEvent = key;
+    record
+}
+
+/// Key down and up records typing `text`, one pair per UTF-16 unit.
+pub(crate) fn key_presses(text: &str) -> Vec<INPUT_RECORD> {
+    let mut records = Vec::with_capacity(text.len() * 2);
+    for unit in text.encode_utf16() {
+        let (vk, unit) = match unit {
+            0x0a | 0x0d => (VK_RETURN, 0x0d),
+            _ => (0, unit),
+        };
+        for down in [true, false] {
+            records.push(key_record(vk, unit, down, 0));
+        }
+    }
+    records
+}
+
+/// Key down and up records of a virtual key, carrying the character it types on the current
+/// layout, as a control character when Ctrl is held.
+fn key_press(vk: u16, modifiers: Modifiers) -> [INPUT_RECORD; 2] {
+    // The low word is the unshifted character, uppercase for letters.
+    let mut unit = unsafe { MapVirtualKeyW(vk as u32, MAPVK_VK_TO_CHAR) } as u16;
+    let letter = (b'A' as u16..=b'Z' as u16).contains(&unit);
+    if modifiers.ctrl && letter {
+        unit &= 0x1f;
+    } else if letter && !modifiers.shift {
+        unit += 0x20;
+    }
+    let state = [
+        (modifiers.ctrl, LEFT_CTRL_PRESSED),
+        (modifiers.alt, LEFT_ALT_PRESSED),
+        (modifiers.shift, SHIFT_PRESSED),
+    ]
+    .iter()
+    .filter(|(held, _)| *held)
+    .fold(0, |state, (_, flag)| state | flag);
+    [
+        key_record(vk, unit, true, state),
+        key_record(vk, unit, false, state),
+    ]
+}
+
+/// Press and release records of a left click on a cell.
+fn click(column: i16, row: i16) -> [INPUT_RECORD; 2] {
+    [FROM_LEFT_1ST_BUTTON_PRESSED, 0].map(|buttons| {
+        let mut record: INPUT_RECORD = unsafe { std::mem::zeroed() };
+        record.EventType = MOUSE_EVENT as u16;
+        record.Event.MouseEvent.dwMousePosition = COORD { X: column, Y: row };
+        record.Event.MouseEvent.dwButtonState = buttons;
+        record
+    })
+}
+
+/// Sends a key with its modifiers through `SendInput`: modifiers down, key down and up,
+/// modifiers up.
+fn send_key(vk: u16, modifiers: Modifiers) -> io::Result<usize> {
+    let held: Vec<u16> = [
+        (modifiers.ctrl, VK_CONTROL),
+        (modifiers.alt, VK_MENU),
+        (modifiers.shift, VK_SHIFT),
+    ]
+    .iter()
+    .filter(|(held, _)| *held)
+    .map(|(_, vk)| *vk)
+    .collect();
+    let input = |vk: u16, up: bool| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: vk,
+                wScan: 0,
+                dwFlags: if up { KEYEVENTF_KEYUP } else { 0 },
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    };
+    let inputs: Vec<INPUT> = held
+        .iter()
+        .map(|&vk| input(vk, false))
+        .chain([input(vk, false), input(vk, true)])
+        .chain(held.iter().rev().map(|&vk| input(vk, true)))
+        .collect();
+    let sent = unsafe {
+        SendInput(
+            inputs.len() as u32,
+            inputs.as_ptr(),
+            std::mem::size_of::<INPUT>() as i32,
+        )
+    };
+    if sent == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(sent as usize)
+}
+
+/// Struct to hold the keyboard repeat settings of the system.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyboardParams {
+    pub delay: Duration, // Time a key is held before it starts repeating
+    pub rate: f64,       // Repeats per second once it does
+}
+
+impl KeyboardParams {
+    /// This function counts the repeats the system would have generated for a key held for
+    /// `held`, so a game reading raw down and up events can repeat at the user's settings.
+    ///
+    /// ## Returns:
+    /// - 0 before the delay, then 1 at the delay and one more every `1 / rate` seconds.
+    pub fn repeats(&self, held: Duration) -> u32 {
+        match held.checked_sub(self.delay) {
+            Some(after) => 1 + (after.as_secs_f64() * self.rate) as u32,
+            None => 0,
+        }
+    }
+}
+
+/// This function reads the keyboard repeat delay and rate set in the Control Panel.
+///
+/// ## Returns:
+/// - `Ok(KeyboardParams)` with the delay in 250 ms steps (250 ms to 1 s) and the rate from
+///   about 2.5 to 30 repeats per second.
+/// - `Err(io::Error)` if a setting can't be read.
+///
+/// ## Note:
+/// - The system only stores a speed index from 0 to 31; the rate is interpolated linearly
+///   between the two documented ends, which is how the keyboard driver maps it.
+pub fn keyboard_params() -> io::Result<KeyboardParams> {
+    let read = |action| {
+        let mut value: u32 = 0;
+        let ok = unsafe { SystemParametersInfoW(action, 0, &mut value as *mut u32 as *mut _, 0) };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(value)
+    };
+    let delay = read(SPI_GETKEYBOARDDELAY)?.min(3);
+    let speed = read(SPI_GETKEYBOARDSPEED)?.min(31);
+    Ok(KeyboardParams {
+        delay: Duration::from_millis(250 * (delay as u64 + 1)),
+        rate: 2.5 + speed as f64 * (30.0 - 2.5) / 31.0,
+    })
+}
@@ -0,0 +1,113 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use windows_sys::Win32::System::Threading::GetCurrentProcessId;
+
+use crate::json::Json;
+use crate::ownership::is_running;
+use crate::reset::ConsoleState;
+
+/// Where the journal of an application lives: the temporary directory of the user.
+fn journal_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("win-term-{}.journal", name))
+}
+
+/// Struct to hold a journal of the console state, saved to disk until it is dropped.
+///
+/// A panic or a normal exit drops the journal, which puts the state back; a process killed
+/// hard (Task Manager, `taskkill /f`, a debugger stopping) can't, and leaves the journal
+/// behind for [`recover`] to find on the next start.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    saved: ConsoleState,
+}
+
+impl Journal {
+    /// This function captures the console state before the application changes it, and writes
+    /// it to the journal of `name`.
+    ///
+    /// ## Returns:
+    /// - `Ok(Journal)` once the journal is on disk.
+    /// - `Err(io::Error)` if it can't be written.
+    ///
+    /// ## Note:
+    /// - `name` identifies the application and becomes part of a file name
+    ///   (`%TEMP%\win-term-<name>.journal`), so it should be a plain word.
+    /// - Call [`recover`] first: a journal left by a crash is overwritten.
+    pub fn begin(name: &str) -> io::Result<Journal> {
+        let saved = ConsoleState::capture();
+        let path = journal_path(name);
+        let record = Json::object([
+            ("pid", (unsafe { GetCurrentProcessId() } as i64).into()),
+            ("input_mode", saved.input_mode.map(i64::from).into()),
+            ("output_mode", saved.output_mode.map(i64::from).into()),
+            ("attributes", saved.attributes.map(i64::from).into()),
+            ("input_cp", (saved.input_cp as i64).into()),
+            ("output_cp", (saved.output_cp as i64).into()),
+        ]);
+        fs::write(&path, record.to_string())?;
+        Ok(Journal { path, saved })
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        self.saved.restore();
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Reads a journal, with the process that wrote it.
+fn read_journal(name: &str) -> Option<(u32, ConsoleState)> {
+    let text = fs::read_to_string(journal_path(name)).ok()?;
+    let record = Json::parse(&text)?;
+    let field = |key: &str| record.get(key).and_then(Json::as_u64);
+    let state = ConsoleState {
+        input_mode: field("input_mode").map(|mode| mode as u32),
+        output_mode: field("output_mode").map(|mode| mode as u32),
+        attributes: field("attributes").map(|attributes| attributes as u16),
+        input_cp: field("input_cp")? as u32,
+        output_cp: field("output_cp")? as u32,
+    };
+    Some((field("pid")? as u32, state))
+}
+
+/// This function tells whether a previous run of `name` died without putting the console
+/// back, see [`recover`].
+pub fn needs_recovery(name: &str) -> bool {
+    read_journal(name).is_some_and(|(pid, _)| !is_running(pid))
+}
+
+/// This function puts back the console state saved by a previous run of `name` that was
+/// killed, then deletes its journal.
+///
+/// ## Returns:
+/// - `Ok(true)` if a journal was found and the console restored from it.
+/// - `Ok(false)` without a journal, or if the process that wrote it is still running (another
+///   instance of the application).
+/// - `Err(io::Error)` if the journal can't be deleted.
+///
+/// ## Note:
+/// - Restoring undoes the VT modes a crashed TUI typically leaves on (alternate screen, mouse
+///   reporting, hidden cursor), then puts back the saved modes, attributes and code pages.
+/// - An unreadable journal is deleted without restoring anything.
+pub fn recover(name: &str) -> io::Result<bool> {
+    let path = journal_path(name);
+    if !path.exists() {
+        return Ok(false);
+    }
+    match read_journal(name) {
+        Some((pid, _)) if is_running(pid) => Ok(false),
+        Some((_, state)) => {
+            state.restore();
+            fs::remove_file(&path)?;
+            Ok(true)
+        }
+        None => {
+            fs::remove_file(&path)?;
+            Ok(false)
+        }
+    }
+}
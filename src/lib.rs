@@ -1,96 +1,397 @@
+#[cfg(feature = "render")]
+pub mod accessibility;
+#[cfg(feature = "alert")]
+pub mod alert;
+#[cfg(feature = "render")]
+pub mod art;
+#[cfg(feature = "bidi")]
+pub mod bidi;
+#[cfg(feature = "pty")]
+pub mod broadcast;
+#[cfg(feature = "window")]
+pub mod buffer;
+pub mod cache;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "render")]
+pub mod capture;
+mod console;
+mod diagnostics;
+#[cfg(feature = "window")]
+pub mod dock;
+pub mod dpi;
+#[cfg(feature = "render")]
+pub mod encoding;
+pub mod environment;
+#[cfg(feature = "input")]
+pub mod events;
+#[cfg(feature = "render")]
+pub mod export;
+pub mod font;
+pub mod format;
+#[cfg(feature = "render")]
+pub mod frame;
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+#[cfg(feature = "render")]
+pub mod highlight;
+#[cfg(feature = "render")]
+pub mod image;
+#[cfg(feature = "input")]
+pub mod input;
+#[cfg(feature = "recovery")]
+pub mod journal;
+#[cfg(any(feature = "pty", feature = "recovery"))]
+mod json;
+#[cfg(feature = "measure")]
+pub mod measure;
+#[cfg(feature = "pty")]
+pub mod metrics;
+#[cfg(feature = "window")]
+pub mod monitor;
+#[cfg(all(test, not(windows)))]
+mod no_console;
+#[cfg(feature = "ocr")]
+pub mod ocr;
+#[cfg(all(windows, feature = "d2d"))]
+pub mod overlay;
+#[cfg(feature = "recovery")]
+pub mod ownership;
+#[cfg(feature = "input")]
+pub mod perf;
+#[cfg(feature = "pty")]
+mod pipe;
+#[cfg(feature = "input")]
+pub mod prompt;
+mod query;
+#[cfg(feature = "pty")]
+pub mod remote;
+#[cfg(feature = "render")]
+pub mod render;
+mod reset;
+#[cfg(feature = "pty")]
+pub mod shell;
+pub mod sim;
+pub mod source;
+#[cfg(feature = "async")]
+pub mod stream;
+pub mod style;
+// The console validates the handles it is given; they are never dereferenced.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub mod sys;
+mod terminal;
+#[cfg(feature = "vt")]
+pub mod vt_query;
+#[cfg(feature = "recovery")]
+pub mod watchdog;
+#[cfg(feature = "widgets")]
+pub mod widgets;
+#[cfg(feature = "window")]
+pub mod window;
+#[cfg(feature = "render")]
+pub mod wrap;
+#[cfg(feature = "pty")]
+pub mod writer;
+
+pub use diagnostics::{debug_banner, debug_report};
+pub use query::{Measure, Measurement};
+pub use reset::{reset_terminal, run_with_reset};
+pub use terminal::{is_console, ConsoleStream, MetricsSample, Terminal};
+
+use std::fmt;
+
 use windows_sys::Win32::{
-    Foundation::HANDLE,
+    Foundation::{HANDLE, RECT},
     System::Console::{
-        GetConsoleScreenBufferInfo, GetConsoleWindow, GetStdHandle, CONSOLE_SCREEN_BUFFER_INFO,
+        GetConsoleOutputCP, GetConsoleScreenBufferInfo, GetStdHandle, CONSOLE_SCREEN_BUFFER_INFO,
         SMALL_RECT, STD_OUTPUT_HANDLE,
     },
-    UI::HiDpi::GetDpiForWindow,
+    UI::WindowsAndMessaging::GetClientRect,
 };
 
 /// Struct to hold terminal size information in terms of width and height.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TerminalSize {
     pub width: i32,  // Width of the terminal in pixels
     pub height: i32, // Height of the terminal in pixels
 }
 
 /// Struct to hold font size information in terms of width and height.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct FontSize {
     pub width: i32,  // Width of a single character in pixels
     pub height: i32, // Height of a single character in pixels
 }
 
+/// Struct to hold terminal size information in terms of character cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalCells {
+    pub columns: i32, // Number of columns of the visible window
+    pub rows: i32,    // Number of rows of the visible window
+}
+
+/// Struct to hold both sizes of the console: the visible window and the whole screen buffer.
+#[derive(Debug, Clone, Copy)]
+pub struct ConsoleGeometry {
+    pub cell: FontSize,          // Size of a cell in pixels
+    pub viewport: TerminalCells, // Visible window (`srWindow`) in cells
+    pub buffer: TerminalCells,   // Screen buffer including scrollback (`dwSize`) in cells
+}
+
+impl ConsoleGeometry {
+    /// This function reads the geometry of the standard output console.
+    ///
+    /// ## Returns:
+    /// - `Ok(ConsoleGeometry)` with the cell size as in [`get_size_of_the_font`] and both sizes in
+    ///   cells.
+    /// - `Err(TerminalError)` if there's an issue obtaining the standard handle, retrieving screen
+    ///   buffer info, or the cell size.
+    pub fn current() -> Result<ConsoleGeometry, TerminalError> {
+        let handle = console::std_handle(STD_OUTPUT_HANDLE)?;
+        let info = console::screen_buffer_info(handle)?;
+        let window = info.srWindow;
+        Ok(ConsoleGeometry {
+            cell: cell_size(handle)?,
+            viewport: TerminalCells {
+                columns: (window.Right - window.Left + 1) as i32,
+                rows: (window.Bottom - window.Top + 1) as i32,
+            },
+            buffer: TerminalCells {
+                columns: info.dwSize.X as i32,
+                rows: info.dwSize.Y as i32,
+            },
+        })
+    }
+
+    /// Size of the visible window in pixels.
+    pub fn viewport_px(&self) -> TerminalSize {
+        TerminalSize {
+            width: self.cell.width * self.viewport.columns,
+            height: self.cell.height * self.viewport.rows,
+        }
+    }
+
+    /// Size of the whole screen buffer in pixels.
+    pub fn buffer_px(&self) -> TerminalSize {
+        TerminalSize {
+            width: self.cell.width * self.buffer.columns,
+            height: self.cell.height * self.buffer.rows,
+        }
+    }
+}
+
+/// This function retrieves the size of the visible terminal window in pixels.
+///
+/// ## Returns:
+/// - `Ok(TerminalSize)` for the part of the screen buffer on screen (`srWindow`), which is what
+///   the user sees, whatever the length of the scrollback.
+/// - `Err(TerminalError)` as for [`ConsoleGeometry::current`].
+pub fn viewport_size_px() -> Result<TerminalSize, TerminalError> {
+    Ok(ConsoleGeometry::current()?.viewport_px())
+}
+
+/// This function retrieves the size of the whole screen buffer in pixels.
+///
+/// ## Returns:
+/// - `Ok(TerminalSize)` for `dwSize`, scrollback included: on conhost with a 9000-line buffer
+///   the height is 9000 rows. Same as [`get_size_of_the_terminal`].
+/// - `Err(TerminalError)` as for [`ConsoleGeometry::current`].
+pub fn buffer_size_px() -> Result<TerminalSize, TerminalError> {
+    Ok(ConsoleGeometry::current()?.buffer_px())
+}
+
+/// This function retrieves the size of the client area of the console window in pixels.
+///
+/// ## Returns:
+/// - `Ok(TerminalSize)` with what `GetClientRect` reports for the window the console is shown
+///   in: the drawn area, padding and partial cells included, without the frame and scroll bars.
+/// - Otherwise the visible window in cells times the cell size, as [`viewport_size_px`].
+/// - `Err(TerminalError)` only if both fail.
+///
+/// ## Note:
+/// - Under a pseudo console the hidden stand-in console window is skipped for the window that
+///   owns it: Windows Terminal's own window, whose client area also holds its tab bar. Over
+///   SSH there's no owner, so the derived size is returned.
+pub fn get_window_pixel_size() -> Result<TerminalSize, TerminalError> {
+    unsafe {
+        let window = console::host_window();
+        let mut rect: RECT = std::mem::zeroed();
+        if !window.is_null()
+            && GetClientRect(window, &mut rect) != 0
+            && rect.right > rect.left
+            && rect.bottom > rect.top
+        {
+            return Ok(TerminalSize {
+                width: rect.right - rect.left,
+                height: rect.bottom - rect.top,
+            });
+        }
+    }
+    viewport_size_px()
+}
+
+/// This function asks the terminal for the size of its text area in pixels (`CSI 14 t`), see
+/// [`vt_query::window_size_px`].
+///
+/// ## Returns:
+/// - `Ok(TerminalSize)` as the terminal reports it, the ground truth under Windows Terminal and
+///   other VT hosts, even over SSH.
+/// - Otherwise, when the terminal doesn't answer within [`vt_query::DEFAULT_TIMEOUT`] or the
+///   console input can't be read, what [`get_window_pixel_size`] computes.
+/// - `Err(TerminalError)` only if both fail.
+#[cfg(feature = "vt")]
+pub fn query_window_pixels_vt() -> Result<TerminalSize, TerminalError> {
+    match vt_query::window_size_px(vt_query::DEFAULT_TIMEOUT) {
+        Ok(Some(size)) => Ok(size),
+        _ => get_window_pixel_size(),
+    }
+}
+
 /// Enum to represent possible errors that can occur while getting terminal or font size.
-#[derive(Debug)]
+///
+/// Variants carry the Win32 error code (`GetLastError`) of the call that failed, 0 when the
+/// failure didn't come with one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TerminalError {
-    NoStdHandle,        // Standard output handle not found
-    NoScreenBufferInfo, // Failed to retrieve console screen buffer information
-    UnsupportedDpi,     // DPI setting is unsupported (not 96, 120, or 144)
+    NoStdHandle(u32),        // Standard output handle not found
+    NoScreenBufferInfo(u32), // Failed to retrieve console screen buffer information
+    UnsupportedDpi,          // DPI of the console window can't be read
+    NoFontInfo(u32),         // Failed to retrieve the current console font
+    NotAConsole,             // The handle is redirected to a file, a pipe or `NUL`
+    InvalidFont,             // Empty or too long face name, or a size of 0
+    FontNotSet(u32),         // Failed to change the console font
+    FullscreenUnsupported,   // The host has no fullscreen display mode (pseudo consoles)
+}
+
+impl TerminalError {
+    /// The Win32 error code of the failure, `None` when there is none.
+    pub fn code(&self) -> Option<u32> {
+        match *self {
+            TerminalError::NoStdHandle(code)
+            | TerminalError::NoScreenBufferInfo(code)
+            | TerminalError::NoFontInfo(code)
+            | TerminalError::FontNotSet(code) => Some(code).filter(|&code| code != 0),
+            TerminalError::UnsupportedDpi
+            | TerminalError::NotAConsole
+            | TerminalError::InvalidFont
+            | TerminalError::FullscreenUnsupported => None,
+        }
+    }
+
+    /// The failure as an OS error, `None` when there is no error code.
+    pub fn os_error(&self) -> Option<std::io::Error> {
+        self.code()
+            .map(|code| std::io::Error::from_raw_os_error(code as i32))
+    }
+}
+
+impl fmt::Display for TerminalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            TerminalError::NoStdHandle(_) => "no console standard handle",
+            TerminalError::NoScreenBufferInfo(_) => "can't read the console screen buffer",
+            TerminalError::UnsupportedDpi => "can't read the DPI of the console window",
+            TerminalError::NoFontInfo(_) => "can't read the console font",
+            TerminalError::NotAConsole => "not a console (redirected to a file or a pipe)",
+            TerminalError::InvalidFont => "invalid console font face or size",
+            TerminalError::FontNotSet(_) => "can't change the console font",
+            TerminalError::FullscreenUnsupported => "the console host has no fullscreen mode",
+        };
+        match self.os_error() {
+            Some(error) => write!(f, "{}: {}", message, error),
+            None => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for TerminalError {}
+
+impl From<TerminalError> for std::io::Error {
+    fn from(error: TerminalError) -> Self {
+        let kind = match error {
+            TerminalError::NoStdHandle(_) => std::io::ErrorKind::NotFound,
+            TerminalError::FullscreenUnsupported => std::io::ErrorKind::Unsupported,
+            _ => std::io::ErrorKind::Other,
+        };
+        std::io::Error::new(kind, error)
+    }
+}
+
+/// This function reads the error of the last failed Win32 call on this thread
+/// (`GetLastError`), for callers mixing the Win32 API with this crate.
+///
+/// ## Note:
+/// - Call it right after the failing call: any other call may overwrite the error.
+/// - A [`TerminalError`] already carries the error of the call that failed, see
+///   [`TerminalError::os_error`].
+pub fn last_os_error() -> std::io::Error {
+    std::io::Error::last_os_error()
+}
+
+/// Error code of the last failed Win32 call on this thread, see [`last_os_error`].
+pub(crate) fn last_error_code() -> u32 {
+    last_os_error().raw_os_error().unwrap_or(0) as u32
 }
 
 /// This function retrieves the font size used by the terminal in pixels.
 ///
 /// ## Assumptions:
-/// - The font size is set to 12 points, and the font type is "Consolas".
-/// - No zooming in or out has been done.
-/// - The DPI is set to either 100%, 125%, or 150% scaling (175% is not supported).
+/// - The cell size is the one the console reports for its current font (see
+///   [`font::FontInfo`]), whatever face and size the user configured.
+/// - Only when the console can't report it: the font size is set to 12 points, and the font
+///   type is "Consolas", or, when the output code page is an East-Asian DBCS one (932, 936,
+///   949, 950), the default font of that code page (MS Gothic, SimSun, GulimChe, MingLiU) at
+///   16 pixels, and no zooming in or out has been done.
+/// - Sources registered with [`source::register_source`] take precedence over all of the above.
+/// - Over SSH, inside a multiplexer or under a pseudo console, where the console font is the
+///   one of a hidden stand-in, the terminal is asked first with `CSI 16 t` (with the `vt`
+///   feature), see [`environment::size_strategies`].
 ///
 /// ## Returns:
 /// - `Ok(FontSize)` with the font width and height in pixels.
-/// - `Err(TerminalError)` if there's an issue obtaining the standard handle or the DPI is unsupported.
+/// - `Err(TerminalError::NotAConsole)` if the standard output is redirected.
+/// - `Err(TerminalError)` if there's an issue obtaining the standard handle, or the font can't be
+///   read and neither can the DPI.
 ///
 /// ## Note:
-/// - The DPI values used are approximations for common scaling settings:
-///   - 96 DPI (100% scaling)
-///   - 120 DPI (125% scaling)
-///   - 144 DPI (150% scaling)
+/// - The fallback uses measured cell sizes for 96, 120 and 144 DPI (100%, 125% and 150%
+///   scaling), and measures Consolas 12pt with [`font::measure`] for any other DPI, e.g. 168
+///   (175%) or 192 (200%), scaling the 96 DPI size by `dpi / 96` if GDI can't.
 pub fn get_size_of_the_font() -> Result<FontSize, TerminalError> {
     unsafe {
         let h_console: HANDLE = GetStdHandle(STD_OUTPUT_HANDLE);
         if h_console.is_null() {
-            return Err(TerminalError::NoStdHandle);
+            return Err(TerminalError::NoStdHandle(last_error_code()));
         }
-        let pixel_size = match GetDpiForWindow(GetConsoleWindow()) {
-            96 => FontSize {
-                width: 9,
-                height: 20,
-            },
-            120 => FontSize {
-                width: 12,
-                height: 25,
-            },
-            144 => FontSize {
-                width: 14,
-                height: 32,
-            },
-            _ => return Err(TerminalError::UnsupportedDpi),
-        };
-        return Ok(pixel_size);
+        if !console::is_console_handle(h_console) {
+            return Err(TerminalError::NotAConsole);
+        }
+        cell_size(h_console)
     }
 }
 
-/// This function retrieves the size of the terminal window in pixels.
+/// This function retrieves the size of the terminal screen buffer in pixels.
 ///
 /// ## Assumptions:
-/// - The font size is set to 12 points, and the font type is "Consolas".
-/// - No zooming in or out has been done.
-/// - The DPI is set to either 100%, 125%, or 150% scaling (175% is not supported).
+/// - The cell size is measured as in [`get_size_of_the_font`], from the terminal first where
+///   the console font isn't the one shown, then from the font the console reports and only
+///   falling back to Consolas 12pt scaled to the DPI when it can't.
 ///
 /// ## Returns:
 /// - `Ok(TerminalSize)` with the terminal's width and height in pixels.
-/// - `Err(TerminalError)` if there's an issue obtaining the standard handle, retrieving screen buffer info, or the DPI is unsupported.
+/// - `Err(TerminalError::NotAConsole)` if the standard output is redirected.
+/// - `Err(TerminalError)` if there's an issue obtaining the standard handle, retrieving screen buffer info, or the DPI.
 ///
 /// ## Note:
-/// - The DPI values used are approximations for common scaling settings:
-///   - 96 DPI (100% scaling)
-///   - 120 DPI (125% scaling)
-///   - 144 DPI (150% scaling)
+/// - This is the size of the screen buffer (`dwSize`), scrollback included, not of the visible
+///   window; use [`viewport_size_px`] for what is on screen.
+/// - The fallback uses measured cell sizes for 96, 120 and 144 DPI and measures the font with
+///   GDI for any other DPI, see [`get_size_of_the_font`].
 pub fn get_size_of_the_terminal() -> Result<TerminalSize, TerminalError> {
     unsafe {
         let h_console: HANDLE = GetStdHandle(STD_OUTPUT_HANDLE);
         if h_console.is_null() {
-            return Err(TerminalError::NoStdHandle);
+            return Err(TerminalError::NoStdHandle(last_error_code()));
         }
 
         let mut info = CONSOLE_SCREEN_BUFFER_INFO {
@@ -106,23 +407,166 @@ pub fn get_size_of_the_terminal() -> Result<TerminalSize, TerminalError> {
             dwMaximumWindowSize: windows_sys::Win32::System::Console::COORD { X: 0, Y: 0 },
         };
         if GetConsoleScreenBufferInfo(h_console, &mut info) == 0 {
-            return Err(TerminalError::NoScreenBufferInfo);
+            let code = last_error_code();
+            return Err(match console::is_console_handle(h_console) {
+                true => TerminalError::NoScreenBufferInfo(code),
+                false => TerminalError::NotAConsole,
+            });
         }
-        let pixel_size = match GetDpiForWindow(GetConsoleWindow()) {
-            96 => TerminalSize {
-                width: 9 * info.dwSize.X as i32,
-                height: 20 * info.dwSize.Y as i32,
-            },
-            120 => TerminalSize {
-                width: 12 * info.dwSize.X as i32,
-                height: 25 * info.dwSize.Y as i32,
-            },
-            144 => TerminalSize {
-                width: 14 * info.dwSize.X as i32,
-                height: 32 * info.dwSize.Y as i32,
-            },
-            _ => return Err(TerminalError::UnsupportedDpi),
+        let font = cell_size(h_console)?;
+        let pixel_size = TerminalSize {
+            width: font.width * info.dwSize.X as i32,
+            height: font.height * info.dwSize.Y as i32,
         };
-        return Ok(pixel_size);
+        Ok(pixel_size)
+    }
+}
+
+/// This function retrieves the size of the visible terminal window in cells.
+///
+/// ## Returns:
+/// - `Ok(TerminalCells)` with the columns and rows of `srWindow`, the part of the screen buffer
+///   on screen, not counting the scrollback.
+/// - `Err(TerminalError)` if there's an issue obtaining the standard handle or retrieving screen
+///   buffer info.
+///
+/// ## Note:
+/// - No font or DPI is involved, so this works whatever font the console uses.
+pub fn get_terminal_cells() -> Result<TerminalCells, TerminalError> {
+    let (columns, rows) = console::visible_cells()?;
+    Ok(TerminalCells { columns, rows })
+}
+
+/// Cell size in the order of [`environment::size_strategies`]: over SSH, in a multiplexer or
+/// under a pseudo console the terminal is asked first (with the `vt` feature), then the
+/// console as in [`cell_size_from`].
+pub(crate) fn cell_size(handle: HANDLE) -> Result<FontSize, TerminalError> {
+    Measure::new().cell_size(handle)
+}
+
+/// Cell size of the console font: the answer of a registered source if any (when `sources`
+/// is set), the size the console reports for its font (see [`font::FontInfo`]), and the DPI
+/// tables when the console can't tell. Never a VT query, so background threads don't read the
+/// console input.
+pub(crate) fn cell_size_from(handle: HANDLE, sources: bool) -> Result<FontSize, TerminalError> {
+    let dpi = console::console_dpi();
+    let context = source::SourceContext {
+        dpi,
+        code_page: unsafe { GetConsoleOutputCP() },
+    };
+    if let Some(metrics) = sources.then(|| source::measure(&context)).flatten() {
+        return Ok(metrics.cell);
+    }
+    if let Ok(info) = font::current_font(handle) {
+        if info.dwFontSize.X > 0 && info.dwFontSize.Y > 0 {
+            return Ok(FontSize {
+                width: info.dwFontSize.X as i32,
+                height: info.dwFontSize.Y as i32,
+            });
+        }
+        // Older hosts leave the width of some TrueType fonts at 0: measure the face instead.
+        if info.dwFontSize.Y > 0 && !font::is_raster(&info) {
+            let face = font::face_name(&info.FaceName);
+            if let Ok(size) = font::measure_height(&face, info.dwFontSize.Y as i32) {
+                return Ok(size);
+            }
+        }
+    }
+    font_size_for_dpi(dpi)
+}
+
+/// DBCS code pages whose console default font is a dual-width font rather than Consolas.
+const DBCS_CODE_PAGES: [u32; 4] = [932, 936, 949, 950];
+
+/// Cell size of the default console font for a DPI, picking the table from the output code page.
+///
+/// DPIs outside the table measure Consolas 12pt with GDI; the DBCS fonts, and Consolas when
+/// GDI fails, scale the 96 DPI cell, rounding to the nearest pixel.
+fn font_size_for_dpi(dpi: u32) -> Result<FontSize, TerminalError> {
+    if dpi == 0 {
+        return Err(TerminalError::UnsupportedDpi);
+    }
+    let code_page = unsafe { GetConsoleOutputCP() };
+    if let Some(size) = default_cell(code_page, dpi) {
+        return Ok(size);
+    }
+    Ok(font::measure("Consolas", 12, dpi).unwrap_or_else(|_| scaled_cell(9, 20, dpi)))
+}
+
+/// Cell of the default font of `code_page` at `dpi` from the tables, `None` for Consolas at a
+/// DPI they don't list.
+fn default_cell(code_page: u32, dpi: u32) -> Option<FontSize> {
+    let dbcs = DBCS_CODE_PAGES.contains(&code_page);
+    let (width, height) = match (dbcs, dpi) {
+        (false, 96) => (9, 20),
+        (false, 120) => (12, 25),
+        (false, 144) => (14, 32),
+        // Half-width cells of MS Gothic / SimSun / GulimChe / MingLiU; full-width glyphs
+        // span two of them.
+        (true, 96) => (8, 16),
+        (true, 120) => (10, 20),
+        (true, 144) => (12, 24),
+        (true, _) => return Some(scaled_cell(8, 16, dpi)),
+        (false, _) => return None,
+    };
+    Some(FontSize { width, height })
+}
+
+/// A 96 DPI cell scaled to `dpi`.
+fn scaled_cell(width: i32, height: i32, dpi: u32) -> FontSize {
+    let scale = |px: i32| (px as f64 * dpi as f64 / 96.0).round() as i32;
+    FontSize {
+        width: scale(width),
+        height: scale(height),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(width: i32, height: i32) -> Option<FontSize> {
+        Some(FontSize { width, height })
+    }
+
+    #[test]
+    fn dbcs_tables() {
+        for code_page in DBCS_CODE_PAGES {
+            assert_eq!(default_cell(code_page, 96), cell(8, 16), "{}", code_page);
+            assert_eq!(default_cell(code_page, 120), cell(10, 20), "{}", code_page);
+            assert_eq!(default_cell(code_page, 144), cell(12, 24), "{}", code_page);
+            // DPIs without a table of their own (175%, 200%) scale the 96 DPI cell.
+            assert_eq!(default_cell(code_page, 168), cell(14, 28), "{}", code_page);
+            assert_eq!(default_cell(code_page, 192), cell(16, 32), "{}", code_page);
+        }
+    }
+
+    #[test]
+    fn consolas_tables() {
+        for code_page in [437, 850, 1252, 65001] {
+            assert_eq!(default_cell(code_page, 96), cell(9, 20));
+            assert_eq!(default_cell(code_page, 120), cell(12, 25));
+            assert_eq!(default_cell(code_page, 144), cell(14, 32));
+            // Measured with GDI instead.
+            assert_eq!(default_cell(code_page, 192), None);
+        }
+    }
+
+    #[test]
+    fn scaling_rounds() {
+        assert_eq!(
+            scaled_cell(9, 20, 192),
+            FontSize {
+                width: 18,
+                height: 40
+            }
+        );
+        assert_eq!(
+            scaled_cell(9, 20, 108),
+            FontSize {
+                width: 10,
+                height: 23
+            }
+        );
     }
 }
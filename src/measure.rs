@@ -0,0 +1,91 @@
+#[cfg(not(test))]
+use std::sync::OnceLock;
+
+use unicode_width::UnicodeWidthChar;
+
+/// Struct to hold where a character of a string lands on the cell grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellSpan {
+    pub start: usize,  // Byte offset of the character in the string
+    pub end: usize,    // Byte offset just past the character and its combining marks
+    pub column: usize, // First cell the character occupies, relative to the start of the string
+    pub width: usize,  // Number of cells it occupies (1 or 2)
+}
+
+/// Whether a wide character is an emoji rather than an East-Asian ideograph or kana.
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32, 0x231A..=0x23FF | 0x2600..=0x27BF | 0x2B00..=0x2BFF | 0x1F000..=0x1FAFF)
+}
+
+/// Cells the host gives an emoji, probed once with `font::emoji_support`.
+#[cfg(not(test))]
+fn emoji_width() -> Option<usize> {
+    static WIDTH: OnceLock<Option<usize>> = OnceLock::new();
+    *WIDTH.get_or_init(|| {
+        crate::font::emoji_support()
+            .ok()
+            .map(|support| support.width)
+            .filter(|&width| width == 1 || width == 2)
+    })
+}
+
+// Unit tests don't probe whatever console they run in: emoji take two cells, as without one.
+#[cfg(test)]
+fn emoji_width() -> Option<usize> {
+    None
+}
+
+/// Width of a character in cells, emoji taking the width the host actually gives them.
+fn width(c: char) -> Option<usize> {
+    match c.width() {
+        Some(2) if is_emoji(c) => Some(emoji_width().unwrap_or(2)),
+        width => width,
+    }
+}
+
+/// This function counts the cells a string occupies.
+///
+/// ## Note:
+/// - East-Asian wide characters take two cells, combining marks and control characters none.
+/// - Emoji take the cells the console host advances for them (see `font::emoji_support`):
+///   two in Windows Terminal, one on legacy conhost. Two when there's no console to ask.
+pub fn cells(text: &str) -> usize {
+    text.chars().filter_map(width).sum()
+}
+
+/// This function maps every character of a string to the cells it occupies.
+///
+/// ## Returns:
+/// - One span per cell-occupying character, in order. Zero-width characters (combining marks,
+///   joiners, controls) are folded into the span of the character before them.
+///
+/// ## Note:
+/// - Cells are the unit the console lays out, not glyphs. With a ligature font (see
+///   `font::has_ligatures`) a host like Windows Terminal may draw `=>` or `!=` as a single
+///   wider glyph, but the ligature still covers exactly the cells of its characters: `=>`
+///   stays two spans of one cell each. Overlays should align to these spans rather than to
+///   glyph boundaries measured from the rendered text.
+pub fn cells_exact(text: &str) -> Vec<CellSpan> {
+    let mut spans: Vec<CellSpan> = Vec::new();
+    let mut column = 0;
+    for (start, c) in text.char_indices() {
+        let end = start + c.len_utf8();
+        match width(c).unwrap_or(0) {
+            0 => {
+                if let Some(last) = spans.last_mut() {
+                    last.end = end;
+                }
+            }
+            width => {
+                spans.push(CellSpan {
+                    start,
+                    end,
+                    column,
+                    width,
+                });
+                column += width;
+            }
+        }
+    }
+    spans
+}
@@ -0,0 +1,298 @@
+use std::io;
+use std::ptr;
+use std::sync::atomic::{fence, AtomicU32, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use windows_sys::Win32::Foundation::{
+    CloseHandle, GetLastError, ERROR_ALREADY_EXISTS, HANDLE, INVALID_HANDLE_VALUE,
+};
+use windows_sys::Win32::System::Memory::{
+    CreateFileMappingW, MapViewOfFile, OpenFileMappingW, UnmapViewOfFile, FILE_MAP_ALL_ACCESS,
+    FILE_MAP_READ, MEMORY_MAPPED_VIEW_ADDRESS, PAGE_READWRITE,
+};
+
+pub use crate::cache::{CachedMetrics, Metrics};
+
+/// How often the watcher threads sample the console geometry.
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Attempts of a read before giving up on a section whose counter stays odd, as it does when
+/// the publisher dies in the middle of a write. The first ones spin, the others yield.
+const READ_ATTEMPTS: u32 = 10_000;
+const READ_SPINS: u32 = 64;
+
+/// Struct to hold the layout of the shared section: a seqlock counter, odd while the
+/// metrics are being written, followed by the fields of the metrics. Every field is atomic so
+/// that a reader racing the writer sees torn values, which the counter rejects, rather than
+/// undefined behavior.
+#[repr(C)]
+struct Section {
+    sequence: AtomicU32,
+    fields: [AtomicU32; 5], // columns, rows, cell_width, cell_height, dpi
+}
+
+impl Metrics {
+    fn to_fields(self) -> [u32; 5] {
+        [
+            self.columns as u32,
+            self.rows as u32,
+            self.cell_width as u32,
+            self.cell_height as u32,
+            self.dpi,
+        ]
+    }
+
+    fn from_fields(fields: [u32; 5]) -> Metrics {
+        Metrics {
+            columns: fields[0] as i32,
+            rows: fields[1] as i32,
+            cell_width: fields[2] as i32,
+            cell_height: fields[3] as i32,
+            dpi: fields[4],
+        }
+    }
+}
+
+fn section_name(name: &str) -> Vec<u16> {
+    format!(r"Local\{}", name)
+        .encode_utf16()
+        .chain([0])
+        .collect()
+}
+
+/// Struct to hold a mapped view of a section, unmapped and closed on drop.
+struct View {
+    mapping: HANDLE,
+    section: *mut Section,
+}
+
+// The view is plain shared memory, only accessed through the seqlock.
+unsafe impl Send for View {}
+
+impl Drop for View {
+    fn drop(&mut self) {
+        unsafe {
+            UnmapViewOfFile(MEMORY_MAPPED_VIEW_ADDRESS {
+                Value: self.section.cast(),
+            });
+            CloseHandle(self.mapping);
+        }
+    }
+}
+
+impl View {
+    fn map(mapping: HANDLE, access: u32) -> io::Result<View> {
+        if mapping.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let section = unsafe { MapViewOfFile(mapping, access, 0, 0, 0) }.Value as *mut Section;
+        if section.is_null() {
+            let e = io::Error::last_os_error();
+            unsafe { CloseHandle(mapping) };
+            return Err(e);
+        }
+        Ok(View { mapping, section })
+    }
+
+    fn section(&self) -> &Section {
+        unsafe { &*self.section }
+    }
+
+    fn write(&self, metrics: Metrics) {
+        let section = self.section();
+        section.sequence.fetch_add(1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        for (field, value) in section.fields.iter().zip(metrics.to_fields()) {
+            field.store(value, Ordering::Relaxed);
+        }
+        section.sequence.fetch_add(1, Ordering::Release);
+    }
+
+    fn read(&self) -> io::Result<(u32, Metrics)> {
+        let section = self.section();
+        for attempt in 0..READ_ATTEMPTS {
+            let before = section.sequence.load(Ordering::Acquire);
+            if before.is_multiple_of(2) {
+                let fields = std::array::from_fn(|i| section.fields[i].load(Ordering::Relaxed));
+                fence(Ordering::Acquire);
+                if section.sequence.load(Ordering::Relaxed) == before {
+                    return Ok((before, Metrics::from_fields(fields)));
+                }
+            }
+            if attempt < READ_SPINS {
+                std::hint::spin_loop();
+            } else {
+                thread::yield_now();
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "the metrics publisher stopped in the middle of a write",
+        ))
+    }
+}
+
+/// Struct to hold a watcher thread publishing the console [`Metrics`] into a named
+/// shared-memory section, stopped when dropped.
+///
+/// Readers in other processes map the section with [`MetricsReader::open`] and read the
+/// latest metrics without any round-trip to this process, e.g. once per frame of a game loop.
+pub struct MetricsPublisher {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// This function starts publishing the console metrics in the section `Local\<name>`.
+///
+/// ## Returns:
+/// - `Ok(MetricsPublisher)` once the section holds the current metrics.
+/// - `Err(io::Error)` if the section can't be created or mapped, with `AlreadyExists` if
+///   another publisher, in this process or another, holds the name.
+///
+/// ## Note:
+/// - The metrics are sampled every 250 ms and written only when they change.
+/// - Writes go through a seqlock, so readers never see a half-written struct and never
+///   block the publisher.
+pub fn publish(name: &str) -> io::Result<MetricsPublisher> {
+    let name = section_name(name);
+    let mapping = unsafe {
+        CreateFileMappingW(
+            INVALID_HANDLE_VALUE,
+            ptr::null(),
+            PAGE_READWRITE,
+            0,
+            std::mem::size_of::<Section>() as u32,
+            name.as_ptr(),
+        )
+    };
+    // The seqlock has a single writer: a second publisher would race the first one.
+    if !mapping.is_null() && unsafe { GetLastError() } == ERROR_ALREADY_EXISTS {
+        unsafe { CloseHandle(mapping) };
+        return Err(io::Error::from_raw_os_error(ERROR_ALREADY_EXISTS as i32));
+    }
+    let view = View::map(mapping, FILE_MAP_ALL_ACCESS)?;
+    let mut last = Metrics::current().unwrap_or_default();
+    view.write(last);
+    let stop = Arc::new((Mutex::new(false), Condvar::new()));
+    let thread = {
+        let stop = Arc::clone(&stop);
+        thread::Builder::new()
+            .name("win-term metrics".to_string())
+            .spawn(move || {
+                let (lock, wake) = &*stop;
+                let mut stopped = lock.lock().unwrap_or_else(|e| e.into_inner());
+                loop {
+                    stopped = wake
+                        .wait_timeout(stopped, POLL_INTERVAL)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0;
+                    if *stopped {
+                        return;
+                    }
+                    if let Some(metrics) = Metrics::current().filter(|m| *m != last) {
+                        view.write(metrics);
+                        last = metrics;
+                    }
+                }
+            })?
+    };
+    Ok(MetricsPublisher {
+        stop,
+        thread: Some(thread),
+    })
+}
+
+impl Drop for MetricsPublisher {
+    fn drop(&mut self) {
+        let (lock, wake) = &*self.stop;
+        *lock.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        wake.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Struct to hold a read-only view of a section written by [`publish`].
+pub struct MetricsReader {
+    view: View,
+}
+
+impl MetricsReader {
+    /// This function maps the section `Local\<name>` published by another process.
+    ///
+    /// ## Returns:
+    /// - `Err(io::Error)` with `NotFound` if no process publishes under that name.
+    pub fn open(name: &str) -> io::Result<MetricsReader> {
+        let name = section_name(name);
+        let mapping = unsafe { OpenFileMappingW(FILE_MAP_READ, 0, name.as_ptr()) };
+        Ok(MetricsReader {
+            view: View::map(mapping, FILE_MAP_READ)?,
+        })
+    }
+
+    /// This function reads the latest metrics, consistent even while the publisher writes.
+    ///
+    /// ## Returns:
+    /// - `Err(io::Error)` with `TimedOut` if the section stays mid-write, the publisher having
+    ///   died (or stalled) while writing it.
+    pub fn read(&self) -> io::Result<Metrics> {
+        self.view.read().map(|(_, metrics)| metrics)
+    }
+
+    /// This function returns the number of updates published so far, to tell cheaply whether
+    /// [`MetricsReader::read`] would return something new. Fails like it.
+    pub fn updates(&self) -> io::Result<u32> {
+        self.view.read().map(|(sequence, _)| sequence / 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seqlock_round_trips() {
+        let section = Box::into_raw(Box::new(Section {
+            sequence: AtomicU32::new(0),
+            fields: Default::default(),
+        }));
+        let view = View {
+            mapping: ptr::null_mut(),
+            section,
+        };
+        let metrics = Metrics {
+            columns: 120,
+            rows: 30,
+            cell_width: -1,
+            cell_height: 16,
+            dpi: 144,
+        };
+        view.write(metrics);
+        view.write(metrics);
+        assert_eq!(view.read().unwrap(), (4, metrics));
+        std::mem::forget(view);
+        drop(unsafe { Box::from_raw(section) });
+    }
+
+    #[test]
+    fn gives_up_on_a_write_left_half_done() {
+        let section = Box::into_raw(Box::new(Section {
+            sequence: AtomicU32::new(0),
+            fields: Default::default(),
+        }));
+        let view = View {
+            mapping: ptr::null_mut(),
+            section,
+        };
+        view.write(Metrics::default());
+        // A publisher dying between the two increments of `write`.
+        view.section().sequence.fetch_add(1, Ordering::Relaxed);
+        let error = view.read().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        std::mem::forget(view);
+        drop(unsafe { Box::from_raw(section) });
+    }
+}
@@ -0,0 +1,163 @@
+use std::io;
+
+use windows_sys::Win32::Foundation::{BOOL, HWND, LPARAM, RECT};
+use windows_sys::Win32::Graphics::Gdi::{
+    EnumDisplayMonitors, EnumDisplaySettingsW, GetMonitorInfoW, MonitorFromWindow, DEVMODEW,
+    ENUM_CURRENT_SETTINGS, HDC, HMONITOR, MONITORINFO, MONITORINFOEXW, MONITOR_DEFAULTTONEAREST,
+    MONITOR_DEFAULTTOPRIMARY,
+};
+use windows_sys::Win32::System::Console::GetConsoleWindow;
+use windows_sys::Win32::UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
+use windows_sys::Win32::UI::WindowsAndMessaging::MONITORINFOF_PRIMARY;
+
+use crate::console::host_window;
+use crate::font::face_name;
+
+/// Struct to hold a rectangle on the virtual screen, in pixels, `right` and `bottom` excluded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScreenRect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl ScreenRect {
+    /// Width in pixels.
+    pub fn width(&self) -> i32 {
+        self.right - self.left
+    }
+
+    /// Height in pixels.
+    pub fn height(&self) -> i32 {
+        self.bottom - self.top
+    }
+
+    /// Whether `other` lies entirely inside this rectangle.
+    pub fn contains(&self, other: &ScreenRect) -> bool {
+        other.left >= self.left
+            && other.top >= self.top
+            && other.right <= self.right
+            && other.bottom <= self.bottom
+    }
+}
+
+impl From<RECT> for ScreenRect {
+    fn from(rect: RECT) -> Self {
+        ScreenRect {
+            left: rect.left,
+            top: rect.top,
+            right: rect.right,
+            bottom: rect.bottom,
+        }
+    }
+}
+
+/// Struct to hold what Windows reports about a display.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Monitor {
+    pub name: String,          // GDI device name, e.g. `\\.\DISPLAY1`
+    pub primary: bool,         // Whether it is the primary display, at the origin
+    pub bounds: ScreenRect,    // The whole display
+    pub work_area: ScreenRect, // The display without the taskbar and docked app bars
+    pub width: u32,            // Horizontal resolution of the display mode, in physical pixels
+    pub height: u32,           // Vertical resolution of the display mode, in physical pixels
+    pub refresh_hz: u32,       // Refresh rate, 0 or 1 for the hardware default
+    pub dpi: u32,              // Effective DPI, scaling included (96 at 100%)
+}
+
+/// Reads a monitor handle.
+fn describe(monitor: HMONITOR) -> Option<Monitor> {
+    unsafe {
+        let mut info: MONITORINFOEXW = std::mem::zeroed();
+        info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+        if GetMonitorInfoW(
+            monitor,
+            &mut info as *mut MONITORINFOEXW as *mut MONITORINFO,
+        ) == 0
+        {
+            return None;
+        }
+        let mut mode: DEVMODEW = std::mem::zeroed();
+        mode.dmSize = std::mem::size_of::<DEVMODEW>() as u16;
+        // A display being reconfigured has no current mode; report what is known.
+        EnumDisplaySettingsW(info.szDevice.as_ptr(), ENUM_CURRENT_SETTINGS, &mut mode);
+        let (mut dpi, mut dpi_y) = (0, 0);
+        if GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi, &mut dpi_y) < 0 {
+            dpi = 0;
+        }
+        Some(Monitor {
+            name: face_name(&info.szDevice),
+            primary: info.monitorInfo.dwFlags & MONITORINFOF_PRIMARY != 0,
+            bounds: info.monitorInfo.rcMonitor.into(),
+            work_area: info.monitorInfo.rcWork.into(),
+            width: mode.dmPelsWidth,
+            height: mode.dmPelsHeight,
+            refresh_hz: mode.dmDisplayFrequency,
+            dpi,
+        })
+    }
+}
+
+/// This function describes the display the console is shown on.
+///
+/// ## Returns:
+/// - `Ok(Monitor)` for the display holding most of the console window, or of the Windows
+///   Terminal window under a pseudo console.
+/// - `Err(io::Error)` without a console window, or if the display can't be read.
+///
+/// ## Note:
+/// - Under a pseudo console whose terminal doesn't own the console window (SSH, most
+///   third-party terminals), the window the user sees is unknown and the primary display is
+///   returned.
+/// - `bounds` and `work_area` are in the coordinates of the calling thread: scaled down by
+///   Windows unless it is per-monitor DPI aware, see
+///   [`dpi::ensure_per_monitor_awareness`](crate::dpi::ensure_per_monitor_awareness).
+pub fn current() -> io::Result<Monitor> {
+    let console = unsafe { GetConsoleWindow() };
+    if console.is_null() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no console window"));
+    }
+    let window = host_window();
+    let monitor = match window.is_null() {
+        false => unsafe { MonitorFromWindow(window, MONITOR_DEFAULTTONEAREST) },
+        true => unsafe { MonitorFromWindow(console, MONITOR_DEFAULTTOPRIMARY) },
+    };
+    describe(monitor).ok_or_else(io::Error::last_os_error)
+}
+
+/// This function describes the display a window is shown on, e.g. an overlay placed next to
+/// the console, see [`current`].
+#[allow(clippy::not_unsafe_ptr_arg_deref)] // Checked by Windows, like in `sys`.
+pub fn for_window(hwnd: HWND) -> io::Result<Monitor> {
+    describe(unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) })
+        .ok_or_else(io::Error::last_os_error)
+}
+
+/// This function lists every display attached to the desktop.
+///
+/// ## Returns:
+/// - The displays in the order Windows enumerates them, the primary one not necessarily
+///   first. Empty without an interactive desktop (services, some CI runners).
+pub fn all() -> Vec<Monitor> {
+    unsafe extern "system" fn collect(
+        monitor: HMONITOR,
+        _dc: HDC,
+        _rect: *mut RECT,
+        monitors: LPARAM,
+    ) -> BOOL {
+        let monitors = &mut *(monitors as *mut Vec<Monitor>);
+        monitors.extend(describe(monitor));
+        1
+    }
+    let mut monitors: Vec<Monitor> = Vec::new();
+    unsafe {
+        EnumDisplayMonitors(
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            Some(collect),
+            &mut monitors as *mut Vec<Monitor> as LPARAM,
+        );
+    }
+    monitors
+}
@@ -0,0 +1,21 @@
+// Off Windows there are no Win32 libraries to link the unit tests against. The functions the
+// tested code references, without the tests reaching them, are defined here as failing the
+// way they do without a console: 0, a null handle or `FALSE`, arguments untouched.
+macro_rules! no_console {
+    ($($name:ident)*) => {$(
+        #[no_mangle]
+        extern "system" fn $name() -> usize {
+            0
+        }
+    )*};
+}
+
+no_console! {
+    CloseHandle
+    GetConsoleMode
+    GetConsoleScreenBufferInfo
+    GetFileType
+    GetLocaleInfoEx
+    GetStdHandle
+    UnmapViewOfFile
+}
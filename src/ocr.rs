@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use crate::capture::Screenshot;
+use crate::console::console_output;
+use crate::export::{current_face, rasterize};
+use crate::frame::Frame;
+use crate::style::Rgb;
+use crate::{cell_size_from, last_error_code, FontSize, TerminalError};
+
+/// Characters beyond printable ASCII that TUIs commonly draw: box drawing and shading.
+const EXTRA_GLYPHS: &str = "─│┌┐└┘├┤┬┴┼═║╔╗╚╝█▀▄░▒▓";
+
+/// Minimum color distance (sum over the channels) between a pixel and the cell background
+/// for the pixel to count as ink.
+const INK_THRESHOLD: u32 = 96;
+
+/// Struct to hold glyph templates of a monospace font, to turn screenshots of cells back into
+/// text where the console buffer can't be read, e.g. behind a pseudo console.
+///
+/// Every candidate character is drawn once, white on black, and reduced to an ink mask. A cell
+/// of a screenshot is reduced the same way, taking its most common color as the background,
+/// and recognized as the template agreeing on the most pixels. Colors and attributes don't
+/// matter, so the matcher reads colored output, but bold and italic glyphs match less
+/// reliably than regular ones.
+#[derive(Debug, Clone)]
+pub struct GlyphMatcher {
+    cell: (usize, usize),           // Cell width and height in pixels
+    glyphs: Vec<(char, Vec<bool>)>, // Ink mask of each candidate, row-major
+}
+
+impl GlyphMatcher {
+    /// This function renders the templates of `chars` in `face` at the `cell` size.
+    ///
+    /// ## Returns:
+    /// - `Err(TerminalError::NoFontInfo(_))` if GDI can't draw the font.
+    pub fn new(
+        face: &str,
+        cell: FontSize,
+        chars: impl IntoIterator<Item = char>,
+    ) -> Result<GlyphMatcher, TerminalError> {
+        let chars: Vec<char> = chars.into_iter().filter(|&ch| ch != ' ').collect();
+        let mut frame = Frame::new(chars.len(), 1);
+        for (cell, &ch) in frame.cells.iter_mut().zip(&chars) {
+            cell.ch = ch;
+            cell.fg = Rgb {
+                r: 255,
+                g: 255,
+                b: 255,
+            };
+            cell.bg = Rgb { r: 0, g: 0, b: 0 };
+        }
+        let rgb = rasterize(&frame, &cell, face)?;
+        let (w, h) = (cell.width.max(1) as usize, cell.height.max(1) as usize);
+        let stride = chars.len() * w * 3;
+        let glyphs = chars
+            .iter()
+            .enumerate()
+            .map(|(i, &ch)| {
+                let mut mask = Vec::with_capacity(w * h);
+                for y in 0..h {
+                    for x in 0..w {
+                        let at = y * stride + (i * w + x) * 3;
+                        mask.push(rgb.get(at).is_some_and(|&r| r >= 128));
+                    }
+                }
+                (ch, mask)
+            })
+            .collect();
+        Ok(GlyphMatcher {
+            cell: (w, h),
+            glyphs,
+        })
+    }
+
+    /// This function renders printable ASCII, box drawing and shading characters in the
+    /// console font at the measured cell size.
+    pub fn for_console() -> Result<GlyphMatcher, TerminalError> {
+        let cell = cell_size_from(
+            console_output().ok_or_else(|| TerminalError::NoStdHandle(last_error_code()))?,
+            true,
+        )?;
+        let chars = ('!'..='~').chain(EXTRA_GLYPHS.chars());
+        GlyphMatcher::new(&current_face(), cell, chars)
+    }
+
+    /// This function reads the text of a screenshot taken at cell boundaries, e.g. by
+    /// [`crate::capture::cells_image`], one line per row of cells.
+    ///
+    /// ## Note:
+    /// - Partial cells at the right and bottom edges are ignored.
+    /// - Trailing blanks are trimmed from every line.
+    pub fn recognize(&self, shot: &Screenshot) -> Vec<String> {
+        let (w, h) = self.cell;
+        let columns = shot.width as usize / w;
+        let rows = shot.height as usize / h;
+        (0..rows)
+            .map(|row| {
+                let line: String = (0..columns)
+                    .map(|column| self.recognize_cell(shot, column * w, row * h))
+                    .collect();
+                line.trim_end().to_string()
+            })
+            .collect()
+    }
+
+    fn recognize_cell(&self, shot: &Screenshot, left: usize, top: usize) -> char {
+        let (w, h) = self.cell;
+        let stride = shot.width as usize * 4;
+        let pixel = |x: usize, y: usize| {
+            let at = (top + y) * stride + (left + x) * 4;
+            [shot.pixels[at], shot.pixels[at + 1], shot.pixels[at + 2]]
+        };
+        let mut counts: HashMap<[u8; 3], usize> = HashMap::new();
+        for y in 0..h {
+            for x in 0..w {
+                *counts.entry(pixel(x, y)).or_default() += 1;
+            }
+        }
+        let background = counts
+            .into_iter()
+            .max_by_key(|&(_, count)| count)
+            .map(|(color, _)| color)
+            .unwrap_or_default();
+        let mut ink = Vec::with_capacity(w * h);
+        for y in 0..h {
+            for x in 0..w {
+                let distance: u32 = pixel(x, y)
+                    .iter()
+                    .zip(background)
+                    .map(|(&a, b)| a.abs_diff(b) as u32)
+                    .sum();
+                ink.push(distance >= INK_THRESHOLD);
+            }
+        }
+        if !ink.contains(&true) {
+            return ' ';
+        }
+        self.glyphs
+            .iter()
+            .max_by_key(|(_, mask)| mask.iter().zip(&ink).filter(|(a, b)| a == b).count())
+            .map_or(' ', |&(ch, _)| ch)
+    }
+}
@@ -0,0 +1,296 @@
+use std::io;
+use std::ptr;
+
+use windows::Win32::Foundation::RECT as D2dRect;
+use windows::Win32::Graphics::Direct2D::Common::{
+    D2D1_ALPHA_MODE_PREMULTIPLIED, D2D1_COLOR_F, D2D1_PIXEL_FORMAT, D2D_POINT_2F, D2D_RECT_F,
+};
+use windows::Win32::Graphics::Direct2D::{
+    D2D1CreateFactory, ID2D1DCRenderTarget, ID2D1Factory, ID2D1RenderTarget,
+    D2D1_FACTORY_TYPE_SINGLE_THREADED, D2D1_FEATURE_LEVEL_DEFAULT, D2D1_RENDER_TARGET_PROPERTIES,
+    D2D1_RENDER_TARGET_TYPE_DEFAULT, D2D1_RENDER_TARGET_USAGE_NONE,
+};
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT_B8G8R8A8_UNORM;
+use windows::Win32::Graphics::Gdi::HDC as D2dHdc;
+use windows_sys::Win32::Foundation::{HWND, POINT, RECT, SIZE};
+use windows_sys::Win32::Graphics::Gdi::{
+    ClientToScreen, CreateCompatibleDC, CreateDIBSection, DeleteDC, DeleteObject, SelectObject,
+    AC_SRC_ALPHA, AC_SRC_OVER, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, BLENDFUNCTION, DIB_RGB_COLORS,
+    HBITMAP, HDC,
+};
+use windows_sys::Win32::System::Console::GetConsoleWindow;
+use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetClientRect, PeekMessageW,
+    RegisterClassExW, ShowWindow, UpdateLayeredWindow, MSG, PM_REMOVE, SW_SHOWNOACTIVATE,
+    ULW_ALPHA, WNDCLASSEXW, WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TRANSPARENT,
+    WS_POPUP,
+};
+
+use crate::cell_size_from;
+use crate::console::console_output;
+use crate::FontSize;
+
+/// Struct to hold the cell-aligned coordinate system of an overlay: cell `(0, 0)` is the top
+/// left cell of the visible window, in pixels of the console client area.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellSpace {
+    pub cell_width: f32,  // Width of a cell in pixels
+    pub cell_height: f32, // Height of a cell in pixels
+    pub columns: u32,     // Whole cells across the client area
+    pub rows: u32,        // Whole cells down the client area
+}
+
+impl CellSpace {
+    /// The top left corner of a cell; fractional cells address points inside it.
+    pub fn point(&self, column: f32, row: f32) -> D2D_POINT_2F {
+        D2D_POINT_2F {
+            x: column * self.cell_width,
+            y: row * self.cell_height,
+        }
+    }
+
+    /// The rectangle covering `columns` x `rows` cells from `(column, row)`.
+    pub fn rect(&self, column: f32, row: f32, columns: f32, rows: f32) -> D2D_RECT_F {
+        D2D_RECT_F {
+            left: column * self.cell_width,
+            top: row * self.cell_height,
+            right: (column + columns) * self.cell_width,
+            bottom: (row + rows) * self.cell_height,
+        }
+    }
+}
+
+/// Struct to hold a transparent layered window laid exactly over the console client area,
+/// drawn with Direct2D in a cell-aligned coordinate system.
+///
+/// The window is click-through, never activated, and owned by the console window, so it
+/// stays above it and follows it when minimized. It isn't thread-safe: create, draw and
+/// drop it from the same thread.
+#[derive(Debug)]
+pub struct D2DSurface {
+    window: HWND,
+    target: ID2D1DCRenderTarget,
+    dc: HDC,
+    bitmap: HBITMAP,
+    size: (i32, i32),
+}
+
+fn other(e: windows::core::Error) -> io::Error {
+    io::Error::other(e.to_string())
+}
+
+impl D2DSurface {
+    /// This function creates the overlay window, shown but empty until the first
+    /// [`D2DSurface::draw`].
+    ///
+    /// ## Returns:
+    /// - `Err(io::Error)` without a console window, or if the window or the Direct2D render
+    ///   target can't be created.
+    ///
+    /// ## Note:
+    /// - Under Windows Terminal the console window is a hidden pseudo-window, so the overlay
+    ///   has nothing to cover.
+    pub fn new() -> io::Result<D2DSurface> {
+        unsafe {
+            let console = GetConsoleWindow();
+            if console.is_null() {
+                return Err(io::Error::new(io::ErrorKind::NotFound, "no console window"));
+            }
+            let instance = GetModuleHandleW(ptr::null());
+            let class: Vec<u16> = "win-term overlay".encode_utf16().chain([0]).collect();
+            let wc = WNDCLASSEXW {
+                cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+                lpfnWndProc: Some(DefWindowProcW),
+                hInstance: instance,
+                lpszClassName: class.as_ptr(),
+                ..std::mem::zeroed()
+            };
+            // Registering twice fails harmlessly; creating the window reports real errors.
+            RegisterClassExW(&wc);
+            let window = CreateWindowExW(
+                WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+                class.as_ptr(),
+                ptr::null(),
+                WS_POPUP,
+                0,
+                0,
+                0,
+                0,
+                console,
+                ptr::null_mut(),
+                instance,
+                ptr::null(),
+            );
+            if window.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let factory: ID2D1Factory =
+                D2D1CreateFactory(D2D1_FACTORY_TYPE_SINGLE_THREADED, None).map_err(other)?;
+            let properties = D2D1_RENDER_TARGET_PROPERTIES {
+                r#type: D2D1_RENDER_TARGET_TYPE_DEFAULT,
+                pixelFormat: D2D1_PIXEL_FORMAT {
+                    format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                    alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+                },
+                // 96 DPI makes device-independent pixels actual pixels.
+                dpiX: 96.0,
+                dpiY: 96.0,
+                usage: D2D1_RENDER_TARGET_USAGE_NONE,
+                minLevel: D2D1_FEATURE_LEVEL_DEFAULT,
+            };
+            let target = match factory.CreateDCRenderTarget(&properties) {
+                Ok(target) => target,
+                Err(e) => {
+                    DestroyWindow(window);
+                    return Err(other(e));
+                }
+            };
+            ShowWindow(window, SW_SHOWNOACTIVATE);
+            Ok(D2DSurface {
+                window,
+                target,
+                dc: CreateCompatibleDC(ptr::null_mut()),
+                bitmap: ptr::null_mut(),
+                size: (0, 0),
+            })
+        }
+    }
+
+    /// This function redraws the overlay: it is realigned to the console client area, cleared
+    /// to transparent, and `draw` paints it between `BeginDraw` and `EndDraw`.
+    ///
+    /// ## Note:
+    /// - Pixels are premultiplied BGRA; anything left transparent shows the console through.
+    /// - Pending window messages of the overlay are processed first, so drawing regularly is
+    ///   enough to keep the window responsive.
+    pub fn draw(&mut self, draw: impl FnOnce(&ID2D1RenderTarget, &CellSpace)) -> io::Result<()> {
+        unsafe {
+            let mut message: MSG = std::mem::zeroed();
+            while PeekMessageW(&mut message, self.window, 0, 0, PM_REMOVE) != 0 {
+                DispatchMessageW(&message);
+            }
+            let console = GetConsoleWindow();
+            let mut client = RECT {
+                left: 0,
+                top: 0,
+                right: 0,
+                bottom: 0,
+            };
+            let mut origin = POINT { x: 0, y: 0 };
+            if GetClientRect(console, &mut client) == 0 || ClientToScreen(console, &mut origin) == 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+            let size = (client.right.max(1), client.bottom.max(1));
+            if size != self.size {
+                self.resize(size)?;
+            }
+            let cell = console_output()
+                .and_then(|handle| cell_size_from(handle, true).ok())
+                .unwrap_or(FontSize {
+                    width: 8,
+                    height: 16,
+                });
+            let space = CellSpace {
+                cell_width: cell.width as f32,
+                cell_height: cell.height as f32,
+                columns: (size.0 / cell.width.max(1)) as u32,
+                rows: (size.1 / cell.height.max(1)) as u32,
+            };
+
+            let bounds = D2dRect {
+                left: 0,
+                top: 0,
+                right: size.0,
+                bottom: size.1,
+            };
+            self.target
+                .BindDC(D2dHdc(self.dc), &bounds)
+                .map_err(other)?;
+            self.target.BeginDraw();
+            self.target.Clear(Some(&D2D1_COLOR_F {
+                r: 0.0,
+                g: 0.0,
+                b: 0.0,
+                a: 0.0,
+            }));
+            draw(&self.target, &space);
+            self.target.EndDraw(None, None).map_err(other)?;
+
+            let extent = SIZE {
+                cx: size.0,
+                cy: size.1,
+            };
+            let source = POINT { x: 0, y: 0 };
+            let blend = BLENDFUNCTION {
+                BlendOp: AC_SRC_OVER as u8,
+                BlendFlags: 0,
+                SourceConstantAlpha: 255,
+                AlphaFormat: AC_SRC_ALPHA as u8,
+            };
+            if UpdateLayeredWindow(
+                self.window,
+                ptr::null_mut(),
+                &origin,
+                &extent,
+                self.dc,
+                &source,
+                0,
+                &blend,
+                ULW_ALPHA,
+            ) == 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+    }
+
+    /// Replaces the backing bitmap with a top-down 32-bit one of `size`.
+    fn resize(&mut self, size: (i32, i32)) -> io::Result<()> {
+        unsafe {
+            let mut info: BITMAPINFO = std::mem::zeroed();
+            info.bmiHeader = BITMAPINFOHEADER {
+                biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: size.0,
+                biHeight: -size.1,
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB,
+                ..std::mem::zeroed()
+            };
+            let mut bits = ptr::null_mut();
+            let bitmap = CreateDIBSection(
+                self.dc,
+                &info,
+                DIB_RGB_COLORS,
+                &mut bits,
+                ptr::null_mut(),
+                0,
+            );
+            if bitmap.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            SelectObject(self.dc, bitmap);
+            if !self.bitmap.is_null() {
+                DeleteObject(self.bitmap);
+            }
+            self.bitmap = bitmap;
+            self.size = size;
+            Ok(())
+        }
+    }
+}
+
+impl Drop for D2DSurface {
+    fn drop(&mut self) {
+        unsafe {
+            DestroyWindow(self.window);
+            DeleteDC(self.dc);
+            if !self.bitmap.is_null() {
+                DeleteObject(self.bitmap);
+            }
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::io;
+use std::ptr;
+
+use windows_sys::Win32::Foundation::{
+    CloseHandle, ERROR_ACCESS_DENIED, HANDLE, INVALID_HANDLE_VALUE, STILL_ACTIVE, WAIT_ABANDONED,
+    WAIT_OBJECT_0,
+};
+use windows_sys::Win32::System::Console::GetConsoleWindow;
+use windows_sys::Win32::System::Memory::{
+    CreateFileMappingW, MapViewOfFile, OpenFileMappingW, UnmapViewOfFile, FILE_MAP_ALL_ACCESS,
+    FILE_MAP_READ, MEMORY_MAPPED_VIEW_ADDRESS, PAGE_READWRITE,
+};
+use windows_sys::Win32::System::Threading::{
+    CreateMutexW, GetCurrentProcessId, GetExitCodeProcess, OpenProcess, ReleaseMutex,
+    WaitForSingleObject, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+
+use crate::last_error_code;
+use crate::reset::ConsoleState;
+
+/// Struct to hold the shared memory section every process of the console sees.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct Shared {
+    owner_pid: u32,  // Process owning the console, 0 when there is none
+    valid: u32,      // One bit per captured field, in declaration order below
+    input_mode: u32, // Console modes and attributes before the first owner changed them
+    output_mode: u32,
+    attributes: u32,
+    input_cp: u32,
+    output_cp: u32,
+}
+
+impl Shared {
+    fn new(owner_pid: u32, state: &ConsoleState) -> Self {
+        let valid = state.input_mode.is_some() as u32
+            | (state.output_mode.is_some() as u32) << 1
+            | (state.attributes.is_some() as u32) << 2;
+        Shared {
+            owner_pid,
+            valid,
+            input_mode: state.input_mode.unwrap_or(0),
+            output_mode: state.output_mode.unwrap_or(0),
+            attributes: state.attributes.unwrap_or(0) as u32,
+            input_cp: state.input_cp,
+            output_cp: state.output_cp,
+        }
+    }
+
+    /// The saved state, `None` if no owner ever wrote one.
+    fn state(&self) -> Option<ConsoleState> {
+        // Code pages are never 0, so a zeroed (fresh) section has no state.
+        (self.output_cp != 0).then(|| ConsoleState {
+            input_mode: (self.valid & 1 != 0).then_some(self.input_mode),
+            output_mode: (self.valid & 2 != 0).then_some(self.output_mode),
+            attributes: (self.valid & 4 != 0).then_some(self.attributes as u16),
+            input_cp: self.input_cp,
+            output_cp: self.output_cp,
+        })
+    }
+}
+
+/// Names of the mutex and the section of the attached console, `None` without a console.
+fn object_names() -> Option<(Vec<u16>, Vec<u16>)> {
+    let window = unsafe { GetConsoleWindow() };
+    if window.is_null() {
+        return None;
+    }
+    let name = |kind| {
+        format!(r"Local\win-term-{:x}-{}", window as usize, kind)
+            .encode_utf16()
+            .chain([0])
+            .collect()
+    };
+    Some((name("owner"), name("state")))
+}
+
+/// Enum to represent the outcome of [`claim`].
+#[derive(Debug)]
+pub enum Role {
+    Owner(ConsoleOwner), // This process owns the console modes until the owner is dropped
+    Observer(u32),       // Another process owns them; its process id
+}
+
+/// Struct to hold the ownership of the console modes, given back when dropped.
+///
+/// The ownership is a mutex held by the claiming thread, so the owner can't be sent to
+/// another thread.
+///
+/// Dropping the owner puts back the console state saved when ownership was first taken, so
+/// the console is left as the user had it, whichever process changed it in between.
+#[derive(Debug)]
+pub struct ConsoleOwner {
+    mutex: HANDLE,
+    mapping: HANDLE,
+    view: *mut Shared,
+    saved: ConsoleState,
+}
+
+/// This function claims the ownership of the console modes among the processes attached to
+/// the same console, e.g. a plugin host and its plugins all using this crate.
+///
+/// ## Returns:
+/// - `Ok(Role::Owner)` if no other process owns the console. The console state is captured
+///   into shared memory for the others to see.
+/// - `Ok(Role::Observer(pid))` if the process `pid` owns it; the caller should leave the
+///   console modes alone and only observe.
+/// - `Err(io::Error)` without a console or if the named objects can't be created.
+///
+/// ## Note:
+/// - The protocol is cooperative: a process that never calls `claim` isn't kept from changing
+///   modes. Within the crate, a [`Watchdog`](crate::watchdog::Watchdog) of an observer won't
+///   restore the console over the owner's state.
+/// - If the previous owner died without giving the console back, the new owner first puts back
+///   the state it had saved, then keeps it as its own saved state.
+/// - Claiming again while owning the console returns `Role::Observer` with the id of this
+///   process.
+pub fn claim() -> io::Result<Role> {
+    let (mutex_name, section_name) = object_names()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no console window"))?;
+    unsafe {
+        let mutex = CreateMutexW(ptr::null(), 0, mutex_name.as_ptr());
+        if mutex.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let mapping = CreateFileMappingW(
+            INVALID_HANDLE_VALUE,
+            ptr::null(),
+            PAGE_READWRITE,
+            0,
+            std::mem::size_of::<Shared>() as u32,
+            section_name.as_ptr(),
+        );
+        if mapping.is_null() {
+            let e = io::Error::last_os_error();
+            CloseHandle(mutex);
+            return Err(e);
+        }
+        let view = MapViewOfFile(mapping, FILE_MAP_ALL_ACCESS, 0, 0, 0).Value as *mut Shared;
+        if view.is_null() {
+            let e = io::Error::last_os_error();
+            CloseHandle(mapping);
+            CloseHandle(mutex);
+            return Err(e);
+        }
+        let previous = ptr::read_volatile(view);
+        let observe = || {
+            UnmapViewOfFile(MEMORY_MAPPED_VIEW_ADDRESS { Value: view.cast() });
+            CloseHandle(mapping);
+            CloseHandle(mutex);
+            Ok(Role::Observer(previous.owner_pid))
+        };
+        let saved = match WaitForSingleObject(mutex, 0) {
+            // The mutex is recursive: this thread already owns the console.
+            WAIT_OBJECT_0 if previous.owner_pid == GetCurrentProcessId() => {
+                ReleaseMutex(mutex);
+                return observe();
+            }
+            WAIT_OBJECT_0 => ConsoleState::capture(),
+            WAIT_ABANDONED => match previous.state() {
+                Some(state) => {
+                    state.restore();
+                    state
+                }
+                None => ConsoleState::capture(),
+            },
+            _ => return observe(),
+        };
+        ptr::write_volatile(view, Shared::new(GetCurrentProcessId(), &saved));
+        Ok(Role::Owner(ConsoleOwner {
+            mutex,
+            mapping,
+            view,
+            saved,
+        }))
+    }
+}
+
+/// This function returns the process id of the console owner, `None` when no process owns it.
+///
+/// ## Note:
+/// - An owner that died without giving the console back owns nothing: its id is only
+///   returned while it runs. The next [`claim`] puts back the state it saved.
+pub fn owner() -> Option<u32> {
+    let (_, section_name) = object_names()?;
+    unsafe {
+        let mapping = OpenFileMappingW(FILE_MAP_READ, 0, section_name.as_ptr());
+        if mapping.is_null() {
+            return None;
+        }
+        let view = MapViewOfFile(mapping, FILE_MAP_READ, 0, 0, 0);
+        let pid = (!view.Value.is_null()).then(|| {
+            let pid = ptr::read_volatile(view.Value as *const Shared).owner_pid;
+            UnmapViewOfFile(view);
+            pid
+        });
+        CloseHandle(mapping);
+        pid.filter(|&pid| pid != 0 && is_running(pid))
+    }
+}
+
+/// Whether this process may change the console modes: nobody owns the console, or it does.
+pub(crate) fn may_change_modes() -> bool {
+    owner().is_none_or(|pid| pid == unsafe { GetCurrentProcessId() })
+}
+
+impl Drop for ConsoleOwner {
+    fn drop(&mut self) {
+        self.saved.restore();
+        unsafe {
+            let mut shared = ptr::read_volatile(self.view);
+            shared.owner_pid = 0;
+            ptr::write_volatile(self.view, shared);
+            UnmapViewOfFile(MEMORY_MAPPED_VIEW_ADDRESS {
+                Value: self.view.cast(),
+            });
+            ReleaseMutex(self.mutex);
+            CloseHandle(self.mapping);
+            CloseHandle(self.mutex);
+        }
+    }
+}
+
+/// Whether a process is still running.
+pub(crate) fn is_running(pid: u32) -> bool {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process.is_null() {
+            // Another user's process still exists; a missing one can't be opened at all.
+            return last_error_code() == ERROR_ACCESS_DENIED;
+        }
+        let mut code = 0;
+        let running = GetExitCodeProcess(process, &mut code) != 0 && code == STILL_ACTIVE as u32;
+        CloseHandle(process);
+        running
+    }
+}
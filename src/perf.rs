@@ -0,0 +1,84 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use windows_sys::Win32::System::Console::{
+    ReadConsoleInputW, WriteConsoleInputW, INPUT_RECORD, MENU_EVENT,
+};
+
+use crate::console::console_input;
+
+/// Command id of the probe records, "wint" in ASCII.
+const PROBE_COMMAND: u32 = 0x7769_6e74;
+
+/// Struct to hold round-trip statistics of [`input_latency_probe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyStats {
+    pub samples: usize, // Number of round trips measured
+    pub min: Duration,  // Fastest round trip
+    pub p50: Duration,  // Median round trip
+    pub p99: Duration,  // 99th percentile, the jitter users notice
+    pub max: Duration,  // Slowest round trip
+}
+
+/// This function measures how long an input record takes to go through the console input
+/// queue, from `WriteConsoleInput` to `ReadConsoleInput`, `samples` times.
+///
+/// ## Returns:
+/// - `Ok(LatencyStats)` over the round trips (at least one is made).
+/// - `Err(io::Error)` without a console input, or if writing or reading the queue fails.
+///
+/// ## Note:
+/// - Probes are `MENU_EVENT` records, which programs are expected to ignore. Input that
+///   arrives from the user meanwhile is read along with them and written back afterwards,
+///   after anything typed later.
+/// - The time measured is that of the console host (conhost, or OpenConsole under Windows
+///   Terminal), which is what differs between hosts and over RDP; the keyboard-to-terminal
+///   part of the path is not included.
+pub fn input_latency_probe(samples: usize) -> io::Result<LatencyStats> {
+    let input = console_input().ok_or_else(io::Error::last_os_error)?;
+    let mut times = Vec::with_capacity(samples.max(1));
+    let mut others: Vec<INPUT_RECORD> = Vec::new();
+    let mut result = Ok(());
+    'probe: for _ in 0..samples.max(1) {
+        let mut probe: INPUT_RECORD = unsafe { std::mem::zeroed() };
+        probe.EventType = MENU_EVENT as u16;
+        probe.Event.MenuEvent.dwCommandId = PROBE_COMMAND;
+        let mut count = 0;
+        let started = Instant::now();
+        if unsafe { WriteConsoleInputW(input, &probe, 1, &mut count) } == 0 || count != 1 {
+            result = Err(io::Error::last_os_error());
+            break;
+        }
+        loop {
+            let mut record: INPUT_RECORD = unsafe { std::mem::zeroed() };
+            if unsafe { ReadConsoleInputW(input, &mut record, 1, &mut count) } == 0 {
+                result = Err(io::Error::last_os_error());
+                break 'probe;
+            }
+            if count == 1
+                && record.EventType == MENU_EVENT as u16
+                && unsafe { record.Event.MenuEvent.dwCommandId } == PROBE_COMMAND
+            {
+                times.push(started.elapsed());
+                break;
+            }
+            if count == 1 {
+                others.push(record);
+            }
+        }
+    }
+    if !others.is_empty() {
+        let mut count = 0;
+        unsafe { WriteConsoleInputW(input, others.as_ptr(), others.len() as u32, &mut count) };
+    }
+    result?;
+    times.sort();
+    let at = |percent: usize| times[(times.len() - 1) * percent / 100];
+    Ok(LatencyStats {
+        samples: times.len(),
+        min: times[0],
+        p50: at(50),
+        p99: at(99),
+        max: times[times.len() - 1],
+    })
+}
@@ -0,0 +1,232 @@
+use std::io::{self, Read, Write};
+use std::mem;
+use std::time::Duration;
+
+use windows_sys::Win32::Foundation::{
+    CloseHandle, GetLastError, ERROR_BROKEN_PIPE, ERROR_PIPE_CONNECTED, GENERIC_ALL, GENERIC_READ,
+    GENERIC_WRITE, HANDLE, INVALID_HANDLE_VALUE,
+};
+use windows_sys::Win32::Security::{
+    AddAccessAllowedAce, GetLengthSid, GetTokenInformation, InitializeAcl,
+    InitializeSecurityDescriptor, SetSecurityDescriptorDacl, TokenUser, ACCESS_ALLOWED_ACE, ACL,
+    ACL_REVISION, SECURITY_ATTRIBUTES, SECURITY_DESCRIPTOR, TOKEN_QUERY, TOKEN_USER,
+};
+use windows_sys::Win32::Storage::FileSystem::{
+    CreateFileW, FlushFileBuffers, ReadFile, WriteFile, FILE_FLAG_FIRST_PIPE_INSTANCE,
+    OPEN_EXISTING, PIPE_ACCESS_DUPLEX,
+};
+use windows_sys::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe, SetNamedPipeHandleState,
+    WaitNamedPipeW, PIPE_NOWAIT, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE,
+    PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+};
+use windows_sys::Win32::System::SystemServices::SECURITY_DESCRIPTOR_REVISION;
+use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+/// Struct to hold one server instance of a byte-mode named pipe, disconnected and closed on
+/// drop.
+pub(crate) struct Pipe(HANDLE);
+
+// The handle is only used through `ReadFile`/`WriteFile`, which are safe from any thread.
+unsafe impl Send for Pipe {}
+
+/// The `\\.\pipe\<name>` path of a pipe, null-terminated.
+pub(crate) fn pipe_path(name: &str) -> Vec<u16> {
+    format!(r"\\.\pipe\{}", name)
+        .encode_utf16()
+        .chain([0])
+        .collect()
+}
+
+/// Calls `f` with security attributes whose DACL only lets the current user in.
+fn current_user_only<T>(f: impl FnOnce(&SECURITY_ATTRIBUTES) -> T) -> io::Result<T> {
+    unsafe {
+        let mut token = std::ptr::null_mut();
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // `TOKEN_USER` followed by the SID it points to; u64s keep it aligned.
+        let mut user = vec![0u64; 64];
+        let mut len = 0;
+        let ok = GetTokenInformation(
+            token,
+            TokenUser,
+            user.as_mut_ptr().cast(),
+            (user.len() * 8) as u32,
+            &mut len,
+        );
+        CloseHandle(token);
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let sid = (*user.as_ptr().cast::<TOKEN_USER>()).User.Sid;
+        let acl_len = std::mem::size_of::<ACL>() + std::mem::size_of::<ACCESS_ALLOWED_ACE>()
+            - std::mem::size_of::<u32>()
+            + GetLengthSid(sid) as usize;
+        let mut acl_buffer = vec![0u64; acl_len.div_ceil(8)];
+        let acl = acl_buffer.as_mut_ptr().cast::<ACL>();
+        let mut descriptor: SECURITY_DESCRIPTOR = std::mem::zeroed();
+        let descriptor_ptr = (&mut descriptor as *mut SECURITY_DESCRIPTOR).cast();
+        if InitializeAcl(acl, acl_len as u32, ACL_REVISION) == 0
+            || AddAccessAllowedAce(acl, ACL_REVISION, GENERIC_ALL, sid) == 0
+            || InitializeSecurityDescriptor(descriptor_ptr, SECURITY_DESCRIPTOR_REVISION) == 0
+            || SetSecurityDescriptorDacl(descriptor_ptr, 1, acl, 0) == 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        let attributes = SECURITY_ATTRIBUTES {
+            nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: descriptor_ptr,
+            bInheritHandle: 0,
+        };
+        Ok(f(&attributes))
+    }
+}
+
+impl Pipe {
+    /// Creates a new instance of the pipe, waiting for a client with [`Pipe::connect`].
+    ///
+    /// Only the current user can open it, and only from this machine. With `first`, this
+    /// must be the first instance: creating it fails with access denied while another
+    /// server, maybe another user squatting the name, has one open.
+    pub(crate) fn create(path: &[u16], first: bool) -> io::Result<Pipe> {
+        let open_mode = match first {
+            true => PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE,
+            false => PIPE_ACCESS_DUPLEX,
+        };
+        let handle = current_user_only(|attributes| unsafe {
+            CreateNamedPipeW(
+                path.as_ptr(),
+                open_mode,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                4096,
+                4096,
+                0,
+                attributes,
+            )
+        })?;
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Pipe(handle))
+    }
+
+    /// Blocks until a client opens the pipe.
+    pub(crate) fn connect(&self) -> io::Result<()> {
+        let connected = unsafe { ConnectNamedPipe(self.0, std::ptr::null_mut()) } != 0
+            // The client got in between `create` and `connect`.
+            || unsafe { GetLastError() } == ERROR_PIPE_CONNECTED;
+        if !connected {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Makes writes return at once: what doesn't fit in the pipe buffer of a client that
+    /// stopped reading isn't written, and `write_all` fails with `WriteZero`.
+    pub(crate) fn set_nonblocking(&self) -> io::Result<()> {
+        let mode = PIPE_READMODE_BYTE | PIPE_NOWAIT;
+        if unsafe { SetNamedPipeHandleState(self.0, &mode, std::ptr::null(), std::ptr::null()) }
+            == 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Disconnects the client without waiting for it to read what is left in the pipe, as
+    /// dropping the instance does.
+    pub(crate) fn discard(self) {
+        unsafe {
+            DisconnectNamedPipe(self.0);
+            CloseHandle(self.0);
+        }
+        mem::forget(self);
+    }
+}
+
+/// Opens and closes the pipe as a client, waking a server blocked in [`Pipe::connect`].
+///
+/// Waits up to `timeout` for an instance to listen, as there is none while the server is
+/// between two instances; returns without connecting if none does.
+pub(crate) fn poke(path: &[u16], timeout: Duration) {
+    if unsafe { WaitNamedPipeW(path.as_ptr(), timeout.as_millis() as u32) } == 0 {
+        return;
+    }
+    let handle = unsafe {
+        CreateFileW(
+            path.as_ptr(),
+            GENERIC_READ | GENERIC_WRITE,
+            0,
+            std::ptr::null(),
+            OPEN_EXISTING,
+            0,
+            std::ptr::null_mut(),
+        )
+    };
+    if handle != INVALID_HANDLE_VALUE {
+        unsafe {
+            CloseHandle(handle);
+        }
+    }
+}
+
+impl Read for &Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut read = 0;
+        let len = buf.len().min(u32::MAX as usize) as u32;
+        if unsafe {
+            ReadFile(
+                self.0,
+                buf.as_mut_ptr(),
+                len,
+                &mut read,
+                std::ptr::null_mut(),
+            )
+        } == 0
+        {
+            let e = io::Error::last_os_error();
+            // The client closing its end reads as the end of the stream.
+            if e.raw_os_error() == Some(ERROR_BROKEN_PIPE as i32) {
+                return Ok(0);
+            }
+            return Err(e);
+        }
+        Ok(read as usize)
+    }
+}
+
+impl Write for &Pipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut written = 0;
+        let len = buf.len().min(u32::MAX as usize) as u32;
+        if unsafe {
+            WriteFile(
+                self.0,
+                buf.as_ptr(),
+                len,
+                &mut written,
+                std::ptr::null_mut(),
+            )
+        } == 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(written as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        unsafe {
+            FlushFileBuffers(self.0);
+            DisconnectNamedPipe(self.0);
+            CloseHandle(self.0);
+        }
+    }
+}
@@ -0,0 +1,560 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use windows_sys::Win32::Foundation::HANDLE;
+use windows_sys::Win32::System::Console::{
+    ReadConsoleInputW, ReadConsoleW, DOUBLE_CLICK, ENABLE_ECHO_INPUT, ENABLE_EXTENDED_FLAGS,
+    ENABLE_LINE_INPUT, ENABLE_MOUSE_INPUT, ENABLE_PROCESSED_INPUT, ENABLE_QUICK_EDIT_MODE,
+    FROM_LEFT_1ST_BUTTON_PRESSED, INPUT_RECORD, KEY_EVENT, MOUSE_EVENT, MOUSE_WHEELED,
+    SHIFT_PRESSED, STD_ERROR_HANDLE, STD_INPUT_HANDLE,
+};
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+    VK_DOWN, VK_END, VK_ESCAPE, VK_HOME, VK_RETURN, VK_SPACE, VK_TAB, VK_UP,
+};
+
+use crate::console::{screen_buffer_info, std_handle, ModeGuard};
+
+/// Console input modes turned off while a prompt reads keys itself.
+const COOKED_INPUT: u32 = ENABLE_ECHO_INPUT | ENABLE_LINE_INPUT | ENABLE_PROCESSED_INPUT;
+
+/// Enum to represent what is echoed for each character typed at a password prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mask {
+    Char(char), // Echo this character once per typed character
+    Hidden,     // Echo nothing, like `sudo`
+}
+
+/// Struct to hold a secret read from the user. The memory is overwritten with zeros when it is
+/// dropped, and `Debug` never prints it.
+pub struct Password(String);
+
+impl Password {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Password(***)")
+    }
+}
+
+impl Drop for Password {
+    fn drop(&mut self) {
+        zeroize(unsafe { self.0.as_bytes_mut() });
+    }
+}
+
+/// Overwrites a buffer in a way the compiler can't optimize away.
+fn zeroize<T: Copy + Default>(buffer: &mut [T]) {
+    for item in buffer.iter_mut() {
+        unsafe { std::ptr::write_volatile(item, T::default()) };
+    }
+    std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
+}
+
+/// This function reads a password from the console, echoing `*` for every character.
+///
+/// See [`read_password_with`] for details.
+pub fn read_password(prompt: &str) -> io::Result<Password> {
+    read_password_with(prompt, Mask::Char('*'))
+}
+
+/// This function prints `prompt` on the standard error and reads a password without echoing it.
+///
+/// ## Returns:
+/// - `Ok(Password)` once Enter is pressed.
+/// - `Err(io::ErrorKind::Interrupted)` if Ctrl+C is pressed, `Err` if the console fails.
+///
+/// ## Note:
+/// - Echo, line editing and Ctrl+C processing are disabled while reading and restored
+///   afterwards, even on error. Backspace erases the last character, Ctrl+U the whole line,
+///   and pasted text is accepted as if typed.
+/// - When the standard input is not a console (piped or redirected), one line is read from it
+///   as-is, so scripts can still feed the password.
+/// - Every intermediate buffer is zeroed before being released.
+pub fn read_password_with(prompt: &str, mask: Mask) -> io::Result<Password> {
+    let mut err = io::stderr();
+    err.write_all(prompt.as_bytes())?;
+    err.flush()?;
+
+    let input = std_handle(STD_INPUT_HANDLE).map_err(|_| io::ErrorKind::NotFound)?;
+    let Some(_mode) = ModeGuard::change(input, 0, COOKED_INPUT) else {
+        return read_redirected();
+    };
+
+    // Reserve up-front so typing doesn't leave reallocated copies of the secret behind.
+    let mut chars: Vec<char> = Vec::with_capacity(256);
+    let result = read_masked(input, mask, &mut chars);
+    let _ = err.write_all(b"\r\n");
+    let password = result.map(|()| {
+        let mut password = String::with_capacity(chars.len() * 4);
+        password.extend(chars.iter());
+        Password(password)
+    });
+    zeroize(&mut chars);
+    password
+}
+
+fn read_masked(input: HANDLE, mask: Mask, chars: &mut Vec<char>) -> io::Result<()> {
+    let mut units = [0u16; 64];
+    let mut pending_high: Option<u16> = None;
+    let echo = |text: &str| {
+        let mut err = io::stderr();
+        let _ = err.write_all(text.as_bytes());
+        let _ = err.flush();
+    };
+    let result = loop {
+        let mut read = 0;
+        let ok = unsafe {
+            ReadConsoleW(
+                input,
+                units.as_mut_ptr().cast(),
+                units.len() as u32,
+                &mut read,
+                std::ptr::null(),
+            )
+        };
+        if ok == 0 {
+            break Err(io::Error::last_os_error());
+        }
+        let mut done = None;
+        for &unit in &units[..read as usize] {
+            let c = match (pending_high.take(), unit) {
+                (None, 0xD800..=0xDBFF) => {
+                    pending_high = Some(unit);
+                    continue;
+                }
+                (Some(high), 0xDC00..=0xDFFF) => char::decode_utf16([high, unit])
+                    .next()
+                    .and_then(Result::ok)
+                    .unwrap_or(char::REPLACEMENT_CHARACTER),
+                (_, unit) => char::from_u32(unit as u32).unwrap_or(char::REPLACEMENT_CHARACTER),
+            };
+            match c {
+                '\r' | '\n' => {
+                    done = Some(Ok(()));
+                    break;
+                }
+                '\u{3}' => {
+                    done = Some(Err(io::Error::from(io::ErrorKind::Interrupted)));
+                    break;
+                }
+                '\u{8}' | '\u{7f}' => {
+                    if let Some(last) = chars.len().checked_sub(1) {
+                        zeroize(&mut chars[last..]);
+                        chars.pop();
+                        if let Mask::Char(_) = mask {
+                            echo("\u{8} \u{8}");
+                        }
+                    }
+                }
+                '\u{15}' => {
+                    if let Mask::Char(_) = mask {
+                        echo(&"\u{8} \u{8}".repeat(chars.len()));
+                    }
+                    zeroize(chars);
+                    chars.clear();
+                }
+                c if c.is_control() => {}
+                c => {
+                    chars.push(c);
+                    if let Mask::Char(m) = mask {
+                        echo(m.encode_utf8(&mut [0; 4]));
+                    }
+                }
+            }
+        }
+        if let Some(done) = done {
+            break done;
+        }
+    };
+    zeroize(&mut units);
+    result
+}
+
+fn read_redirected() -> io::Result<Password> {
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    let len = line.trim_end_matches(['\r', '\n']).len();
+    // Zero the line ending too, before the string shrinks past it.
+    zeroize(unsafe { &mut line.as_bytes_mut()[len..] });
+    line.truncate(len);
+    Ok(Password(line))
+}
+
+/// This function asks a yes/no question on the standard error, e.g. `Overwrite? [y/N] `.
+///
+/// ## Returns:
+/// - `Ok(true)` for `y`, `Ok(false)` for `n`, and `Ok(default)` when Enter is pressed.
+/// - `Err(io::ErrorKind::Interrupted)` on Ctrl+C or Escape, `Err` if the console fails.
+///
+/// ## Note:
+/// - When the standard input is not a console, one line is read instead: `y`/`yes` and
+///   `n`/`no` in any case, an empty line (or end of input) for the default, and anything else
+///   is an `InvalidInput` error.
+pub fn confirm(prompt: &str, default: bool) -> io::Result<bool> {
+    let mut err = io::stderr();
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    write!(err, "{} {} ", prompt, hint)?;
+    err.flush()?;
+
+    let input = std_handle(STD_INPUT_HANDLE).map_err(|_| io::ErrorKind::NotFound)?;
+    let Some(_mode) = ModeGuard::change(input, 0, COOKED_INPUT) else {
+        let line = read_line()?;
+        return match line.trim().to_lowercase().as_str() {
+            "" => Ok(default),
+            "y" | "yes" => Ok(true),
+            "n" | "no" => Ok(false),
+            _ => Err(io::ErrorKind::InvalidInput.into()),
+        };
+    };
+
+    let answer = loop {
+        match read_event(input)? {
+            Event::Char('y' | 'Y') => break true,
+            Event::Char('n' | 'N') => break false,
+            Event::Enter => break default,
+            Event::Cancel => {
+                err.write_all(b"\r\n")?;
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            _ => {}
+        }
+    };
+    writeln!(err, "{}", if answer { "yes" } else { "no" })?;
+    Ok(answer)
+}
+
+/// This function lets the user pick one of `items` from a list drawn under `prompt`.
+///
+/// ## Returns:
+/// - `Ok(index)` of the chosen item, `default` being highlighted first.
+/// - `Err(io::ErrorKind::InvalidInput)` if `items` is empty or `default` is out of range.
+/// - `Err(io::ErrorKind::Interrupted)` on Ctrl+C or Escape, `Err` if the console fails.
+///
+/// ## Note:
+/// - Up/Down (or Shift+Tab/Tab, or the mouse wheel) move the highlight, Home/End jump to the
+///   ends, Enter picks the highlighted item, and clicking an item picks it directly.
+/// - The list is replaced by the chosen item once done, and should fit in the window.
+/// - When the standard input is not a console, the items are printed numbered and one line is
+///   read: an item number or its exact text, or an empty line for the default.
+pub fn select<T: AsRef<str>>(prompt: &str, items: &[T], default: usize) -> io::Result<usize> {
+    if default >= items.len() {
+        return Err(io::ErrorKind::InvalidInput.into());
+    }
+    let items: Vec<&str> = items.iter().map(AsRef::as_ref).collect();
+    let mut menu = Menu::new(prompt, items, default, None);
+    let Some(input) = menu.begin()? else {
+        let line = read_line()?;
+        return menu.parse_index(line.trim()).unwrap_or(Ok(default));
+    };
+    let result = loop {
+        match read_event(input) {
+            Ok(Event::Up) => menu.move_to(menu.cursor.wrapping_sub(1)),
+            Ok(Event::Down) => menu.move_to(menu.cursor + 1),
+            Ok(Event::Home) => menu.move_to(0),
+            Ok(Event::End) => menu.move_to(menu.items.len() - 1),
+            Ok(Event::Enter) => break Ok(menu.cursor),
+            Ok(Event::Click(row, _)) => {
+                if let Some(index) = menu.item_at(row) {
+                    break Ok(index);
+                }
+            }
+            Ok(Event::Cancel) => break Err(io::ErrorKind::Interrupted.into()),
+            Err(e) => break Err(e),
+            Ok(_) => {}
+        }
+        menu.draw()?;
+    };
+    let summary = result.as_ref().map(|&i| menu.items[i].to_string());
+    menu.end(summary.as_deref().ok())?;
+    result
+}
+
+/// This function lets the user check any number of `items` from a list drawn under `prompt`.
+///
+/// ## Returns:
+/// - `Ok(indices)` of the checked items in list order, starting from `defaults` (missing
+///   entries are unchecked).
+/// - `Err(io::ErrorKind::InvalidInput)` if `items` is empty.
+/// - `Err(io::ErrorKind::Interrupted)` on Ctrl+C or Escape, `Err` if the console fails.
+///
+/// ## Note:
+/// - Keys work as in [`select`], plus Space or a click toggle an item and Enter confirms.
+/// - When the standard input is not a console, the items are printed numbered and one line of
+///   item numbers separated by commas or spaces is read, an empty line keeping the defaults.
+pub fn multi_select<T: AsRef<str>>(
+    prompt: &str,
+    items: &[T],
+    defaults: &[bool],
+) -> io::Result<Vec<usize>> {
+    if items.is_empty() {
+        return Err(io::ErrorKind::InvalidInput.into());
+    }
+    let items: Vec<&str> = items.iter().map(AsRef::as_ref).collect();
+    let checked = (0..items.len())
+        .map(|i| defaults.get(i).copied().unwrap_or(false))
+        .collect();
+    let mut menu = Menu::new(prompt, items, 0, Some(checked));
+    let Some(input) = menu.begin()? else {
+        let line = read_line()?;
+        if !line.trim().is_empty() {
+            let mut checked = vec![false; menu.items.len()];
+            for part in line.split([',', ' ']).filter(|p| !p.trim().is_empty()) {
+                checked[menu.parse_index(part.trim()).unwrap()?] = true;
+            }
+            menu.checked = Some(checked);
+        }
+        return Ok(menu.checked_indices());
+    };
+    let result = loop {
+        match read_event(input) {
+            Ok(Event::Up) => menu.move_to(menu.cursor.wrapping_sub(1)),
+            Ok(Event::Down) => menu.move_to(menu.cursor + 1),
+            Ok(Event::Home) => menu.move_to(0),
+            Ok(Event::End) => menu.move_to(menu.items.len() - 1),
+            Ok(Event::Char(' ')) => menu.toggle(menu.cursor),
+            Ok(Event::Enter) => break Ok(menu.checked_indices()),
+            // The second press of a double-click would undo the first one.
+            Ok(Event::Click(row, false)) => {
+                if let Some(index) = menu.item_at(row) {
+                    menu.move_to(index);
+                    menu.toggle(index);
+                }
+            }
+            Ok(Event::Cancel) => break Err(io::ErrorKind::Interrupted.into()),
+            Err(e) => break Err(e),
+            Ok(_) => {}
+        }
+        menu.draw()?;
+    };
+    let summary = result.as_ref().map(|indices| {
+        let names: Vec<&str> = indices.iter().map(|&i| menu.items[i]).collect();
+        names.join(", ")
+    });
+    menu.end(summary.as_deref().ok())?;
+    result
+}
+
+/// Enum to represent the console input a prompt reacts to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Event {
+    Up,
+    Down,
+    Home,
+    End,
+    Enter,
+    Cancel,           // Ctrl+C or Escape
+    Char(char),       // Any other printable key
+    Click(i16, bool), // Left click on a buffer row, and whether it was a double-click
+    Other,            // Anything the prompts ignore
+}
+
+/// Blocks until the next key press or mouse click, skipping key releases and mouse moves.
+fn read_event(input: HANDLE) -> io::Result<Event> {
+    loop {
+        let mut record: INPUT_RECORD = unsafe { std::mem::zeroed() };
+        let mut read = 0;
+        if unsafe { ReadConsoleInputW(input, &mut record, 1, &mut read) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if read == 0 {
+            continue;
+        }
+        let event = match record.EventType as u32 {
+            KEY_EVENT => {
+                let key = unsafe { record.Event.KeyEvent };
+                if key.bKeyDown == 0 {
+                    continue;
+                }
+                match (key.wVirtualKeyCode, unsafe { key.uChar.UnicodeChar }) {
+                    (VK_UP, _) => Event::Up,
+                    (VK_DOWN, _) => Event::Down,
+                    // Tab moves like it does between widgets, see `widgets::FocusRing`.
+                    (VK_TAB, _) if key.dwControlKeyState & SHIFT_PRESSED != 0 => Event::Up,
+                    (VK_TAB, _) => Event::Down,
+                    (VK_HOME, _) => Event::Home,
+                    (VK_END, _) => Event::End,
+                    (VK_RETURN, _) => Event::Enter,
+                    (VK_ESCAPE, _) | (_, 3) => Event::Cancel,
+                    (VK_SPACE, _) => Event::Char(' '),
+                    (_, 0) => continue,
+                    (_, unit) => char::from_u32(unit as u32)
+                        .filter(|c| !c.is_control())
+                        .map_or(Event::Other, Event::Char),
+                }
+            }
+            MOUSE_EVENT => {
+                let mouse = unsafe { record.Event.MouseEvent };
+                if mouse.dwEventFlags & MOUSE_WHEELED != 0 {
+                    // The high word of the button state is the signed wheel delta.
+                    if (mouse.dwButtonState as i32) >> 16 > 0 {
+                        Event::Up
+                    } else {
+                        Event::Down
+                    }
+                } else if mouse.dwEventFlags & !DOUBLE_CLICK == 0
+                    && mouse.dwButtonState & FROM_LEFT_1ST_BUTTON_PRESSED != 0
+                {
+                    let double = mouse.dwEventFlags & DOUBLE_CLICK != 0;
+                    Event::Click(mouse.dwMousePosition.Y, double)
+                } else {
+                    continue;
+                }
+            }
+            _ => continue,
+        };
+        return Ok(event);
+    }
+}
+
+fn read_line() -> io::Result<String> {
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    Ok(line)
+}
+
+/// Struct to hold the state of a list drawn by [`select`] and [`multi_select`].
+struct Menu<'a> {
+    prompt: &'a str,
+    items: Vec<&'a str>,
+    cursor: usize,              // Highlighted item
+    checked: Option<Vec<bool>>, // Check marks, for `multi_select` only
+    top: Option<i16>,           // Buffer row of the first item, once drawn
+    guards: Vec<ModeGuard>,     // Input and output modes restored when done
+}
+
+impl<'a> Menu<'a> {
+    fn new(
+        prompt: &'a str,
+        items: Vec<&'a str>,
+        cursor: usize,
+        checked: Option<Vec<bool>>,
+    ) -> Self {
+        Menu {
+            prompt,
+            items,
+            cursor,
+            checked,
+            top: None,
+            guards: Vec::new(),
+        }
+    }
+
+    /// Switches the console to raw input with mouse reporting and draws the list, returning
+    /// the input handle, or prints the numbered fallback and returns `None` if the standard
+    /// input is not a console.
+    fn begin(&mut self) -> io::Result<Option<HANDLE>> {
+        let input = std_handle(STD_INPUT_HANDLE).map_err(|_| io::ErrorKind::NotFound)?;
+        // Quick edit mode would turn clicks into text selection instead of mouse events.
+        let Some(mode) = ModeGuard::change(
+            input,
+            ENABLE_MOUSE_INPUT | ENABLE_EXTENDED_FLAGS,
+            COOKED_INPUT | ENABLE_QUICK_EDIT_MODE,
+        ) else {
+            let mut err = io::stderr();
+            writeln!(err, "{}", self.prompt)?;
+            for (i, item) in self.items.iter().enumerate() {
+                let mark = match &self.checked {
+                    Some(checked) if checked[i] => "*",
+                    _ if self.checked.is_none() && i == self.cursor => "*",
+                    _ => " ",
+                };
+                writeln!(err, "{} {:>3}) {}", mark, i + 1, item)?;
+            }
+            write!(err, "> ")?;
+            err.flush()?;
+            return Ok(None);
+        };
+        self.guards.push(mode);
+        self.guards.extend(ModeGuard::virtual_terminal());
+        let mut err = io::stderr();
+        write!(err, "\x1b[?25l{}\r\n", self.prompt)?;
+        self.draw()?;
+        Ok(Some(input))
+    }
+
+    /// Draws the list, over the previous one if any.
+    fn draw(&mut self) -> io::Result<()> {
+        let mut out = String::new();
+        if self.top.is_some() {
+            out.push_str(&format!("\x1b[{}A", self.items.len()));
+        }
+        for (i, item) in self.items.iter().enumerate() {
+            let pointer = if i == self.cursor { '>' } else { ' ' };
+            out.push_str(&format!("\r\x1b[2K{} ", pointer));
+            if let Some(checked) = &self.checked {
+                out.push_str(if checked[i] { "[x] " } else { "[ ] " });
+            }
+            if i == self.cursor {
+                out.push_str(&format!("\x1b[7m{}\x1b[27m\r\n", item));
+            } else {
+                out.push_str(&format!("{}\r\n", item));
+            }
+        }
+        let mut err = io::stderr();
+        err.write_all(out.as_bytes())?;
+        err.flush()?;
+        if self.top.is_none() {
+            // Drawing may have scrolled the buffer, so the rows are only known afterwards.
+            self.top = std_handle(STD_ERROR_HANDLE)
+                .and_then(screen_buffer_info)
+                .ok()
+                .map(|info| info.dwCursorPosition.Y - self.items.len() as i16);
+        }
+        Ok(())
+    }
+
+    /// Erases the list and prints the answer after the prompt, or nothing when cancelled.
+    fn end(&mut self, answer: Option<&str>) -> io::Result<()> {
+        let mut err = io::stderr();
+        write!(err, "\x1b[{}A\r\x1b[J", self.items.len() + 1)?;
+        match answer {
+            Some(answer) => write!(err, "{} {}\r\n", self.prompt, answer)?,
+            None => write!(err, "{}\r\n", self.prompt)?,
+        }
+        write!(err, "\x1b[?25h")?;
+        err.flush()?;
+        self.guards.clear();
+        Ok(())
+    }
+
+    /// Moves the highlight, wrapping around both ends.
+    fn move_to(&mut self, index: usize) {
+        self.cursor = match index {
+            usize::MAX => self.items.len() - 1,
+            i if i >= self.items.len() => 0,
+            i => i,
+        };
+    }
+
+    fn toggle(&mut self, index: usize) {
+        if let Some(checked) = &mut self.checked {
+            checked[index] = !checked[index];
+        }
+    }
+
+    fn item_at(&self, row: i16) -> Option<usize> {
+        let index = usize::try_from(row - self.top?).ok()?;
+        (index < self.items.len()).then_some(index)
+    }
+
+    fn checked_indices(&self) -> Vec<usize> {
+        let checked = self.checked.as_deref().unwrap_or_default();
+        (0..checked.len()).filter(|&i| checked[i]).collect()
+    }
+
+    /// Parses a 1-based item number or an item's exact text, `None` for an empty answer.
+    fn parse_index(&self, answer: &str) -> Option<io::Result<usize>> {
+        if answer.is_empty() {
+            return None;
+        }
+        let index = match answer.parse::<usize>() {
+            Ok(n) => n.checked_sub(1).filter(|&i| i < self.items.len()),
+            Err(_) => self.items.iter().position(|item| *item == answer),
+        };
+        Some(index.ok_or_else(|| io::ErrorKind::InvalidInput.into()))
+    }
+}
@@ -0,0 +1,185 @@
+#[cfg(feature = "vt")]
+use std::time::Duration;
+
+use windows_sys::Win32::Foundation::HANDLE;
+use windows_sys::Win32::System::Console::STD_OUTPUT_HANDLE;
+
+use crate::console::{screen_buffer_info, std_handle};
+use crate::environment::{self, SizeStrategy};
+use crate::{cell_size_from, FontSize, TerminalCells, TerminalError, TerminalSize};
+
+/// Struct to hold what a [`Measure`] found, `None` for what it wasn't asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Measurement {
+    pub cells: TerminalCells,         // Visible window in cells, always measured
+    pub font: Option<FontSize>,       // Cell size in pixels, see `Measure::want_font`
+    pub pixels: Option<TerminalSize>, // Visible window in pixels, see `Measure::want_pixels`
+}
+
+/// Struct to hold what a measurement should report and which strategies it may use.
+///
+/// The free functions of the crate each measure everything they might need; a `Measure` lets
+/// a caller on a hot path (a render loop, a resize handler) ask only for what it uses, and
+/// rule out the strategies that cost a round trip, e.g.
+/// `Measure::new().want_pixels().allow_vt_queries(false).run()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Measure {
+    font: bool,
+    pixels: bool,
+    vt_queries: bool,
+    sources: bool,
+    #[cfg(feature = "vt")]
+    timeout: Duration,
+}
+
+impl Default for Measure {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Measure {
+    /// Creates a measurement of the visible window in cells only, which costs a single
+    /// `GetConsoleScreenBufferInfo`; VT queries and registered sources are allowed.
+    pub fn new() -> Self {
+        Measure {
+            font: false,
+            pixels: false,
+            vt_queries: true,
+            sources: true,
+            #[cfg(feature = "vt")]
+            timeout: crate::vt_query::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Also measures the cell size in pixels.
+    pub fn want_font(mut self) -> Self {
+        self.font = true;
+        self
+    }
+
+    /// Also measures the visible window in pixels.
+    pub fn want_pixels(mut self) -> Self {
+        self.pixels = true;
+        self
+    }
+
+    /// Whether the terminal may be asked with `CSI 14 t` / `CSI 16 t`.
+    ///
+    /// They are only sent where [`environment::size_strategies`] puts them first (SSH,
+    /// multiplexers, pseudo consoles), or when the local detection fails, and each one can
+    /// wait up to the timeout for an answer. Without the `vt` feature they are never sent.
+    pub fn allow_vt_queries(mut self, allow: bool) -> Self {
+        self.vt_queries = allow;
+        self
+    }
+
+    /// Whether the sources registered with
+    /// [`register_source`](crate::source::register_source) are asked for the cell size, which
+    /// may be anything from a table lookup to a round trip.
+    pub fn allow_sources(mut self, allow: bool) -> Self {
+        self.sources = allow;
+        self
+    }
+
+    /// Sets how long a VT query waits for its answer, [`crate::vt_query::DEFAULT_TIMEOUT`]
+    /// by default.
+    #[cfg(feature = "vt")]
+    pub fn vt_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// This function measures the terminal of the standard output.
+    ///
+    /// ## Returns:
+    /// - `Ok(Measurement)` with the cells, and the font and pixel sizes that were asked for.
+    /// - `Err(TerminalError::NotAConsole)` if the standard output is redirected.
+    /// - `Err(TerminalError)` if the screen buffer can't be read, or a size that was asked for
+    ///   can't be measured by any allowed strategy.
+    ///
+    /// ## Note:
+    /// - Pixels come from the terminal when it answers, otherwise from the cells times the
+    ///   cell size, as in [`crate::viewport_size_px`].
+    pub fn run(&self) -> Result<Measurement, TerminalError> {
+        let handle = std_handle(STD_OUTPUT_HANDLE)?;
+        let window = screen_buffer_info(handle)?.srWindow;
+        let cells = TerminalCells {
+            columns: (window.Right - window.Left + 1) as i32,
+            rows: (window.Bottom - window.Top + 1) as i32,
+        };
+        let vt_first = self.vt_first();
+        let mut pixels = None;
+        if self.pixels && vt_first {
+            pixels = self.vt_pixels();
+        }
+        let mut font = None;
+        if self.font || (self.pixels && pixels.is_none()) {
+            let cell = self.cell_size_ordered(handle, vt_first)?;
+            font = self.font.then_some(cell);
+            pixels = pixels.or(self.pixels.then_some(TerminalSize {
+                width: cell.width * cells.columns,
+                height: cell.height * cells.rows,
+            }));
+        }
+        Ok(Measurement {
+            cells,
+            font,
+            pixels,
+        })
+    }
+
+    /// Whether VT queries may be sent in this build.
+    fn vt_allowed(&self) -> bool {
+        cfg!(feature = "vt") && self.vt_queries
+    }
+
+    /// Cell size of the console `handle` with the allowed strategies, in the order of
+    /// [`environment::size_strategies`].
+    pub(crate) fn cell_size(&self, handle: HANDLE) -> Result<FontSize, TerminalError> {
+        self.cell_size_ordered(handle, self.vt_first())
+    }
+
+    /// Whether the terminal is asked before the console.
+    fn vt_first(&self) -> bool {
+        self.vt_allowed() && environment::size_strategies()[0] == SizeStrategy::VtQuery
+    }
+
+    /// Cell size from the terminal first if `vt_first`, from the console otherwise, each
+    /// falling back to the other.
+    fn cell_size_ordered(&self, handle: HANDLE, vt_first: bool) -> Result<FontSize, TerminalError> {
+        if vt_first {
+            if let Some(size) = self.vt_cell_size() {
+                return Ok(size);
+            }
+        }
+        cell_size_from(handle, self.sources).or_else(|error| match vt_first {
+            true => Err(error),
+            false => self.vt_cell_size().ok_or(error),
+        })
+    }
+
+    /// Cell size the terminal answers, if VT queries are allowed.
+    #[cfg(feature = "vt")]
+    fn vt_cell_size(&self) -> Option<FontSize> {
+        self.vt_allowed()
+            .then(|| crate::vt_query::cell_size(self.timeout).ok().flatten())
+            .flatten()
+    }
+
+    #[cfg(not(feature = "vt"))]
+    fn vt_cell_size(&self) -> Option<FontSize> {
+        None
+    }
+
+    /// Visible window in pixels the terminal answers.
+    #[cfg(feature = "vt")]
+    fn vt_pixels(&self) -> Option<TerminalSize> {
+        crate::vt_query::window_size_px(self.timeout).ok().flatten()
+    }
+
+    #[cfg(not(feature = "vt"))]
+    fn vt_pixels(&self) -> Option<TerminalSize> {
+        None
+    }
+}
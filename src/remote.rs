@@ -0,0 +1,272 @@
+use std::io::{self, BufRead, BufReader, Write};
+
+use windows_sys::Win32::System::Console::{WriteConsoleInputW, WriteConsoleW};
+
+use crate::console::{console_input, console_output, resize_window, screen_buffer_info};
+use crate::frame::Frame;
+use crate::input::key_presses;
+use crate::json::Json;
+use crate::pipe::{pipe_path, Pipe};
+use crate::{cell_size_from, last_error_code, TerminalError};
+
+/// JSON-RPC 2.0 error codes.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+/// Struct to hold the JSON-RPC error code and message of a failed call.
+struct CallError(i64, String);
+
+impl From<TerminalError> for CallError {
+    fn from(e: TerminalError) -> Self {
+        CallError(SERVER_ERROR, e.to_string())
+    }
+}
+
+impl From<io::Error> for CallError {
+    fn from(e: io::Error) -> Self {
+        CallError(SERVER_ERROR, e.to_string())
+    }
+}
+
+fn invalid_params(message: &str) -> CallError {
+    CallError(INVALID_PARAMS, message.to_string())
+}
+
+/// This function answers JSON-RPC 2.0 requests read from `input`, one JSON object per line,
+/// writing one response per line to `output`, until `input` ends.
+///
+/// ## Methods:
+/// - `measure`: `{"cell": {"width", "height"}, "window": {"columns", "rows"}}` of the console,
+///   a member being `null` when it can't be measured.
+/// - `snapshot`: `{"width", "height", "lines": [...]}`, the text of the visible window.
+/// - `write` with `{"text"}`: writes the text to the console window, VT sequences included.
+/// - `resize` with `{"columns", "rows"}`: resizes the window and buffer in cells.
+/// - `send_input` with `{"text"}`: types the text into the console input as key presses,
+///   `\n` pressing Enter. Returns the number of key events written.
+///
+/// ## Note:
+/// - The console is reached through `CONOUT$` and `CONIN$`, so the methods work when stdin
+///   and stdout are the transport.
+/// - Notifications (requests without an `id`) are executed without a response.
+pub fn serve(input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle(&line) {
+            writeln!(output, "{}", response)?;
+            output.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// This function serves JSON-RPC over the standard input and output, see [`serve`].
+pub fn serve_stdio() -> io::Result<()> {
+    serve(io::stdin().lock(), io::stdout().lock())
+}
+
+/// This function serves JSON-RPC on the named pipe `\\.\pipe\<name>`, see [`serve`].
+///
+/// ## Note:
+/// - Clients are served one after the other, each until it closes its end; the function
+///   only returns on errors.
+/// - Only the current user can connect, and only from this machine.
+/// - Each instance is created as the first one, so the function fails with access denied
+///   when another server, maybe of another user, holds the name.
+pub fn serve_pipe(name: &str) -> io::Result<()> {
+    let path = pipe_path(name);
+    loop {
+        let pipe = Pipe::create(&path, true)?;
+        pipe.connect()?;
+        // A client dropping mid-request is its problem, not the server's.
+        let _ = serve(BufReader::new(&pipe), &pipe);
+    }
+}
+
+/// Answers one request line, `None` for valid notifications.
+fn handle(line: &str) -> Option<Json> {
+    respond(line, call)
+}
+
+/// Answers one request line with `call` running the method.
+fn respond(line: &str, call: impl FnOnce(&str, &Json) -> Result<Json, CallError>) -> Option<Json> {
+    let Some(request) = Json::parse(line) else {
+        return Some(error(
+            Json::Null,
+            CallError(PARSE_ERROR, "parse error".into()),
+        ));
+    };
+    let id = request.get("id").cloned();
+    // An invalid request is answered even without an id: only valid notifications aren't.
+    let Some(method) = request.get("method").and_then(Json::as_str) else {
+        return Some(error(
+            id.unwrap_or(Json::Null),
+            CallError(INVALID_REQUEST, "expected an object with a method".into()),
+        ));
+    };
+    let result = call(method, request.get("params").unwrap_or(&Json::Null));
+    let id = id?;
+    Some(match result {
+        Ok(value) => Json::object([("jsonrpc", "2.0".into()), ("result", value), ("id", id)]),
+        Err(e) => error(id, e),
+    })
+}
+
+fn error(id: Json, CallError(code, message): CallError) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        (
+            "error",
+            Json::object([("code", code.into()), ("message", message.into())]),
+        ),
+        ("id", id),
+    ])
+}
+
+fn call(method: &str, params: &Json) -> Result<Json, CallError> {
+    let output = || {
+        console_output()
+            .ok_or_else(|| CallError::from(TerminalError::NoStdHandle(last_error_code())))
+    };
+    let text = || {
+        params
+            .get("text")
+            .and_then(Json::as_str)
+            .ok_or_else(|| invalid_params("expected {\"text\": string}"))
+    };
+    match method {
+        "measure" => {
+            let handle = output()?;
+            let cell = cell_size_from(handle, true).ok().map(|size| {
+                Json::object([
+                    ("width", (size.width as i64).into()),
+                    ("height", (size.height as i64).into()),
+                ])
+            });
+            let window = screen_buffer_info(handle).ok().map(|info| {
+                let window = info.srWindow;
+                Json::object([
+                    ("columns", ((window.Right - window.Left + 1) as i64).into()),
+                    ("rows", ((window.Bottom - window.Top + 1) as i64).into()),
+                ])
+            });
+            Ok(Json::object([
+                ("cell", cell.unwrap_or(Json::Null)),
+                ("window", window.unwrap_or(Json::Null)),
+            ]))
+        }
+        "snapshot" => {
+            let frame = Frame::capture_from(output()?)?;
+            let lines = frame
+                .rows()
+                .map(|row| {
+                    let line: String = row
+                        .iter()
+                        .filter(|cell| !cell.wide_tail)
+                        .map(|cell| cell.ch)
+                        .collect();
+                    Json::String(line.trim_end().to_string())
+                })
+                .collect();
+            Ok(Json::object([
+                ("width", (frame.width as i64).into()),
+                ("height", (frame.height as i64).into()),
+                ("lines", Json::Array(lines)),
+            ]))
+        }
+        "write" => {
+            let units: Vec<u16> = text()?.encode_utf16().collect();
+            let handle = output()?;
+            let mut written = 0;
+            let ok = unsafe {
+                WriteConsoleW(
+                    handle,
+                    units.as_ptr(),
+                    units.len() as u32,
+                    &mut written,
+                    std::ptr::null(),
+                )
+            };
+            if ok == 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+            Ok(true.into())
+        }
+        "resize" => {
+            let size = |key| {
+                params
+                    .get(key)
+                    .and_then(Json::as_u64)
+                    .and_then(|n| i16::try_from(n).ok())
+            };
+            let (Some(columns), Some(rows)) = (size("columns"), size("rows")) else {
+                return Err(invalid_params("expected {\"columns\": n, \"rows\": n}"));
+            };
+            resize_window(output()?, columns, rows)?;
+            Ok(true.into())
+        }
+        "send_input" => {
+            let records = key_presses(text()?);
+            let input = console_input()
+                .ok_or_else(|| CallError::from(TerminalError::NoStdHandle(last_error_code())))?;
+            let mut written = 0;
+            let ok = unsafe {
+                WriteConsoleInputW(input, records.as_ptr(), records.len() as u32, &mut written)
+            };
+            if ok == 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+            Ok((written as i64).into())
+        }
+        _ => Err(CallError(
+            METHOD_NOT_FOUND,
+            format!("unknown method {}", method),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers like `handle`, without the console: every method is unknown.
+    fn handle(line: &str) -> Option<Json> {
+        respond(line, |method, _| {
+            Err(CallError(METHOD_NOT_FOUND, format!("no {}", method)))
+        })
+    }
+
+    fn error_code(response: &Json) -> Option<u64> {
+        let code = response.get("error")?.get("code")?;
+        match code {
+            Json::Number(code) => Some(-code as u64),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn answers_invalid_requests_without_an_id() {
+        for line in [r#"{"jsonrpc":"2.0"}"#, "[]", "42", r#"{"method":1}"#] {
+            let response = handle(line).expect(line);
+            assert_eq!(error_code(&response), Some(32600), "{}", line);
+            assert_eq!(response.get("id"), Some(&Json::Null), "{}", line);
+        }
+        let response = handle(r#"{"jsonrpc":"2.0","id":7}"#).unwrap();
+        assert_eq!(response.get("id"), Some(&Json::Number(7.0)));
+    }
+
+    #[test]
+    fn answers_calls_but_not_notifications() {
+        let response = handle(r#"{"jsonrpc":"2.0","method":"nope","id":"a"}"#).unwrap();
+        assert_eq!(error_code(&response), Some(32601));
+        assert_eq!(response.get("id"), Some(&Json::String("a".into())));
+        assert_eq!(handle(r#"{"jsonrpc":"2.0","method":"nope"}"#), None);
+        let response = handle("{").unwrap();
+        assert_eq!(error_code(&response), Some(32700));
+    }
+}
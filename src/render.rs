@@ -0,0 +1,414 @@
+use crate::measure::cells;
+use crate::style::{self, Attributes, Underline};
+use crate::widgets::available_columns;
+
+/// Indentation of code blocks.
+const INDENT: usize = 4;
+
+/// Columns between two table columns.
+const TABLE_GAP: usize = 2;
+
+/// Struct to hold the inline style of a character of rendered Markdown.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Inline {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    code: bool,
+    link: Option<String>, // Target of the link the character belongs to
+}
+
+/// A line of styled characters.
+type Styled = Vec<(char, Inline)>;
+
+/// This function renders a subset of Markdown for the terminal, sized to the width of the
+/// visible window (80 columns if it can't be measured).
+///
+/// See [`markdown_to_width`].
+pub fn markdown(text: &str) -> Vec<String> {
+    markdown_to_width(text, available_columns().unwrap_or(80))
+}
+
+/// This function renders a subset of Markdown for the terminal, for help screens and READMEs.
+///
+/// ## Returns:
+/// - One string per row, never wider than `columns` cells except for code block lines.
+///
+/// ## Note:
+/// - Supported: ATX headers (`#`), paragraphs, `**bold**`, `*italic*`, `` `code` ``,
+///   `[links](url)`, fenced code blocks, `-`/`*`/`+` and numbered lists (nested by
+///   indentation) and pipe tables. Anything else is shown as text.
+/// - Styles are SGR sequences and links OSC 8 hyperlinks, both only when
+///   [`style::colors_enabled`] is `true`; otherwise the output is plain text and links are
+///   followed by their URL in angle brackets.
+/// - Code blocks are indented and not wrapped, so they can be copied as is.
+pub fn markdown_to_width(text: &str, columns: usize) -> Vec<String> {
+    let columns = columns.max(1);
+    let styled = style::colors_enabled();
+    let mut rows: Vec<String> = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let flush = |paragraph: &mut Vec<&str>, rows: &mut Vec<String>| {
+        if !paragraph.is_empty() {
+            let line = inline(&paragraph.join(" "), styled, &Inline::default());
+            rows.extend(wrap(&line, columns).iter().map(|row| paint(row, styled)));
+            paragraph.clear();
+        }
+    };
+    let lines: Vec<&str> = text.lines().collect();
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        let trimmed = line.trim_start();
+        index += 1;
+        if trimmed.starts_with("```") {
+            flush(&mut paragraph, &mut rows);
+            while index < lines.len() && !lines[index].trim_start().starts_with("```") {
+                let code: Styled = lines[index]
+                    .replace('\t', "    ")
+                    .chars()
+                    .map(|c| (c, code_style()))
+                    .collect();
+                rows.push(format!("{}{}", " ".repeat(INDENT), paint(&code, styled)));
+                index += 1;
+            }
+            // The closing fence.
+            index += 1;
+        } else if let Some((level, title)) = header(trimmed) {
+            flush(&mut paragraph, &mut rows);
+            let base = Inline {
+                bold: true,
+                underline: level == 1,
+                ..Inline::default()
+            };
+            let line = inline(title, styled, &base);
+            rows.extend(wrap(&line, columns).iter().map(|row| paint(row, styled)));
+        } else if let Some((marker, item)) = list_item(trimmed) {
+            flush(&mut paragraph, &mut rows);
+            // Nested items keep the indentation of the source.
+            let nesting = line.len() - trimmed.len();
+            let indent = nesting + cells(&marker);
+            let mut text = item.to_string();
+            // Continuation lines of the item, indented past its marker.
+            while index < lines.len()
+                && !lines[index].trim().is_empty()
+                && lines[index].starts_with(' ')
+                && list_item(lines[index].trim_start()).is_none()
+            {
+                text.push(' ');
+                text.push_str(lines[index].trim());
+                index += 1;
+            }
+            let line = inline(&text, styled, &Inline::default());
+            for (row_index, row) in wrap(&line, columns.saturating_sub(indent))
+                .iter()
+                .enumerate()
+            {
+                let prefix = match row_index {
+                    0 => format!("{}{}", " ".repeat(nesting), marker),
+                    _ => " ".repeat(indent),
+                };
+                rows.push(format!("{}{}", prefix, paint(row, styled)));
+            }
+        } else if trimmed.starts_with('|')
+            && lines.get(index).is_some_and(|next| is_table_rule(next))
+        {
+            flush(&mut paragraph, &mut rows);
+            let mut table = vec![table_cells(trimmed)];
+            // The rule under the header.
+            index += 1;
+            while index < lines.len() && lines[index].trim_start().starts_with('|') {
+                table.push(table_cells(lines[index].trim_start()));
+                index += 1;
+            }
+            rows.extend(render_table(&table, columns, styled));
+        } else if trimmed.is_empty() {
+            flush(&mut paragraph, &mut rows);
+            if rows.last().is_some_and(|row| !row.is_empty()) {
+                rows.push(String::new());
+            }
+        } else {
+            paragraph.push(trimmed);
+        }
+    }
+    flush(&mut paragraph, &mut rows);
+    while rows.last().is_some_and(|row| row.is_empty()) {
+        rows.pop();
+    }
+    rows
+}
+
+fn code_style() -> Inline {
+    Inline {
+        code: true,
+        ..Inline::default()
+    }
+}
+
+/// Level and title of an ATX header line.
+fn header(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let title = line[level..].strip_prefix(' ')?;
+    (1..=6)
+        .contains(&level)
+        .then(|| (level, title.trim().trim_end_matches('#').trim_end()))
+}
+
+/// Marker to draw and text of a list item line.
+fn list_item(line: &str) -> Option<(String, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = line.strip_prefix(bullet) {
+            return Some(("• ".to_string(), item));
+        }
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let item = line[digits..].strip_prefix(". ")?;
+    (digits > 0).then(|| (format!("{}. ", &line[..digits]), item))
+}
+
+/// Whether a line is the `|---|:---:|` rule between the header and the body of a table.
+fn is_table_rule(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with('|')
+        && line.contains('-')
+        && line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+fn table_cells(line: &str) -> Vec<&str> {
+    let line = line.trim().trim_start_matches('|');
+    let line = line.strip_suffix('|').unwrap_or(line);
+    line.split('|').map(str::trim).collect()
+}
+
+/// Lays a table out in columns, narrowing the widest columns until it fits.
+fn render_table(table: &[Vec<&str>], columns: usize, styled: bool) -> Vec<String> {
+    let count = table.iter().map(Vec::len).max().unwrap_or(0);
+    let header = Inline {
+        bold: true,
+        ..Inline::default()
+    };
+    let cells_of: Vec<Vec<Styled>> = table
+        .iter()
+        .enumerate()
+        .map(|(row, line)| {
+            let base = if row == 0 {
+                &header
+            } else {
+                &Inline::default()
+            };
+            (0..count)
+                .map(|column| inline(line.get(column).copied().unwrap_or(""), styled, base))
+                .collect()
+        })
+        .collect();
+    let mut widths: Vec<usize> = (0..count)
+        .map(|column| {
+            cells_of
+                .iter()
+                .map(|row| width(&row[column]))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let budget = columns.saturating_sub(TABLE_GAP * count.saturating_sub(1));
+    while widths.iter().sum::<usize>() > budget {
+        let Some(widest) = (0..count).max_by_key(|&column| widths[column]) else {
+            break;
+        };
+        if widths[widest] <= 1 {
+            break;
+        }
+        widths[widest] -= 1;
+    }
+    let gap = " ".repeat(TABLE_GAP);
+    let mut rows = Vec::with_capacity(table.len() + 1);
+    for (index, row) in cells_of.iter().enumerate() {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &column_width)| {
+                let cut = truncate(cell, column_width);
+                let pad = column_width - width(&cut);
+                format!("{}{}", paint(&cut, styled), " ".repeat(pad))
+            })
+            .collect();
+        rows.push(line.join(&gap).trim_end().to_string());
+        if index == 0 {
+            let rule: Vec<String> = widths.iter().map(|&w| "─".repeat(w)).collect();
+            rows.push(rule.join(&gap));
+        }
+    }
+    rows
+}
+
+/// Parses inline Markdown (emphasis, code spans, links and `\` escapes) into styled
+/// characters. Without styles, links keep their URL after the text.
+fn inline(text: &str, styled: bool, base: &Inline) -> Styled {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = Styled::new();
+    let mut style = base.clone();
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        let next = chars.get(index + 1).copied();
+        match c {
+            '\\' if next.is_some_and(|next| next.is_ascii_punctuation()) => {
+                out.push((chars[index + 1], style.clone()));
+                index += 2;
+            }
+            '`' => match chars[index + 1..].iter().position(|&c| c == '`') {
+                Some(len) => {
+                    let code = Inline {
+                        code: true,
+                        ..style.clone()
+                    };
+                    let span = &chars[index + 1..index + 1 + len];
+                    out.extend(span.iter().map(|&c| (c, code.clone())));
+                    index += len + 2;
+                }
+                None => {
+                    out.push((c, style.clone()));
+                    index += 1;
+                }
+            },
+            '*' | '_' if next == Some(c) => {
+                style.bold = !style.bold;
+                index += 2;
+            }
+            '*' | '_' if c == '*' || !is_intraword(&chars, index) => {
+                style.italic = !style.italic;
+                index += 1;
+            }
+            '[' => match link(&chars[index..]) {
+                Some((label, url, len)) => {
+                    let linked = Inline {
+                        link: styled.then(|| url.clone()),
+                        underline: true,
+                        ..style.clone()
+                    };
+                    out.extend(inline(&label, styled, &linked));
+                    if !styled {
+                        out.extend(format!(" <{}>", url).chars().map(|c| (c, style.clone())));
+                    }
+                    index += len;
+                }
+                None => {
+                    out.push((c, style.clone()));
+                    index += 1;
+                }
+            },
+            _ => {
+                out.push((c, style.clone()));
+                index += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Whether the `_` at `index` sits inside a word, like in `snake_case`, and is literal.
+fn is_intraword(chars: &[char], index: usize) -> bool {
+    let alphanumeric = |at: Option<&char>| at.is_some_and(|c| c.is_alphanumeric());
+    index > 0 && alphanumeric(chars.get(index - 1)) && alphanumeric(chars.get(index + 1))
+}
+
+/// Label, URL and length in characters of a `[label](url)` link at the start of `chars`.
+fn link(chars: &[char]) -> Option<(String, String, usize)> {
+    let close = chars.iter().position(|&c| c == ']')?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = close + 2 + chars[close + 2..].iter().position(|&c| c == ')')?;
+    let label = chars[1..close].iter().collect();
+    let url = chars[close + 2..end].iter().collect();
+    Some((label, url, end + 1))
+}
+
+fn width(line: &[(char, Inline)]) -> usize {
+    let text: String = line.iter().map(|(c, _)| *c).collect();
+    cells(&text)
+}
+
+/// The longest prefix of a styled line fitting in `columns` cells.
+fn truncate(line: &[(char, Inline)], columns: usize) -> Styled {
+    let mut out = Styled::new();
+    for item in line {
+        out.push(item.clone());
+        if width(&out) > columns {
+            out.pop();
+            break;
+        }
+    }
+    out
+}
+
+/// Greedy word wrap of styled characters, words wider than the line being cut between cells.
+fn wrap(line: &[(char, Inline)], columns: usize) -> Vec<Styled> {
+    let mut rows: Vec<Styled> = Vec::new();
+    let mut row = Styled::new();
+    let words = line
+        .split(|(c, _)| *c == ' ')
+        .filter(|word| !word.is_empty());
+    for word in words {
+        if !row.is_empty() && width(&row) + 1 + width(word) > columns {
+            rows.push(std::mem::take(&mut row));
+        }
+        if !row.is_empty() {
+            row.push((' ', Inline::default()));
+        }
+        let mut rest = word;
+        loop {
+            let room = columns.saturating_sub(width(&row));
+            if width(rest) <= room {
+                row.extend_from_slice(rest);
+                break;
+            }
+            // A row always takes at least one character, even a wide one on a 1-column line.
+            let cut = truncate(rest, room).len().max(1);
+            row.extend_from_slice(&rest[..cut]);
+            rows.push(std::mem::take(&mut row));
+            rest = &rest[cut..];
+        }
+    }
+    if !row.is_empty() || rows.is_empty() {
+        rows.push(row);
+    }
+    rows
+}
+
+/// Turns styled characters into text, with SGR and OSC 8 sequences when styled.
+fn paint(line: &[(char, Inline)], styled: bool) -> String {
+    let mut out = String::new();
+    let mut current = Inline::default();
+    for (c, inline) in line {
+        if styled && *inline != current {
+            if inline.link != current.link {
+                out.push_str(&format!(
+                    "\x1b]8;;{}\x1b\\",
+                    inline.link.as_deref().unwrap_or("")
+                ));
+            }
+            let attributes = Attributes {
+                bold: inline.bold,
+                italic: inline.italic,
+                underline: match inline.underline {
+                    true => Underline::Single,
+                    false => Underline::None,
+                },
+                ..Attributes::default()
+            };
+            out.push_str(&attributes.sgr(false));
+            if inline.code {
+                out.push_str("\x1b[36m");
+            }
+            current = inline.clone();
+        }
+        out.push(*c);
+    }
+    if styled && current != Inline::default() {
+        if current.link.is_some() {
+            out.push_str("\x1b]8;;\x1b\\");
+        }
+        out.push_str("\x1b[0m");
+    }
+    out
+}
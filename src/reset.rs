@@ -0,0 +1,200 @@
+use std::io::{self, Write};
+use std::process::{Command, ExitStatus};
+
+use windows_sys::Win32::Globalization::GetOEMCP;
+use windows_sys::Win32::System::Console::{
+    FlushConsoleInputBuffer, GetConsoleCP, GetConsoleMode, GetConsoleOutputCP, SetConsoleCP,
+    SetConsoleCursorInfo, SetConsoleMode, SetConsoleOutputCP, SetConsoleTextAttribute,
+    CONSOLE_CURSOR_INFO, ENABLE_AUTO_POSITION, ENABLE_ECHO_INPUT, ENABLE_EXTENDED_FLAGS,
+    ENABLE_INSERT_MODE, ENABLE_LINE_INPUT, ENABLE_MOUSE_INPUT, ENABLE_PROCESSED_INPUT,
+    ENABLE_PROCESSED_OUTPUT, ENABLE_QUICK_EDIT_MODE, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+    ENABLE_WRAP_AT_EOL_OUTPUT, STD_INPUT_HANDLE, STD_OUTPUT_HANDLE,
+};
+
+use crate::console::{screen_buffer_info, std_handle};
+
+/// Input mode of a fresh conhost window.
+const DEFAULT_INPUT_MODE: u32 = ENABLE_PROCESSED_INPUT
+    | ENABLE_LINE_INPUT
+    | ENABLE_ECHO_INPUT
+    | ENABLE_MOUSE_INPUT
+    | ENABLE_INSERT_MODE
+    | ENABLE_QUICK_EDIT_MODE
+    | ENABLE_EXTENDED_FLAGS
+    | ENABLE_AUTO_POSITION;
+
+/// Output mode of a fresh conhost window.
+const DEFAULT_OUTPUT_MODE: u32 = ENABLE_PROCESSED_OUTPUT | ENABLE_WRAP_AT_EOL_OUTPUT;
+
+/// Undoes every VT mode an application may have left on, in the order `reset(1)` would.
+const VT_RESET: &str = concat!(
+    "\x1b[?1049l",                                             // Leave the alternate screen
+    "\x1b[?1000l\x1b[?1002l\x1b[?1003l\x1b[?1005l\x1b[?1006l", // Mouse reporting off
+    "\x1b[?1004l",                                             // Focus events off
+    "\x1b[?2004l",                                             // Bracketed paste off
+    "\x1b[?1l\x1b>",                                           // Normal cursor and keypad keys
+    "\x1b[?6l",                                                // Origin mode off
+    "\x1b[?7h",                                                // Autowrap on
+    "\x1b[r",                                                  // No scroll region
+    "\x1b[0m",                                                 // Default attributes
+    "\x1b[0 q",                                                // Default cursor shape
+    "\x1b[?25h",                                               // Cursor visible
+    "\x1b(B", // ASCII character set, out of DEC line drawing
+);
+
+/// This function restores the console to sane defaults, the Windows analogue of `reset(1)` for
+/// when a program crashed and left it garbled.
+///
+/// ## Note:
+/// - Leaves the alternate screen, turns mouse reporting, bracketed paste and focus events off,
+///   drops any scroll region, and resets attributes, cursor shape and visibility.
+/// - Puts back the input and output modes of a fresh window (line input, echo, quick edit,
+///   VT output), the default gray on black attributes, and the system OEM code page for both
+///   input and output.
+/// - Discards pending input, such as the mouse reports a crashed program left unread.
+/// - Every step is attempted even when an earlier one fails.
+///
+/// ## Returns:
+/// - `Ok(())` if every step succeeded.
+/// - `Err` with the first failure otherwise (e.g. when the standard handles aren't consoles).
+pub fn reset_terminal() -> io::Result<()> {
+    let mut first_error: Option<io::Error> = None;
+    let mut check = |result: io::Result<()>| {
+        if let Err(e) = result {
+            first_error.get_or_insert(e);
+        }
+    };
+    unsafe {
+        match std_handle(STD_OUTPUT_HANDLE) {
+            Ok(output) => {
+                // VT processing first, so the sequences below are interpreted.
+                if SetConsoleMode(
+                    output,
+                    DEFAULT_OUTPUT_MODE | ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+                ) == 0
+                {
+                    check(win32(SetConsoleMode(output, DEFAULT_OUTPUT_MODE)));
+                }
+                let mut out = io::stdout();
+                check(
+                    out.write_all(VT_RESET.as_bytes())
+                        .and_then(|()| out.flush()),
+                );
+                check(win32(SetConsoleTextAttribute(output, 0x07)));
+                let cursor = CONSOLE_CURSOR_INFO {
+                    dwSize: 25,
+                    bVisible: 1,
+                };
+                check(win32(SetConsoleCursorInfo(output, &cursor)));
+            }
+            Err(e) => check(Err(e.into())),
+        }
+        match std_handle(STD_INPUT_HANDLE) {
+            Ok(input) => {
+                check(win32(SetConsoleMode(input, DEFAULT_INPUT_MODE)));
+                check(win32(FlushConsoleInputBuffer(input)));
+            }
+            Err(e) => check(Err(e.into())),
+        }
+        let oem = GetOEMCP();
+        check(win32(SetConsoleOutputCP(oem)));
+        check(win32(SetConsoleCP(oem)));
+    }
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// The error of a failed Win32 call, read right after it.
+fn win32(result: i32) -> io::Result<()> {
+    match result {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+/// This function runs a command on this console and puts the console back as it was before
+/// only if the command exited abnormally, the `reset` for one program run.
+///
+/// ## Returns:
+/// - `Ok(ExitStatus)` of the command, whatever it is.
+/// - `Err(io::Error)` if it can't be started or waited on.
+///
+/// ## Note:
+/// - Abnormal means any status but success: a non-zero exit code, or a crash (the NTSTATUS
+///   of the exception, e.g. `0xC0000005`, is the exit code then).
+/// - The modes, attributes and code pages are the ones captured before the command starts,
+///   not the defaults of [`reset_terminal`]; VT modes are undone and pending input, such as
+///   unread mouse reports, is discarded.
+/// - A successful command is trusted to have cleaned up: its output stays untouched.
+pub fn run_with_reset(command: &mut Command) -> io::Result<ExitStatus> {
+    let saved = ConsoleState::capture();
+    let status = command.status()?;
+    if !status.success() {
+        saved.restore();
+        if let Ok(input) = std_handle(STD_INPUT_HANDLE) {
+            unsafe { FlushConsoleInputBuffer(input) };
+        }
+    }
+    Ok(status)
+}
+
+/// Struct to hold the console state captured when an application starts, to put it back later.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ConsoleState {
+    pub(crate) input_mode: Option<u32>,
+    pub(crate) output_mode: Option<u32>,
+    pub(crate) attributes: Option<u16>,
+    pub(crate) input_cp: u32,
+    pub(crate) output_cp: u32,
+}
+
+impl ConsoleState {
+    pub(crate) fn capture() -> Self {
+        let mode = |handle| {
+            let mut mode = 0;
+            (unsafe { GetConsoleMode(handle, &mut mode) } != 0).then_some(mode)
+        };
+        let input = std_handle(STD_INPUT_HANDLE).ok();
+        let output = std_handle(STD_OUTPUT_HANDLE).ok();
+        ConsoleState {
+            input_mode: input.and_then(mode),
+            output_mode: output.and_then(mode),
+            attributes: output
+                .and_then(|h| screen_buffer_info(h).ok())
+                .map(|info| info.wAttributes),
+            input_cp: unsafe { GetConsoleCP() },
+            output_cp: unsafe { GetConsoleOutputCP() },
+        }
+    }
+
+    /// Undoes the VT modes, then puts the captured modes, attributes and code pages back.
+    pub(crate) fn restore(&self) {
+        unsafe {
+            if let Ok(output) = std_handle(STD_OUTPUT_HANDLE) {
+                let vt = DEFAULT_OUTPUT_MODE | ENABLE_VIRTUAL_TERMINAL_PROCESSING;
+                if SetConsoleMode(output, vt) != 0 {
+                    let mut out = io::stdout();
+                    let _ = out.write_all(VT_RESET.as_bytes());
+                    let _ = out.flush();
+                }
+                if let Some(mode) = self.output_mode {
+                    SetConsoleMode(output, mode);
+                }
+                if let Some(attributes) = self.attributes {
+                    SetConsoleTextAttribute(output, attributes);
+                }
+            }
+            if let (Ok(input), Some(mode)) = (std_handle(STD_INPUT_HANDLE), self.input_mode) {
+                SetConsoleMode(input, mode);
+            }
+            if self.output_cp != 0 {
+                SetConsoleOutputCP(self.output_cp);
+            }
+            if self.input_cp != 0 {
+                SetConsoleCP(self.input_cp);
+            }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Enum to represent the shell integration marks (FTCS, OSC 133) Windows Terminal uses for
+/// command navigation and selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellMark {
+    PromptStart,             // `OSC 133;A`: the prompt is about to be printed
+    CommandStart,            // `OSC 133;B`: the prompt ended, the user types the command
+    CommandExecuted,         // `OSC 133;C`: the command was submitted, its output follows
+    CommandEnd(Option<i32>), // `OSC 133;D[;code]`: the command finished, with its exit code
+}
+
+impl ShellMark {
+    /// The escape sequence of the mark, terminated by ST.
+    pub fn sequence(&self) -> String {
+        match self {
+            ShellMark::PromptStart => "\x1b]133;A\x1b\\".to_string(),
+            ShellMark::CommandStart => "\x1b]133;B\x1b\\".to_string(),
+            ShellMark::CommandExecuted => "\x1b]133;C\x1b\\".to_string(),
+            ShellMark::CommandEnd(Some(code)) => format!("\x1b]133;D;{}\x1b\\", code),
+            ShellMark::CommandEnd(None) => "\x1b]133;D\x1b\\".to_string(),
+        }
+    }
+
+    /// Parses the payload of an OSC sequence, e.g. `133;D;1`.
+    fn parse(payload: &[u8]) -> Option<Self> {
+        let payload = std::str::from_utf8(payload).ok()?;
+        let mut parts = payload.strip_prefix("133;")?.split(';');
+        let mark = match parts.next()? {
+            "A" => ShellMark::PromptStart,
+            "B" => ShellMark::CommandStart,
+            "C" => ShellMark::CommandExecuted,
+            "D" => ShellMark::CommandEnd(parts.next().and_then(|code| code.parse().ok())),
+            _ => return None,
+        };
+        Some(mark)
+    }
+}
+
+fn emit(mark: ShellMark) -> io::Result<()> {
+    let mut out = io::stdout();
+    out.write_all(mark.sequence().as_bytes())?;
+    out.flush()
+}
+
+/// This function marks the start of a prompt on the standard output.
+pub fn mark_prompt_start() -> io::Result<()> {
+    emit(ShellMark::PromptStart)
+}
+
+/// This function marks the end of a prompt, where the command typed by the user starts.
+pub fn mark_command_start() -> io::Result<()> {
+    emit(ShellMark::CommandStart)
+}
+
+/// This function marks that the command was submitted and its output starts.
+pub fn mark_command_executed() -> io::Result<()> {
+    emit(ShellMark::CommandExecuted)
+}
+
+/// This function marks the end of a command with its exit code.
+///
+/// ## Note:
+/// - Windows Terminal flags the command as failed in the scrollbar when `exit_code` isn't 0.
+pub fn mark_command_end(exit_code: i32) -> io::Result<()> {
+    emit(ShellMark::CommandEnd(Some(exit_code)))
+}
+
+/// This function reports the current working directory with the ConEmu `OSC 9;9` sequence, so
+/// "Duplicate tab" and "Split pane" in Windows Terminal open in `path`.
+///
+/// ## Note:
+/// - Relative paths are resolved against the process's current directory, and the `\\?\`
+///   prefix of canonicalized paths is dropped, as the terminal expects a plain `C:\...` path.
+/// - Windows Terminal only follows the reported directory when the profile's starting
+///   directory isn't set explicitly.
+pub fn report_cwd(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = std::path::absolute(path)?;
+    let path = path.to_string_lossy();
+    let path = match path.strip_prefix(r"\\?\UNC\") {
+        Some(share) => format!(r"\\{}", share),
+        None => path.strip_prefix(r"\\?\").unwrap_or(&path).to_string(),
+    };
+    let mut out = io::stdout();
+    write!(out, "\x1b]9;9;\"{}\"\x1b\\", path)?;
+    out.flush()
+}
+
+/// Longest OSC payload kept; shell marks are a few bytes, anything longer isn't one.
+const MAX_PAYLOAD: usize = 32;
+
+/// Enum to represent where the parser is in an escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,    // After ESC
+    Osc,       // Inside `ESC ]`
+    OscEscape, // After ESC inside an OSC, expecting `\`
+}
+
+/// Struct to hold a streaming parser that picks shell marks out of the output of a shell, e.g.
+/// the bytes read from a ConPTY pipe.
+///
+/// Sequences split across reads are recognized; everything else is ignored.
+#[derive(Debug, Clone)]
+pub struct MarkParser {
+    state: State,
+    payload: Vec<u8>,
+    overflow: bool, // The current OSC payload went past `MAX_PAYLOAD`
+}
+
+impl Default for MarkParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MarkParser {
+    pub fn new() -> Self {
+        MarkParser {
+            state: State::Ground,
+            payload: Vec::with_capacity(MAX_PAYLOAD),
+            overflow: false,
+        }
+    }
+
+    /// Feeds the next chunk of output, returning the marks completed in it, in order.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<ShellMark> {
+        let mut marks = Vec::new();
+        for &byte in bytes {
+            self.state = match (self.state, byte) {
+                (State::Ground, 0x1b) => State::Escape,
+                (State::Ground, _) => State::Ground,
+                (State::Escape, b']') => {
+                    self.payload.clear();
+                    self.overflow = false;
+                    State::Osc
+                }
+                (State::Escape, 0x1b) => State::Escape,
+                (State::Escape, _) => State::Ground,
+                (State::Osc, 0x07) => {
+                    marks.extend(self.finish());
+                    State::Ground
+                }
+                (State::Osc, 0x1b) => State::OscEscape,
+                // CAN and SUB abort the sequence.
+                (State::Osc, 0x18 | 0x1a) => State::Ground,
+                (State::Osc, _) => {
+                    if self.payload.len() < MAX_PAYLOAD {
+                        self.payload.push(byte);
+                    } else {
+                        self.overflow = true;
+                    }
+                    State::Osc
+                }
+                (State::OscEscape, b'\\') => {
+                    marks.extend(self.finish());
+                    State::Ground
+                }
+                // Any other ESC ends the OSC unterminated and starts a new sequence.
+                (State::OscEscape, b']') => {
+                    self.payload.clear();
+                    self.overflow = false;
+                    State::Osc
+                }
+                (State::OscEscape, _) => State::Ground,
+            };
+        }
+        marks
+    }
+
+    fn finish(&mut self) -> Option<ShellMark> {
+        if self.overflow {
+            return None;
+        }
+        ShellMark::parse(&self.payload)
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Trait to represent the time source of the timing-dependent parts of the crate: animation
+/// playback, output throttling and, with the `async` feature, simulated event streams.
+///
+/// [`SystemClock`] is the wall clock; [`SimClock`] is a virtual one for tests, where sleeping
+/// returns at once and time only moves when the test (or a sleep) advances it.
+pub trait Clock {
+    /// The current time.
+    fn now(&self) -> Instant;
+
+    /// Waits for `duration`.
+    fn sleep(&self, duration: Duration);
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        (**self).sleep(duration)
+    }
+}
+
+/// Struct to represent the wall clock: `Instant::now` and `thread::sleep`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration)
+    }
+}
+
+/// Struct to hold a virtual clock, so that timing-dependent code runs deterministically and
+/// faster than real time.
+///
+/// Time starts at 0 and only moves forward: through [`SimClock::advance`], and through
+/// [`Clock::sleep`], which advances the clock by the duration instead of waiting. Clones share
+/// the same time, so a test can keep one and hand another to the code it drives:
+///
+/// ```text
+/// let clock = SimClock::new();
+/// let stats = animation.play_with(&mut out, rect, &options, Some(1), clock.clone())?;
+/// assert_eq!(clock.elapsed(), total_delay);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SimClock {
+    start: Instant,                // What `now` returns at time 0
+    elapsed: Arc<Mutex<Duration>>, // Virtual time since the start
+}
+
+impl SimClock {
+    /// Creates a clock at time 0.
+    pub fn new() -> Self {
+        SimClock {
+            start: Instant::now(),
+            elapsed: Arc::new(Mutex::new(Duration::ZERO)),
+        }
+    }
+
+    /// The virtual time since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner()) += duration;
+    }
+
+    /// Moves the clock forward to `elapsed`, if it isn't past it already.
+    pub fn advance_to(&self, elapsed: Duration) {
+        let mut current = self.elapsed.lock().unwrap_or_else(|e| e.into_inner());
+        *current = (*current).max(elapsed);
+    }
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        SimClock::new()
+    }
+}
+
+impl Clock for SimClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sleeping_advances_shared_time() {
+        let clock = SimClock::new();
+        let handed = clock.clone();
+        let start = clock.now();
+        handed.sleep(Duration::from_millis(250));
+        assert_eq!(clock.elapsed(), Duration::from_millis(250));
+        assert_eq!(clock.now() - start, Duration::from_millis(250));
+        clock.advance_to(Duration::from_millis(100));
+        assert_eq!(handed.elapsed(), Duration::from_millis(250));
+        clock.advance_to(Duration::from_secs(1));
+        assert_eq!(handed.elapsed(), Duration::from_secs(1));
+    }
+}
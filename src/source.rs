@@ -0,0 +1,155 @@
+use std::fmt;
+use std::sync::RwLock;
+
+use crate::{FontSize, TerminalCells};
+
+/// Struct to hold what the crate knows about the console when it asks a [`SizeSource`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceContext {
+    pub dpi: u32,       // DPI of the console window, 0 if there is none
+    pub code_page: u32, // Console output code page
+}
+
+/// Struct to hold what a [`SizeSource`] measured: the cell size, and the grid and DPI when it
+/// knows them better than the console does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellMetrics {
+    pub cell: FontSize,                  // Cell size in pixels
+    pub viewport: Option<TerminalCells>, // Visible window in cells, `None` to keep the console's
+    pub dpi: Option<u32>,                // DPI of the window, `None` to keep the console's
+}
+
+impl From<FontSize> for CellMetrics {
+    fn from(cell: FontSize) -> Self {
+        CellMetrics {
+            cell,
+            viewport: None,
+            dpi: None,
+        }
+    }
+}
+
+impl CellMetrics {
+    /// Whether every size is at least one pixel or one cell.
+    fn is_valid(&self) -> bool {
+        let cells = |cells: TerminalCells| cells.columns > 0 && cells.rows > 0;
+        self.cell.width > 0 && self.cell.height > 0 && self.viewport.is_none_or(cells)
+    }
+}
+
+/// Trait for custom measurement sources, for environments the built-in detection can't see
+/// into (embedded terminals, test rigs).
+pub trait SizeSource: Send + Sync {
+    /// Returns the cell metrics, or `None` to let the next source try.
+    fn measure(&self, context: &SourceContext) -> Option<CellMetrics>;
+}
+
+impl<F> SizeSource for F
+where
+    F: Fn(&SourceContext) -> Option<CellMetrics> + Send + Sync,
+{
+    fn measure(&self, context: &SourceContext) -> Option<CellMetrics> {
+        self(context)
+    }
+}
+
+/// Struct to hold registered sources, asked in registration order.
+#[derive(Default)]
+pub(crate) struct Sources(Vec<Box<dyn SizeSource>>);
+
+impl fmt::Debug for Sources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Sources({})", self.0.len())
+    }
+}
+
+impl Sources {
+    pub(crate) const fn new() -> Sources {
+        Sources(Vec::new())
+    }
+
+    pub(crate) fn push(&mut self, source: Box<dyn SizeSource>) {
+        self.0.push(source);
+    }
+
+    /// The first valid answer.
+    pub(crate) fn measure(&self, context: &SourceContext) -> Option<CellMetrics> {
+        self.0
+            .iter()
+            .filter_map(|source| source.measure(context))
+            .find(CellMetrics::is_valid)
+    }
+}
+
+static SOURCES: RwLock<Sources> = RwLock::new(Sources::new());
+
+/// This function registers a measurement source consulted before the built-in detection by
+/// [`get_size_of_the_font`](crate::get_size_of_the_font) and everything built on it.
+///
+/// ## Note:
+/// - Sources are asked in registration order and the first answer wins.
+/// - Answers with a zero or negative size are ignored.
+/// - The free functions only take the cell size; a [`Terminal`](crate::Terminal) also takes
+///   the grid and the DPI. [`Terminal::register_source`](crate::Terminal::register_source)
+///   registers a source for one terminal only.
+pub fn register_source(source: Box<dyn SizeSource>) {
+    SOURCES
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(source);
+}
+
+/// This function removes every registered source, going back to the built-in detection.
+pub fn clear_sources() {
+    SOURCES.write().unwrap_or_else(|e| e.into_inner()).0.clear();
+}
+
+/// Asks the registered sources in order.
+pub(crate) fn measure(context: &SourceContext) -> Option<CellMetrics> {
+    SOURCES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .measure(context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTEXT: SourceContext = SourceContext {
+        dpi: 96,
+        code_page: 65001,
+    };
+
+    fn cell(width: i32, height: i32) -> FontSize {
+        FontSize { width, height }
+    }
+
+    #[test]
+    fn first_valid_answer_wins() {
+        let mut sources = Sources::new();
+        assert_eq!(sources.measure(&CONTEXT), None);
+        sources.push(Box::new(|_: &SourceContext| None));
+        sources.push(Box::new(|_: &SourceContext| Some(cell(0, 16).into())));
+        sources.push(Box::new(|_: &SourceContext| {
+            Some(CellMetrics {
+                viewport: Some(TerminalCells {
+                    columns: 0,
+                    rows: 30,
+                }),
+                ..cell(8, 16).into()
+            })
+        }));
+        sources.push(Box::new(|context: &SourceContext| {
+            Some(CellMetrics {
+                dpi: Some(context.dpi * 2),
+                ..cell(8, 16).into()
+            })
+        }));
+        sources.push(Box::new(|_: &SourceContext| Some(cell(10, 20).into())));
+        let metrics = sources.measure(&CONTEXT).unwrap();
+        assert_eq!(metrics.cell, cell(8, 16));
+        assert_eq!(metrics.dpi, Some(192));
+        assert_eq!(metrics.viewport, None);
+    }
+}
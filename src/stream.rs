@@ -0,0 +1,875 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+#[cfg(feature = "gamepad")]
+use std::time::Instant;
+
+use windows_sys::Win32::Foundation::{HANDLE, WAIT_OBJECT_0, WAIT_TIMEOUT};
+use windows_sys::Win32::System::Console::{
+    ReadConsoleInputW, ENABLE_EXTENDED_FLAGS, ENABLE_MOUSE_INPUT, ENABLE_QUICK_EDIT_MODE,
+    ENABLE_WINDOW_INPUT, FOCUS_EVENT, INPUT_RECORD, KEY_EVENT, LEFT_ALT_PRESSED, LEFT_CTRL_PRESSED,
+    MOUSE_EVENT, RIGHT_ALT_PRESSED, RIGHT_CTRL_PRESSED, SHIFT_PRESSED, WINDOW_BUFFER_SIZE_EVENT,
+};
+use windows_sys::Win32::System::Threading::WaitForSingleObject;
+
+use crate::console::{console_input, console_output, ModeGuard};
+use crate::events::{self, ResizeEvent};
+#[cfg(feature = "gamepad")]
+use crate::gamepad::{GamepadState, Gamepads};
+use crate::input::Modifiers;
+use crate::sim::SimClock;
+
+/// How long the reader thread waits for input before looking whether the stream was dropped.
+#[cfg(not(feature = "gamepad"))]
+const READ_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long the reader thread waits for input before looking whether the stream was dropped,
+/// and how often it polls the controllers: XInput has no events to wait on.
+#[cfg(feature = "gamepad")]
+const READ_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Records read at once.
+const READ_RECORDS: usize = 64;
+
+/// Struct to hold a key press or release read from the console input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub down: bool,           // Pressed rather than released
+    pub key: u16,             // Virtual key code, e.g. `VK_LEFT`
+    pub text: Option<char>,   // Character typed, `None` for keys without one
+    pub modifiers: Modifiers, // Modifier keys held, left and right alike
+    pub repeat: u16,          // Times the key repeated while held, 1 for a single press
+}
+
+/// Struct to hold a mouse action read from the console input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    pub column: i16,          // Buffer column under the pointer
+    pub row: i16,             // Buffer row under the pointer
+    pub buttons: u32,         // Buttons held (`FROM_LEFT_1ST_BUTTON_PRESSED`, ...)
+    pub flags: u32,           // `MOUSE_MOVED`, `DOUBLE_CLICK`, `MOUSE_WHEELED`, 0 for a click
+    pub modifiers: Modifiers, // Modifier keys held
+}
+
+/// Struct to hold a change of an XInput controller, polled by an [`EventStream`].
+#[cfg(feature = "gamepad")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GamepadEvent {
+    pub index: u32,                  // Controller slot, 0 to `gamepad::MAX_GAMEPADS` - 1
+    pub state: Option<GamepadState>, // New state, `None` when the controller was disconnected
+}
+
+/// Enum to represent an event of an [`EventStream`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleEvent {
+    Key(KeyEvent),       // A key went down or up
+    Mouse(MouseEvent),   // The mouse moved, clicked or scrolled over the buffer
+    Resize(ResizeEvent), // The visible window changed size
+    Focus(bool),         // The console window gained (`true`) or lost the keyboard focus
+    #[cfg(feature = "gamepad")]
+    Gamepad(GamepadEvent), // A controller connected, disconnected or changed state
+}
+
+/// Struct to hold the events read but not yet polled, and the task waiting for them.
+#[derive(Debug, Default)]
+struct Queue {
+    events: VecDeque<ConsoleEvent>,
+    waker: Option<Waker>, // Task of the last pending poll
+    closed: bool,         // Whether no event will come anymore
+}
+
+/// Struct to hold the queue shared by the reader thread and the stream.
+#[derive(Debug, Default, Clone)]
+struct Channel(Arc<Mutex<Queue>>);
+
+impl Channel {
+    fn send(&self, events: impl IntoIterator<Item = ConsoleEvent>) {
+        let mut queue = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let before = queue.events.len();
+        queue.events.extend(events);
+        if queue.events.len() > before {
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    fn close(&self) {
+        let mut queue = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        queue.closed = true;
+        if let Some(waker) = queue.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).closed
+    }
+
+    fn poll_next(&self, cx: &mut Context<'_>) -> Poll<Option<ConsoleEvent>> {
+        let mut queue = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(event) = queue.events.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        if queue.closed {
+            return Poll::Ready(None);
+        }
+        queue.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Struct to hold the events of a simulated [`EventStream`], each due at a time of its clock.
+#[derive(Debug)]
+struct Script {
+    clock: SimClock,
+    events: VecDeque<(Duration, ConsoleEvent)>, // Sorted by the time they are due at
+}
+
+impl Script {
+    fn next(&mut self) -> Option<ConsoleEvent> {
+        let (at, event) = self.events.pop_front()?;
+        self.clock.advance_to(at);
+        Some(event)
+    }
+}
+
+/// Struct to hold the console input events read by a background thread, for async programs,
+/// stopped when dropped.
+///
+/// It works with any executor, without depending on one:
+///
+/// ```text
+/// let mut events = EventStream::open()?;
+/// while let Some(event) = events.next().await {
+///     ...
+/// }
+/// ```
+///
+/// It is also a `futures_core::Stream`, for `StreamExt` combinators and `select!` over
+/// streams; the inherent [`EventStream::next`] needs no extension trait in scope.
+#[derive(Debug)]
+pub struct EventStream {
+    channel: Channel,
+    thread: Option<JoinHandle<()>>,
+    script: Option<Script>, // Events of a simulated stream, read instead of the channel
+}
+
+impl EventStream {
+    /// This function starts reading the console input from a background thread.
+    ///
+    /// ## Returns:
+    /// - `Ok(EventStream)` once the thread runs.
+    /// - `Err(io::Error)` without a console input or output, or if its mode can't be changed.
+    ///
+    /// ## Note:
+    /// - Window and mouse input are enabled and quick edit disabled, so the host queues resize
+    ///   and mouse records rather than selecting text; the previous mode is restored when the
+    ///   stream is dropped.
+    /// - The stream reads every record: nothing else in the program should read the console
+    ///   input meanwhile, [`events::ResizeWatcher`] included. Resizes carry the visible
+    ///   window measured when the record is read, as the watcher reports them.
+    /// - The stream ends (`None`) if the console input can't be read anymore.
+    /// - With the `gamepad` feature, the thread also polls the XInput controllers every
+    ///   16 ms, and reports the ones already connected first.
+    pub fn open() -> io::Result<EventStream> {
+        let input = console_input().ok_or_else(io::Error::last_os_error)?;
+        let output = console_output().ok_or_else(io::Error::last_os_error)?;
+        let mode = ModeGuard::change(
+            input,
+            ENABLE_WINDOW_INPUT | ENABLE_MOUSE_INPUT | ENABLE_EXTENDED_FLAGS,
+            ENABLE_QUICK_EDIT_MODE,
+        )
+        .ok_or_else(io::Error::last_os_error)?;
+        let mut last = events::measure(output);
+        // Console handles are process-wide, they cross into the thread as integers.
+        let (input, output) = (input as usize, output as usize);
+        let channel = Channel::default();
+        let thread = {
+            let channel = channel.clone();
+            thread::Builder::new()
+                .name("win-term event stream".to_string())
+                .spawn(move || {
+                    let _mode = mode;
+                    let (input, output) = (input as HANDLE, output as HANDLE);
+                    let mut records: [INPUT_RECORD; READ_RECORDS] = unsafe { std::mem::zeroed() };
+                    let mut surrogate = None;
+                    #[cfg(feature = "gamepad")]
+                    let (mut gamepads, mut polled) = (Gamepads::new(), None::<Instant>);
+                    while !channel.is_closed() {
+                        let timeout = READ_INTERVAL.as_millis() as u32;
+                        let ready = match unsafe { WaitForSingleObject(input, timeout) } {
+                            WAIT_OBJECT_0 => true,
+                            WAIT_TIMEOUT => false,
+                            _ => break,
+                        };
+                        #[cfg(feature = "gamepad")]
+                        if polled.is_none_or(|at| at.elapsed() >= READ_INTERVAL) {
+                            polled = Some(Instant::now());
+                            channel.send(gamepads.poll().into_iter().map(|(index, state)| {
+                                ConsoleEvent::Gamepad(GamepadEvent { index, state })
+                            }));
+                        }
+                        if !ready {
+                            continue;
+                        }
+                        let mut count = 0;
+                        let read = unsafe {
+                            ReadConsoleInputW(
+                                input,
+                                records.as_mut_ptr(),
+                                READ_RECORDS as u32,
+                                &mut count,
+                            )
+                        };
+                        if read == 0 {
+                            break;
+                        }
+                        let mut batch = Vec::new();
+                        for record in &records[..count as usize] {
+                            if record.EventType == WINDOW_BUFFER_SIZE_EVENT as u16 {
+                                let current = events::measure(output);
+                                if let Some(size) = current.filter(|_| current != last) {
+                                    batch.push(ConsoleEvent::Resize(size));
+                                }
+                                last = current;
+                            } else if let Some(event) = translate(record, &mut surrogate) {
+                                batch.push(event);
+                            }
+                        }
+                        channel.send(batch);
+                    }
+                    channel.close();
+                })?
+        };
+        Ok(EventStream {
+            channel,
+            thread: Some(thread),
+            script: None,
+        })
+    }
+
+    /// This function creates a stream of scripted events rather than the console input, so
+    /// that an event loop runs deterministically in tests, faster than real time.
+    ///
+    /// ## Note:
+    /// - Each event comes with the time it happens at, since `clock` started. Events are
+    ///   sorted by it; events at the same time keep the order they are given in.
+    /// - An event polled before its time isn't waited for: the clock advances to it, as if
+    ///   the program had been idle until then. Events already due come at once, so a loop
+    ///   that sleeps on the same clock (a frame limiter) gets what happened meanwhile.
+    /// - The stream ends (`None`) after the last event.
+    pub fn simulated(
+        clock: &SimClock,
+        events: impl IntoIterator<Item = (Duration, ConsoleEvent)>,
+    ) -> EventStream {
+        let mut events: Vec<_> = events.into_iter().collect();
+        events.sort_by_key(|&(at, _)| at);
+        EventStream {
+            channel: Channel::default(),
+            thread: None,
+            script: Some(Script {
+                clock: clock.clone(),
+                events: events.into(),
+            }),
+        }
+    }
+
+    /// This function polls for the next event, as `futures::Stream::poll_next` does.
+    ///
+    /// ## Returns:
+    /// - `Poll::Ready(Some(event))` with the oldest event not polled yet.
+    /// - `Poll::Ready(None)` once the console input can't be read anymore.
+    /// - `Poll::Pending` otherwise, waking the task of `cx` on the next event.
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<ConsoleEvent>> {
+        if let Some(script) = &mut self.script {
+            return Poll::Ready(script.next());
+        }
+        self.channel.poll_next(cx)
+    }
+
+    /// This function waits for the next event, see [`EventStream::poll_next`].
+    #[allow(clippy::should_implement_trait)] // A future, like `StreamExt::next`.
+    pub fn next(&mut self) -> Next<'_> {
+        Next { stream: self }
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        self.channel.close();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl futures_core::Stream for EventStream {
+    type Item = ConsoleEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<ConsoleEvent>> {
+        EventStream::poll_next(self.get_mut(), cx)
+    }
+}
+
+/// Struct to hold the future returned by [`EventStream::next`].
+#[derive(Debug)]
+pub struct Next<'a> {
+    stream: &'a mut EventStream,
+}
+
+impl Future for Next<'_> {
+    type Output = Option<ConsoleEvent>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.stream.poll_next(cx)
+    }
+}
+
+/// Struct to hold the output a [`ConsoleWriter`] shares with its writing thread.
+#[derive(Debug)]
+struct Outbox {
+    buffer: Vec<u8>,          // Bytes accepted, not yet taken by the thread
+    writing: usize,           // Bytes the thread is writing
+    high_water: usize,        // Bytes stored (buffered and being written) past which writes wait
+    waker: Option<Waker>,     // Task of the last pending write or flush
+    error: Option<io::Error>, // Why the sink failed, reported by the next write or flush
+    closed: bool,             // Whether the thread must stop once the buffer is written
+}
+
+impl Outbox {
+    fn stored(&self) -> usize {
+        self.buffer.len() + self.writing
+    }
+}
+
+/// Struct to hold a writer to the console that never blocks its caller, for async programs:
+/// a background thread writes what [`ConsoleWriter::poll_write`] buffers, stopped when
+/// dropped.
+///
+/// The buffer is bounded by a high-water mark. Past it, writes are `Pending` until the thread
+/// has written a chunk to the console, so an app rendering faster than a slow host (a console
+/// over RDP) waits for it instead of queuing frames without end. Like [`EventStream`], it
+/// works with any executor:
+///
+/// ```text
+/// let mut out = ConsoleWriter::stdout(64 * 1024)?;
+/// out.write_all(frame.as_bytes()).await?;
+/// out.flush().await?;
+/// ```
+#[derive(Debug)]
+pub struct ConsoleWriter {
+    shared: Arc<(Mutex<Outbox>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ConsoleWriter {
+    /// This function starts a writer to the standard output, see [`ConsoleWriter::new`].
+    pub fn stdout(high_water: usize) -> io::Result<ConsoleWriter> {
+        ConsoleWriter::new(io::stdout(), high_water)
+    }
+
+    /// This function starts writing to `sink` from a background thread, storing up to
+    /// `high_water` bytes (at least 1) not written yet.
+    ///
+    /// ## Returns:
+    /// - `Ok(ConsoleWriter)` once the thread runs.
+    /// - `Err(io::Error)` if the thread can't be started.
+    ///
+    /// ## Note:
+    /// - The thread writes everything buffered at once, then flushes `sink`; a failure is
+    ///   reported by the next write or flush, and drops what is still buffered.
+    /// - Dropping the writer waits for the thread to write what is buffered, as dropping a
+    ///   `BufWriter` does.
+    pub fn new(sink: impl io::Write + Send + 'static, high_water: usize) -> io::Result<Self> {
+        let high_water = high_water.max(1);
+        let shared = Arc::new((
+            Mutex::new(Outbox {
+                buffer: Vec::with_capacity(high_water),
+                writing: 0,
+                high_water,
+                waker: None,
+                error: None,
+                closed: false,
+            }),
+            Condvar::new(),
+        ));
+        let thread = {
+            let shared = Arc::clone(&shared);
+            thread::Builder::new()
+                .name("win-term console writer".to_string())
+                .spawn(move || drain(&shared, sink))?
+        };
+        Ok(ConsoleWriter {
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    /// This function buffers bytes of `buf` for the thread to write, as
+    /// `AsyncWrite::poll_write` does.
+    ///
+    /// ## Returns:
+    /// - `Poll::Ready(Ok(n))` with the `n` bytes buffered, as many as fit under the mark.
+    /// - `Poll::Ready(Err(io::Error))` if writing to the console failed.
+    /// - `Poll::Pending` while the writer stores `high_water` bytes, waking the task of `cx`
+    ///   once the thread wrote some of them.
+    pub fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let (lock, wake) = &*self.shared;
+        let mut outbox = lock.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(e) = outbox.error.take() {
+            return Poll::Ready(Err(e));
+        }
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        let room = outbox.high_water.saturating_sub(outbox.stored());
+        if room == 0 {
+            outbox.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let n = room.min(buf.len());
+        outbox.buffer.extend_from_slice(&buf[..n]);
+        wake.notify_all();
+        Poll::Ready(Ok(n))
+    }
+
+    /// This function waits for the thread to write and flush everything buffered, as
+    /// `AsyncWrite::poll_flush` does.
+    ///
+    /// ## Returns:
+    /// - `Poll::Ready(Ok(()))` once nothing is left to write.
+    /// - `Poll::Ready(Err(io::Error))` if writing to the console failed.
+    /// - `Poll::Pending` otherwise, waking the task of `cx` when the thread wrote a chunk.
+    pub fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut outbox = self.shared.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(e) = outbox.error.take() {
+            return Poll::Ready(Err(e));
+        }
+        if outbox.stored() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        outbox.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    /// This function writes all of `buf`, waiting whenever the buffer is full, see
+    /// [`ConsoleWriter::poll_write`].
+    pub fn write_all<'a>(&'a mut self, buf: &'a [u8]) -> WriteAll<'a> {
+        WriteAll { writer: self, buf }
+    }
+
+    /// This function waits for everything buffered to be written, see
+    /// [`ConsoleWriter::poll_flush`].
+    pub fn flush(&mut self) -> Flush<'_> {
+        Flush { writer: self }
+    }
+}
+
+/// Writes what the writer buffers to `sink`, a chunk at a time, until it is dropped.
+fn drain(shared: &(Mutex<Outbox>, Condvar), mut sink: impl io::Write) {
+    let (lock, wake) = shared;
+    let mut chunk = Vec::new();
+    loop {
+        let mut outbox = lock.lock().unwrap_or_else(|e| e.into_inner());
+        while outbox.buffer.is_empty() && !outbox.closed {
+            outbox = wake.wait(outbox).unwrap_or_else(|e| e.into_inner());
+        }
+        if outbox.buffer.is_empty() {
+            return;
+        }
+        // The two buffers trade places, so neither is allocated again.
+        std::mem::swap(&mut outbox.buffer, &mut chunk);
+        outbox.writing = chunk.len();
+        drop(outbox);
+        let result = sink.write_all(&chunk).and_then(|()| sink.flush());
+        chunk.clear();
+        let mut outbox = lock.lock().unwrap_or_else(|e| e.into_inner());
+        outbox.writing = 0;
+        if let Err(e) = result {
+            outbox.buffer.clear();
+            outbox.error = Some(e);
+        }
+        if let Some(waker) = outbox.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for ConsoleWriter {
+    fn drop(&mut self) {
+        let (lock, wake) = &*self.shared;
+        lock.lock().unwrap_or_else(|e| e.into_inner()).closed = true;
+        wake.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Struct to hold the future returned by [`ConsoleWriter::write_all`].
+#[derive(Debug)]
+pub struct WriteAll<'a> {
+    writer: &'a mut ConsoleWriter,
+    buf: &'a [u8],
+}
+
+impl Future for WriteAll<'_> {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        while !self.buf.is_empty() {
+            let this = &mut *self;
+            match this.writer.poll_write(cx, this.buf) {
+                Poll::Ready(Ok(n)) => this.buf = &this.buf[n..],
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Struct to hold the future returned by [`ConsoleWriter::flush`].
+#[derive(Debug)]
+pub struct Flush<'a> {
+    writer: &'a mut ConsoleWriter,
+}
+
+impl Future for Flush<'_> {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.writer.poll_flush(cx)
+    }
+}
+
+/// Modifier keys held in the `dwControlKeyState` of a record.
+fn modifiers(state: u32) -> Modifiers {
+    Modifiers {
+        ctrl: state & (LEFT_CTRL_PRESSED | RIGHT_CTRL_PRESSED) != 0,
+        alt: state & (LEFT_ALT_PRESSED | RIGHT_ALT_PRESSED) != 0,
+        shift: state & SHIFT_PRESSED != 0,
+    }
+}
+
+/// The event of a key, mouse or focus record. Characters beyond the BMP come as two key
+/// records, one per UTF-16 unit: the first is kept in `surrogate` and gives no event.
+fn translate(record: &INPUT_RECORD, surrogate: &mut Option<u16>) -> Option<ConsoleEvent> {
+    match record.EventType as u32 {
+        KEY_EVENT => {
+            let key = unsafe { record.Event.KeyEvent };
+            let unit = unsafe { key.uChar.UnicodeChar };
+            let high = surrogate.take();
+            let text = match unit {
+                0 => None,
+                0xD800..=0xDBFF => {
+                    *surrogate = Some(unit);
+                    return None;
+                }
+                0xDC00..=0xDFFF => char::decode_utf16([high?, unit]).next()?.ok(),
+                _ => char::from_u32(unit as u32),
+            };
+            Some(ConsoleEvent::Key(KeyEvent {
+                down: key.bKeyDown != 0,
+                key: key.wVirtualKeyCode,
+                text,
+                modifiers: modifiers(key.dwControlKeyState),
+                repeat: key.wRepeatCount,
+            }))
+        }
+        MOUSE_EVENT => {
+            let mouse = unsafe { record.Event.MouseEvent };
+            Some(ConsoleEvent::Mouse(MouseEvent {
+                column: mouse.dwMousePosition.X,
+                row: mouse.dwMousePosition.Y,
+                buttons: mouse.dwButtonState,
+                flags: mouse.dwEventFlags,
+                modifiers: modifiers(mouse.dwControlKeyState),
+            }))
+        }
+        FOCUS_EVENT => Some(ConsoleEvent::Focus(unsafe {
+            record.Event.FocusEvent.bSetFocus != 0
+        })),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Wake;
+
+    #[derive(Default)]
+    struct Wakes(AtomicUsize);
+
+    impl Wake for Wakes {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn channel_wakes_the_pending_task() {
+        let wakes = Arc::new(Wakes::default());
+        let waker = Waker::from(Arc::clone(&wakes));
+        let mut cx = Context::from_waker(&waker);
+        let channel = Channel::default();
+        assert_eq!(channel.poll_next(&mut cx), Poll::Pending);
+        channel.send([]);
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 0);
+        channel.send([ConsoleEvent::Focus(true), ConsoleEvent::Focus(false)]);
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            channel.poll_next(&mut cx),
+            Poll::Ready(Some(ConsoleEvent::Focus(true)))
+        );
+        assert_eq!(
+            channel.poll_next(&mut cx),
+            Poll::Ready(Some(ConsoleEvent::Focus(false)))
+        );
+        assert_eq!(channel.poll_next(&mut cx), Poll::Pending);
+        channel.close();
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 2);
+        assert_eq!(channel.poll_next(&mut cx), Poll::Ready(None));
+    }
+
+    #[test]
+    fn simulated_events_advance_the_clock() {
+        let waker = Waker::from(Arc::new(Wakes::default()));
+        let mut cx = Context::from_waker(&waker);
+        let clock = SimClock::new();
+        let ms = Duration::from_millis;
+        let mut events = EventStream::simulated(
+            &clock,
+            [
+                (ms(500), ConsoleEvent::Focus(false)),
+                (ms(0), ConsoleEvent::Focus(true)),
+                (ms(200), ConsoleEvent::Focus(false)),
+                (ms(200), ConsoleEvent::Focus(true)),
+            ],
+        );
+        let mut next = || match events.poll_next(&mut cx) {
+            Poll::Ready(event) => event.map(|event| (event, clock.elapsed())),
+            Poll::Pending => panic!("a simulated stream never waits"),
+        };
+        assert_eq!(next(), Some((ConsoleEvent::Focus(true), ms(0))));
+        assert_eq!(next(), Some((ConsoleEvent::Focus(false), ms(200))));
+        clock.advance(ms(700));
+        assert_eq!(next(), Some((ConsoleEvent::Focus(true), ms(900))));
+        assert_eq!(next(), Some((ConsoleEvent::Focus(false), ms(900))));
+        assert_eq!(next(), None);
+    }
+
+    #[cfg(feature = "gamepad")]
+    #[test]
+    fn simulates_gamepads_on_the_clock() {
+        use crate::gamepad::Button;
+        use windows_sys::Win32::UI::Input::XboxController::XINPUT_GAMEPAD_A;
+
+        let waker = Waker::from(Arc::new(Wakes::default()));
+        let mut cx = Context::from_waker(&waker);
+        let clock = SimClock::new();
+        let pressed = GamepadState {
+            buttons: XINPUT_GAMEPAD_A,
+            ..GamepadState::default()
+        };
+        let pad = |state| ConsoleEvent::Gamepad(GamepadEvent { index: 0, state });
+        let mut events = EventStream::simulated(
+            &clock,
+            [
+                (READ_INTERVAL * 2, pad(None)),
+                (Duration::ZERO, pad(Some(GamepadState::default()))),
+                (READ_INTERVAL, pad(Some(pressed))),
+                (READ_INTERVAL, ConsoleEvent::Focus(false)),
+            ],
+        );
+        let mut next = || match events.poll_next(&mut cx) {
+            Poll::Ready(event) => event.map(|event| (event, clock.elapsed())),
+            Poll::Pending => panic!("a simulated stream never waits"),
+        };
+        let connected = next().unwrap();
+        assert_eq!(
+            connected,
+            (pad(Some(GamepadState::default())), Duration::ZERO)
+        );
+        let (event, at) = next().unwrap();
+        assert_eq!(at, READ_INTERVAL);
+        match event {
+            ConsoleEvent::Gamepad(GamepadEvent {
+                state: Some(state), ..
+            }) => assert!(state.pressed(Button::A)),
+            event => panic!("{:?}", event),
+        }
+        assert_eq!(next(), Some((ConsoleEvent::Focus(false), READ_INTERVAL)));
+        assert_eq!(next(), Some((pad(None), READ_INTERVAL * 2)));
+        assert_eq!(next(), None);
+    }
+
+    #[test]
+    fn streams_like_the_inherent_poll() {
+        use futures_core::Stream;
+
+        let waker = Waker::from(Arc::new(Wakes::default()));
+        let mut cx = Context::from_waker(&waker);
+        let clock = SimClock::new();
+        let script = [(Duration::ZERO, ConsoleEvent::Focus(true))];
+        let mut events = EventStream::simulated(&clock, script);
+        assert_eq!(
+            Stream::poll_next(Pin::new(&mut events), &mut cx),
+            Poll::Ready(Some(ConsoleEvent::Focus(true)))
+        );
+        assert_eq!(Stream::size_hint(&events), (0, None));
+        assert_eq!(events.poll_next(&mut cx), Poll::Ready(None));
+    }
+
+    /// Sink that blocks every write until the test opens its gate, as a stalled console
+    /// host does, keeping what it was given.
+    #[derive(Clone, Default)]
+    struct Stalled(Arc<(Mutex<Gate>, Condvar)>);
+
+    #[derive(Default)]
+    struct Gate {
+        open: bool,
+        written: Vec<u8>,
+    }
+
+    impl Stalled {
+        fn open(&self) {
+            self.0 .0.lock().unwrap().open = true;
+            self.0 .1.notify_all();
+        }
+
+        fn written(&self) -> Vec<u8> {
+            self.0 .0.lock().unwrap().written.clone()
+        }
+    }
+
+    impl io::Write for Stalled {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let (lock, wake) = &*self.0;
+            let mut gate = lock.lock().unwrap();
+            while !gate.open {
+                gate = wake.wait(gate).unwrap();
+            }
+            gate.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// The bytes a write accepted, `None` if it is pending.
+    fn accepted(poll: Poll<io::Result<usize>>) -> Option<usize> {
+        match poll {
+            Poll::Ready(result) => Some(result.unwrap()),
+            Poll::Pending => None,
+        }
+    }
+
+    /// Polls until `poll` is ready, the writer thread being the one to make progress.
+    fn ready<T>(mut poll: impl FnMut() -> Poll<T>) -> T {
+        for _ in 0..1000 {
+            if let Poll::Ready(value) = poll() {
+                return value;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        panic!("never ready");
+    }
+
+    #[test]
+    fn writes_wait_above_the_high_water_mark() {
+        let wakes = Arc::new(Wakes::default());
+        let waker = Waker::from(Arc::clone(&wakes));
+        let mut cx = Context::from_waker(&waker);
+        let sink = Stalled::default();
+        let mut writer = ConsoleWriter::new(sink.clone(), 8).unwrap();
+        assert_eq!(accepted(writer.poll_write(&mut cx, b"01234")), Some(5));
+        assert_eq!(accepted(writer.poll_write(&mut cx, b"56789ab")), Some(3));
+        assert_eq!(accepted(writer.poll_write(&mut cx, b"89ab")), None);
+        assert!(writer.poll_flush(&mut cx).is_pending());
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 0);
+        sink.open();
+        ready(|| match wakes.0.load(Ordering::SeqCst) {
+            0 => Poll::Pending,
+            _ => Poll::Ready(()),
+        });
+        assert_eq!(ready(|| writer.poll_write(&mut cx, b"89ab")).unwrap(), 4);
+        ready(|| writer.poll_flush(&mut cx)).unwrap();
+        assert_eq!(sink.written(), b"0123456789ab");
+    }
+
+    #[test]
+    fn reports_sink_errors() {
+        struct Broken;
+
+        impl io::Write for Broken {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let waker = Waker::from(Arc::new(Wakes::default()));
+        let mut cx = Context::from_waker(&waker);
+        let mut writer = ConsoleWriter::new(Broken, 4).unwrap();
+        assert_eq!(accepted(writer.poll_write(&mut cx, b"ab")), Some(2));
+        let error = ready(|| writer.poll_flush(&mut cx)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(accepted(writer.poll_write(&mut cx, b"cd")), Some(2));
+    }
+
+    fn key(unit: u16, state: u32) -> INPUT_RECORD {
+        let mut record: INPUT_RECORD = unsafe { std::mem::zeroed() };
+        record.EventType = KEY_EVENT as u16;
+        record.Event.KeyEvent.bKeyDown = 1;
+        record.Event.KeyEvent.wRepeatCount = 1;
+        record.Event.KeyEvent.uChar.UnicodeChar = unit;
+        record.Event.KeyEvent.dwControlKeyState = state;
+        record
+    }
+
+    #[test]
+    fn translates_keys() {
+        let mut surrogate = None;
+        let Some(ConsoleEvent::Key(event)) = translate(&key('a' as u16, 0), &mut surrogate) else {
+            panic!("not a key");
+        };
+        assert_eq!(event.text, Some('a'));
+        assert!(event.down);
+        let Some(ConsoleEvent::Key(event)) =
+            translate(&key(0, RIGHT_CTRL_PRESSED | SHIFT_PRESSED), &mut surrogate)
+        else {
+            panic!("not a key");
+        };
+        let expected = Modifiers {
+            ctrl: true,
+            alt: false,
+            shift: true,
+        };
+        assert_eq!((event.text, event.modifiers), (None, expected));
+    }
+
+    #[test]
+    fn joins_surrogate_pairs() {
+        let mut surrogate = None;
+        assert_eq!(translate(&key(0xD83D, 0), &mut surrogate), None);
+        let Some(ConsoleEvent::Key(event)) = translate(&key(0xDE00, 0), &mut surrogate) else {
+            panic!("not a key");
+        };
+        assert_eq!(event.text, Some('\u{1F600}'));
+        assert_eq!(translate(&key(0xDE00, 0), &mut surrogate), None);
+    }
+}
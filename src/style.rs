@@ -0,0 +1,742 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use windows_sys::Win32::System::Console::{
+    GetConsoleScreenBufferInfoEx, CONSOLE_SCREEN_BUFFER_INFOEX, STD_OUTPUT_HANDLE,
+};
+
+use crate::console::std_handle;
+
+/// Struct to hold a truecolor value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb {
+    pub r: u8, // Red channel
+    pub g: u8, // Green channel
+    pub b: u8, // Blue channel
+}
+
+impl Rgb {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Rgb { r, g, b }
+    }
+}
+
+/// Enum to represent the indexed palettes a truecolor value can be downgraded to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Palette {
+    Ansi16,   // The 16 legacy console colors (SGR 30-37 / 90-97)
+    Xterm256, // The xterm 256-color palette (SGR 38;5;n)
+}
+
+/// Default colors of the Windows console ("Campbell" scheme), in SGR order.
+const ANSI16: [Rgb; 16] = [
+    Rgb::new(12, 12, 12),
+    Rgb::new(197, 15, 31),
+    Rgb::new(19, 161, 14),
+    Rgb::new(193, 156, 0),
+    Rgb::new(0, 55, 218),
+    Rgb::new(136, 23, 152),
+    Rgb::new(58, 150, 221),
+    Rgb::new(204, 204, 204),
+    Rgb::new(118, 118, 118),
+    Rgb::new(231, 72, 86),
+    Rgb::new(22, 198, 12),
+    Rgb::new(249, 241, 165),
+    Rgb::new(59, 120, 255),
+    Rgb::new(180, 0, 158),
+    Rgb::new(97, 214, 214),
+    Rgb::new(242, 242, 242),
+];
+
+/// Channel levels of the 6x6x6 color cube in the 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl Palette {
+    /// Number of entries in the palette.
+    pub fn len(self) -> usize {
+        match self {
+            Palette::Ansi16 => 16,
+            Palette::Xterm256 => 256,
+        }
+    }
+
+    /// Always `false`, palettes are never empty.
+    pub fn is_empty(self) -> bool {
+        false
+    }
+
+    /// Returns the color behind a palette index.
+    ///
+    /// ## Note:
+    /// - The first 16 entries of both palettes use the default Windows console colors, so the
+    ///   result matches what an unconfigured conhost displays.
+    /// - Indices past the end of `Ansi16` wrap around.
+    pub fn rgb(self, index: u8) -> Rgb {
+        match (self, index) {
+            (Palette::Ansi16, i) => ANSI16[(i % 16) as usize],
+            (Palette::Xterm256, 0..=15) => ANSI16[index as usize],
+            (Palette::Xterm256, 16..=231) => {
+                let i = index - 16;
+                Rgb::new(
+                    CUBE_LEVELS[(i / 36) as usize],
+                    CUBE_LEVELS[(i / 6 % 6) as usize],
+                    CUBE_LEVELS[(i % 6) as usize],
+                )
+            }
+            (Palette::Xterm256, _) => {
+                let level = 8 + 10 * (index - 232);
+                Rgb::new(level, level, level)
+            }
+        }
+    }
+
+    fn lab_table(self) -> &'static [Lab] {
+        static ANSI16_LAB: OnceLock<Vec<Lab>> = OnceLock::new();
+        static XTERM256_LAB: OnceLock<Vec<Lab>> = OnceLock::new();
+        let cell = match self {
+            Palette::Ansi16 => &ANSI16_LAB,
+            Palette::Xterm256 => &XTERM256_LAB,
+        };
+        cell.get_or_init(|| {
+            (0..self.len())
+                .map(|i| Lab::from_rgb(self.rgb(i as u8)))
+                .collect()
+        })
+    }
+
+    fn cache(self) -> &'static Mutex<HashMap<Rgb, u8>> {
+        static ANSI16_CACHE: OnceLock<Mutex<HashMap<Rgb, u8>>> = OnceLock::new();
+        static XTERM256_CACHE: OnceLock<Mutex<HashMap<Rgb, u8>>> = OnceLock::new();
+        let cell = match self {
+            Palette::Ansi16 => &ANSI16_CACHE,
+            Palette::Xterm256 => &XTERM256_CACHE,
+        };
+        cell.get_or_init(|| Mutex::new(HashMap::new()))
+    }
+}
+
+/// Colors remembered per palette before the cache starts over, about 1 MiB each.
+const CACHE_LIMIT: usize = 1 << 16;
+
+/// This function downgrades a truecolor value to the perceptually closest palette entry.
+///
+/// ## Returns:
+/// - The palette index, usable directly in `SGR 38;5;n` (or mapped to `30-37`/`90-97` for `Ansi16`).
+///   Always the same as [`nearest`].
+///
+/// ## Note:
+/// - Distances are measured with CIEDE2000 in CIELAB space rather than plain RGB distance, which
+///   keeps hues intact (dark blues no longer collapse to black, skin tones no longer turn red).
+/// - Results are memoized per palette by exact color, so the expensive search only happens once
+///   per distinct color; the cache is emptied when it holds 65 536 colors.
+pub fn quantize(rgb: Rgb, palette: Palette) -> u8 {
+    let cache = palette.cache();
+    if let Some(&index) = cache.lock().unwrap_or_else(|e| e.into_inner()).get(&rgb) {
+        return index;
+    }
+    let index = nearest(rgb, palette);
+    let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    if cache.len() >= CACHE_LIMIT {
+        cache.clear();
+    }
+    cache.insert(rgb, index);
+    index
+}
+
+/// This function searches the whole palette for the entry closest to `rgb`, without memoization.
+pub fn nearest(rgb: Rgb, palette: Palette) -> u8 {
+    closest(Lab::from_rgb(rgb), palette.lab_table().iter().copied())
+}
+
+/// This function searches any list of up to 256 colors for the one closest to `rgb`, e.g. the
+/// table [`console_palette`] returns, with the same CIEDE2000 distance as [`nearest`].
+pub fn nearest_in(rgb: Rgb, colors: &[Rgb]) -> u8 {
+    closest(
+        Lab::from_rgb(rgb),
+        colors.iter().map(|&color| Lab::from_rgb(color)),
+    )
+}
+
+/// Index of the color closest to `target`.
+fn closest(target: Lab, colors: impl Iterator<Item = Lab>) -> u8 {
+    let mut best = (0, f64::MAX);
+    for (i, lab) in colors.enumerate().take(256) {
+        let distance = ciede2000(target, lab);
+        if distance < best.1 {
+            best = (i as u8, distance);
+        }
+    }
+    best.0
+}
+
+/// This function reads the 16 colors the console shows for the legacy colors, as the user
+/// configured them.
+///
+/// ## Returns:
+/// - `Some([Rgb; 16])` in SGR order (black, red, green, yellow, …), unlike the console which
+///   swaps red and blue.
+/// - `None` without a console on the standard output.
+///
+/// ## Note:
+/// - Under a pseudo console the table is the one the host gave the console, usually the
+///   default "Campbell" colors whatever scheme the terminal draws with.
+pub fn console_palette() -> Option<[Rgb; 16]> {
+    let handle = std_handle(STD_OUTPUT_HANDLE).ok()?;
+    let mut info: CONSOLE_SCREEN_BUFFER_INFOEX = unsafe { std::mem::zeroed() };
+    info.cbSize = std::mem::size_of::<CONSOLE_SCREEN_BUFFER_INFOEX>() as u32;
+    if unsafe { GetConsoleScreenBufferInfoEx(handle, &mut info) } == 0 {
+        return None;
+    }
+    Some(std::array::from_fn(|i| {
+        // COLORREF is 0x00BBGGRR.
+        let color = info.ColorTable[i & 0b1010 | (i & 1) << 2 | (i >> 2) & 1];
+        Rgb::new(color as u8, (color >> 8) as u8, (color >> 16) as u8)
+    }))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Lab {
+    l: f64,
+    a: f64,
+    b: f64,
+}
+
+impl Lab {
+    fn from_rgb(rgb: Rgb) -> Self {
+        fn linear(c: u8) -> f64 {
+            let c = c as f64 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        }
+        fn f(t: f64) -> f64 {
+            if t > 216.0 / 24389.0 {
+                t.cbrt()
+            } else {
+                (24389.0 / 27.0 * t + 16.0) / 116.0
+            }
+        }
+        let (r, g, b) = (linear(rgb.r), linear(rgb.g), linear(rgb.b));
+        // sRGB -> XYZ (D65), normalized by the reference white.
+        let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / 0.95047;
+        let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+        let z = (0.0193339 * r + 0.119192 * g + 0.9503041 * b) / 1.08883;
+        let (fx, fy, fz) = (f(x), f(y), f(z));
+        Lab {
+            l: 116.0 * fy - 16.0,
+            a: 500.0 * (fx - fy),
+            b: 200.0 * (fy - fz),
+        }
+    }
+}
+
+/// CIEDE2000 color difference (Sharma, Wu & Dalal reference formulation).
+fn ciede2000(p: Lab, q: Lab) -> f64 {
+    use std::f64::consts::PI;
+    let c1 = p.a.hypot(p.b);
+    let c2 = q.a.hypot(q.b);
+    let c_bar7 = ((c1 + c2) / 2.0).powi(7);
+    let g = 0.5 * (1.0 - (c_bar7 / (c_bar7 + 25f64.powi(7))).sqrt());
+    let a1 = (1.0 + g) * p.a;
+    let a2 = (1.0 + g) * q.a;
+    let c1 = a1.hypot(p.b);
+    let c2 = a2.hypot(q.b);
+    let hue = |b: f64, a: f64| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            b.atan2(a).rem_euclid(2.0 * PI)
+        }
+    };
+    let h1 = hue(p.b, a1);
+    let h2 = hue(q.b, a2);
+
+    let dl = q.l - p.l;
+    let dc = c2 - c1;
+    let dh = if c1 * c2 == 0.0 {
+        0.0
+    } else if (h2 - h1).abs() <= PI {
+        h2 - h1
+    } else if h2 - h1 > PI {
+        h2 - h1 - 2.0 * PI
+    } else {
+        h2 - h1 + 2.0 * PI
+    };
+    let dh = 2.0 * (c1 * c2).sqrt() * (dh / 2.0).sin();
+
+    let l_bar = (p.l + q.l) / 2.0;
+    let c_bar = (c1 + c2) / 2.0;
+    let h_bar = if c1 * c2 == 0.0 {
+        h1 + h2
+    } else if (h1 - h2).abs() <= PI {
+        (h1 + h2) / 2.0
+    } else if h1 + h2 < 2.0 * PI {
+        (h1 + h2 + 2.0 * PI) / 2.0
+    } else {
+        (h1 + h2 - 2.0 * PI) / 2.0
+    };
+    let t = 1.0 - 0.17 * (h_bar - PI / 6.0).cos()
+        + 0.24 * (2.0 * h_bar).cos()
+        + 0.32 * (3.0 * h_bar + PI / 30.0).cos()
+        - 0.20 * (4.0 * h_bar - 63.0 * PI / 180.0).cos();
+    let l_bar_sq = (l_bar - 50.0).powi(2);
+    let sl = 1.0 + 0.015 * l_bar_sq / (20.0 + l_bar_sq).sqrt();
+    let sc = 1.0 + 0.045 * c_bar;
+    let sh = 1.0 + 0.015 * c_bar * t;
+    let c_bar7 = c_bar.powi(7);
+    let d_theta = 30f64.to_radians() * (-((h_bar.to_degrees() - 275.0) / 25.0).powi(2)).exp();
+    let rt = -2.0 * (c_bar7 / (c_bar7 + 25f64.powi(7))).sqrt() * (2.0 * d_theta).sin();
+
+    ((dl / sl).powi(2) + (dc / sc).powi(2) + (dh / sh).powi(2) + rt * (dc / sc) * (dh / sh)).sqrt()
+}
+
+/// Enum to represent whether the crate colors its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ColorChoice {
+    #[default]
+    Auto, // Follow the environment conventions and whether stdout is a console
+    Always, // Color even when redirected or with `NO_COLOR` set
+    Never,  // Never color
+}
+
+/// Programmatic override, `ColorChoice as u8`.
+static COLOR_CHOICE: AtomicU8 = AtomicU8::new(ColorChoice::Auto as u8);
+
+/// This function overrides the environment-based color decision for the whole process, e.g.
+/// from a `--color=always|never|auto` flag.
+pub fn set_color_choice(choice: ColorChoice) {
+    COLOR_CHOICE.store(choice as u8, Ordering::Relaxed);
+}
+
+/// This function tells whether the crate's widgets and styles should emit colors.
+///
+/// ## Returns:
+/// - The override set by [`set_color_choice`], unless it is `Auto`.
+/// - Otherwise, in order: `false` if `FORCE_COLOR` is `0` and `true` if it is set to
+///   anything else (an empty `FORCE_COLOR` counts as unset), `true` if `CLICOLOR_FORCE` is
+///   set to anything but `0`, `false` if `NO_COLOR` is set and non-empty or `CLICOLOR` is
+///   `0`, and finally whether the standard output is a console.
+///
+/// ## Note:
+/// - The environment is read once and cached; only the override can change afterwards.
+/// - Reverse video, bold and other non-color attributes are not affected.
+pub fn colors_enabled() -> bool {
+    match COLOR_CHOICE.load(Ordering::Relaxed) {
+        x if x == ColorChoice::Always as u8 => true,
+        x if x == ColorChoice::Never as u8 => false,
+        _ => {
+            static AUTO: OnceLock<bool> = OnceLock::new();
+            *AUTO.get_or_init(auto_colors)
+        }
+    }
+}
+
+fn auto_colors() -> bool {
+    let var = |name: &str| env::var_os(name).map(|value| value.to_string_lossy().into_owned());
+    colors_from(var, stdout_is_console)
+}
+
+/// Decides from the variables `var` reads, then from `console` when none of them decides.
+fn colors_from(var: impl Fn(&str) -> Option<String>, console: impl FnOnce() -> bool) -> bool {
+    // An empty `FORCE_COLOR` is the same as none (force-color.org), `0` turns colors off.
+    match var("FORCE_COLOR")
+        .filter(|value| !value.is_empty())
+        .as_deref()
+    {
+        Some("0") => return false,
+        Some(_) => return true,
+        None => {}
+    }
+    if var("CLICOLOR_FORCE").is_some_and(|value| !value.is_empty() && value != "0") {
+        return true;
+    }
+    if var("NO_COLOR").is_some_and(|value| !value.is_empty())
+        || var("CLICOLOR").as_deref() == Some("0")
+    {
+        return false;
+    }
+    console()
+}
+
+/// Whether the standard output is a console.
+#[cfg(not(test))]
+fn stdout_is_console() -> bool {
+    let Ok(handle) = std_handle(STD_OUTPUT_HANDLE) else {
+        return false;
+    };
+    let mut mode = 0;
+    unsafe { windows_sys::Win32::System::Console::GetConsoleMode(handle, &mut mode) != 0 }
+}
+
+// Unit tests don't look at the console they run in.
+#[cfg(test)]
+fn stdout_is_console() -> bool {
+    false
+}
+
+/// Enum to represent how many colors the terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ColorDepth {
+    None,      // No colors, see [`colors_enabled`]
+    Ansi16,    // SGR 30-37 / 90-97
+    Xterm256,  // SGR 38;5;n
+    TrueColor, // SGR 38;2;r;g;b
+}
+
+impl ColorDepth {
+    /// This function builds the SGR sequence selecting `rgb` as the foreground color, downgraded
+    /// to the depth with [`quantize`]. Empty for `ColorDepth::None`.
+    pub fn foreground(self, rgb: Rgb) -> String {
+        match self {
+            ColorDepth::None => String::new(),
+            ColorDepth::Ansi16 => match quantize(rgb, Palette::Ansi16) {
+                index @ 0..=7 => format!("\x1b[{}m", 30 + index),
+                index => format!("\x1b[{}m", 90 + index - 8),
+            },
+            ColorDepth::Xterm256 => format!("\x1b[38;5;{}m", quantize(rgb, Palette::Xterm256)),
+            ColorDepth::TrueColor => format!("\x1b[38;2;{};{};{}m", rgb.r, rgb.g, rgb.b),
+        }
+    }
+}
+
+/// This function detects how many colors the terminal can show.
+///
+/// ## Returns:
+/// - `None` when [`colors_enabled`] is `false`.
+/// - `TrueColor` when `$COLORTERM` is `truecolor` or `24bit`, or in Windows Terminal
+///   (`$WT_SESSION`).
+/// - `Xterm256` when `$TERM` ends with `256color`, e.g. under tmux or over SSH.
+/// - `Ansi16` otherwise, which every console host shows.
+pub fn color_depth() -> ColorDepth {
+    if !colors_enabled() {
+        return ColorDepth::None;
+    }
+    let var = |name: &str| env::var(name).unwrap_or_default();
+    if matches!(var("COLORTERM").as_str(), "truecolor" | "24bit") || !var("WT_SESSION").is_empty() {
+        ColorDepth::TrueColor
+    } else if var("TERM").ends_with("256color") {
+        ColorDepth::Xterm256
+    } else {
+        ColorDepth::Ansi16
+    }
+}
+
+/// Enum to represent the underline styles of `SGR 4:x`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Underline {
+    #[default]
+    None,
+    Single, // SGR 4 / 4:1
+    Double, // SGR 4:2
+    Curly,  // SGR 4:3
+    Dotted, // SGR 4:4
+    Dashed, // SGR 4:5
+}
+
+/// Struct to hold the text attributes of a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Attributes {
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: Underline,
+    pub underline_color: Option<Rgb>, // SGR 58, `None` uses the foreground color
+    pub blink: bool,
+    pub reverse: bool,
+    pub hidden: bool,
+    pub strikethrough: bool,
+}
+
+/// `COMMON_LVB_REVERSE_VIDEO` and `COMMON_LVB_UNDERSCORE` from the console API.
+const LEGACY_REVERSE: u16 = 0x4000;
+const LEGACY_UNDERSCORE: u16 = 0x8000;
+const LEGACY_INTENSITY: u16 = 0x0008;
+
+impl Attributes {
+    /// This function builds the SGR sequence selecting these attributes (after a reset).
+    ///
+    /// ## Note:
+    /// - With `extended` set, underline styles use colon sub-parameters (`4:3`) and the
+    ///   underline color `58:2::r:g:b`, which Windows Terminal and most modern hosts understand.
+    /// - Without it, every underline style degrades to a plain `SGR 4` and the underline color
+    ///   is dropped, since legacy parsers would otherwise misread the colons.
+    /// - The underline color is also dropped when [`colors_enabled`] is `false`.
+    pub fn sgr(&self, extended: bool) -> String {
+        let mut params = vec!["0".to_string()];
+        let mut push = |on: bool, param: &str| {
+            if on {
+                params.push(param.to_string());
+            }
+        };
+        push(self.bold, "1");
+        push(self.dim, "2");
+        push(self.italic, "3");
+        push(self.blink, "5");
+        push(self.reverse, "7");
+        push(self.hidden, "8");
+        push(self.strikethrough, "9");
+        let style = match self.underline {
+            Underline::None => None,
+            Underline::Single => Some(1),
+            Underline::Double => Some(2),
+            Underline::Curly => Some(3),
+            Underline::Dotted => Some(4),
+            Underline::Dashed => Some(5),
+        };
+        match (style, extended) {
+            (None, _) => {}
+            (Some(1), _) | (Some(_), false) => params.push("4".to_string()),
+            (Some(style), true) => params.push(format!("4:{}", style)),
+        }
+        if let (Some(color), true, Some(_), true) =
+            (self.underline_color, extended, style, colors_enabled())
+        {
+            params.push(format!("58:2::{}:{}:{}", color.r, color.g, color.b));
+        }
+        format!("\x1b[{}m", params.join(";"))
+    }
+
+    /// This function maps the attributes onto legacy console `wAttributes` bits, for hosts
+    /// without VT support.
+    ///
+    /// ## Note:
+    /// - Bold becomes `FOREGROUND_INTENSITY`, any underline `COMMON_LVB_UNDERSCORE`, reverse
+    ///   `COMMON_LVB_REVERSE_VIDEO`. Italic, dim, blink, hidden and strikethrough have no legacy
+    ///   equivalent and are dropped.
+    pub fn legacy(&self) -> u16 {
+        let mut bits = 0;
+        if self.bold {
+            bits |= LEGACY_INTENSITY;
+        }
+        if self.underline != Underline::None {
+            bits |= LEGACY_UNDERSCORE;
+        }
+        if self.reverse {
+            bits |= LEGACY_REVERSE;
+        }
+        bits
+    }
+
+    /// This function applies the parameters of an SGR sequence (the part between `CSI` and
+    /// `m`), so attributes emitted by [`Attributes::sgr`] round-trip.
+    ///
+    /// ## Note:
+    /// - Color parameters other than the underline color are skipped, unknown ones ignored.
+    pub fn apply_sgr(&mut self, params: &str) {
+        let mut params = params.split(';').peekable();
+        while let Some(param) = params.next() {
+            let mut sub = param.split(':');
+            let code: u16 = match sub.next().unwrap_or("") {
+                "" => 0,
+                code => match code.parse() {
+                    Ok(code) => code,
+                    Err(_) => continue,
+                },
+            };
+            match code {
+                0 => *self = Attributes::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => {
+                    self.underline = match sub.next() {
+                        Some("0") => Underline::None,
+                        Some("2") => Underline::Double,
+                        Some("3") => Underline::Curly,
+                        Some("4") => Underline::Dotted,
+                        Some("5") => Underline::Dashed,
+                        _ => Underline::Single,
+                    }
+                }
+                5 => self.blink = true,
+                7 => self.reverse = true,
+                8 => self.hidden = true,
+                9 => self.strikethrough = true,
+                21 => self.underline = Underline::Double,
+                22 => (self.bold, self.dim) = (false, false),
+                23 => self.italic = false,
+                24 => self.underline = Underline::None,
+                25 => self.blink = false,
+                27 => self.reverse = false,
+                28 => self.hidden = false,
+                29 => self.strikethrough = false,
+                38 | 48 | 58 => {
+                    // Either `58:2::r:g:b` / `58:5:n` in one parameter, or `58;2;r;g;b` / `58;5;n`.
+                    let values: Vec<&str> = if param.contains(':') {
+                        sub.collect()
+                    } else {
+                        let count = match params.peek() {
+                            Some(&"2") => 4,
+                            Some(&"5") => 2,
+                            _ => 0,
+                        };
+                        (0..count).filter_map(|_| params.next()).collect()
+                    };
+                    if code == 58 {
+                        self.underline_color = parse_color(&values);
+                    }
+                }
+                59 => self.underline_color = None,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Parses the values after `38`/`48`/`58`: `2;r;g;b` (with an optional empty color space
+/// id in the colon form) or `5;n`.
+fn parse_color(values: &[&str]) -> Option<Rgb> {
+    match values {
+        ["5", index] => index.parse().ok().map(|i| Palette::Xterm256.rgb(i)),
+        ["2", rest @ ..] => {
+            let rest = if rest.len() == 4 { &rest[1..] } else { rest };
+            match rest {
+                [r, g, b] => Some(Rgb::new(r.parse().ok()?, g.parse().ok()?, b.parse().ok()?)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn colors_with(vars: &[(&str, &str)], console: bool) -> bool {
+        let var = |name: &str| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        };
+        colors_from(var, || console)
+    }
+
+    #[test]
+    fn force_color_follows_force_color_org() {
+        assert!(colors_with(&[("FORCE_COLOR", "1")], false));
+        assert!(colors_with(&[("FORCE_COLOR", "true")], false));
+        assert!(!colors_with(&[("FORCE_COLOR", "0")], true));
+        // Empty is unset: fall through to the other variables, then the console.
+        assert!(!colors_with(&[("FORCE_COLOR", "")], false));
+        assert!(colors_with(&[("FORCE_COLOR", "")], true));
+        assert!(!colors_with(
+            &[("FORCE_COLOR", ""), ("NO_COLOR", "1")],
+            true
+        ));
+    }
+
+    #[test]
+    fn other_variables() {
+        assert!(colors_with(&[("CLICOLOR_FORCE", "1")], false));
+        assert!(!colors_with(&[("CLICOLOR_FORCE", "0")], false));
+        assert!(!colors_with(&[("NO_COLOR", "1")], true));
+        assert!(colors_with(&[("NO_COLOR", "")], true));
+        assert!(!colors_with(&[("CLICOLOR", "0")], true));
+        assert!(colors_with(
+            &[("FORCE_COLOR", "1"), ("NO_COLOR", "1")],
+            false
+        ));
+        assert!(colors_with(&[], true));
+        assert!(!colors_with(&[], false));
+    }
+
+    #[test]
+    fn quantize_matches_nearest() {
+        for palette in [Palette::Ansi16, Palette::Xterm256] {
+            for r in (0..=255).step_by(17) {
+                for g in (0..=255).step_by(17) {
+                    for b in (0..=255).step_by(51) {
+                        let rgb = Rgb::new(r, g, b);
+                        assert_eq!(quantize(rgb, palette), nearest(rgb, palette), "{:?}", rgb);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn palette_colors_map_to_themselves() {
+        for i in 0..16 {
+            assert_eq!(nearest(Palette::Ansi16.rgb(i), Palette::Ansi16), i);
+        }
+        // The cube and the grays, the first 16 duplicate the console colors.
+        for i in 16..=255 {
+            let rgb = Palette::Xterm256.rgb(i);
+            assert_eq!(Palette::Xterm256.rgb(nearest(rgb, Palette::Xterm256)), rgb);
+        }
+    }
+
+    fn parsed(params: &str) -> Attributes {
+        let mut attributes = Attributes::default();
+        attributes.apply_sgr(params);
+        attributes
+    }
+
+    #[test]
+    fn sgr_resets_and_toggles() {
+        assert_eq!(parsed(""), Attributes::default());
+        assert!(parsed("1;3").bold && parsed("1;3").italic);
+        assert_eq!(parsed("1;0"), Attributes::default());
+        assert_eq!(parsed("1;2;22"), Attributes::default());
+        assert_eq!(parsed("7;27;8;28;9;29;5;25"), Attributes::default());
+        // Unknown and malformed parameters are skipped.
+        assert!(parsed("x;1;99").bold);
+    }
+
+    #[test]
+    fn sgr_underline_styles() {
+        assert_eq!(parsed("4").underline, Underline::Single);
+        assert_eq!(parsed("4:1").underline, Underline::Single);
+        assert_eq!(parsed("4:2").underline, Underline::Double);
+        assert_eq!(parsed("21").underline, Underline::Double);
+        assert_eq!(parsed("4:3").underline, Underline::Curly);
+        assert_eq!(parsed("4:4").underline, Underline::Dotted);
+        assert_eq!(parsed("4:5").underline, Underline::Dashed);
+        assert_eq!(parsed("4:3;4:0").underline, Underline::None);
+        assert_eq!(parsed("4:3;24").underline, Underline::None);
+    }
+
+    #[test]
+    fn sgr_colors() {
+        let red = Some(Rgb::new(255, 0, 0));
+        assert_eq!(parsed("58:2::255:0:0").underline_color, red);
+        assert_eq!(parsed("58:2:255:0:0").underline_color, red);
+        assert_eq!(parsed("58;2;255;0;0").underline_color, red);
+        assert_eq!(
+            parsed("58;5;196").underline_color,
+            Some(Palette::Xterm256.rgb(196))
+        );
+        assert_eq!(parsed("58;2;255;0;0;59").underline_color, None);
+        // Foreground and background values are consumed, not read as attributes.
+        assert_eq!(parsed("38;2;1;1;1"), Attributes::default());
+        assert_eq!(parsed("48;5;1;4").underline, Underline::Single);
+        assert!(!parsed("38;5;1").bold);
+    }
+
+    #[test]
+    fn sgr_round_trips() {
+        let attributes = Attributes {
+            bold: true,
+            italic: true,
+            underline: Underline::Curly,
+            reverse: true,
+            strikethrough: true,
+            ..Attributes::default()
+        };
+        let sgr = attributes.sgr(true);
+        let params = sgr
+            .strip_prefix("\x1b[")
+            .unwrap()
+            .strip_suffix('m')
+            .unwrap();
+        assert_eq!(parsed(params), attributes);
+        // Legacy parsers get a plain underline.
+        let params = attributes.sgr(false);
+        assert_eq!(params, "\x1b[0;1;3;7;9;4m");
+    }
+}
@@ -0,0 +1,188 @@
+use std::io;
+
+use windows_sys::Win32::Foundation::HANDLE;
+use windows_sys::Win32::System::Console::{
+    FlushConsoleInputBuffer, GetConsoleMode, GetNumberOfConsoleInputEvents, PeekConsoleInputW,
+    ReadConsoleInputW, SetConsoleMode, WriteConsoleInputW, CONSOLE_FONT_INFOEX, CONSOLE_MODE,
+    CONSOLE_SCREEN_BUFFER_INFO, INPUT_RECORD, STD_HANDLE,
+};
+
+pub use crate::console::ModeGuard;
+use crate::font::FontWeight;
+use crate::TerminalError;
+
+/// This function returns a standard handle (`STD_OUTPUT_HANDLE`, `STD_INPUT_HANDLE` or
+/// `STD_ERROR_HANDLE`), as every function of the crate gets it.
+///
+/// ## Returns:
+/// - `Ok(HANDLE)`, which may be a file or a pipe when the stream is redirected, see
+///   [`is_console_handle`].
+/// - `Err(TerminalError::NoStdHandle)` if the process has none.
+pub fn std_handle(which: STD_HANDLE) -> Result<HANDLE, TerminalError> {
+    crate::console::std_handle(which)
+}
+
+/// This function returns the active screen buffer of the attached console (`CONOUT$`),
+/// even when the standard output and error are redirected.
+///
+/// ## Returns:
+/// - `Some(HANDLE)`, opened once per process and never closed: don't pass it to
+///   `CloseHandle`.
+/// - `None` without a console.
+pub fn console_output() -> Option<HANDLE> {
+    crate::console::console_output()
+}
+
+/// This function returns the input buffer of the attached console (`CONIN$`), see
+/// [`console_output`].
+pub fn console_input() -> Option<HANDLE> {
+    crate::console::console_input()
+}
+
+/// This function tells whether a handle is a console, rather than a file, a pipe or the
+/// `NUL` device.
+pub fn is_console_handle(handle: HANDLE) -> bool {
+    crate::console::is_console_handle(handle)
+}
+
+/// This function reads the DPI of the window the console is shown in, the owner window under
+/// a pseudo console (Windows Terminal), as the crate measures with.
+///
+/// ## Returns:
+/// - The DPI, 0 if it can't be read.
+pub fn console_dpi() -> u32 {
+    crate::console::console_dpi()
+}
+
+/// This function reads the screen buffer info of a console output handle with
+/// `GetConsoleScreenBufferInfo`.
+///
+/// ## Returns:
+/// - `Ok(CONSOLE_SCREEN_BUFFER_INFO)` with the buffer size, the cursor, the attributes and the
+///   visible window.
+/// - `Err(TerminalError::NotAConsole)` if the handle is redirected.
+/// - `Err(TerminalError::NoScreenBufferInfo)` if the console refuses.
+pub fn screen_buffer_info(handle: HANDLE) -> Result<CONSOLE_SCREEN_BUFFER_INFO, TerminalError> {
+    crate::console::screen_buffer_info(handle)
+}
+
+/// This function reads the mode of a console input or output handle with `GetConsoleMode`.
+///
+/// ## Returns:
+/// - `Ok(CONSOLE_MODE)` with the `ENABLE_*` flags of the handle.
+/// - `Err(io::Error)` if the handle isn't a console.
+pub fn mode(handle: HANDLE) -> io::Result<CONSOLE_MODE> {
+    let mut mode = 0;
+    if unsafe { GetConsoleMode(handle, &mut mode) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(mode)
+}
+
+/// This function sets the mode of a console handle with `SetConsoleMode`.
+///
+/// ## Returns:
+/// - `Err(io::Error)` if the handle isn't a console or the host refuses the mode (older
+///   hosts refuse the VT flags).
+///
+/// ## Note:
+/// - The mode stays after the process exits; [`ModeGuard::change`] puts it back instead.
+pub fn set_mode(handle: HANDLE, mode: CONSOLE_MODE) -> io::Result<()> {
+    if unsafe { SetConsoleMode(handle, mode) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// This function reads the current font of a console output handle with
+/// `GetCurrentConsoleFontEx`.
+///
+/// ## Returns:
+/// - `Ok(CONSOLE_FONT_INFOEX)` as the console reports it; [`crate::font::FontInfo`] is the
+///   decoded form.
+/// - `Err(TerminalError::NoFontInfo)` if the font can't be read.
+pub fn current_font(handle: HANDLE) -> Result<CONSOLE_FONT_INFOEX, TerminalError> {
+    crate::font::current_font(handle)
+}
+
+/// This function changes the font of a console output handle, as [`crate::font::set`] does
+/// for the standard output.
+pub fn set_font(
+    handle: HANDLE,
+    face: &str,
+    points: u16,
+    weight: FontWeight,
+) -> Result<(), TerminalError> {
+    crate::font::set_font(handle, face, points, weight)
+}
+
+/// This function counts the records waiting in a console input buffer.
+///
+/// ## Returns:
+/// - `Ok(count)` of keyboard, mouse, resize, focus and menu records.
+/// - `Err(io::Error)` if the handle isn't a console input.
+pub fn pending_input(handle: HANDLE) -> io::Result<usize> {
+    let mut count = 0;
+    if unsafe { GetNumberOfConsoleInputEvents(handle, &mut count) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(count as usize)
+}
+
+/// This function reads input records with `ReadConsoleInputW`, removing them from the buffer.
+///
+/// ## Returns:
+/// - `Ok(count)` of records written to the front of `records`.
+/// - `Err(io::Error)` if the handle isn't a console input.
+///
+/// ## Note:
+/// - This blocks until at least one record is available; check [`pending_input`] first to
+///   avoid waiting.
+pub fn read_input(handle: HANDLE, records: &mut [INPUT_RECORD]) -> io::Result<usize> {
+    let mut count = 0;
+    let length = records.len().min(u32::MAX as usize) as u32;
+    if unsafe { ReadConsoleInputW(handle, records.as_mut_ptr(), length, &mut count) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(count as usize)
+}
+
+/// This function reads input records with `PeekConsoleInputW`, leaving them in the buffer.
+///
+/// ## Returns:
+/// - `Ok(count)` of records written to the front of `records`, 0 right away if there are none.
+/// - `Err(io::Error)` if the handle isn't a console input.
+pub fn peek_input(handle: HANDLE, records: &mut [INPUT_RECORD]) -> io::Result<usize> {
+    let mut count = 0;
+    let length = records.len().min(u32::MAX as usize) as u32;
+    if unsafe { PeekConsoleInputW(handle, records.as_mut_ptr(), length, &mut count) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(count as usize)
+}
+
+/// This function appends records to a console input buffer with `WriteConsoleInputW`, as if
+/// the user made them.
+///
+/// ## Returns:
+/// - `Ok(count)` of records written.
+/// - `Err(io::Error)` if the handle isn't a console input.
+pub fn write_input(handle: HANDLE, records: &[INPUT_RECORD]) -> io::Result<usize> {
+    let mut count = 0;
+    let length = records.len().min(u32::MAX as usize) as u32;
+    if unsafe { WriteConsoleInputW(handle, records.as_ptr(), length, &mut count) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(count as usize)
+}
+
+/// This function discards every record waiting in a console input buffer.
+///
+/// ## Returns:
+/// - `Err(io::Error)` if the handle isn't a console input.
+pub fn flush_input(handle: HANDLE) -> io::Result<()> {
+    if unsafe { FlushConsoleInputBuffer(handle) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
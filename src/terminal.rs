@@ -0,0 +1,380 @@
+use std::collections::VecDeque;
+#[cfg(feature = "vt")]
+use std::io;
+#[cfg(feature = "vt")]
+use std::time::Duration;
+use std::time::SystemTime;
+
+use windows_sys::Win32::{
+    Foundation::{HANDLE, HWND},
+    System::Console::{
+        GetConsoleOutputCP, CONSOLE_SCREEN_BUFFER_INFO, STD_ERROR_HANDLE, STD_OUTPUT_HANDLE,
+    },
+};
+
+#[cfg(feature = "window")]
+use crate::buffer::ScreenBufferInfo;
+use crate::console::{
+    console_output, host_window, is_console_handle, screen_buffer_info, std_handle, window_dpi,
+};
+use crate::font::{set_font, FontWeight};
+use crate::query::Measure;
+use crate::source::{self, SizeSource, SourceContext, Sources};
+#[cfg(feature = "vt")]
+use crate::vt_query::{self, Negotiation};
+use crate::{
+    last_error_code, ConsoleGeometry, FontSize, TerminalCells, TerminalError, TerminalSize,
+};
+
+/// Enum to represent the console output a [`Terminal`] queries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleStream {
+    Stdout,         // The standard output
+    Stderr,         // The standard error, still a console when the output is piped
+    Console,        // `CONOUT$`, the console even when both standard handles are redirected
+    Custom(HANDLE), // Any console screen buffer handle, e.g. from `CreateConsoleScreenBuffer`
+}
+
+impl ConsoleStream {
+    /// The handle of the stream, whether or not it is a console.
+    pub(crate) fn handle(self) -> Result<HANDLE, TerminalError> {
+        match self {
+            ConsoleStream::Stdout => std_handle(STD_OUTPUT_HANDLE),
+            ConsoleStream::Stderr => std_handle(STD_ERROR_HANDLE),
+            ConsoleStream::Console => {
+                console_output().ok_or_else(|| TerminalError::NoStdHandle(last_error_code()))
+            }
+            ConsoleStream::Custom(handle) if handle.is_null() => Err(TerminalError::NoStdHandle(0)),
+            ConsoleStream::Custom(handle) => Ok(handle),
+        }
+    }
+}
+
+/// This function tells whether a stream is attached to a console, rather than redirected to a
+/// file, a pipe or `NUL` (as under CI).
+///
+/// ## Note:
+/// - The size and font functions fail with `TerminalError::NotAConsole` on such streams.
+pub fn is_console(stream: ConsoleStream) -> bool {
+    stream.handle().is_ok_and(is_console_handle)
+}
+
+/// Number of changes a [`Terminal`] remembers, see [`Terminal::metrics_history`].
+const HISTORY_LEN: usize = 32;
+
+/// Struct to hold the geometry of a [`Terminal`] from the refresh where it changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetricsSample {
+    pub at: SystemTime,        // When the refresh saw the change
+    pub cells: TerminalCells,  // Visible window in cells
+    pub buffer: TerminalCells, // Screen buffer in cells
+    pub font: FontSize,        // Cell size in pixels
+    pub dpi: u32,              // DPI of the window the console is shown in
+}
+
+/// Struct to hold a console output handle and what was last queried about it.
+///
+/// Getting the handle, the window, the DPI and the screen buffer on every call is what the
+/// free functions of the crate do; a `Terminal` does it once, in [`Terminal::stdout`], and
+/// again only on [`Terminal::refresh`]. The accessors are then plain field reads.
+#[derive(Debug)]
+pub struct Terminal {
+    handle: HANDLE,
+    window: HWND,
+    dpi: u32,
+    code_page: u32,
+    viewport: TerminalCells,
+    buffer: TerminalCells,
+    cell: FontSize,
+    #[cfg(feature = "window")]
+    info: ScreenBufferInfo,
+    history: VecDeque<MetricsSample>,
+    sources: Sources,
+    #[cfg(feature = "vt")]
+    negotiation: Option<Negotiation>,
+}
+
+// The console handle and window can be used from any thread.
+unsafe impl Send for Terminal {}
+
+impl Terminal {
+    /// This function opens the terminal of the standard output.
+    ///
+    /// ## Returns:
+    /// - `Ok(Terminal)` with the handle, the window it is shown in and a first snapshot of its
+    ///   state.
+    /// - `Err(TerminalError)` if there's no standard handle, it isn't a console, or the cell
+    ///   size can't be measured.
+    pub fn stdout() -> Result<Terminal, TerminalError> {
+        Terminal::from_stream(ConsoleStream::Stdout)
+    }
+
+    /// This function opens the terminal of the standard error, see [`Terminal::stdout`].
+    pub fn stderr() -> Result<Terminal, TerminalError> {
+        Terminal::from_stream(ConsoleStream::Stderr)
+    }
+
+    /// This function opens the terminal of any console output, see [`Terminal::stdout`].
+    ///
+    /// ## Note:
+    /// - A `Custom` handle must stay open for as long as the `Terminal` is used.
+    pub fn from_stream(stream: ConsoleStream) -> Result<Terminal, TerminalError> {
+        let handle = stream.handle()?;
+        let info = screen_buffer_info(handle)?;
+        let mut terminal = Terminal {
+            handle,
+            window: std::ptr::null_mut(),
+            dpi: 0,
+            code_page: 0,
+            viewport: TerminalCells {
+                columns: 0,
+                rows: 0,
+            },
+            buffer: TerminalCells {
+                columns: 0,
+                rows: 0,
+            },
+            cell: FontSize {
+                width: 0,
+                height: 0,
+            },
+            #[cfg(feature = "window")]
+            info: info.into(),
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            sources: Sources::new(),
+            #[cfg(feature = "vt")]
+            negotiation: None,
+        };
+        terminal.refresh_with(info)?;
+        Ok(terminal)
+    }
+
+    /// This function queries the console again, after a resize, a font or DPI change.
+    ///
+    /// The sources of [`Terminal::register_source`] are asked first, then the ones of
+    /// [`source::register_source`](crate::source::register_source); an answer replaces the
+    /// measured cell size, and the visible window and DPI when it has them. Otherwise the cell
+    /// size answered to [`Terminal::negotiate`], if any, is kept rather than measured again.
+    ///
+    /// ## Returns:
+    /// - `Err(TerminalError)` if the screen buffer or the cell size can't be read anymore; the
+    ///   previous snapshot is kept then.
+    pub fn refresh(&mut self) -> Result<(), TerminalError> {
+        let info = screen_buffer_info(self.handle)?;
+        self.refresh_with(info)
+    }
+
+    fn refresh_with(&mut self, info: CONSOLE_SCREEN_BUFFER_INFO) -> Result<(), TerminalError> {
+        let window = host_window();
+        let context = SourceContext {
+            dpi: window_dpi(window),
+            code_page: unsafe { GetConsoleOutputCP() },
+        };
+        let metrics = self
+            .sources
+            .measure(&context)
+            .or_else(|| source::measure(&context));
+        let cell = match metrics
+            .map(|metrics| metrics.cell)
+            .or(self.negotiated_cell())
+        {
+            Some(cell) => cell,
+            None => Measure::new().allow_sources(false).cell_size(self.handle)?,
+        };
+        self.window = window;
+        self.code_page = context.code_page;
+        self.dpi = metrics
+            .and_then(|metrics| metrics.dpi)
+            .unwrap_or(context.dpi);
+        let visible = info.srWindow;
+        self.viewport = metrics
+            .and_then(|metrics| metrics.viewport)
+            .unwrap_or(TerminalCells {
+                columns: (visible.Right - visible.Left + 1) as i32,
+                rows: (visible.Bottom - visible.Top + 1) as i32,
+            });
+        self.buffer = TerminalCells {
+            columns: info.dwSize.X as i32,
+            rows: info.dwSize.Y as i32,
+        };
+        self.cell = cell;
+        #[cfg(feature = "window")]
+        {
+            self.info = info.into();
+        }
+        self.record();
+        Ok(())
+    }
+
+    /// This function registers a measurement source for this terminal only, asked before the
+    /// ones of [`source::register_source`](crate::source::register_source), see
+    /// [`Terminal::refresh`].
+    ///
+    /// ## Note:
+    /// - The source is first asked by the next refresh, not by this call.
+    pub fn register_source(&mut self, source: Box<dyn SizeSource>) {
+        self.sources.push(source);
+    }
+
+    /// The cell size the terminal answered to [`Terminal::negotiate`].
+    #[cfg(feature = "vt")]
+    fn negotiated_cell(&self) -> Option<FontSize> {
+        self.negotiation
+            .as_ref()
+            .and_then(|negotiation| negotiation.cell)
+    }
+
+    #[cfg(not(feature = "vt"))]
+    fn negotiated_cell(&self) -> Option<FontSize> {
+        None
+    }
+
+    /// This function asks the terminal everything the crate may need from it in one round
+    /// trip, see [`vt_query::negotiate`](crate::vt_query::negotiate), and keeps the answers.
+    ///
+    /// ## Returns:
+    /// - `Ok(&Negotiation)` with the answers, also returned by [`Terminal::negotiation`]
+    ///   afterwards.
+    /// - `Err(io::Error)` as for [`vt_query::query`](crate::vt_query::query); the previous
+    ///   answers are kept then.
+    ///
+    /// ## Note:
+    /// - The answered cell size replaces the measured one, until [`Terminal::set_font`],
+    ///   which measures it again. Call this again after the user changes the font.
+    #[cfg(feature = "vt")]
+    pub fn negotiate(&mut self, timeout: Duration) -> io::Result<&Negotiation> {
+        let negotiation = vt_query::negotiate(timeout)?;
+        if let Some(cell) = negotiation.cell {
+            self.cell = cell;
+            self.record();
+        }
+        Ok(self.negotiation.insert(negotiation))
+    }
+
+    /// The answers of the last [`Terminal::negotiate`], `None` before.
+    #[cfg(feature = "vt")]
+    pub fn negotiation(&self) -> Option<&Negotiation> {
+        self.negotiation.as_ref()
+    }
+
+    /// Appends the current geometry to the history if it differs from the last entry.
+    fn record(&mut self) {
+        let sample = MetricsSample {
+            at: SystemTime::now(),
+            cells: self.viewport,
+            buffer: self.buffer,
+            font: self.cell,
+            dpi: self.dpi,
+        };
+        let same = |last: &MetricsSample| {
+            MetricsSample {
+                at: last.at,
+                ..sample
+            } == *last
+        };
+        if self.history.back().is_some_and(same) {
+            return;
+        }
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(sample);
+    }
+
+    /// This function changes the font of this console output, then refreshes the snapshot so
+    /// [`Terminal::font_size`] and the pixel sizes follow.
+    ///
+    /// ## Returns:
+    /// - `Err(TerminalError)` as for [`font::set`](crate::font::set), or as for
+    ///   [`Terminal::refresh`] once the font changed.
+    pub fn set_font(
+        &mut self,
+        face: &str,
+        points: u16,
+        weight: FontWeight,
+    ) -> Result<(), TerminalError> {
+        set_font(self.handle, face, points, weight)?;
+        #[cfg(feature = "vt")]
+        if let Some(negotiation) = &mut self.negotiation {
+            negotiation.cell = None;
+        }
+        self.refresh()
+    }
+
+    /// The console output handle.
+    pub fn handle(&self) -> HANDLE {
+        self.handle
+    }
+
+    /// The window the console is shown in: the console window under conhost, the window owning
+    /// it under a pseudo console (Windows Terminal's). Null without one, as over SSH.
+    pub fn window(&self) -> HWND {
+        self.window
+    }
+
+    /// DPI of the window the console is shown in, 0 if it couldn't be read.
+    pub fn dpi(&self) -> u32 {
+        self.dpi
+    }
+
+    /// DPI of [`Terminal::window`] read now, unlike [`Terminal::dpi`] which is the one of the
+    /// last refresh; 0 if it can't be read.
+    pub fn current_dpi(&self) -> u32 {
+        window_dpi(self.window)
+    }
+
+    /// Output code page of the console.
+    pub fn code_page(&self) -> u32 {
+        self.code_page
+    }
+
+    /// Size of a cell in pixels, measured as in [`crate::get_size_of_the_font`].
+    pub fn font_size(&self) -> FontSize {
+        self.cell
+    }
+
+    /// Visible window in cells.
+    pub fn cells(&self) -> TerminalCells {
+        self.viewport
+    }
+
+    /// Screen buffer in cells, scrollback included.
+    pub fn buffer_cells(&self) -> TerminalCells {
+        self.buffer
+    }
+
+    /// Everything the console reported about the screen buffer at the last refresh, cursor
+    /// and maximum window size included.
+    #[cfg(feature = "window")]
+    pub fn buffer_info(&self) -> ScreenBufferInfo {
+        self.info
+    }
+
+    /// This function returns the geometry changes seen by [`Terminal::refresh`], oldest first,
+    /// for bug reports ("did the window resize while it failed?").
+    ///
+    /// ## Returns:
+    /// - The geometry when the terminal was opened, then one sample per refresh where the
+    ///   size, the buffer, the cell size or the DPI changed; only the last 32 are kept.
+    ///
+    /// ## Note:
+    /// - Changes between two refreshes aren't seen: refresh from the callback of an
+    ///   `events::ResizeWatcher` or `events::DpiWatcher` for a complete timeline.
+    pub fn metrics_history(&self) -> impl Iterator<Item = &MetricsSample> {
+        self.history.iter()
+    }
+
+    /// Visible window in pixels.
+    pub fn pixel_size(&self) -> TerminalSize {
+        self.geometry().viewport_px()
+    }
+
+    /// Both sizes at once, see [`ConsoleGeometry`].
+    pub fn geometry(&self) -> ConsoleGeometry {
+        ConsoleGeometry {
+            cell: self.cell,
+            viewport: self.viewport,
+            buffer: self.buffer,
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use windows_sys::Win32::Foundation::{HANDLE, WAIT_OBJECT_0};
+use windows_sys::Win32::System::Console::{
+    PeekConsoleInputW, ReadConsoleInputW, WriteConsoleInputW, WriteConsoleW, ENABLE_ECHO_INPUT,
+    ENABLE_LINE_INPUT, ENABLE_PROCESSED_INPUT, ENABLE_VIRTUAL_TERMINAL_INPUT,
+    ENABLE_VIRTUAL_TERMINAL_PROCESSING, INPUT_RECORD, KEY_EVENT,
+};
+use windows_sys::Win32::System::Threading::WaitForSingleObject;
+
+use crate::console::{console_input, console_output, ModeGuard};
+use crate::environment::passthrough;
+use crate::source::{CellMetrics, SizeSource, SourceContext};
+use crate::{FontSize, TerminalCells, TerminalSize};
+
+/// Time terminals get to answer by default; a local terminal answers in a few milliseconds,
+/// this leaves room for an SSH round trip.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// This function sends a VT query to the terminal and reads its answer from the console input.
+///
+/// ## Returns:
+/// - `Ok(Some(reply))` with the first CSI sequence the terminal sent back, ESC included
+///   (e.g. `"\x1b[6;16;8t"`).
+/// - `Ok(None)` if nothing came back within `timeout`: the terminal doesn't know the query.
+/// - `Err(io::Error)` without a console, or if it can't be written or read.
+///
+/// ## Note:
+/// - The query goes to `CONOUT$`, so it reaches the terminal even when the standard output is
+///   redirected, and through the passthrough of tmux or screen when one is detected.
+/// - VT input is enabled and line input, echo and Ctrl+C processing are disabled while
+///   waiting; the modes are restored afterwards.
+/// - Input that arrives meanwhile is read along with the reply and written back afterwards,
+///   after anything typed later. Nothing else should read the console input at the same time.
+pub fn query(request: &str, timeout: Duration) -> io::Result<Option<String>> {
+    Ok(query_burst(request, timeout, |_| true)?.into_iter().next())
+}
+
+/// Sends several queries at once and reads the replies up to the one `last` accepts, or up
+/// to the deadline; see [`query`] for the modes and the input read meanwhile.
+fn query_burst(
+    requests: &str,
+    timeout: Duration,
+    last: impl Fn(&str) -> bool,
+) -> io::Result<Vec<String>> {
+    let input = console_input().ok_or_else(io::Error::last_os_error)?;
+    let output = console_output().ok_or_else(io::Error::last_os_error)?;
+    let _input_mode = ModeGuard::change(
+        input,
+        ENABLE_VIRTUAL_TERMINAL_INPUT,
+        ENABLE_LINE_INPUT | ENABLE_ECHO_INPUT | ENABLE_PROCESSED_INPUT,
+    )
+    .ok_or_else(io::Error::last_os_error)?;
+    let _output_mode = ModeGuard::change(output, ENABLE_VIRTUAL_TERMINAL_PROCESSING, 0)
+        .ok_or_else(io::Error::last_os_error)?;
+    let units: Vec<u16> = passthrough(requests).encode_utf16().collect();
+    let mut written = 0;
+    if unsafe {
+        WriteConsoleW(
+            output,
+            units.as_ptr(),
+            units.len() as u32,
+            &mut written,
+            std::ptr::null(),
+        )
+    } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    read_replies(input, Instant::now() + timeout, last)
+}
+
+/// Reads key records until a complete CSI sequence that `last` accepts, keeping every other
+/// record aside and writing them back once done.
+///
+/// Returns the sequences read, in order, the last one accepted unless the deadline passed.
+fn read_replies(
+    input: HANDLE,
+    deadline: Instant,
+    last: impl Fn(&str) -> bool,
+) -> io::Result<Vec<String>> {
+    let mut replies = Vec::new();
+    let mut reply = String::new();
+    let mut others: Vec<INPUT_RECORD> = Vec::new();
+    let result = loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero()
+            || unsafe { WaitForSingleObject(input, left.as_millis() as u32) } != WAIT_OBJECT_0
+        {
+            break Ok(());
+        }
+        let mut record: INPUT_RECORD = unsafe { std::mem::zeroed() };
+        let mut count = 0;
+        if unsafe { ReadConsoleInputW(input, &mut record, 1, &mut count) } == 0 {
+            break Err(io::Error::last_os_error());
+        }
+        if count == 0 {
+            continue;
+        }
+        match reply_char(&record) {
+            // The terminal types its reply: key downs carry it, key ups repeat it.
+            Some((_, false)) if !reply.is_empty() => {}
+            Some((c, true)) if !reply.is_empty() || c == '\x1b' => {
+                reply.push(c);
+                if reply.len() > 2 && reply.starts_with("\x1b[") && ('\x40'..='\x7e').contains(&c) {
+                    skip_key_ups(input);
+                    let done = last(&reply);
+                    replies.push(std::mem::take(&mut reply));
+                    if done {
+                        break Ok(());
+                    }
+                }
+                if reply.len() == 2 && c != '[' {
+                    // Alt+key, not a reply.
+                    reply.clear();
+                }
+            }
+            _ => others.push(record),
+        }
+    };
+    if !others.is_empty() {
+        let mut count = 0;
+        unsafe { WriteConsoleInputW(input, others.as_ptr(), others.len() as u32, &mut count) };
+    }
+    result.map(|()| replies)
+}
+
+/// The character of a key record and whether the key went down.
+fn reply_char(record: &INPUT_RECORD) -> Option<(char, bool)> {
+    if record.EventType != KEY_EVENT as u16 {
+        return None;
+    }
+    let key = unsafe { record.Event.KeyEvent };
+    let unit = unsafe { key.uChar.UnicodeChar };
+    char::from_u32(unit as u32)
+        .filter(|_| unit != 0)
+        .map(|c| (c, key.bKeyDown != 0))
+}
+
+/// Reads the key ups of the reply still at the front of the input queue.
+fn skip_key_ups(input: HANDLE) {
+    loop {
+        let mut record: INPUT_RECORD = unsafe { std::mem::zeroed() };
+        let mut count = 0;
+        if unsafe { PeekConsoleInputW(input, &mut record, 1, &mut count) } == 0
+            || count == 0
+            || !matches!(reply_char(&record), Some((_, false)))
+        {
+            return;
+        }
+        unsafe { ReadConsoleInputW(input, &mut record, 1, &mut count) };
+    }
+}
+
+/// Parses a `CSI code ; height ; width t` reply.
+fn size_reply(reply: &str, code: &str) -> Option<(i32, i32)> {
+    let mut fields = reply.strip_prefix("\x1b[")?.strip_suffix('t')?.split(';');
+    if fields.next()? != code {
+        return None;
+    }
+    let height = fields.next()?.parse().ok()?;
+    let width = fields.next()?.parse().ok()?;
+    (height > 0 && width > 0).then_some((width, height))
+}
+
+/// This function asks the terminal for the size of a cell in pixels (`CSI 16 t`).
+///
+/// ## Returns:
+/// - `Ok(Some(FontSize))` from the `CSI 6 ; height ; width t` reply.
+/// - `Ok(None)` if the terminal doesn't answer within `timeout`, or answers with zeros (some
+///   do when they don't know).
+/// - `Err(io::Error)` as for [`query`].
+///
+/// ## Note:
+/// - Windows Terminal answers with the exact cell size, DPI scaling included, which the
+///   console font and the DPI tables only approximate.
+pub fn cell_size(timeout: Duration) -> io::Result<Option<FontSize>> {
+    Ok(query("\x1b[16t", timeout)?
+        .and_then(|reply| size_reply(&reply, "6"))
+        .map(|(width, height)| FontSize { width, height }))
+}
+
+/// This function asks the terminal for the size of its text area in pixels (`CSI 14 t`).
+///
+/// ## Returns:
+/// - `Ok(Some(TerminalSize))` from the `CSI 4 ; height ; width t` reply.
+/// - `Ok(None)` and `Err(io::Error)` as for [`cell_size`].
+///
+/// ## Note:
+/// - [`crate::query_window_pixels_vt`] falls back to the local computation instead.
+pub fn window_size_px(timeout: Duration) -> io::Result<Option<TerminalSize>> {
+    Ok(query("\x1b[14t", timeout)?
+        .and_then(|reply| size_reply(&reply, "4"))
+        .map(|(width, height)| TerminalSize { width, height }))
+}
+
+/// Struct to hold what the terminal answered to [`negotiate`], `None` for what it didn't.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Negotiation {
+    pub cell: Option<FontSize>,       // Cell size in pixels (`CSI 16 t`)
+    pub pixels: Option<TerminalSize>, // Text area in pixels (`CSI 14 t`)
+    pub cells: Option<TerminalCells>, // Text area in cells (`CSI 18 t`)
+    pub attributes: Vec<u16>,         // Primary device attributes (`CSI c`), empty if none
+}
+
+impl Negotiation {
+    /// Whether the terminal answered at all, i.e. understands VT queries.
+    pub fn answered(&self) -> bool {
+        !self.attributes.is_empty()
+    }
+
+    /// Whether the terminal reports sixel graphics (attribute 4).
+    pub fn sixel(&self) -> bool {
+        self.attributes.contains(&4)
+    }
+
+    /// Fills in the fields from the replies, in any order.
+    fn from_replies(replies: &[String]) -> Negotiation {
+        let mut negotiation = Negotiation::default();
+        for reply in replies {
+            if let Some((width, height)) = size_reply(reply, "6") {
+                negotiation.cell = Some(FontSize { width, height });
+            } else if let Some((width, height)) = size_reply(reply, "4") {
+                negotiation.pixels = Some(TerminalSize { width, height });
+            } else if let Some((columns, rows)) = size_reply(reply, "8") {
+                negotiation.cells = Some(TerminalCells { columns, rows });
+            } else if let Some(attributes) = device_attributes(reply) {
+                negotiation.attributes = attributes;
+            }
+        }
+        negotiation
+    }
+}
+
+/// Parses a `CSI ? attributes c` reply.
+fn device_attributes(reply: &str) -> Option<Vec<u16>> {
+    let params = reply.strip_prefix("\x1b[?")?.strip_suffix('c')?;
+    params.split(';').map(|param| param.parse().ok()).collect()
+}
+
+/// This function asks the terminal everything the crate may need from it in one burst: the
+/// cell size, the text area in pixels and in cells, and its device attributes.
+///
+/// ## Returns:
+/// - `Ok(Negotiation)` with every answer that came back within `timeout`.
+/// - `Err(io::Error)` as for [`query`].
+///
+/// ## Note:
+/// - The queries are written together and the device attributes asked last: terminals
+///   answer in order and all of them answer `CSI c`, so its reply ends the wait instead of
+///   the timeout. One round trip instead of one per query, which is what counts over SSH.
+/// - A terminal that doesn't understand VT at all costs the whole timeout once.
+pub fn negotiate(timeout: Duration) -> io::Result<Negotiation> {
+    let replies = query_burst("\x1b[16t\x1b[14t\x1b[18t\x1b[c", timeout, |reply| {
+        device_attributes(reply).is_some()
+    })?;
+    Ok(Negotiation::from_replies(&replies))
+}
+
+/// Struct to hold a [`SizeSource`] asking the terminal with `CSI 16 t`, for
+/// [`register_source`](crate::source::register_source).
+///
+/// Registering it makes [`get_size_of_the_font`](crate::get_size_of_the_font) and everything
+/// built on it exact under terminals that answer, at the cost of a round trip per
+/// measurement; the built-in detection is used when they don't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VtCellSize {
+    timeout: Duration,
+}
+
+impl Default for VtCellSize {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VtCellSize {
+    /// Creates a source waiting [`DEFAULT_TIMEOUT`] for the answer.
+    pub fn new() -> Self {
+        VtCellSize {
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Sets how long to wait for the answer.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl SizeSource for VtCellSize {
+    fn measure(&self, _context: &SourceContext) -> Option<CellMetrics> {
+        cell_size(self.timeout)
+            .ok()
+            .flatten()
+            .map(CellMetrics::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_replies() {
+        assert_eq!(size_reply("\x1b[6;16;8t", "6"), Some((8, 16)));
+        assert_eq!(size_reply("\x1b[4;600;800t", "4"), Some((800, 600)));
+        assert_eq!(size_reply("\x1b[6;16;8t", "4"), None);
+        assert_eq!(size_reply("\x1b[6;0;0t", "6"), None);
+        assert_eq!(size_reply("\x1b[6;16t", "6"), None);
+        assert_eq!(size_reply("\x1b[?62;4c", "6"), None);
+    }
+
+    #[test]
+    fn negotiation_replies() {
+        let replies: Vec<String> = ["\x1b[6;20;10t", "\x1b[8;30;120t", "\x1b[?62;4;22c"]
+            .iter()
+            .map(|reply| reply.to_string())
+            .collect();
+        let negotiation = Negotiation::from_replies(&replies);
+        assert_eq!(
+            negotiation.cell,
+            Some(FontSize {
+                width: 10,
+                height: 20
+            })
+        );
+        assert_eq!(negotiation.pixels, None);
+        assert_eq!(
+            negotiation.cells,
+            Some(TerminalCells {
+                columns: 120,
+                rows: 30
+            })
+        );
+        assert!(negotiation.answered() && negotiation.sixel());
+        assert!(!Negotiation::from_replies(&[]).answered());
+        assert_eq!(device_attributes("\x1b[?1;2c"), Some(vec![1, 2]));
+        assert_eq!(device_attributes("\x1b[>0;10;1c"), None);
+    }
+}
@@ -0,0 +1,118 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::ownership::may_change_modes;
+use crate::reset::ConsoleState;
+
+/// Struct to hold what the watchdog thread shares with the application.
+#[derive(Debug)]
+struct State {
+    last_beat: Instant,
+    fired: bool, // The console was restored and no heartbeat came since
+    stop: bool,
+}
+
+/// Struct to hold a watchdog thread restoring the console when the application stops sending
+/// heartbeats, e.g. a fullscreen app hung with the alternate screen and mouse reporting on.
+///
+/// The console state is captured when the watchdog starts. When no [`Watchdog::heartbeat`]
+/// arrives for the timeout, the watchdog leaves the alternate screen, undoes the VT modes, puts
+/// the captured console modes, attributes and code pages back, and prints the notice on the
+/// standard error. A later heartbeat re-arms it. Dropping the watchdog stops the thread.
+#[derive(Debug)]
+pub struct Watchdog {
+    shared: Arc<(Mutex<State>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    /// This function starts a watchdog with a default notice.
+    pub fn start(timeout: Duration) -> Self {
+        Self::with_notice(
+            timeout,
+            "The application stopped responding; the console was restored.",
+        )
+    }
+
+    /// This function starts a watchdog printing `notice` when it fires.
+    pub fn with_notice(timeout: Duration, notice: &str) -> Self {
+        let shared = Arc::new((
+            Mutex::new(State {
+                last_beat: Instant::now(),
+                fired: false,
+                stop: false,
+            }),
+            Condvar::new(),
+        ));
+        let saved = ConsoleState::capture();
+        let notice = notice.to_string();
+        let thread = {
+            let shared = Arc::clone(&shared);
+            thread::Builder::new()
+                .name("win-term watchdog".to_string())
+                .spawn(move || watch(&shared, timeout, saved, &notice))
+                .ok()
+        };
+        Watchdog { shared, thread }
+    }
+
+    /// This function tells the watchdog the application is alive.
+    pub fn heartbeat(&self) {
+        let mut state = self.shared.0.lock().unwrap_or_else(|e| e.into_inner());
+        state.last_beat = Instant::now();
+        state.fired = false;
+    }
+
+    /// Whether the watchdog restored the console since the last heartbeat.
+    pub fn has_fired(&self) -> bool {
+        self.shared
+            .0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .fired
+    }
+}
+
+fn watch(shared: &(Mutex<State>, Condvar), timeout: Duration, saved: ConsoleState, notice: &str) {
+    let (lock, wake) = shared;
+    let mut state = lock.lock().unwrap_or_else(|e| e.into_inner());
+    loop {
+        if state.stop {
+            return;
+        }
+        let deadline = state.last_beat + timeout;
+        let now = Instant::now();
+        if state.fired || now < deadline {
+            // Once fired, only a heartbeat (or stopping) matters; poll for it at the timeout.
+            let wait = if state.fired { timeout } else { deadline - now };
+            state = wake
+                .wait_timeout(state, wait)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+            continue;
+        }
+        state.fired = true;
+        drop(state);
+        // An observer leaves the console to its owner, see `ownership::claim`.
+        if may_change_modes() {
+            saved.restore();
+        }
+        let mut err = io::stderr();
+        let _ = writeln!(err, "{}", notice);
+        let _ = err.flush();
+        state = lock.lock().unwrap_or_else(|e| e.into_inner());
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        let (lock, wake) = &*self.shared;
+        lock.lock().unwrap_or_else(|e| e.into_inner()).stop = true;
+        wake.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
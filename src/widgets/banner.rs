@@ -0,0 +1,200 @@
+use super::available_columns;
+
+/// Enum to represent the embedded banner fonts, both drawn from the same 5-pixel-high glyphs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BannerFont {
+    #[default]
+    Block, // One `█` per pixel, 5 rows high
+    Compact, // Two pixels per cell with half blocks, 3 rows high
+}
+
+impl BannerFont {
+    fn rows(self) -> usize {
+        match self {
+            BannerFont::Block => GLYPH_HEIGHT,
+            BannerFont::Compact => GLYPH_HEIGHT.div_ceil(2),
+        }
+    }
+}
+
+const GLYPH_HEIGHT: usize = 5;
+
+/// Glyph bitmaps, `#` for a set pixel. Lowercase letters use the uppercase glyphs and anything
+/// missing is drawn as `?`.
+const GLYPHS: &[(char, [&str; GLYPH_HEIGHT])] = &[
+    ('A', [" ## ", "#  #", "####", "#  #", "#  #"]),
+    ('B', ["### ", "#  #", "### ", "#  #", "### "]),
+    ('C', [" ###", "#   ", "#   ", "#   ", " ###"]),
+    ('D', ["### ", "#  #", "#  #", "#  #", "### "]),
+    ('E', ["####", "#   ", "### ", "#   ", "####"]),
+    ('F', ["####", "#   ", "### ", "#   ", "#   "]),
+    ('G', [" ###", "#   ", "# ##", "#  #", " ###"]),
+    ('H', ["#  #", "#  #", "####", "#  #", "#  #"]),
+    ('I', ["###", " # ", " # ", " # ", "###"]),
+    ('J', ["  ##", "   #", "   #", "#  #", " ## "]),
+    ('K', ["#  #", "# # ", "##  ", "# # ", "#  #"]),
+    ('L', ["#   ", "#   ", "#   ", "#   ", "####"]),
+    ('M', ["#   #", "## ##", "# # #", "#   #", "#   #"]),
+    ('N', ["#   #", "##  #", "# # #", "#  ##", "#   #"]),
+    ('O', [" ## ", "#  #", "#  #", "#  #", " ## "]),
+    ('P', ["### ", "#  #", "### ", "#   ", "#   "]),
+    ('Q', [" ## ", "#  #", "#  #", "# ##", " ###"]),
+    ('R', ["### ", "#  #", "### ", "# # ", "#  #"]),
+    ('S', [" ###", "#   ", " ## ", "   #", "### "]),
+    ('T', ["#####", "  #  ", "  #  ", "  #  ", "  #  "]),
+    ('U', ["#  #", "#  #", "#  #", "#  #", " ## "]),
+    ('V', ["#   #", "#   #", "#   #", " # # ", "  #  "]),
+    ('W', ["#   #", "#   #", "# # #", "## ##", "#   #"]),
+    ('X', ["#   #", " # # ", "  #  ", " # # ", "#   #"]),
+    ('Y', ["#   #", " # # ", "  #  ", "  #  ", "  #  "]),
+    ('Z', ["####", "   #", " ## ", "#   ", "####"]),
+    ('0', [" ## ", "# ##", "## #", "#  #", " ## "]),
+    ('1', [" # ", "## ", " # ", " # ", "###"]),
+    ('2', ["### ", "   #", " ## ", "#   ", "####"]),
+    ('3', ["### ", "   #", " ## ", "   #", "### "]),
+    ('4', ["#  #", "#  #", "####", "   #", "   #"]),
+    ('5', ["####", "#   ", "### ", "   #", "### "]),
+    ('6', [" ## ", "#   ", "### ", "#  #", " ## "]),
+    ('7', ["####", "   #", "  # ", " #  ", " #  "]),
+    ('8', [" ## ", "#  #", " ## ", "#  #", " ## "]),
+    ('9', [" ## ", "#  #", " ###", "   #", " ## "]),
+    (' ', ["  ", "  ", "  ", "  ", "  "]),
+    ('!', ["#", "#", "#", " ", "#"]),
+    ('?', ["### ", "   #", " ## ", "    ", " #  "]),
+    ('.', [" ", " ", " ", " ", "#"]),
+    (',', ["  ", "  ", "  ", " #", "# "]),
+    (':', [" ", "#", " ", "#", " "]),
+    (';', ["  ", " #", "  ", " #", "# "]),
+    ('\'', ["#", "#", " ", " ", " "]),
+    ('"', ["# #", "# #", "   ", "   ", "   "]),
+    ('-', ["   ", "   ", "###", "   ", "   "]),
+    ('+', ["   ", " # ", "###", " # ", "   "]),
+    ('=', ["   ", "###", "   ", "###", "   "]),
+    ('*', ["   ", "# #", " # ", "# #", "   "]),
+    ('_', ["    ", "    ", "    ", "    ", "####"]),
+    ('/', ["    #", "   # ", "  #  ", " #   ", "#    "]),
+    ('%', ["#   #", "   # ", "  #  ", " #   ", "#   #"]),
+    ('(', [" #", "# ", "# ", "# ", " #"]),
+    (')', ["# ", " #", " #", " #", "# "]),
+    ('<', ["  #", " # ", "#  ", " # ", "  #"]),
+    ('>', ["#  ", " # ", "  #", " # ", "#  "]),
+];
+
+fn glyph(c: char) -> &'static [&'static str; GLYPH_HEIGHT] {
+    let c = c.to_ascii_uppercase();
+    let find = |c: char| GLYPHS.iter().find(|(g, _)| *g == c).map(|(_, rows)| rows);
+    find(c).or_else(|| find('?')).expect("`?` has a glyph")
+}
+
+/// Width in cells of `text` drawn in a banner font, a blank column separating glyphs.
+fn text_width(text: &str) -> usize {
+    let glyphs: usize = text.chars().map(|c| glyph(c)[0].len()).sum();
+    glyphs + text.chars().count().saturating_sub(1)
+}
+
+/// Draws one line of text, without fitting it.
+fn draw(text: &str, font: BannerFont) -> Vec<String> {
+    let mut pixels = vec![String::new(); GLYPH_HEIGHT];
+    for (i, c) in text.chars().enumerate() {
+        for (row, bits) in pixels.iter_mut().zip(glyph(c)) {
+            if i > 0 {
+                row.push(' ');
+            }
+            row.push_str(bits);
+        }
+    }
+    match font {
+        BannerFont::Block => pixels
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .map(|p| if p == '#' { '█' } else { ' ' })
+                    .collect()
+            })
+            .collect(),
+        BannerFont::Compact => pixels
+            .chunks(2)
+            .map(|pair| {
+                let lower = pair.get(1).map(|row| row.as_bytes());
+                pair[0]
+                    .bytes()
+                    .enumerate()
+                    .map(|(x, upper)| {
+                        let lower = lower.is_some_and(|row| row[x] == b'#');
+                        match (upper == b'#', lower) {
+                            (true, true) => '█',
+                            (true, false) => '▀',
+                            (false, true) => '▄',
+                            (false, false) => ' ',
+                        }
+                    })
+                    .collect()
+            })
+            .collect(),
+    }
+}
+
+/// This function draws `text` as large banner letters fit to the terminal width, e.g. for a
+/// CLI splash screen.
+///
+/// See [`banner_with_width`] for details; the width is the visible window's, or 80 columns.
+pub fn banner(text: &str, font: BannerFont) -> Vec<String> {
+    banner_with_width(text, font, available_columns().unwrap_or(80))
+}
+
+/// This function draws `text` as large banner letters in at most `width` columns.
+///
+/// ## Returns:
+/// - The rows of the banner, trailing spaces included so every row of a line has the same
+///   width.
+///
+/// ## Note:
+/// - When the text doesn't fit in `font`, it is drawn in [`BannerFont::Compact`] instead, and
+///   when it doesn't fit on one line either, it is wrapped at spaces into several banner lines
+///   separated by an empty row. Words wider than `width` on their own are split.
+/// - Lowercase letters are drawn as uppercase, and characters without a glyph as `?`.
+pub fn banner_with_width(text: &str, font: BannerFont, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let text = text.trim();
+    if text_width(text) <= width {
+        return draw(text, font);
+    }
+    // Both fonts are as wide, Compact only saves height, so it is the one used for the
+    // several lines wrapping needs.
+    let font = BannerFont::Compact;
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        let mut word = word.to_string();
+        while text_width(&word) > width {
+            let mut head = word.chars().next().map_or(0, char::len_utf8);
+            for (i, _) in word.char_indices().skip(1) {
+                if text_width(&word[..i]) > width {
+                    break;
+                }
+                head = i;
+            }
+            flush_word(&mut lines, &word[..head], width);
+            word = word[head..].to_string();
+        }
+        flush_word(&mut lines, &word, width);
+    }
+    let mut rows = Vec::with_capacity(lines.len() * (font.rows() + 1));
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            rows.push(String::new());
+        }
+        rows.extend(draw(line, font));
+    }
+    rows
+}
+
+/// Appends a word to the last line if it fits there, or starts a new line with it.
+fn flush_word(lines: &mut Vec<String>, word: &str, width: usize) {
+    match lines.last_mut() {
+        Some(line) if text_width(&format!("{} {}", line, word)) <= width => {
+            line.push(' ');
+            line.push_str(word);
+        }
+        _ => lines.push(word.to_string()),
+    }
+}
@@ -0,0 +1,589 @@
+use super::available_columns;
+use crate::measure::cells;
+use crate::style;
+
+/// Narrowest side (in cells, gutter included) for which side-by-side mode is still readable.
+const MIN_SIDE_COLUMNS: usize = 40;
+
+/// Enum to represent how a [`DiffView`] lays out changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiffMode {
+    #[default]
+    Auto, // Side-by-side when the width allows it, unified otherwise
+    Unified,    // One column, `-`/`+` prefixed lines
+    SideBySide, // Old text on the left, new text on the right
+}
+
+/// Enum to represent one line of a diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Hunk(String),                  // Hunk header (`@@ -1,3 +1,4 @@ ...`)
+    Context(usize, usize, String), // Unchanged line, with its old and new line numbers
+    Removed(usize, String),        // Line only in the old text
+    Added(usize, String),          // Line only in the new text
+}
+
+/// Struct to hold a colorized diff that wraps to the terminal width.
+#[derive(Debug, Clone)]
+pub struct DiffView {
+    lines: Vec<DiffLine>,
+    mode: DiffMode,
+    width: Option<usize>,
+}
+
+impl DiffView {
+    /// This function diffs two texts line by line (Myers' algorithm), keeping `context`
+    /// unchanged lines around every change and grouping the rest into hunks.
+    pub fn from_texts(old: &str, new: &str, context: usize) -> Self {
+        let a: Vec<&str> = old.lines().collect();
+        let b: Vec<&str> = new.lines().collect();
+        let ops = myers(&a, &b);
+
+        let changed: Vec<bool> = ops.iter().map(|op| !matches!(op, Op::Equal(..))).collect();
+        let keep = |i: usize| {
+            let start = i.saturating_sub(context);
+            let end = (i + context + 1).min(ops.len());
+            changed[start..end].iter().any(|&c| c)
+        };
+
+        let mut lines = Vec::new();
+        let mut i = 0;
+        while i < ops.len() {
+            if !keep(i) {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < ops.len() && keep(i) {
+                i += 1;
+            }
+            let hunk = &ops[start..i];
+            let (old_start, new_start) = hunk[0].position();
+            let old_len = hunk
+                .iter()
+                .filter(|op| !matches!(op, Op::Insert(..)))
+                .count();
+            let new_len = hunk
+                .iter()
+                .filter(|op| !matches!(op, Op::Delete(..)))
+                .count();
+            lines.push(DiffLine::Hunk(format!(
+                "@@ -{},{} +{},{} @@",
+                old_start + 1,
+                old_len,
+                new_start + 1,
+                new_len
+            )));
+            for op in hunk {
+                lines.push(match *op {
+                    Op::Equal(x, y) => DiffLine::Context(x + 1, y + 1, a[x].to_string()),
+                    Op::Delete(x, _) => DiffLine::Removed(x + 1, a[x].to_string()),
+                    Op::Insert(_, y) => DiffLine::Added(y + 1, b[y].to_string()),
+                });
+            }
+        }
+        DiffView::from_lines(lines)
+    }
+
+    /// This function parses a unified patch (as produced by `git diff`). File headers and
+    /// anything outside hunks are ignored.
+    pub fn from_patch(patch: &str) -> Self {
+        let mut lines = Vec::new();
+        let (mut old, mut new) = (0, 0);
+        let mut in_hunk = false;
+        for line in patch.lines() {
+            if let Some(header) = line.strip_prefix("@@ ") {
+                let mut ranges = header.split_whitespace();
+                let start = |range: Option<&str>, sign: char| {
+                    range
+                        .and_then(|r| r.strip_prefix(sign))
+                        .and_then(|r| r.split(',').next())
+                        .and_then(|n| n.parse::<usize>().ok())
+                        .unwrap_or(1)
+                };
+                old = start(ranges.next(), '-');
+                new = start(ranges.next(), '+');
+                in_hunk = true;
+                lines.push(DiffLine::Hunk(line.to_string()));
+                continue;
+            }
+            if !in_hunk {
+                continue;
+            }
+            match line.chars().next() {
+                Some('-') => {
+                    lines.push(DiffLine::Removed(old, line[1..].to_string()));
+                    old += 1;
+                }
+                Some('+') => {
+                    lines.push(DiffLine::Added(new, line[1..].to_string()));
+                    new += 1;
+                }
+                Some(' ') | None => {
+                    let text = line.get(1..).unwrap_or("").to_string();
+                    lines.push(DiffLine::Context(old, new, text));
+                    old += 1;
+                    new += 1;
+                }
+                // `\ No newline at end of file` and the next file's headers.
+                _ => in_hunk = line.starts_with('\\'),
+            }
+        }
+        DiffView::from_lines(lines)
+    }
+
+    pub fn from_lines(lines: Vec<DiffLine>) -> Self {
+        DiffView {
+            lines,
+            mode: DiffMode::Auto,
+            width: None,
+        }
+    }
+
+    pub fn mode(mut self, mode: DiffMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets the width in cells to render to. Without it, the terminal width is used
+    /// (80 columns if it can't be measured).
+    pub fn width(mut self, columns: usize) -> Self {
+        self.width = Some(columns);
+        self
+    }
+
+    pub fn lines(&self) -> &[DiffLine] {
+        &self.lines
+    }
+
+    /// Returns the mode actually used for the current width.
+    pub fn effective_mode(&self) -> DiffMode {
+        match self.mode {
+            DiffMode::Auto if self.columns() > 2 * MIN_SIDE_COLUMNS => DiffMode::SideBySide,
+            DiffMode::Auto => DiffMode::Unified,
+            mode => mode,
+        }
+    }
+
+    fn columns(&self) -> usize {
+        self.width.or_else(available_columns).unwrap_or(80)
+    }
+
+    /// This function renders the diff into terminal rows.
+    ///
+    /// ## Returns:
+    /// - One string per row, never wider than the width. Long lines wrap onto continuation
+    ///   rows, removed lines are red, added lines green and hunk headers cyan.
+    pub fn render(&self) -> Vec<String> {
+        let gutter = self.number_width();
+        match self.effective_mode() {
+            DiffMode::SideBySide => self.render_side_by_side(gutter),
+            _ => self.render_unified(gutter),
+        }
+    }
+
+    fn number_width(&self) -> usize {
+        let max = self
+            .lines
+            .iter()
+            .map(|line| match line {
+                DiffLine::Hunk(_) => 0,
+                DiffLine::Context(old, new, _) => *old.max(new),
+                DiffLine::Removed(n, _) | DiffLine::Added(n, _) => *n,
+            })
+            .max()
+            .unwrap_or(0);
+        max.to_string().len()
+    }
+
+    fn render_unified(&self, gutter: usize) -> Vec<String> {
+        let columns = self.columns();
+        // "1234 1234 - text"
+        let text_width = columns.saturating_sub(2 * gutter + 4).max(1);
+        let mut rows = Vec::new();
+        for line in &self.lines {
+            let (old, new, sign, color, text) = match line {
+                DiffLine::Hunk(header) => {
+                    for chunk in wrap(header, columns.max(1)) {
+                        rows.push(format!(
+                            "{}{}{}",
+                            paint("\x1b[36m"),
+                            chunk,
+                            paint("\x1b[0m")
+                        ));
+                    }
+                    continue;
+                }
+                DiffLine::Context(old, new, text) => (Some(*old), Some(*new), ' ', "", text),
+                DiffLine::Removed(old, text) => (Some(*old), None, '-', paint("\x1b[31m"), text),
+                DiffLine::Added(new, text) => (None, Some(*new), '+', paint("\x1b[32m"), text),
+            };
+            for (i, chunk) in wrap(text, text_width).into_iter().enumerate() {
+                let (old, new) = if i == 0 { (old, new) } else { (None, None) };
+                rows.push(format!(
+                    "{}{} {} {} {}{}",
+                    color,
+                    number(old, gutter),
+                    number(new, gutter),
+                    if i == 0 { sign } else { ' ' },
+                    chunk,
+                    if color.is_empty() { "" } else { "\x1b[0m" }
+                ));
+            }
+        }
+        rows
+    }
+
+    fn render_side_by_side(&self, gutter: usize) -> Vec<String> {
+        let columns = self.columns();
+        let side = (columns.saturating_sub(1) / 2).max(1);
+        // "1234 text" on each side, separated by "│".
+        let text_width = side.saturating_sub(gutter + 1).max(1);
+        let mut rows = Vec::new();
+        let mut i = 0;
+        while i < self.lines.len() {
+            match &self.lines[i] {
+                DiffLine::Hunk(header) => {
+                    for chunk in wrap(header, columns.max(1)) {
+                        rows.push(format!(
+                            "{}{}{}",
+                            paint("\x1b[36m"),
+                            chunk,
+                            paint("\x1b[0m")
+                        ));
+                    }
+                    i += 1;
+                }
+                DiffLine::Context(old, new, text) => {
+                    let left = Some((*old, text.as_str(), ""));
+                    let right = Some((*new, text.as_str(), ""));
+                    push_pair(&mut rows, left, right, gutter, text_width);
+                    i += 1;
+                }
+                _ => {
+                    // Pair a run of removals with the run of additions that follows it.
+                    let start = i;
+                    while matches!(self.lines.get(i), Some(DiffLine::Removed(..))) {
+                        i += 1;
+                    }
+                    let removed = &self.lines[start..i];
+                    let added_start = i;
+                    while matches!(self.lines.get(i), Some(DiffLine::Added(..))) {
+                        i += 1;
+                    }
+                    let added = &self.lines[added_start..i];
+                    for k in 0..removed.len().max(added.len()) {
+                        let left = match removed.get(k) {
+                            Some(DiffLine::Removed(n, text)) => {
+                                Some((*n, text.as_str(), paint("\x1b[31m")))
+                            }
+                            _ => None,
+                        };
+                        let right = match added.get(k) {
+                            Some(DiffLine::Added(n, text)) => {
+                                Some((*n, text.as_str(), paint("\x1b[32m")))
+                            }
+                            _ => None,
+                        };
+                        push_pair(&mut rows, left, right, gutter, text_width);
+                    }
+                }
+            }
+        }
+        rows
+    }
+}
+
+/// The color sequence, or nothing when colors are disabled.
+fn paint(sgr: &'static str) -> &'static str {
+    if style::colors_enabled() {
+        sgr
+    } else {
+        ""
+    }
+}
+
+type Side<'a> = Option<(usize, &'a str, &'static str)>;
+
+fn push_pair(rows: &mut Vec<String>, left: Side, right: Side, gutter: usize, width: usize) {
+    let wrapped = |side: Side| {
+        side.map(|(_, text, _)| wrap(text, width))
+            .unwrap_or_default()
+    };
+    let (left_chunks, right_chunks) = (wrapped(left), wrapped(right));
+    for k in 0..left_chunks.len().max(right_chunks.len()).max(1) {
+        let cell = |side: Side, chunks: &[String]| {
+            let (n, color) = match side {
+                Some((n, _, color)) => (Some(n).filter(|_| k == 0), color),
+                None => (None, ""),
+            };
+            let chunk = chunks.get(k).map(String::as_str).unwrap_or("");
+            let padding = width.saturating_sub(cells(chunk));
+            let reset = if color.is_empty() { "" } else { "\x1b[0m" };
+            format!(
+                "{}{} {}{}{}",
+                color,
+                number(n, gutter),
+                chunk,
+                " ".repeat(padding),
+                reset
+            )
+        };
+        rows.push(format!(
+            "{}│{}",
+            cell(left, &left_chunks),
+            cell(right, &right_chunks)
+        ));
+    }
+}
+
+fn number(n: Option<usize>, width: usize) -> String {
+    match n {
+        Some(n) => format!("{:>width$}", n, width = width),
+        None => " ".repeat(width),
+    }
+}
+
+/// Splits `text` into chunks of at most `width` cells (tabs expanded to 4 spaces). A wide
+/// character never straddles two chunks; one wider than `width` gets a chunk of its own.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut chunks = vec![String::new()];
+    let mut used = 0;
+    let mut buffer = [0; 4];
+    for c in text.replace('\t', "    ").chars() {
+        let c_width = cells(c.encode_utf8(&mut buffer));
+        if used + c_width > width && used > 0 {
+            chunks.push(String::new());
+            used = 0;
+        }
+        chunks.last_mut().unwrap().push(c);
+        used += c_width;
+    }
+    chunks
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Equal(usize, usize),  // Indices in the old and new texts
+    Delete(usize, usize), // Index in the old text, and where the new text stands at that point
+    Insert(usize, usize), // Where the old text stands at that point, and index in the new text
+}
+
+impl Op {
+    /// Position of the operation in both texts, used for hunk headers.
+    fn position(self) -> (usize, usize) {
+        match self {
+            Op::Equal(x, y) | Op::Delete(x, y) | Op::Insert(x, y) => (x, y),
+        }
+    }
+}
+
+/// Myers' O(ND) shortest edit script between two sequences of lines, in linear space.
+fn myers(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let mut ops = Vec::with_capacity(a.len().max(b.len()));
+    diff_range(a, b, 0, 0, &mut ops);
+    ops
+}
+
+/// Appends the edit script of `a` to `b`, which start at `x0` and `y0` in the whole texts.
+///
+/// Common ends are taken off first; what remains is split on the middle snake of Myers'
+/// paper, so each half needs at most half the edits and only two diagonals vectors are live.
+fn diff_range(a: &[&str], b: &[&str], x0: usize, y0: usize, ops: &mut Vec<Op>) {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    ops.extend((0..prefix).map(|i| Op::Equal(x0 + i, y0 + i)));
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    let (x0, y0) = (x0 + prefix, y0 + prefix);
+    let suffix = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+
+    if a.is_empty() {
+        ops.extend((0..b.len()).map(|j| Op::Insert(x0, y0 + j)));
+    } else if b.is_empty() {
+        ops.extend((0..a.len()).map(|i| Op::Delete(x0 + i, y0)));
+    } else {
+        // Both ends differ and neither side is empty, so at least two edits remain and both
+        // halves are smaller.
+        let (x, y, u, v) = middle_snake(a, b);
+        diff_range(&a[..x], &b[..y], x0, y0, ops);
+        ops.extend((0..u - x).map(|i| Op::Equal(x0 + x + i, y0 + y + i)));
+        diff_range(&a[u..], &b[v..], x0 + u, y0 + v, ops);
+    }
+
+    let (x0, y0) = (x0 + a.len(), y0 + b.len());
+    ops.extend((0..suffix).map(|i| Op::Equal(x0 + i, y0 + i)));
+}
+
+/// Start and end `(x, y, u, v)` of the snake in the middle of a shortest edit script, found
+/// by searching from both ends at once.
+fn middle_snake(a: &[&str], b: &[&str]) -> (usize, usize, usize, usize) {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2;
+    let offset = max + 1;
+    // Furthest x reached on each diagonal, from the start and from the end.
+    let mut forward = vec![0isize; 2 * offset as usize + 1];
+    let mut backward = vec![0isize; 2 * offset as usize + 1];
+    let at = |k: isize| (k + offset) as usize;
+
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                forward[at(k + 1)]
+            } else {
+                forward[at(k - 1)] + 1
+            };
+            let (start_x, start_y) = (x, x - k);
+            while x < n && x - k < m && a[x as usize] == b[(x - k) as usize] {
+                x += 1;
+            }
+            forward[at(k)] = x;
+            // The backward search has done `d - 1` rounds.
+            let reverse = delta - k;
+            if odd && reverse.abs() < d && x + backward[at(reverse)] >= n {
+                let (x, y) = (x as usize, (x - k) as usize);
+                return (start_x as usize, start_y as usize, x, y);
+            }
+        }
+        for k in (-d..=d).step_by(2) {
+            // `x` counts from the end of `a` here.
+            let mut x = if k == -d || (k != d && backward[at(k - 1)] < backward[at(k + 1)]) {
+                backward[at(k + 1)]
+            } else {
+                backward[at(k - 1)] + 1
+            };
+            let (end_x, end_y) = (x, x - k);
+            while x < n && x - k < m && a[(n - 1 - x) as usize] == b[(m - 1 - (x - k)) as usize] {
+                x += 1;
+            }
+            backward[at(k)] = x;
+            let forward_k = delta - k;
+            if !odd && forward_k.abs() <= d && forward[at(forward_k)] + x >= n {
+                let (x, y) = ((n - x) as usize, (m - (x - k)) as usize);
+                return (x, y, (n - end_x) as usize, (m - end_y) as usize);
+            }
+        }
+    }
+    unreachable!("the searches meet by round (n + m + 1) / 2")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rebuilds the new text from the old one and the script, checking every position.
+    fn apply(a: &[&str], b: &[&str], ops: &[Op]) -> usize {
+        let (mut x, mut y, mut edits) = (0, 0, 0);
+        for op in ops {
+            match *op {
+                Op::Equal(i, j) => {
+                    assert_eq!((i, j), (x, y));
+                    assert_eq!(a[i], b[j]);
+                    (x, y) = (x + 1, y + 1);
+                }
+                Op::Delete(i, j) => {
+                    assert_eq!((i, j), (x, y));
+                    x += 1;
+                    edits += 1;
+                }
+                Op::Insert(i, j) => {
+                    assert_eq!((i, j), (x, y));
+                    y += 1;
+                    edits += 1;
+                }
+            }
+        }
+        assert_eq!((x, y), (a.len(), b.len()));
+        edits
+    }
+
+    /// Fewest insertions and deletions, from the longest common subsequence.
+    fn shortest(a: &[&str], b: &[&str]) -> usize {
+        let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i][j] = match a[i] == b[j] {
+                    true => lcs[i + 1][j + 1] + 1,
+                    false => lcs[i + 1][j].max(lcs[i][j + 1]),
+                };
+            }
+        }
+        a.len() + b.len() - 2 * lcs[0][0]
+    }
+
+    #[test]
+    fn scripts_are_shortest() {
+        let words = ["a", "b", "c", "d"];
+        let mut seed = 0x2545_f491_u32;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed
+        };
+        for _ in 0..500 {
+            let (n, m) = (next() % 12, next() % 12);
+            let a: Vec<&str> = (0..n).map(|_| words[next() as usize % 4]).collect();
+            let b: Vec<&str> = (0..m).map(|_| words[next() as usize % 4]).collect();
+            let ops = myers(&a, &b);
+            assert_eq!(apply(&a, &b, &ops), shortest(&a, &b), "{:?} -> {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn unrelated_texts() {
+        let old: Vec<String> = (0..3000).map(|i| format!("old {}", i)).collect();
+        let new: Vec<String> = (0..3000).map(|i| format!("new {}", i)).collect();
+        let a: Vec<&str> = old.iter().map(String::as_str).collect();
+        let b: Vec<&str> = new.iter().map(String::as_str).collect();
+        assert_eq!(apply(&a, &b, &myers(&a, &b)), 6000);
+    }
+
+    #[test]
+    fn hunks() {
+        let view = DiffView::from_texts("a\nb\nc\nd\ne\n", "a\nb\nC\nd\ne\n", 1);
+        assert_eq!(
+            view.lines(),
+            [
+                DiffLine::Hunk("@@ -2,3 +2,3 @@".to_string()),
+                DiffLine::Context(2, 2, "b".to_string()),
+                DiffLine::Removed(3, "c".to_string()),
+                DiffLine::Added(3, "C".to_string()),
+                DiffLine::Context(4, 4, "d".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn wraps_by_cells() {
+        assert_eq!(wrap("", 4), [""]);
+        assert_eq!(wrap("abcdef", 4), ["abcd", "ef"]);
+        assert_eq!(wrap("日本語", 4), ["日本", "語"]);
+        assert_eq!(wrap("a日本", 4), ["a日", "本"]);
+        assert_eq!(wrap("日", 1), ["日"]);
+        assert_eq!(wrap("a\tb", 4), ["a   ", " b"]);
+    }
+
+    #[test]
+    fn narrow_widths() {
+        style::set_color_choice(style::ColorChoice::Never);
+        let view = DiffView::from_texts("a\n", "日本語のテキスト\n", 0);
+        for columns in 0..12 {
+            for mode in [DiffMode::Unified, DiffMode::SideBySide] {
+                let rows = view.clone().mode(mode).width(columns).render();
+                assert!(!rows.is_empty());
+            }
+        }
+        let rows = view.mode(DiffMode::SideBySide).width(41).render();
+        // Both halves keep the same width whatever the characters.
+        for row in &rows[1..] {
+            assert_eq!(cells(row), 41, "{:?}", row);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::io;
+
+use windows_sys::Win32::System::Console::{
+    ReadConsoleInputW, ENABLE_ECHO_INPUT, ENABLE_LINE_INPUT, INPUT_RECORD, KEY_EVENT,
+    SHIFT_PRESSED, STD_INPUT_HANDLE,
+};
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::{VK_ESCAPE, VK_RETURN, VK_SPACE, VK_TAB};
+
+use crate::console::{std_handle, ModeGuard};
+
+/// Enum to represent the keys that drive a [`FocusRing`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusKey {
+    Next,     // Tab
+    Previous, // Shift+Tab
+    Activate, // Enter or Space
+    Cancel,   // Escape
+    Other,    // Any other key, left to the focused widget
+}
+
+impl FocusKey {
+    /// Maps a virtual key code and whether Shift is held.
+    pub fn from_virtual_key(key: u16, shift: bool) -> Self {
+        match key {
+            VK_TAB if shift => FocusKey::Previous,
+            VK_TAB => FocusKey::Next,
+            VK_RETURN | VK_SPACE => FocusKey::Activate,
+            VK_ESCAPE => FocusKey::Cancel,
+            _ => FocusKey::Other,
+        }
+    }
+}
+
+/// Enum to represent what a key did to a [`FocusRing`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusAction {
+    Moved(usize),     // Focus moved to this widget
+    Activated(usize), // The focused widget was activated
+    Cancelled,        // Escape was pressed
+    Ignored,          // The key is the focused widget's to handle, or nothing is focusable
+}
+
+/// Struct to hold which of a row of widgets has the keyboard focus, so interactive screens
+/// work without a mouse: Tab and Shift+Tab move the focus, wrapping around and skipping
+/// disabled widgets, and Enter or Space activate the focused one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FocusRing {
+    enabled: Vec<bool>,     // Whether each widget can take the focus
+    focused: Option<usize>, // `None` when no widget is enabled
+}
+
+impl FocusRing {
+    /// A ring of `len` enabled widgets, the first one focused.
+    pub fn new(len: usize) -> Self {
+        FocusRing {
+            enabled: vec![true; len],
+            focused: (len > 0).then_some(0),
+        }
+    }
+
+    /// The focused widget.
+    pub fn focused(&self) -> Option<usize> {
+        self.focused
+    }
+
+    /// Focuses `index`, returning whether it could take the focus.
+    pub fn focus(&mut self, index: usize) -> bool {
+        if !self.enabled.get(index).copied().unwrap_or(false) {
+            return false;
+        }
+        self.focused = Some(index);
+        true
+    }
+
+    /// Enables or disables a widget. Disabling the focused widget moves the focus on.
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        let Some(slot) = self.enabled.get_mut(index) else {
+            return;
+        };
+        *slot = enabled;
+        match self.focused {
+            Some(focused) if focused == index && !enabled => {
+                self.focused = self.step(index, 1);
+            }
+            None if enabled => self.focused = Some(index),
+            _ => {}
+        }
+    }
+
+    /// The next enabled widget from `from` in direction `delta`, wrapping around.
+    fn step(&self, from: usize, delta: isize) -> Option<usize> {
+        let len = self.enabled.len() as isize;
+        (1..=len)
+            .map(|i| (from as isize + delta * i).rem_euclid(len) as usize)
+            .find(|&index| self.enabled[index])
+    }
+
+    /// This function applies a key to the ring.
+    pub fn handle(&mut self, key: FocusKey) -> FocusAction {
+        let Some(focused) = self.focused else {
+            return match key {
+                FocusKey::Cancel => FocusAction::Cancelled,
+                _ => FocusAction::Ignored,
+            };
+        };
+        match key {
+            FocusKey::Next | FocusKey::Previous => {
+                let delta = if key == FocusKey::Next { 1 } else { -1 };
+                match self.step(focused, delta) {
+                    Some(index) => {
+                        self.focused = Some(index);
+                        FocusAction::Moved(index)
+                    }
+                    None => FocusAction::Ignored,
+                }
+            }
+            FocusKey::Activate => FocusAction::Activated(focused),
+            FocusKey::Cancel => FocusAction::Cancelled,
+            FocusKey::Other => FocusAction::Ignored,
+        }
+    }
+
+    /// This function draws the label of widget `index` with the focus indicator, the same one
+    /// the prompts use: a `>` pointer and reverse video on the focused widget, dim text on
+    /// disabled ones.
+    ///
+    /// ## Note:
+    /// - Every label gets the same two-cell prefix, so the layout doesn't shift as the focus
+    ///   moves.
+    pub fn indicator(&self, index: usize, label: &str) -> String {
+        if self.focused == Some(index) {
+            format!("> \x1b[7m{}\x1b[27m", label)
+        } else if !self.enabled.get(index).copied().unwrap_or(false) {
+            format!("  \x1b[2m{}\x1b[22m", label)
+        } else {
+            format!("  {}", label)
+        }
+    }
+}
+
+/// This function blocks until the next key press on the console and maps it to a
+/// [`FocusKey`], with line input and echo off while it waits.
+///
+/// ## Returns:
+/// - `Err(io::ErrorKind::NotFound)` if the standard input is not a console.
+pub fn read_focus_key() -> io::Result<FocusKey> {
+    let input = std_handle(STD_INPUT_HANDLE).map_err(|_| io::ErrorKind::NotFound)?;
+    let _mode = ModeGuard::change(input, 0, ENABLE_LINE_INPUT | ENABLE_ECHO_INPUT)
+        .ok_or(io::ErrorKind::NotFound)?;
+    loop {
+        let mut record: INPUT_RECORD = unsafe { std::mem::zeroed() };
+        let mut read = 0;
+        if unsafe { ReadConsoleInputW(input, &mut record, 1, &mut read) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if read == 0 || record.EventType as u32 != KEY_EVENT {
+            continue;
+        }
+        let key = unsafe { record.Event.KeyEvent };
+        if key.bKeyDown != 0 {
+            let shift = key.dwControlKeyState & SHIFT_PRESSED != 0;
+            return Ok(FocusKey::from_virtual_key(key.wVirtualKeyCode, shift));
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::fmt::Write;
+use std::ops::Range;
+
+use super::available_columns;
+use crate::style;
+
+/// Columns used per row besides the bytes themselves: the offset, the separators and the `|` gutters.
+const HEX_FIXED_COLUMNS: usize = 10 + 3;
+
+/// Struct to hold a scrollable offset/hex/ASCII view over a byte buffer.
+#[derive(Debug, Clone)]
+pub struct HexView<'a> {
+    data: &'a [u8],
+    width: Option<usize>,
+    scroll: usize,
+    highlights: Vec<(Range<usize>, u8)>,
+}
+
+impl<'a> HexView<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        HexView {
+            data,
+            width: None,
+            scroll: 0,
+            highlights: Vec::new(),
+        }
+    }
+
+    /// Sets the width in cells the view must fit in. Without it, the terminal width is used
+    /// (80 columns if it can't be measured).
+    pub fn width(mut self, columns: usize) -> Self {
+        self.width = Some(columns);
+        self
+    }
+
+    /// Highlights bytes in `range` with the 256-color background `color`.
+    pub fn highlight(mut self, range: Range<usize>, color: u8) -> Self {
+        self.highlights.push((range, color));
+        self
+    }
+
+    /// Number of bytes shown per row: the largest multiple of 8 that fits the width
+    /// (falling back to 4, 2, then 1 on very narrow terminals).
+    pub fn bytes_per_row(&self) -> usize {
+        let width = self.width.or_else(available_columns).unwrap_or(80);
+        let fits = |n: usize| HEX_FIXED_COLUMNS + 4 * n + (n.div_ceil(8) - 1) <= width;
+        let mut n = 8;
+        while fits(n + 8) {
+            n += 8;
+        }
+        if fits(n) {
+            return n;
+        }
+        [4, 2].into_iter().find(|&m| fits(m)).unwrap_or(1)
+    }
+
+    /// Total number of rows needed to show the whole buffer.
+    pub fn row_count(&self) -> usize {
+        self.data.len().div_ceil(self.bytes_per_row())
+    }
+
+    /// First row currently shown.
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    /// Scrolls so `row` is the first visible row, clamped to the last row.
+    pub fn scroll_to(&mut self, row: usize) {
+        self.scroll = row.min(self.row_count().saturating_sub(1));
+    }
+
+    /// Scrolls by `delta` rows (negative values scroll up).
+    pub fn scroll_by(&mut self, delta: isize) {
+        self.scroll_to(self.scroll.saturating_add_signed(delta));
+    }
+
+    /// Scrolls so the row containing the byte at `offset` is visible in a viewport of `rows`.
+    pub fn reveal(&mut self, offset: usize, rows: usize) {
+        let row = offset / self.bytes_per_row();
+        if row < self.scroll {
+            self.scroll_to(row);
+        } else if row >= self.scroll + rows.max(1) {
+            self.scroll_to(row + 1 - rows.max(1));
+        }
+    }
+
+    fn highlight_at(&self, offset: usize) -> Option<u8> {
+        self.highlights
+            .iter()
+            .rev()
+            .find(|(range, _)| range.contains(&offset))
+            .map(|(_, color)| *color)
+    }
+
+    /// This function formats `rows` rows starting at the current scroll position.
+    ///
+    /// ## Returns:
+    /// - One string per row, e.g. `00000010  48 65 6c 6c 6f 20 57 6f  72 6c 64 0a  |Hello World.|`,
+    ///   with highlighted bytes wrapped in `SGR 48;5;n`. Non-printable bytes show as `.`.
+    pub fn lines(&self, rows: usize) -> Vec<String> {
+        let per_row = self.bytes_per_row();
+        let colored = style::colors_enabled();
+        let mut lines = Vec::with_capacity(rows);
+        for row in self.scroll..(self.scroll + rows).min(self.row_count()) {
+            let start = row * per_row;
+            let chunk = &self.data[start..(start + per_row).min(self.data.len())];
+            let mut hex = String::new();
+            let mut ascii = String::new();
+            for i in 0..per_row {
+                if i > 0 {
+                    hex.push(' ');
+                    if i % 8 == 0 {
+                        hex.push(' ');
+                    }
+                }
+                let Some(&byte) = chunk.get(i) else {
+                    hex.push_str("  ");
+                    continue;
+                };
+                let glyph = if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                };
+                match self.highlight_at(start + i) {
+                    Some(color) if colored => {
+                        let _ = write!(hex, "\x1b[48;5;{}m{:02x}\x1b[49m", color, byte);
+                        let _ = write!(ascii, "\x1b[48;5;{}m{}\x1b[49m", color, glyph);
+                    }
+                    // Without colors, highlighted bytes are still told apart in reverse video.
+                    Some(_) => {
+                        let _ = write!(hex, "\x1b[7m{:02x}\x1b[27m", byte);
+                        let _ = write!(ascii, "\x1b[7m{}\x1b[27m", glyph);
+                    }
+                    None => {
+                        let _ = write!(hex, "{:02x}", byte);
+                        ascii.push(glyph);
+                    }
+                }
+            }
+            lines.push(format!("{:08x}  {}  |{}|", start, hex, ascii));
+        }
+        lines
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+
+use super::available_columns;
+use crate::console::{visible_cells, ModeGuard};
+use crate::measure::cells_exact;
+use crate::style;
+
+/// Rows assumed when the height of the window can't be measured.
+const FALLBACK_ROWS: usize = 24;
+
+/// Enum to represent the severity found in a log line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error, // Also `FATAL` and `CRITICAL`
+}
+
+impl LogLevel {
+    /// This function finds the level of a line from the first level word it contains (`ERROR`,
+    /// `WARN`, `WARNING`, `INFO`, `DEBUG`, `TRACE`, `FATAL`, `CRITICAL`, in any case).
+    pub fn detect(line: &str) -> Option<LogLevel> {
+        line.split(|c: char| !c.is_ascii_alphabetic())
+            .find_map(|word| match word.to_ascii_uppercase().as_str() {
+                "ERROR" | "FATAL" | "CRITICAL" => Some(LogLevel::Error),
+                "WARN" | "WARNING" => Some(LogLevel::Warn),
+                "INFO" => Some(LogLevel::Info),
+                "DEBUG" => Some(LogLevel::Debug),
+                "TRACE" => Some(LogLevel::Trace),
+                _ => None,
+            })
+    }
+
+    fn sgr(self) -> &'static str {
+        match self {
+            LogLevel::Error => "\x1b[31m",
+            LogLevel::Warn => "\x1b[33m",
+            LogLevel::Info => "",
+            LogLevel::Debug | LogLevel::Trace => "\x1b[2m",
+        }
+    }
+}
+
+/// Struct to hold a `tail -f` style view of a log: the last lines that fit the window,
+/// filtered and colored by level.
+///
+/// Lines are fed with [`LogTail::push`], from a channel with [`LogTail::drain`] or from a
+/// growing file with a [`LogFollower`]; [`LogTail::draw`] then updates the whole window,
+/// rewriting only the rows that changed since the previous draw.
+#[derive(Debug)]
+pub struct LogTail {
+    lines: VecDeque<(String, Option<LogLevel>)>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    min_level: Option<LogLevel>,
+    width: Option<usize>,
+    height: Option<usize>,
+    drawn: Vec<String>,
+    drawn_size: (usize, usize),
+    _vt: Option<ModeGuard>,
+}
+
+impl Default for LogTail {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LogTail {
+    /// Creates an empty view of the standard output, enabling VT processing on the console
+    /// for as long as it lives.
+    pub fn new() -> Self {
+        LogTail {
+            lines: VecDeque::new(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            min_level: None,
+            width: None,
+            height: None,
+            drawn: Vec::new(),
+            drawn_size: (0, 0),
+            _vt: ModeGuard::virtual_terminal(),
+        }
+    }
+
+    /// Only keeps lines containing `pattern`. With several, a line needs to contain one.
+    pub fn include(mut self, pattern: impl Into<String>) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// Drops lines containing `pattern`, even if they match an include.
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Drops lines whose level is below `level`. Lines without a level are kept.
+    pub fn min_level(mut self, level: LogLevel) -> Self {
+        self.min_level = Some(level);
+        self
+    }
+
+    /// Sets the size in cells to lay out to. Without it, the visible window is measured on
+    /// every draw (80x24 if it can't be).
+    pub fn size(mut self, columns: usize, rows: usize) -> Self {
+        self.width = Some(columns);
+        self.height = Some(rows);
+        self
+    }
+
+    fn columns(&self) -> usize {
+        self.width.or_else(available_columns).unwrap_or(80).max(1)
+    }
+
+    fn rows(&self) -> usize {
+        self.height
+            .or_else(|| visible_cells().ok().map(|(_, rows)| rows as usize))
+            .unwrap_or(FALLBACK_ROWS)
+            .max(1)
+    }
+
+    /// Whether a line passes the filters.
+    pub fn accepts(&self, line: &str) -> bool {
+        let level = LogLevel::detect(line);
+        (self.include.is_empty() || self.include.iter().any(|p| line.contains(p.as_str())))
+            && !self.exclude.iter().any(|p| line.contains(p.as_str()))
+            && self
+                .min_level
+                .is_none_or(|min| level.is_none_or(|level| level >= min))
+    }
+
+    /// This function adds a line to the view if it passes the filters.
+    ///
+    /// ## Note:
+    /// - Only as many lines as the window has rows are kept: every line takes at least one
+    ///   row, so older ones can't be shown anyway.
+    pub fn push(&mut self, line: &str) {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if !self.accepts(line) {
+            return;
+        }
+        self.lines
+            .push_back((line.replace('\t', "    "), LogLevel::detect(line)));
+        let rows = self.rows();
+        while self.lines.len() > rows {
+            self.lines.pop_front();
+        }
+    }
+
+    /// Pushes every line waiting in a channel, without blocking. Returns how many were read.
+    pub fn drain(&mut self, receiver: &Receiver<String>) -> usize {
+        let mut count = 0;
+        while let Ok(line) = receiver.try_recv() {
+            self.push(&line);
+            count += 1;
+        }
+        count
+    }
+
+    /// This function lays the kept lines out for the current size.
+    ///
+    /// ## Returns:
+    /// - Exactly one string per row of the window, the newest line on the last row. Long
+    ///   lines wrap onto several rows, and are colored by level when colors are enabled.
+    pub fn render(&self) -> Vec<String> {
+        let (columns, rows) = (self.columns(), self.rows());
+        let colored = style::colors_enabled();
+        let mut out: VecDeque<String> = VecDeque::with_capacity(rows);
+        for (line, level) in self.lines.iter().rev() {
+            let sgr = match (colored, level) {
+                (true, Some(level)) => level.sgr(),
+                _ => "",
+            };
+            for chunk in chunks(line, columns).into_iter().rev() {
+                if out.len() == rows {
+                    break;
+                }
+                match sgr {
+                    "" => out.push_front(chunk),
+                    sgr => out.push_front(format!("{}{}\x1b[0m", sgr, chunk)),
+                }
+            }
+        }
+        while out.len() < rows {
+            out.push_front(String::new());
+        }
+        out.into()
+    }
+
+    /// This function draws the view over the whole window.
+    ///
+    /// ## Note:
+    /// - Only rows that differ from the previous draw are written. When new lines pushed the
+    ///   old ones up, the window is scrolled (`CSI n S`) and only the new rows are written.
+    /// - A change of size redraws everything from the lines kept, which reflow to the new
+    ///   width.
+    pub fn draw(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let size = (self.columns(), self.rows());
+        let rows = self.render();
+        let mut text = String::new();
+        if size != self.drawn_size {
+            self.drawn.clear();
+            text.push_str("\x1b[2J");
+        } else if let Some(shift) =
+            (1..rows.len()).find(|&k| self.drawn[k..] == rows[..rows.len() - k])
+        {
+            // New lines scrolled the old ones up: let the terminal move them.
+            text.push_str(&format!("\x1b[{}S", shift));
+            self.drawn.drain(..shift);
+        }
+        for (index, row) in rows.iter().enumerate() {
+            if self.drawn.get(index) != Some(row) {
+                text.push_str(&format!("\x1b[{};1H\x1b[2K{}", index + 1, row));
+            }
+        }
+        if !text.is_empty() {
+            out.write_all(text.as_bytes())?;
+            out.flush()?;
+        }
+        self.drawn = rows;
+        self.drawn_size = size;
+        Ok(())
+    }
+}
+
+/// Cuts a line into pieces of at most `columns` cells.
+fn chunks(line: &str, columns: usize) -> Vec<String> {
+    let mut chunks = vec![String::new()];
+    let mut used = 0;
+    for span in cells_exact(line) {
+        if used + span.width > columns && used > 0 {
+            chunks.push(String::new());
+            used = 0;
+        }
+        if let Some(chunk) = chunks.last_mut() {
+            chunk.push_str(&line[span.start..span.end]);
+        }
+        used += span.width;
+    }
+    chunks
+}
+
+/// Struct to hold a file being followed like `tail -f`, for feeding a [`LogTail`].
+#[derive(Debug)]
+pub struct LogFollower {
+    path: PathBuf,
+    offset: u64,
+    partial: Vec<u8>,
+}
+
+impl LogFollower {
+    /// This function starts following a file from its current end.
+    ///
+    /// ## Returns:
+    /// - `Err(io::Error)` if the file can't be opened.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<LogFollower> {
+        let offset = File::open(path.as_ref())?.metadata()?.len();
+        Ok(LogFollower {
+            path: path.as_ref().to_path_buf(),
+            offset,
+            partial: Vec::new(),
+        })
+    }
+
+    /// This function pushes the lines appended to the file since the last poll.
+    ///
+    /// ## Returns:
+    /// - `Ok(count)` with the number of complete lines read. A last line without its newline
+    ///   yet is held back until it is finished.
+    /// - `Err(io::Error)` if the file can't be read.
+    ///
+    /// ## Note:
+    /// - The file is reopened on every poll, so rotation (rename and recreate) is followed.
+    ///   A file that got shorter was truncated and is read again from the start.
+    pub fn poll(&mut self, tail: &mut LogTail) -> io::Result<usize> {
+        let mut file = File::open(&self.path)?;
+        let len = file.metadata()?.len();
+        if len < self.offset {
+            self.offset = 0;
+            self.partial.clear();
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut bytes = Vec::new();
+        file.take(len - self.offset).read_to_end(&mut bytes)?;
+        self.offset += bytes.len() as u64;
+        self.partial.extend_from_slice(&bytes);
+        let mut count = 0;
+        while let Some(end) = self.partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.partial.drain(..=end).collect();
+            tail.push(&String::from_utf8_lossy(&line));
+            count += 1;
+        }
+        Ok(count)
+    }
+}
@@ -0,0 +1,73 @@
+mod banner;
+mod diff;
+mod focus;
+mod hex;
+mod log;
+mod progress;
+#[cfg(feature = "qrcode")]
+mod qr;
+
+pub use self::banner::{banner, banner_with_width, BannerFont};
+pub use self::diff::{DiffLine, DiffMode, DiffView};
+pub use self::focus::{read_focus_key, FocusAction, FocusKey, FocusRing};
+pub use self::hex::HexView;
+pub use self::log::{LogFollower, LogLevel, LogTail};
+pub use self::progress::{MultiProgress, ProgressBar};
+#[cfg(feature = "qrcode")]
+pub use self::qr::{qr_code, QrCells, QrWarning};
+
+use std::sync::RwLock;
+
+use crate::console::visible_cells;
+
+/// Struct to hold the page the widgets lay out for when the standard output isn't a console,
+/// e.g. redirected to a file or a CI log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VirtualPage {
+    pub width: usize, // Width of the page in cells
+}
+
+static VIRTUAL_PAGE: RwLock<Option<VirtualPage>> = RwLock::new(None);
+
+impl VirtualPage {
+    /// A page of `$COLUMNS` cells when it is set to a positive number, 80 otherwise.
+    pub fn from_env() -> Self {
+        let width = std::env::var("COLUMNS")
+            .ok()
+            .and_then(|columns| columns.trim().parse().ok())
+            .filter(|&width| width > 0)
+            .unwrap_or(80);
+        VirtualPage { width }
+    }
+
+    /// This function makes every width-dependent widget (diff, hex, progress, QR) lay out for
+    /// this page whenever the standard output isn't a console.
+    ///
+    /// ## Note:
+    /// - A real console always wins; the page is only a stand-in for the missing window.
+    /// - Widgets given an explicit width keep it.
+    pub fn enable(self) {
+        *VIRTUAL_PAGE.write().unwrap_or_else(|e| e.into_inner()) = Some(VirtualPage {
+            width: self.width.max(1),
+        });
+    }
+
+    /// This function goes back to the built-in fallbacks when there's no console.
+    pub fn disable() {
+        *VIRTUAL_PAGE.write().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// The enabled page, if any.
+    pub fn current() -> Option<VirtualPage> {
+        *VIRTUAL_PAGE.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Width of the visible terminal window in cells, or of the virtual page when there's no
+/// console window.
+pub(crate) fn available_columns() -> Option<usize> {
+    visible_cells()
+        .ok()
+        .map(|(columns, _)| columns as usize)
+        .or_else(|| VirtualPage::current().map(|page| page.width))
+}
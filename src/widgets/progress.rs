@@ -0,0 +1,310 @@
+use std::fmt::Write;
+use std::io::{self, Write as _};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use super::available_columns;
+use crate::console::{visible_cells, ModeGuard};
+use crate::format;
+
+/// Struct to hold a set of progress bars pinned to the bottom rows of the terminal.
+///
+/// Log lines printed through [`MultiProgress::println`] scroll in the area above the bars,
+/// which is confined with a VT scroll region (`DECSTBM`). The layout is recomputed from the
+/// visible window on every update, so the bars stay at the bottom across resizes.
+#[derive(Debug, Clone)]
+pub struct MultiProgress {
+    state: Arc<Mutex<MultiState>>,
+}
+
+/// Struct to hold a handle to one bar of a [`MultiProgress`]. Handles are cheap to clone and
+/// can be moved to worker threads.
+#[derive(Debug, Clone)]
+pub struct ProgressBar {
+    state: Arc<Mutex<MultiState>>,
+    id: u64,
+}
+
+#[derive(Debug)]
+struct BarState {
+    id: u64, // Never reused, so handles of cleared bars match nothing
+    label: String,
+    position: u64,
+    total: u64,
+    finished: bool,
+    started: Instant,
+}
+
+#[derive(Debug)]
+struct MultiState {
+    bars: Vec<BarState>,
+    next_id: u64,
+    columns: usize,
+    rows: usize,
+    reserved: usize,
+    _vt: Option<ModeGuard>,
+}
+
+fn lock(state: &Mutex<MultiState>) -> MutexGuard<'_, MultiState> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl Default for MultiProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultiProgress {
+    /// Creates an empty manager writing to the standard output, enabling VT processing on
+    /// the console for as long as it lives.
+    pub fn new() -> Self {
+        MultiProgress {
+            state: Arc::new(Mutex::new(MultiState {
+                bars: Vec::new(),
+                next_id: 0,
+                columns: 0,
+                rows: 0,
+                reserved: 0,
+                _vt: ModeGuard::virtual_terminal(),
+            })),
+        }
+    }
+
+    /// Adds a bar below the existing ones. A `total` of 0 draws an indeterminate bar, a
+    /// segment bouncing from end to end as the bar is updated or [`ProgressBar::tick`]ed.
+    pub fn add(&self, label: impl Into<String>, total: u64) -> ProgressBar {
+        let mut state = lock(&self.state);
+        let id = state.next_id;
+        state.next_id += 1;
+        state.bars.push(BarState {
+            id,
+            label: label.into(),
+            position: 0,
+            total,
+            finished: false,
+            started: Instant::now(),
+        });
+        let _ = state.redraw(None);
+        ProgressBar {
+            state: self.state.clone(),
+            id,
+        }
+    }
+
+    /// Prints a log line in the scrolling area above the bars.
+    pub fn println(&self, line: &str) -> io::Result<()> {
+        let mut state = lock(&self.state);
+        let mut out = state.layout();
+        let _ = write!(out, "\x1b[{};1H\n{}", state.region_bottom(), line);
+        state.write(out)
+    }
+
+    /// Removes every bar and gives the whole window back to normal output. Handles of the
+    /// removed bars stay valid but update nothing.
+    pub fn clear(&self) -> io::Result<()> {
+        let mut state = lock(&self.state);
+        state.bars.clear();
+        state.release()
+    }
+}
+
+impl ProgressBar {
+    fn update(&self, change: impl FnOnce(&mut BarState)) {
+        let mut state = lock(&self.state);
+        if let Some(index) = state.bars.iter().position(|bar| bar.id == self.id) {
+            change(&mut state.bars[index]);
+            let _ = state.redraw(Some(index));
+        }
+    }
+
+    /// Redraws the bar without changing it, to move an indeterminate bar along.
+    pub fn tick(&self) {
+        self.update(|_| {});
+    }
+
+    pub fn set_position(&self, position: u64) {
+        self.update(|bar| bar.position = position);
+    }
+
+    pub fn inc(&self, delta: u64) {
+        self.update(|bar| bar.position = bar.position.saturating_add(delta));
+    }
+
+    pub fn set_total(&self, total: u64) {
+        self.update(|bar| bar.total = total);
+    }
+
+    pub fn set_label(&self, label: impl Into<String>) {
+        let label = label.into();
+        self.update(|bar| bar.label = label);
+    }
+
+    /// Marks the bar as complete, filling it if it has a total.
+    pub fn finish(&self) {
+        self.update(|bar| {
+            bar.finished = true;
+            bar.position = bar.position.max(bar.total);
+        });
+    }
+}
+
+impl MultiState {
+    fn region_bottom(&self) -> usize {
+        self.rows.saturating_sub(self.reserved).max(1)
+    }
+
+    /// Re-reads the window size and, if it or the number of bars changed, moves the scroll
+    /// region. Returns the escape sequences to write, with a full redraw when needed.
+    fn layout(&mut self) -> String {
+        let mut out = String::new();
+        let (columns, rows) = visible_cells()
+            .map(|(columns, rows)| (columns as usize, rows as usize))
+            .unwrap_or_else(|_| (available_columns().unwrap_or(80), 24));
+        // Always keep at least one row for the log area.
+        let reserved = self.bars.len().min(rows.saturating_sub(1));
+        if (columns, rows, reserved) == (self.columns, self.rows, self.reserved) {
+            return out;
+        }
+        if reserved > self.reserved {
+            // Push existing output up instead of drawing the new bar over it.
+            let _ = write!(out, "\x1b[{};1H", rows);
+            out.extend(std::iter::repeat_n('\n', reserved - self.reserved));
+        }
+        (self.columns, self.rows, self.reserved) = (columns, rows, reserved);
+        // DECSTBM homes the cursor, so put it back at the bottom of the log area.
+        let _ = write!(
+            out,
+            "\x1b[1;{}r\x1b[{};1H",
+            self.region_bottom(),
+            self.region_bottom()
+        );
+        for index in 0..self.bars.len() {
+            self.draw_bar(&mut out, index);
+        }
+        out
+    }
+
+    fn redraw(&mut self, index: Option<usize>) -> io::Result<()> {
+        let mut out = self.layout();
+        if out.is_empty() {
+            if let Some(index) = index {
+                self.draw_bar(&mut out, index);
+            }
+        }
+        self.write(out)
+    }
+
+    fn draw_bar(&self, out: &mut String, index: usize) {
+        // Bars that don't fit (more bars than rows) are simply not drawn.
+        let first = self.bars.len() - self.reserved;
+        if index < first {
+            return;
+        }
+        let bar = &self.bars[index];
+        let row = self.region_bottom() + 1 + (index - first);
+        let elapsed = bar.started.elapsed();
+        let timing = match (bar.finished, bar.position) {
+            (true, _) => format!(" in {}", format::duration(elapsed)),
+            (false, position) if bar.total > position && position > 0 => {
+                let remaining = elapsed.mul_f64((bar.total - position) as f64 / position as f64);
+                format!(" ETA {}", format::duration(remaining))
+            }
+            _ => String::new(),
+        };
+        let counter = match bar.total {
+            0 => format!(" {}{}", format::count(bar.position), timing),
+            total => format!(
+                " {}/{} {:>3}%{}",
+                format::count(bar.position),
+                format::count(total),
+                bar.position.min(total) * 100 / total,
+                timing
+            ),
+        };
+        let label: String = bar.label.chars().take(self.columns / 3).collect();
+        let room = self
+            .columns
+            .saturating_sub(label.chars().count() + counter.chars().count() + 4);
+        let (start, filled) = match (bar.total, bar.finished) {
+            (0, true) => (0, room),
+            (0, false) => bounce(room, elapsed),
+            (total, _) => (
+                0,
+                (bar.position.min(total) as u128 * room as u128 / total as u128) as usize,
+            ),
+        };
+        let _ = write!(
+            out,
+            "\x1b7\x1b[{};1H\x1b[2K{} [{}{}{}]{}\x1b8",
+            row,
+            label,
+            "-".repeat(start),
+            "#".repeat(filled),
+            "-".repeat(room - start - filled),
+            counter
+        );
+    }
+
+    /// Resets the scroll region and leaves the cursor below the last bar.
+    fn release(&mut self) -> io::Result<()> {
+        if self.rows == 0 {
+            return Ok(());
+        }
+        let out = format!("\x1b[r\x1b[{};1H\n", self.rows);
+        (self.columns, self.rows, self.reserved) = (0, 0, 0);
+        self.write(out)
+    }
+
+    fn write(&self, out: String) -> io::Result<()> {
+        if out.is_empty() {
+            return Ok(());
+        }
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(out.as_bytes())?;
+        stdout.flush()
+    }
+}
+
+/// Start and length of the segment of an indeterminate bar `room` cells wide, going back and
+/// forth across it once every two seconds.
+fn bounce(room: usize, elapsed: Duration) -> (usize, usize) {
+    let length = (room / 4).max(1).min(room);
+    let travel = room - length;
+    if travel == 0 {
+        return (0, length);
+    }
+    let step = (elapsed.as_millis() * travel as u128 / 1000) as usize % (2 * travel);
+    (step.min(2 * travel - step), length)
+}
+
+impl Drop for MultiState {
+    fn drop(&mut self) {
+        let _ = self.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounce_stays_inside_the_bar() {
+        for room in 0..12 {
+            for millis in (0..4000).step_by(50) {
+                let (start, length) = bounce(room, Duration::from_millis(millis));
+                assert!(start + length <= room, "{} cells at {} ms", room, millis);
+                assert_eq!(length, (room / 4).max(1).min(room));
+            }
+        }
+    }
+
+    #[test]
+    fn bounce_goes_back_and_forth() {
+        assert_eq!(bounce(8, Duration::ZERO), (0, 2));
+        assert_eq!(bounce(8, Duration::from_millis(1000)), (6, 2));
+        assert_eq!(bounce(8, Duration::from_millis(2000)), (0, 2));
+    }
+}
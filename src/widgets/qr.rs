@@ -0,0 +1,95 @@
+use qrcode::types::{Color, QrError};
+use qrcode::QrCode;
+
+use super::available_columns;
+use crate::get_size_of_the_font;
+
+/// Modules of light border required around the symbol by the QR specification.
+const QUIET_ZONE: usize = 4;
+
+/// Smallest module, in physical pixels, that phone cameras reliably resolve off a screen.
+const MIN_MODULE_PX: i32 = 3;
+
+/// Enum to represent the reasons a rendered QR code may not be scannable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QrWarning {
+    TooWide { columns: usize, available: usize }, // The code (quiet zone included) is wider than the terminal
+    ModulesTooSmall { width_px: i32, height_px: i32 }, // A module is smaller than `MIN_MODULE_PX` on screen
+}
+
+/// Struct to hold a QR code rendered with half blocks.
+#[derive(Debug, Clone)]
+pub struct QrCells {
+    pub lines: Vec<String>, // Rows of cells, each wrapped in its own SGR colors
+    pub columns: usize,     // Width of every line in cells
+    pub warnings: Vec<QrWarning>, // Why the code may not scan, empty when it should
+}
+
+/// This function renders `data` as a QR code made of half-block cells.
+///
+/// ## Returns:
+/// - `Ok(QrCells)` with one string per terminal row, dark modules drawn black on bright white.
+/// - `Err(QrError)` if the data doesn't fit in a QR code.
+///
+/// ## Note:
+/// - Each cell holds two modules vertically. Modules are repeated horizontally so they come
+///   out as close to square as the measured font allows (Consolas is roughly 1:2, so usually
+///   once).
+/// - If the font or terminal size can't be measured, no warning about them is produced.
+pub fn qr_code(data: impl AsRef<[u8]>) -> Result<QrCells, QrError> {
+    let code = QrCode::new(data)?;
+    let font = get_size_of_the_font().ok();
+    let repeat = match &font {
+        Some(font) if font.width > 0 => ((font.height as f64 / 2.0) / font.width as f64)
+            .round()
+            .max(1.0) as usize,
+        _ => 1,
+    };
+
+    let size = code.width();
+    let colors = code.to_colors();
+    let total = size + 2 * QUIET_ZONE;
+    let dark = |x: usize, y: usize| {
+        let (x, y) = (x.wrapping_sub(QUIET_ZONE), y.wrapping_sub(QUIET_ZONE));
+        x < size && y < size && colors[y * size + x] == Color::Dark
+    };
+
+    let mut lines = Vec::with_capacity(total.div_ceil(2));
+    for row in (0..total).step_by(2) {
+        let mut line = String::from("\x1b[30;107m");
+        for x in 0..total {
+            let glyph = match (dark(x, row), dark(x, row + 1)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            };
+            line.extend(std::iter::repeat_n(glyph, repeat));
+        }
+        line.push_str("\x1b[0m");
+        lines.push(line);
+    }
+
+    let columns = total * repeat;
+    let mut warnings = Vec::new();
+    if let Some(font) = font {
+        if let Some(available) = available_columns() {
+            if columns > available {
+                warnings.push(QrWarning::TooWide { columns, available });
+            }
+        }
+        let (width_px, height_px) = (font.width * repeat as i32, font.height / 2);
+        if width_px < MIN_MODULE_PX || height_px < MIN_MODULE_PX {
+            warnings.push(QrWarning::ModulesTooSmall {
+                width_px,
+                height_px,
+            });
+        }
+    }
+
+    Ok(QrCells {
+        lines,
+        columns,
+        warnings,
+    })
+}
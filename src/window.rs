@@ -0,0 +1,257 @@
+use std::io;
+
+use windows_sys::Win32::Foundation::{SetLastError, ERROR_CALL_NOT_IMPLEMENTED, HWND, RECT};
+use windows_sys::Win32::System::Console::{
+    GetConsoleOriginalTitleW, GetConsoleTitleW, SetConsoleDisplayMode, SetConsoleTitleW,
+    CONSOLE_FULLSCREEN_MODE, CONSOLE_WINDOWED_MODE, COORD, STD_OUTPUT_HANDLE,
+};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    GetWindowRect, SetWindowPos, ShowWindow, SHOW_WINDOW_CMD, SWP_NOACTIVATE, SWP_NOSIZE,
+    SWP_NOZORDER, SW_MAXIMIZE, SW_RESTORE,
+};
+
+use crate::console::{host_window, resize_window, std_handle, visible_cells};
+use crate::dpi::in_physical_pixels;
+use crate::environment::{self, TerminalHost};
+use crate::TerminalError;
+
+/// Longest title read; the console host itself stops well before.
+const MAX_TITLE: usize = 1 << 16;
+
+/// Reads a title with one of the `GetConsole*TitleW` functions, growing the buffer until the
+/// title fits.
+fn read_title(get: unsafe extern "system" fn(*mut u16, u32) -> u32) -> io::Result<String> {
+    let mut buffer = vec![0u16; 256];
+    loop {
+        let len = unsafe {
+            // A title of 0 characters doesn't set the last error.
+            SetLastError(0);
+            get(buffer.as_mut_ptr(), buffer.len() as u32)
+        } as usize;
+        if len == 0 {
+            let error = io::Error::last_os_error();
+            return match error.raw_os_error() {
+                Some(0) => Ok(String::new()),
+                _ => Err(error),
+            };
+        }
+        // Hosts either report the full length or fill the buffer: grow in both cases.
+        if len < buffer.len() - 1 || buffer.len() >= MAX_TITLE {
+            let len = len.min(buffer.len());
+            let end = buffer[..len].iter().position(|&c| c == 0).unwrap_or(len);
+            return Ok(String::from_utf16_lossy(&buffer[..end]));
+        }
+        buffer.resize((len + 1).max(buffer.len() * 2).min(MAX_TITLE), 0);
+    }
+}
+
+/// This function reads the title of the console window.
+///
+/// ## Returns:
+/// - `Ok(String)` with the current title, empty if it has none.
+/// - `Err(io::Error)` without a console.
+///
+/// ## Note:
+/// - Under a pseudo console this is the title last set through the console, by this process
+///   or another, not one a program set with an OSC sequence the terminal kept to itself.
+pub fn title() -> io::Result<String> {
+    read_title(GetConsoleTitleW)
+}
+
+/// This function reads the title the console window had when the console was created,
+/// usually the path of the first program it ran.
+///
+/// ## Returns:
+/// - `Ok(String)` with the original title, see [`title`].
+/// - `Err(io::Error)` without a console.
+pub fn original_title() -> io::Result<String> {
+    read_title(GetConsoleOriginalTitleW)
+}
+
+/// This function sets the title of the console window.
+///
+/// ## Returns:
+/// - `Err(io::Error)` with `InvalidInput` if `title` contains a null character, or the error
+///   of `SetConsoleTitleW` without a console.
+///
+/// ## Note:
+/// - The title is kept after the process exits, for the next program in the console: save it
+///   with [`title`] first to put it back.
+/// - Under a pseudo console the host forwards the title to the terminal, which shows it on
+///   its tab.
+pub fn set_title(title: &str) -> io::Result<()> {
+    if title.contains('\0') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "a title can't contain a null character",
+        ));
+    }
+    let wide: Vec<u16> = title.encode_utf16().chain([0]).collect();
+    if unsafe { SetConsoleTitleW(wide.as_ptr()) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// The window the user sees the console in, or `NotFound`.
+fn visible_window() -> io::Result<HWND> {
+    let window = host_window();
+    if window.is_null() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no console window"));
+    }
+    Ok(window)
+}
+
+/// This function reads where the console window is on the screen.
+///
+/// ## Returns:
+/// - `Ok((x, y))` of the top left corner of the window frame, in physical pixels of the
+///   virtual screen whatever the DPI awareness of the process.
+/// - `Err(io::Error)` without a visible console window (under a pseudo console whose terminal
+///   doesn't own the console window, e.g. over SSH).
+///
+/// ## Note:
+/// - Under Windows Terminal this is the terminal window.
+/// - Since Windows 10 the frame includes invisible resize borders of a few pixels on the
+///   left, right and bottom: a window snapped at `x` shows its visible edge slightly inside.
+pub fn position() -> io::Result<(i32, i32)> {
+    let window = visible_window()?;
+    let mut rect = RECT {
+        left: 0,
+        top: 0,
+        right: 0,
+        bottom: 0,
+    };
+    // The error is read before the awareness is put back.
+    in_physical_pixels(|| match unsafe { GetWindowRect(window, &mut rect) } {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok((rect.left, rect.top)),
+    })
+}
+
+/// This function moves the console window, keeping its size, without activating it.
+///
+/// ## Returns:
+/// - `Err(io::Error)` without a visible console window, see [`position`], or if Windows
+///   refuses the move.
+///
+/// ## Note:
+/// - `x` and `y` are physical pixels of the virtual screen, as [`position`] returns and
+///   [`crate::monitor::Monitor::work_area`] holds for a per-monitor aware process.
+/// - Moving the window to a monitor with another scaling changes its DPI, and the pixel size
+///   of the cells with it.
+pub fn set_position(x: i32, y: i32) -> io::Result<()> {
+    let window = visible_window()?;
+    in_physical_pixels(|| {
+        let flags = SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE;
+        match unsafe { SetWindowPos(window, std::ptr::null_mut(), x, y, 0, 0, flags) } {
+            0 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    })
+}
+
+/// This function resizes the visible window of the standard output to `columns` x `rows`
+/// cells, e.g. 120 x 40, growing the screen buffer first when it is smaller.
+///
+/// ## Returns:
+/// - `Ok(())` once the console reports the new size.
+/// - `Err(io::Error)` with `InvalidInput` if the size is below 1 x 1 or doesn't fit on the
+///   screen with the current font (the largest window the console allows).
+/// - `Err(io::Error)` with `Unsupported` if the host accepted the request but kept its size:
+///   Windows Terminal and other pseudo console hosts size the console after their own
+///   window, and ignore programs asking for another.
+/// - `Err(io::Error)` without a console, or if the console refuses the new size.
+///
+/// ## Note:
+/// - The buffer keeps its scrollback rows and takes the new width; the window is moved back
+///   to the left edge if it was scrolled horizontally.
+pub fn resize_cells(columns: i16, rows: i16) -> io::Result<()> {
+    let handle = std_handle(STD_OUTPUT_HANDLE)?;
+    resize_window(handle, columns, rows)?;
+    let (width, height) = visible_cells()?;
+    if (width, height) != (columns as i32, rows as i32) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "the console host kept its size of {}x{} cells instead of {}x{}",
+                width, height, columns, rows
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Shows the console window the way `command` says.
+fn show(command: SHOW_WINDOW_CMD) -> io::Result<()> {
+    let window = visible_window()?;
+    // The result is whether the window was visible before, not whether it worked.
+    unsafe { ShowWindow(window, command) };
+    Ok(())
+}
+
+/// This function maximizes the console window on its monitor.
+///
+/// ## Returns:
+/// - `Err(io::Error)` without a visible console window, see [`position`].
+///
+/// ## Note:
+/// - Under conhost the window can't grow past the largest window of the screen buffer: it
+///   maximizes to the screen only when the buffer is wide enough, see
+///   [`crate::buffer::ScreenBufferInfo::max_window`].
+/// - Under Windows Terminal this maximizes the terminal window, every tab with it.
+pub fn maximize() -> io::Result<()> {
+    show(SW_MAXIMIZE)
+}
+
+/// This function restores the console window to its size and position from before it was
+/// maximized or minimized.
+///
+/// ## Returns:
+/// - `Err(io::Error)` without a visible console window, see [`position`].
+///
+/// ## Note:
+/// - This doesn't leave the fullscreen mode, see [`set_fullscreen`].
+pub fn restore() -> io::Result<()> {
+    show(SW_RESTORE)
+}
+
+/// This function switches the console between the fullscreen display mode and a window, as
+/// Alt+Enter does in conhost.
+///
+/// ## Returns:
+/// - `Err(io::Error)` with `Unsupported`, holding [`TerminalError::FullscreenUnsupported`],
+///   under a pseudo console (Windows Terminal, SSH) or on a system without the mode: the
+///   terminal decides about its own window there.
+/// - `Err(io::Error)` without a console, or if the console refuses the mode.
+///
+/// ## Note:
+/// - Leaving the fullscreen mode where it doesn't exist is `Ok(())`: the console is already
+///   windowed.
+/// - Entering it resizes the screen buffer to the screen; the new size is read on the next
+///   [`crate::Terminal::refresh`].
+pub fn set_fullscreen(fullscreen: bool) -> io::Result<()> {
+    let handle = std_handle(STD_OUTPUT_HANDLE)?;
+    if environment::host().is_some_and(TerminalHost::is_pseudo_console) {
+        return match fullscreen {
+            true => Err(TerminalError::FullscreenUnsupported.into()),
+            false => Ok(()),
+        };
+    }
+    let mode = match fullscreen {
+        true => CONSOLE_FULLSCREEN_MODE,
+        false => CONSOLE_WINDOWED_MODE,
+    };
+    let mut size = COORD { X: 0, Y: 0 };
+    if unsafe { SetConsoleDisplayMode(handle, mode, &mut size) } == 0 {
+        let error = io::Error::last_os_error();
+        return match error.raw_os_error() {
+            Some(code) if code as u32 == ERROR_CALL_NOT_IMPLEMENTED => match fullscreen {
+                true => Err(TerminalError::FullscreenUnsupported.into()),
+                false => Ok(()),
+            },
+            _ => Err(error),
+        };
+    }
+    Ok(())
+}
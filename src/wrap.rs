@@ -0,0 +1,418 @@
+use crate::measure::{cells, cells_exact};
+use crate::widgets::available_columns;
+
+/// Soft hyphen: an invisible break opportunity that shows as `-` when a line breaks there.
+const SOFT_HYPHEN: char = '\u{AD}';
+
+/// Cost of breaking a line inside a word, in squared cells of slack: a hyphen is only used
+/// when it saves the paragraph about three cells of raggedness.
+const HYPHEN_PENALTY: usize = 9;
+
+/// Shortest run of letters kept on either side of a heuristic hyphenation point.
+const MIN_FRAGMENT: usize = 3;
+
+/// This function wraps text greedily to `width` cells, filling each line as much as possible.
+///
+/// ## Note:
+/// - Lines break at spaces; a word wider than `width` is split between cells.
+/// - Every `\n` starts a new line, so existing line breaks are kept.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut used = 0;
+        for word in paragraph.split(' ').filter(|word| !word.is_empty()) {
+            let word_width = cells(word);
+            if used > 0 && used + 1 + word_width <= width {
+                line.push(' ');
+                line.push_str(word);
+                used += 1 + word_width;
+                continue;
+            }
+            if used > 0 {
+                lines.push(std::mem::take(&mut line));
+                used = 0;
+            }
+            for span in cells_exact(word) {
+                if used + span.width > width && used > 0 {
+                    lines.push(std::mem::take(&mut line));
+                    used = 0;
+                }
+                line.push_str(&word[span.start..span.end]);
+                used += span.width;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Struct to hold a paragraph laid out with optimal line breaks, optionally justified and
+/// hyphenated, for man-page style help text.
+#[derive(Debug, Clone)]
+pub struct Paragraph<'a> {
+    text: &'a str,
+    width: Option<usize>,
+    justify: bool,
+    hyphenate: bool,
+}
+
+impl<'a> Paragraph<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Paragraph {
+            text,
+            width: None,
+            justify: false,
+            hyphenate: false,
+        }
+    }
+
+    /// Sets the width in cells to lay out to. Without it, the terminal width is used
+    /// (80 columns if it can't be measured), so the layout follows the live width.
+    pub fn width(mut self, columns: usize) -> Self {
+        self.width = Some(columns);
+        self
+    }
+
+    /// Stretches the spaces of every line but the last of each paragraph to fill the width.
+    pub fn justify(mut self, justify: bool) -> Self {
+        self.justify = justify;
+        self
+    }
+
+    /// Also breaks inside words that have no soft hyphen, between two consonants surrounded
+    /// by vowels (`ter-minal`). Only ASCII words are hyphenated this way.
+    pub fn hyphenate(mut self, hyphenate: bool) -> Self {
+        self.hyphenate = hyphenate;
+        self
+    }
+
+    /// This function lays the text out into lines.
+    ///
+    /// ## Returns:
+    /// - The lines, none wider than the width unless a single character is.
+    ///
+    /// ## Note:
+    /// - Consecutive non-blank lines of the text form one paragraph; blank lines separate
+    ///   paragraphs and are kept.
+    /// - Breaks are chosen to minimize the sum of the squared free cells of every line but
+    ///   the last (a lite Knuth-Plass), which evens out the right margin compared to [`wrap`].
+    /// - Lines may break after a `-` and at soft hyphens (U+00AD) even when `hyphenate` is
+    ///   off; soft hyphens where no break happens are removed.
+    pub fn lines(&self) -> Vec<String> {
+        let width = self.width.or_else(available_columns).unwrap_or(80).max(1);
+        let mut lines = Vec::new();
+        let mut words: Vec<&str> = Vec::new();
+        for line in self.text.lines().chain(std::iter::once("")) {
+            if !line.trim().is_empty() {
+                words.extend(line.split_whitespace());
+                continue;
+            }
+            if !words.is_empty() {
+                let fragments = fragments(&words, width, self.hyphenate);
+                lines.extend(self.layout(&fragments, width));
+                words.clear();
+            }
+            lines.push(String::new());
+        }
+        // The sentinel blank line above isn't part of the text.
+        lines.pop();
+        lines
+    }
+
+    fn layout(&self, fragments: &[Fragment], width: usize) -> Vec<String> {
+        let breaks = best_breaks(fragments, width);
+        let mut lines = Vec::with_capacity(breaks.len());
+        let mut start = 0;
+        for (index, &end) in breaks.iter().enumerate() {
+            let last = index + 1 == breaks.len();
+            lines.push(render(&fragments[start..end], width, self.justify && !last));
+            start = end;
+        }
+        lines
+    }
+}
+
+/// Struct to hold a piece of a word between two break opportunities.
+#[derive(Debug)]
+struct Fragment {
+    text: String,   // The piece, without soft hyphens
+    width: usize,   // Its width in cells
+    word_end: bool, // Whether a space follows it when no line break does
+    hyphen: bool,   // Whether a `-` must be added when the line breaks after it
+}
+
+/// Splits words at their break opportunities, and splits fragments wider than the line.
+fn fragments(words: &[&str], width: usize, hyphenate: bool) -> Vec<Fragment> {
+    let mut fragments = Vec::new();
+    for word in words {
+        let mut pieces: Vec<(String, bool)> = Vec::new();
+        for (index, part) in word.split(SOFT_HYPHEN).enumerate() {
+            if index > 0 {
+                if let Some(last) = pieces.last_mut() {
+                    last.1 = true;
+                }
+            }
+            let mut rest = part;
+            while let Some(at) = rest.find('-').filter(|&at| at + 1 < rest.len()) {
+                pieces.push((rest[..=at].to_string(), false));
+                rest = &rest[at + 1..];
+            }
+            if rest.is_empty() {
+                continue;
+            }
+            if hyphenate {
+                let mut start = 0;
+                for at in syllable_breaks(rest) {
+                    pieces.push((rest[start..at].to_string(), true));
+                    start = at;
+                }
+                rest = &rest[start..];
+            }
+            pieces.push((rest.to_string(), false));
+        }
+        // A trailing soft hyphen has nothing to join.
+        if let Some(last) = pieces.last_mut() {
+            last.1 = false;
+        }
+        let count = pieces.len();
+        for (index, (text, hyphen)) in pieces.into_iter().enumerate() {
+            let word_end = index + 1 == count;
+            split_wide(&mut fragments, text, width, word_end, hyphen);
+        }
+    }
+    fragments
+}
+
+/// Pushes a fragment, cut between cells when it can't fit on a line even alone.
+fn split_wide(out: &mut Vec<Fragment>, text: String, width: usize, word_end: bool, hyphen: bool) {
+    let total = cells(&text) + hyphen as usize;
+    if total <= width {
+        out.push(Fragment {
+            width: cells(&text),
+            text,
+            word_end,
+            hyphen,
+        });
+        return;
+    }
+    let spans = cells_exact(&text);
+    let mut start = 0;
+    let mut used = 0;
+    for (index, span) in spans.iter().enumerate() {
+        if used + span.width > width && used > 0 {
+            out.push(Fragment {
+                text: text[start..span.start].to_string(),
+                width: used,
+                word_end: false,
+                hyphen: false,
+            });
+            start = span.start;
+            used = 0;
+        }
+        used += span.width;
+        if index + 1 == spans.len() {
+            out.push(Fragment {
+                text: text[start..].to_string(),
+                width: used,
+                word_end,
+                hyphen: hyphen && used < width,
+            });
+        }
+    }
+}
+
+/// Whether an ASCII letter is a vowel, counting `y`.
+fn is_vowel(c: u8) -> bool {
+    matches!(
+        c.to_ascii_lowercase(),
+        b'a' | b'e' | b'i' | b'o' | b'u' | b'y'
+    )
+}
+
+/// Pairs of consonants read as one sound, never split.
+const DIGRAPHS: [&[u8; 2]; 8] = [b"ch", b"ck", b"gh", b"ph", b"sh", b"th", b"wh", b"qu"];
+
+/// Whether two consonants start a syllable together, like `gr` or `pl`.
+fn is_cluster(pair: [u8; 2]) -> bool {
+    DIGRAPHS.iter().any(|digraph| **digraph == pair)
+        || (b"bcdfgkpt".contains(&pair[0]) && matches!(pair[1], b'l' | b'r'))
+}
+
+/// Heuristic hyphenation points of an ASCII word: between two consonants with a vowel on
+/// each side (vowel-consonant | consonant-vowel).
+fn syllable_breaks(word: &str) -> Vec<usize> {
+    let bytes = word.as_bytes();
+    if !bytes.iter().all(u8::is_ascii_alphabetic) || bytes.len() < 2 * MIN_FRAGMENT {
+        return Vec::new();
+    }
+    let mut breaks: Vec<usize> = Vec::new();
+    for at in MIN_FRAGMENT..=bytes.len() - MIN_FRAGMENT {
+        let (before, left, right, after) = (bytes[at - 2], bytes[at - 1], bytes[at], bytes[at + 1]);
+        let pair = [left.to_ascii_lowercase(), right.to_ascii_lowercase()];
+        if is_vowel(before)
+            && !is_vowel(left)
+            && !is_vowel(right)
+            && is_vowel(after)
+            && !is_cluster(pair)
+            && breaks.last().is_none_or(|&last| at - last >= MIN_FRAGMENT)
+        {
+            breaks.push(at);
+        }
+    }
+    breaks
+}
+
+/// Width of the line holding `fragments`, including its spaces and a trailing hyphen.
+fn line_width(fragments: &[Fragment]) -> usize {
+    let Some(last) = fragments.last() else {
+        return 0;
+    };
+    let spaces = fragments[..fragments.len() - 1]
+        .iter()
+        .filter(|fragment| fragment.word_end)
+        .count();
+    let hyphen = (!last.word_end && last.hyphen) as usize;
+    fragments
+        .iter()
+        .map(|fragment| fragment.width)
+        .sum::<usize>()
+        + spaces
+        + hyphen
+}
+
+/// End index of every line, chosen by dynamic programming over the break opportunities.
+fn best_breaks(fragments: &[Fragment], width: usize) -> Vec<usize> {
+    let count = fragments.len();
+    // best[j]: lowest cost of laying out fragments[..j], and where its last line starts.
+    let mut best: Vec<(usize, usize)> = vec![(usize::MAX, 0); count + 1];
+    best[0] = (0, 0);
+    for end in 1..=count {
+        for start in (0..end).rev() {
+            let used = line_width(&fragments[start..end]);
+            if used > width && start + 1 < end {
+                break;
+            }
+            if best[start].0 == usize::MAX {
+                continue;
+            }
+            let last = fragments[end - 1].word_end;
+            let slack = width.saturating_sub(used);
+            let cost = match end == count {
+                true => 0,
+                false => slack * slack + if last { 0 } else { HYPHEN_PENALTY },
+            };
+            let total = best[start].0.saturating_add(cost);
+            if total < best[end].0 {
+                best[end] = (total, start);
+            }
+        }
+    }
+    let mut breaks = Vec::new();
+    let mut end = count;
+    while end > 0 {
+        breaks.push(end);
+        end = best[end].1;
+    }
+    breaks.reverse();
+    breaks
+}
+
+/// Joins the fragments of one line, spreading the free cells over its spaces if justified.
+fn render(fragments: &[Fragment], width: usize, justify: bool) -> String {
+    let gaps = fragments[..fragments.len().saturating_sub(1)]
+        .iter()
+        .filter(|fragment| fragment.word_end)
+        .count();
+    let extra = match justify && gaps > 0 {
+        true => width.saturating_sub(line_width(fragments)),
+        false => 0,
+    };
+    let mut line = String::new();
+    let mut gap = 0;
+    for (index, fragment) in fragments.iter().enumerate() {
+        line.push_str(&fragment.text);
+        if index + 1 == fragments.len() {
+            if !fragment.word_end && fragment.hyphen {
+                line.push('-');
+            }
+        } else if fragment.word_end {
+            // The leftmost gaps take the remainder.
+            let spaces = 1 + extra / gaps + (gap < extra % gaps) as usize;
+            line.extend(std::iter::repeat_n(' ', spaces));
+            gap += 1;
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_greedily() {
+        assert_eq!(wrap("the quick brown fox", 10), ["the quick", "brown fox"]);
+        assert_eq!(wrap("a\n\nb", 10), ["a", "", "b"]);
+        assert_eq!(wrap("abcdefgh", 3), ["abc", "def", "gh"]);
+        assert_eq!(
+            wrap("\u{4E2D}\u{6587}\u{5B57}", 5),
+            ["\u{4E2D}\u{6587}", "\u{5B57}"]
+        );
+    }
+
+    #[test]
+    fn breaks_evenly() {
+        // Greedy fills the first line and leaves the second nearly empty.
+        let text = "a b c d eeeeee";
+        assert_eq!(wrap(text, 6), ["a b c", "d", "eeeeee"]);
+        let lines = Paragraph::new(text).width(6).lines();
+        assert_eq!(lines, ["a b", "c d", "eeeeee"]);
+    }
+
+    #[test]
+    fn justifies_all_but_the_last_line() {
+        let lines = Paragraph::new("aa b cc dd e")
+            .width(7)
+            .justify(true)
+            .lines();
+        assert!(lines[..lines.len() - 1].iter().all(|line| line.len() == 7));
+        assert!(!lines.last().unwrap().contains("  "));
+        assert_eq!(lines.join(" ").split_whitespace().count(), 5);
+    }
+
+    #[test]
+    fn keeps_paragraphs() {
+        let lines = Paragraph::new("one two\nthree\n\nfour").width(20).lines();
+        assert_eq!(lines, ["one two three", "", "four"]);
+    }
+
+    #[test]
+    fn breaks_at_soft_and_hard_hyphens() {
+        let lines = Paragraph::new("xx well-known").width(8).lines();
+        assert_eq!(lines, ["xx well-", "known"]);
+        let lines = Paragraph::new("xx ter\u{AD}minal").width(7).lines();
+        assert_eq!(lines, ["xx ter-", "minal"]);
+        let lines = Paragraph::new("ter\u{AD}minal").width(20).lines();
+        assert_eq!(lines, ["terminal"]);
+    }
+
+    #[test]
+    fn hyphenates_between_consonants() {
+        assert_eq!(syllable_breaks("terminal"), [3]);
+        assert_eq!(syllable_breaks("another"), Vec::<usize>::new());
+        assert_eq!(syllable_breaks("naïve"), Vec::<usize>::new());
+        let lines = Paragraph::new("a terminal")
+            .width(6)
+            .hyphenate(true)
+            .lines();
+        assert_eq!(lines, ["a ter-", "minal"]);
+    }
+
+    #[test]
+    fn splits_words_wider_than_the_line() {
+        let lines = Paragraph::new("abcdefghij").width(4).lines();
+        assert_eq!(lines, ["abcd", "efgh", "ij"]);
+    }
+}
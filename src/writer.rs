@@ -0,0 +1,686 @@
+use std::cell::RefCell;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::{mem, str};
+
+use windows_sys::Win32::Foundation::HANDLE;
+use windows_sys::Win32::System::Console::{
+    GetConsoleMode, WriteConsoleW, STD_ERROR_HANDLE, STD_HANDLE, STD_OUTPUT_HANDLE,
+};
+
+use crate::console::{is_console_handle, screen_buffer_info, std_handle};
+use crate::format;
+use crate::sim::{Clock, SystemClock};
+
+/// Units of UTF-16 kept by a thread between console writes; a larger write gives its buffer
+/// back to this size afterwards.
+const KEPT_UNITS: usize = 64 * 1024;
+
+/// Units of UTF-16 passed to one `WriteConsoleW` call.
+const CHUNK_UNITS: usize = 16 * 1024;
+
+thread_local! {
+    // UTF-16 of the console write in progress on this thread, reused by every write so that
+    // a logger writing line after line converts without allocating.
+    static UNITS: RefCell<Vec<u16>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Struct to hold the options of a transcript file written by [`TerminalWriter::tee_with`].
+///
+/// With `timestamps` or `events`, the file starts with a `# transcript started at <unix secs>`
+/// line, text lines read `[  12.345] text` and events `[  12.345] # resize 120x30`, a
+/// line-oriented format replay tools can parse back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TeeOptions {
+    pub strip_ansi: bool, // Drop escape sequences, keeping the plain text the user saw
+    pub max_bytes: u64,   // Size after which the file is rotated, 0 to never rotate
+    pub keep: usize,      // Rotated files kept beside the current one (`log.1`, `log.2`, ...)
+    pub timestamps: bool, // Prefix each line with the seconds elapsed since the tee started
+    pub events: bool,     // Record window resizes and console mode changes as `#` lines
+}
+
+impl Default for TeeOptions {
+    fn default() -> Self {
+        TeeOptions {
+            strip_ansi: true,
+            max_bytes: 10 * 1024 * 1024,
+            keep: 3,
+            timestamps: false,
+            events: false,
+        }
+    }
+}
+
+/// Enum to represent where the ANSI stripper is in an escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Strip {
+    Text,
+    Escape,    // After ESC
+    Nf,        // After ESC and intermediates (0x20-0x2F), up to the final byte (`ESC ( B`)
+    Csi,       // Inside `ESC [`
+    String,    // Inside an OSC, DCS, APC or PM, up to BEL or ST
+    StringEsc, // After ESC inside a string, expecting `\`
+}
+
+/// Struct to hold the console state an annotated transcript compares against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Observed {
+    window: Option<(i16, i16)>, // Visible columns and rows
+    mode: Option<u32>,          // Output console mode
+}
+
+/// Struct to hold an open transcript file.
+#[derive(Debug)]
+struct Tee {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    options: TeeOptions,
+    strip: Strip,
+    stream: STD_HANDLE,
+    started: Instant,
+    line_start: bool,
+    observed: Option<Observed>,
+    scratch: Vec<u8>, // Bytes of the write in progress, reused by every write
+}
+
+impl Tee {
+    fn open(path: PathBuf, options: TeeOptions, stream: STD_HANDLE) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        let mut tee = Tee {
+            path,
+            file,
+            written,
+            options,
+            strip: Strip::Text,
+            stream,
+            started: Instant::now(),
+            line_start: true,
+            observed: None,
+            scratch: Vec::new(),
+        };
+        if tee.options.timestamps || tee.options.events {
+            let since_epoch = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            let header = format!("# transcript started at {} (unix)\n", since_epoch.as_secs());
+            tee.write_raw(header.as_bytes())?;
+        }
+        Ok(tee)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<()> {
+        if !self.options.strip_ansi && !self.options.timestamps && !self.options.events {
+            return self.write_raw(buf);
+        }
+        let mut out = mem::take(&mut self.scratch);
+        out.clear();
+        for &byte in buf {
+            if self.options.strip_ansi && !strip_byte(&mut self.strip, byte) {
+                continue;
+            }
+            if self.line_start && (self.options.timestamps || self.options.events) {
+                self.annotate_changes(&mut out);
+                if self.options.timestamps {
+                    self.timestamp(&mut out);
+                }
+                self.line_start = false;
+            }
+            out.push(byte);
+            self.line_start = byte == b'\n';
+        }
+        let result = self.write_raw(&out);
+        self.scratch = out;
+        result
+    }
+
+    /// Appends `[  12.345] `, the time since the tee started.
+    fn timestamp(&self, out: &mut Vec<u8>) {
+        let _ = write!(out, "[{:>8.3}] ", self.started.elapsed().as_secs_f64());
+    }
+
+    /// Writes a `#` line for each change of the window size or console mode since last time.
+    fn annotate_changes(&mut self, out: &mut Vec<u8>) {
+        if !self.options.events {
+            return;
+        }
+        let handle = std_handle(self.stream).ok();
+        let window = handle.and_then(|h| screen_buffer_info(h).ok()).map(|info| {
+            let window = info.srWindow;
+            (
+                window.Right - window.Left + 1,
+                window.Bottom - window.Top + 1,
+            )
+        });
+        let mode = handle.and_then(|h| {
+            let mut mode = 0;
+            (unsafe { GetConsoleMode(h, &mut mode) } != 0).then_some(mode)
+        });
+        let now = Observed { window, mode };
+        let previous = self.observed.replace(now);
+        if previous == Some(now) {
+            return;
+        }
+        let mut stamp = Vec::new();
+        self.timestamp(&mut stamp);
+        if let (Some((columns, rows)), true) = (window, previous.map(|p| p.window) != Some(window))
+        {
+            out.extend_from_slice(&stamp);
+            let _ = writeln!(out, "# resize {}x{}", columns, rows);
+        }
+        if let (Some(mode), true) = (mode, previous.map(|p| p.mode) != Some(mode)) {
+            out.extend_from_slice(&stamp);
+            let _ = writeln!(out, "# mode 0x{:04x}", mode);
+        }
+    }
+
+    /// Records a `#` event line, ending the current line first.
+    fn annotate(&mut self, event: &str) -> io::Result<()> {
+        let mut line = Vec::new();
+        if !self.line_start {
+            line.push(b'\n');
+            self.line_start = true;
+        }
+        if self.options.timestamps {
+            self.timestamp(&mut line);
+        }
+        let _ = writeln!(line, "# {}", event);
+        self.write_raw(&line)
+    }
+
+    /// Appends to the file, rotating it first if the bytes would take it past `max_bytes`.
+    /// An empty file is never rotated: a write larger than `max_bytes` goes into it whole.
+    fn write_raw(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let max = self.options.max_bytes;
+        if max > 0 && self.written > 0 && self.written + bytes.len() as u64 > max {
+            self.rotate()?;
+        }
+        self.file.write_all(bytes)?;
+        self.written += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Shifts `log` to `log.1`, `log.1` to `log.2`, ... dropping the oldest, and starts over.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let numbered = |n: usize| {
+            let mut name = self.path.clone().into_os_string();
+            name.push(format!(".{}", n));
+            PathBuf::from(name)
+        };
+        if self.options.keep == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            let _ = fs::remove_file(numbered(self.options.keep));
+            for n in (1..self.options.keep).rev() {
+                let _ = fs::rename(numbered(n), numbered(n + 1));
+            }
+            fs::rename(&self.path, numbered(1))?;
+            self.file = File::create(&self.path)?;
+        }
+        self.written = 0;
+        Ok(())
+    }
+}
+
+/// Whether `byte` is text rather than part of an escape sequence, `state` carrying a sequence
+/// split across calls.
+fn strip_byte(state: &mut Strip, byte: u8) -> bool {
+    let text = *state == Strip::Text && byte != 0x1b;
+    *state = match (*state, byte) {
+        (Strip::Text, 0x1b) => Strip::Escape,
+        (Strip::Text, _) => Strip::Text,
+        (Strip::Escape, b'[') => Strip::Csi,
+        (Strip::Escape, b']' | b'P' | b'_' | b'^') => Strip::String,
+        (Strip::Escape, 0x20..=0x2f) => Strip::Nf,
+        (Strip::Escape, _) => Strip::Text,
+        (Strip::Nf, 0x20..=0x2f) => Strip::Nf,
+        (Strip::Nf, _) => Strip::Text,
+        (Strip::Csi, 0x40..=0x7e) => Strip::Text,
+        (Strip::Csi, _) => Strip::Csi,
+        (Strip::String, 0x07) => Strip::Text,
+        (Strip::String, 0x1b) => Strip::StringEsc,
+        (Strip::String, _) => Strip::String,
+        (Strip::StringEsc, b'\\') => Strip::Text,
+        (Strip::StringEsc, _) => Strip::String,
+    };
+    text
+}
+
+/// Appends the UTF-16 of `buf` to `units`, `partial` carrying the bytes of a character split
+/// across calls. Invalid bytes become U+FFFD.
+fn decode(partial: &mut Vec<u8>, mut buf: &[u8], units: &mut Vec<u16>) {
+    // The character split by the previous call, completed one byte at a time.
+    while !partial.is_empty() && !buf.is_empty() {
+        partial.push(buf[0]);
+        match str::from_utf8(partial) {
+            Ok(text) => {
+                units.extend(text.encode_utf16());
+                partial.clear();
+            }
+            Err(e) if e.error_len().is_some() => {
+                // The new byte doesn't continue the character: it starts over on its own.
+                units.push(0xFFFD);
+                partial.clear();
+                continue;
+            }
+            Err(_) => {}
+        }
+        buf = &buf[1..];
+    }
+    loop {
+        match str::from_utf8(buf) {
+            Ok(text) => {
+                units.extend(text.encode_utf16());
+                return;
+            }
+            Err(e) => {
+                let (valid, rest) = buf.split_at(e.valid_up_to());
+                units.extend(str::from_utf8(valid).unwrap_or_default().encode_utf16());
+                match e.error_len() {
+                    Some(len) => {
+                        units.push(0xFFFD);
+                        buf = &rest[len..];
+                    }
+                    None => {
+                        partial.extend_from_slice(rest);
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Writes all of `units` to the console `handle`, in chunks that don't split surrogate pairs.
+fn write_units(handle: HANDLE, mut units: &[u16]) -> io::Result<()> {
+    while !units.is_empty() {
+        let mut len = units.len().min(CHUNK_UNITS);
+        if len < units.len() && (0xD800..0xDC00).contains(&units[len - 1]) {
+            len -= 1;
+        }
+        let mut written = 0;
+        let ok = unsafe {
+            WriteConsoleW(
+                handle,
+                units.as_ptr().cast(),
+                len as u32,
+                &mut written,
+                std::ptr::null(),
+            )
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if written == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        units = &units[(written as usize).min(len)..];
+    }
+    Ok(())
+}
+
+/// Struct to hold a console screen buffer written with `WriteConsoleW`, converting the UTF-8
+/// into the thread's reusable UTF-16 buffer.
+struct Console {
+    handle: HANDLE,
+    stream: STD_HANDLE,
+    partial: Vec<u8>, // Bytes of a character split across writes
+}
+
+// The handle is a console screen buffer, which any thread may write to.
+unsafe impl Send for Console {}
+
+impl Write for Console {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // What the program printed through the standard output buffer goes first.
+        if self.stream == STD_OUTPUT_HANDLE {
+            io::stdout().flush()?;
+        }
+        UNITS.with(|units| {
+            let mut units = units.borrow_mut();
+            units.clear();
+            decode(&mut self.partial, buf, &mut units);
+            let result = write_units(self.handle, &units);
+            units.shrink_to(KEPT_UNITS);
+            result.map(|()| buf.len())
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Struct to hold a writer to the standard output or error that can mirror everything written
+/// through it into a transcript file.
+///
+/// Output goes to the terminal first; a failing transcript is closed and reported by
+/// [`TerminalWriter::tee_error`], and never fails or repeats the terminal write.
+///
+/// On a console, lines are written with `WriteConsoleW` from a UTF-16 buffer each thread
+/// reuses, so logging a line doesn't allocate; a redirected stream is written as bytes.
+pub struct TerminalWriter {
+    out: Box<dyn Write + Send>,
+    stream: STD_HANDLE,
+    tee: Option<Tee>,
+    tee_error: Option<io::Error>,
+}
+
+impl TerminalWriter {
+    pub fn stdout() -> Self {
+        Self::new(
+            Self::output(STD_OUTPUT_HANDLE, io::stdout()),
+            STD_OUTPUT_HANDLE,
+        )
+    }
+
+    pub fn stderr() -> Self {
+        Self::new(
+            Self::output(STD_ERROR_HANDLE, io::stderr()),
+            STD_ERROR_HANDLE,
+        )
+    }
+
+    /// The console behind `stream`, or `std` if the stream is redirected. Like the standard
+    /// streams, the output is line buffered and the error isn't.
+    fn output(stream: STD_HANDLE, std: impl Write + Send + 'static) -> Box<dyn Write + Send> {
+        let handle = match std_handle(stream) {
+            Ok(handle) if is_console_handle(handle) => handle,
+            _ => return Box::new(std),
+        };
+        let console = Console {
+            handle,
+            stream,
+            partial: Vec::new(),
+        };
+        if stream == STD_OUTPUT_HANDLE {
+            Box::new(LineWriter::new(console))
+        } else {
+            Box::new(console)
+        }
+    }
+
+    fn new(out: Box<dyn Write + Send>, stream: STD_HANDLE) -> Self {
+        TerminalWriter {
+            out,
+            stream,
+            tee: None,
+            tee_error: None,
+        }
+    }
+
+    /// This function mirrors the output into `path` with the default [`TeeOptions`]: escape
+    /// sequences stripped, rotated past 10 MiB keeping 3 old files.
+    pub fn tee(self, path: impl AsRef<Path>) -> io::Result<Self> {
+        self.tee_with(path, TeeOptions::default())
+    }
+
+    /// This function mirrors the output into `path`, appending to it if it exists.
+    ///
+    /// ## Returns:
+    /// - `Err` if the file can't be opened.
+    pub fn tee_with(mut self, path: impl AsRef<Path>, options: TeeOptions) -> io::Result<Self> {
+        self.tee = Some(Tee::open(
+            path.as_ref().to_path_buf(),
+            options,
+            self.stream,
+        )?);
+        self.tee_error = None;
+        Ok(self)
+    }
+
+    /// This function records an event line (`# text`) in the transcript, e.g. when the app
+    /// switches to the alternate screen. Nothing is shown on the terminal.
+    pub fn annotate(&mut self, event: &str) {
+        if let Some(tee) = &mut self.tee {
+            if let Err(e) = tee.annotate(event) {
+                self.tee = None;
+                self.tee_error = Some(e);
+            }
+        }
+    }
+
+    /// The error that closed the transcript, if any.
+    pub fn tee_error(&self) -> Option<&io::Error> {
+        self.tee_error.as_ref()
+    }
+}
+
+impl Write for TerminalWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.out.write(buf)?;
+        if let Some(tee) = &mut self.tee {
+            if let Err(e) = tee.write(&buf[..n]) {
+                self.tee = None;
+                self.tee_error = Some(e);
+            }
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(tee) = &mut self.tee {
+            if let Err(e) = tee.file.flush() {
+                self.tee = None;
+                self.tee_error = Some(e);
+            }
+        }
+        self.out.flush()
+    }
+}
+
+/// Struct to hold a writer that caps the output rate, for proxying the output of a child
+/// process (e.g. read from a ConPTY pipe) that may flood the terminal.
+///
+/// Up to `bytes_per_second` bytes go through in every second; the excess of that second is
+/// dropped, and replaced by one `[win-term: N dropped]` line once output is allowed again, so
+/// a runaway child can't keep a slow host busy rendering.
+pub struct Throttle<W: Write, C: Clock = SystemClock> {
+    inner: W,
+    rate: u64,
+    clock: C,
+    window: Instant,
+    written: u64,
+    dropped: u64,
+}
+
+impl<W: Write> Throttle<W> {
+    /// Wraps `inner`, letting through at most `bytes_per_second` bytes per second (at least 1).
+    pub fn new(inner: W, bytes_per_second: u64) -> Self {
+        Throttle::with_clock(inner, bytes_per_second, SystemClock)
+    }
+}
+
+impl<W: Write, C: Clock> Throttle<W, C> {
+    /// Wraps `inner` like [`Throttle::new`], measuring the seconds on `clock`, e.g. a
+    /// [`SimClock`](crate::sim::SimClock) to test a flood without waiting.
+    pub fn with_clock(inner: W, bytes_per_second: u64, clock: C) -> Self {
+        Throttle {
+            inner,
+            rate: bytes_per_second.max(1),
+            window: clock.now(),
+            clock,
+            written: 0,
+            dropped: 0,
+        }
+    }
+
+    /// The number of bytes dropped since the last indicator line.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// The wrapped writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Starts a new second when the current one is over, reporting what it dropped.
+    fn roll(&mut self) -> io::Result<()> {
+        let now = self.clock.now();
+        if now.duration_since(self.window).as_secs() < 1 {
+            return Ok(());
+        }
+        self.window = now;
+        self.written = 0;
+        if self.dropped > 0 {
+            // The cut may have left colors or a half sequence behind; reset them first.
+            let line = format!(
+                "\x1b[0m\r\n[win-term: {} dropped]\r\n",
+                format::bytes(self.dropped)
+            );
+            self.dropped = 0;
+            self.inner.write_all(line.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write, C: Clock> Write for Throttle<W, C> {
+    /// Always consumes the whole buffer, writing only what the current second allows.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.roll()?;
+        let allowed = (self.rate - self.written).min(buf.len() as u64) as usize;
+        if allowed > 0 {
+            self.inner.write_all(&buf[..allowed])?;
+            self.written += allowed as u64;
+        }
+        self.dropped += (buf.len() - allowed) as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.roll()?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::SimClock;
+    use std::time::Duration;
+
+    fn stripped(chunks: &[&str]) -> String {
+        let mut state = Strip::Text;
+        let out: Vec<u8> = chunks
+            .iter()
+            .flat_map(|chunk| chunk.bytes())
+            .filter(|&byte| strip_byte(&mut state, byte))
+            .collect();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn decoded(chunks: &[&[u8]]) -> String {
+        let (mut partial, mut units) = (Vec::new(), Vec::new());
+        for chunk in chunks {
+            decode(&mut partial, chunk, &mut units);
+        }
+        String::from_utf16(&units).unwrap()
+    }
+
+    #[test]
+    fn decodes_characters_split_across_writes() {
+        assert_eq!(
+            decoded(&[b"caf\xc3", b"\xa9 \xf0\x9f", b"\x98", b"\x80!"]),
+            "café 😀!"
+        );
+        assert_eq!(decoded(&["│ é".as_bytes()]), "│ é");
+    }
+
+    #[test]
+    fn replaces_invalid_bytes() {
+        assert_eq!(decoded(&[b"a\xffb"]), "a\u{fffd}b");
+        assert_eq!(decoded(&[b"a\xe2", b"b"]), "a\u{fffd}b");
+        assert_eq!(decoded(&[b"\xe2\x94", b"\xe2\x94\x82"]), "\u{fffd}│");
+    }
+
+    #[test]
+    fn keeps_annotations_out_of_escape_sequences() {
+        let dir = std::env::temp_dir().join(format!("win-term-stamp-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log");
+        let options = TeeOptions {
+            timestamps: true,
+            ..TeeOptions::default()
+        };
+        let mut tee = Tee::open(path.clone(), options, STD_OUTPUT_HANDLE).unwrap();
+        tee.write(b"\x1b[31mone\n\x1b[0m").unwrap();
+        tee.write(b"two\n").unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().skip(1).map(|line| &line[11..]).collect();
+        assert_eq!(lines, ["one", "two"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn throttles_per_second_of_the_clock() {
+        let clock = SimClock::new();
+        let mut throttle = Throttle::with_clock(Vec::new(), 4, &clock);
+        assert_eq!(throttle.write(b"0123456789").unwrap(), 10);
+        assert_eq!(throttle.dropped(), 6);
+        clock.advance(Duration::from_millis(900));
+        throttle.write_all(b"ab").unwrap();
+        assert_eq!(throttle.dropped(), 8);
+        clock.advance(Duration::from_millis(100));
+        throttle.write_all(b"cd").unwrap();
+        assert_eq!(throttle.dropped(), 0);
+        let out = String::from_utf8(throttle.into_inner()).unwrap();
+        assert!(out.starts_with("0123\x1b[0m\r\n[win-term: "), "{:?}", out);
+        assert!(out.ends_with(" dropped]\r\ncd"), "{:?}", out);
+    }
+
+    #[test]
+    fn rotates_only_non_empty_files() {
+        let dir = std::env::temp_dir().join(format!("win-term-tee-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log");
+        let options = TeeOptions {
+            strip_ansi: false,
+            max_bytes: 8,
+            keep: 2,
+            ..TeeOptions::default()
+        };
+        let mut tee = Tee::open(path.clone(), options, STD_OUTPUT_HANDLE).unwrap();
+        let read = |n: &str| fs::read_to_string(dir.join(n)).ok();
+        tee.write(b"0123456789ab").unwrap();
+        assert_eq!(read("log").as_deref(), Some("0123456789ab"));
+        assert_eq!(read("log.1"), None);
+        tee.write(b"cd").unwrap();
+        assert_eq!(read("log").as_deref(), Some("cd"));
+        assert_eq!(read("log.1").as_deref(), Some("0123456789ab"));
+        tee.write(b"0123456789").unwrap();
+        tee.write(b"ef").unwrap();
+        assert_eq!(read("log").as_deref(), Some("ef"));
+        assert_eq!(read("log.1").as_deref(), Some("0123456789"));
+        assert_eq!(read("log.2").as_deref(), Some("cd"));
+        assert_eq!(read("log.3"), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn strips_csi_and_strings() {
+        assert_eq!(stripped(&["\x1b[1;31mred\x1b[0m plain"]), "red plain");
+        assert_eq!(stripped(&["\x1b[?25l\x1b[2J\x1b[Hx"]), "x");
+        assert_eq!(stripped(&["\x1b]0;title\x07a\x1b]8;;url\x1b\\b"]), "ab");
+        assert_eq!(stripped(&["\x1bPq#0;2;0;0;0\x1b\\c"]), "c");
+    }
+
+    #[test]
+    fn strips_escapes_with_intermediates() {
+        assert_eq!(stripped(&["\x1b(Btext\x1b)0"]), "text");
+        assert_eq!(stripped(&["\x1b#8\x1b 7a"]), "a");
+        assert_eq!(stripped(&["\x1b7b\x1b8\x1bMc"]), "bc");
+    }
+
+    #[test]
+    fn keeps_sequences_split_across_writes() {
+        assert_eq!(stripped(&["a\x1b", "[3", "1mb"]), "ab");
+        assert_eq!(stripped(&["a\x1b(", "Bb"]), "ab");
+        assert_eq!(stripped(&["\x1b]0;ti", "tle\x1b", "\\c"]), "c");
+    }
+}
@@ -0,0 +1,56 @@
+using System.Runtime.InteropServices;
+
+namespace WinTerm.Interop;
+
+[StructLayout(LayoutKind.Sequential)]
+public struct WtSize
+{
+    public int Width;
+    public int Height;
+}
+
+[StructLayout(LayoutKind.Sequential)]
+public struct WtCells
+{
+    public int Columns;
+    public int Rows;
+}
+
+// The functions are `extern "system"`, the default calling convention of DllImport.
+public static class NativeMethods
+{
+    private const string Dll = "win_term";
+
+    public const int Ok = 0;
+    public const int NoStdHandle = 1;
+    public const int NotAConsole = 5;
+    public const int NullArgument = -1;
+
+    [DllImport(Dll, EntryPoint = "wt_abi_version")]
+    public static extern uint AbiVersion();
+
+    [DllImport(Dll, EntryPoint = "wt_font_size")]
+    public static extern int FontSize(out WtSize size);
+
+    [DllImport(Dll, EntryPoint = "wt_terminal_size")]
+    public static extern int TerminalSize(out WtSize size);
+
+    [DllImport(Dll, EntryPoint = "wt_viewport_size")]
+    public static extern int ViewportSize(out WtSize size);
+
+    [DllImport(Dll, EntryPoint = "wt_terminal_cells")]
+    public static extern int TerminalCells(out WtCells cells);
+
+    [DllImport(Dll, EntryPoint = "wt_font_size")]
+    public static extern unsafe int FontSizeRaw(WtSize* size);
+
+    [DllImport(Dll, EntryPoint = "wt_last_error")]
+    private static extern nint LastErrorPtr();
+
+    [DllImport(Dll, EntryPoint = "wt_status_message")]
+    private static extern nint StatusMessagePtr(int status);
+
+    public static string? LastError() => Marshal.PtrToStringUTF8(LastErrorPtr());
+
+    public static string StatusMessage(int status) => Marshal.PtrToStringUTF8(StatusMessagePtr(status))!;
+}
@@ -0,0 +1,52 @@
+using System.Runtime.InteropServices;
+using WinTerm.Interop;
+
+var failures = 0;
+
+void Check(bool condition, string what)
+{
+    Console.Error.WriteLine($"{(condition ? "ok  " : "FAIL")} {what}");
+    if (!condition)
+    {
+        failures++;
+    }
+}
+
+// The result of a measurement is either a size or a status with its message.
+void CheckResult(int status, int width, int height, string what)
+{
+    if (status == NativeMethods.Ok)
+    {
+        Check(width > 0 && height > 0, $"{what}: {width}x{height}");
+        Check(NativeMethods.LastError() is null, $"{what}: no last error after success");
+    }
+    else
+    {
+        var message = NativeMethods.LastError();
+        Check(!string.IsNullOrEmpty(message), $"{what}: status {status}, \"{message}\"");
+        Check(message!.StartsWith(NativeMethods.StatusMessage(status)), $"{what}: message matches the status");
+    }
+}
+
+Check(NativeMethods.AbiVersion() == 1, "ABI version 1");
+Check(Marshal.SizeOf<WtSize>() == 8 && Marshal.SizeOf<WtCells>() == 8, "struct layout");
+
+CheckResult(NativeMethods.FontSize(out var font), font.Width, font.Height, "font size");
+CheckResult(NativeMethods.TerminalSize(out var terminal), terminal.Width, terminal.Height, "terminal size");
+CheckResult(NativeMethods.ViewportSize(out var viewport), viewport.Width, viewport.Height, "viewport size");
+CheckResult(NativeMethods.TerminalCells(out var cells), cells.Columns, cells.Rows, "terminal cells");
+
+// Run with the output redirected, as under CI, the standard output isn't a console.
+if (Console.IsOutputRedirected)
+{
+    Check(NativeMethods.FontSize(out _) is NativeMethods.NotAConsole or NativeMethods.NoStdHandle,
+        "redirected output is reported");
+}
+
+unsafe
+{
+    Check(NativeMethods.FontSizeRaw(null) == NativeMethods.NullArgument, "null pointer rejected");
+}
+Check(NativeMethods.StatusMessage(42) == "unknown status", "unknown status message");
+
+return failures == 0 ? 0 : 1;